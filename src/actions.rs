@@ -1,13 +1,85 @@
 use crate::args::{ArgumentResolver, ALIAS_ARGS_NAME};
+use crate::conditions;
+use crate::conditions::{ConditionContext, ConditionError, StepResult, StepResultMap};
 use crate::config::RawCommandConfigVariant::Shorthand;
-use crate::config::{ActionConfig, AliasActionConfig, ExecutionConfigVariant};
-use crate::exec::{CommandExecutor, ExecutionError, ExitStatus};
-use crate::variables::{substitute_variables, VariableMap};
+use crate::config::{
+    split_dependency, ActionConfig, AliasActionConfig, DelegateActionConfig,
+    ExecutionConfigVariant, ServicesActionConfig, StepConfig, CONFIG_ARG,
+    CONFIG_PATH_VARIABLE_NAME, INLINE_CONFIG_VARIABLE_NAME,
+};
+use crate::exec::{CommandExecutor, ExecutionError, ExecutionResult, ExitStatus, Service};
+use crate::filters::{FilterError, OutputFilter};
+use crate::interrupt;
+use crate::step_headers;
+use crate::step_headers::{StepEvent, StepSymbols};
+use crate::template;
+use crate::variables::{substitute_variables, substitute_variables_with_args, VariableMap};
+use linked_hash_map::LinkedHashMap;
+use std::env;
+use std::path::PathBuf;
+use std::time::Instant;
 use thiserror::Error;
 
+/// The name of the variable containing the name (or index) of the action that failed.
+pub const FAILED_STEP_VARIABLE_NAME: &str = "PLZ_FAILED_STEP";
+
+/// The name of the variable containing the exit code of the action that failed.
+pub const EXIT_CODE_VARIABLE_NAME: &str = "PLZ_EXIT_CODE";
+
+/// The name of the variable containing the arguments passed to an alias, separated by spaces.
+/// If an alias references it, the arguments are placed there instead of at the end of the alias.
+pub const ALIAS_ARGS_VARIABLE_NAME: &str = "PLZ_ARGS";
+
+/// The name of the variable containing the number of delegate actions leading to the current process.
+const DELEGATE_DEPTH_VARIABLE_NAME: &str = "PLZ_DELEGATE_DEPTH";
+
+/// The maximum number of nested delegate actions, used to catch commands that delegate to themselves.
+const MAX_DELEGATE_DEPTH: usize = 16;
+
 pub struct ActionExecutor {
     pub command_executor: Box<dyn CommandExecutor>,
     pub arg_resolver: Box<dyn ArgumentResolver>,
+
+    /// The symbols to print step headers with, or `None` if step headers are disabled.
+    pub step_symbols: Option<StepSymbols>,
+
+    /// The name plz was invoked as, used to refer to the plz processes executed by delegate actions.
+    pub binary_name: String,
+
+    /// The arguments forwarded to the plz processes executed by delegate actions and dependencies.
+    pub forwarded_args: ForwardedArgs,
+}
+
+/// The arguments that chose the config files and environment for the current process, which are
+/// forwarded to nested plz processes so they use the same ones.
+#[derive(Clone, Debug, Default)]
+pub struct ForwardedArgs {
+    /// The paths of the config files provided with `--config`. These need to be absolute, since
+    /// the working directory is changed to the config file's directory.
+    pub config_paths: Vec<PathBuf>,
+
+    /// The environment provided with `--env`.
+    pub environment: Option<String>,
+}
+
+impl ForwardedArgs {
+    /// Returns the arguments to pass before the command.
+    /// The `--config` arguments are only included if `include_config` is `true`.
+    fn args(&self, include_config: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        if include_config {
+            for config_path in &self.config_paths {
+                args.push(CONFIG_ARG.to_string());
+                args.push(config_path.to_string_lossy().to_string());
+            }
+        }
+        if let Some(environment) = &self.environment {
+            args.push("--env".to_string());
+            args.push(environment.clone());
+        }
+
+        args
+    }
 }
 
 impl ActionExecutor {
@@ -18,30 +90,231 @@ impl ActionExecutor {
         variables: &VariableMap,
     ) -> Result<(), ActionError> {
         match action_config {
-            ActionConfig::SingleStep(single_command_action) => {
-                self.execute_actions(vec![single_command_action.action.clone()], variables)
-            }
+            ActionConfig::SingleStep(single_command_action) => self.execute_actions(
+                &[StepConfig::from(single_command_action.action.clone())],
+                variables,
+            ),
 
             ActionConfig::MultiStep(multi_command_action) => {
-                self.execute_actions(multi_command_action.actions.clone(), variables)
+                if multi_command_action.session {
+                    self.execute_session(&multi_command_action.actions, variables)
+                } else {
+                    self.execute_actions(&multi_command_action.actions, variables)
+                }
             }
 
             ActionConfig::Alias(alias_action) => self.execute_alias(alias_action, variables),
+
+            ActionConfig::Delegate(delegate_action) => {
+                self.execute_delegate(delegate_action, delegate_depth(), variables)
+            }
+
+            ActionConfig::Services(services_action) => {
+                self.execute_services(services_action, variables)
+            }
+        }
+    }
+
+    /// Executes the commands a command depends on, in order, stopping at the first one that fails.
+    /// Each dependency is executed by a separate plz process. Dependencies on commands in other
+    /// directories are executed from that directory, so they use the config files found there.
+    pub fn execute_dependencies(
+        &self,
+        depends_on: &[String],
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        for dependency in depends_on {
+            let dependency = substitute_variables(dependency, variables);
+            let (directory, command) = split_dependency(&dependency);
+
+            // Prevent the config chosen for this process from being used in the other directory
+            let mut dependency_variables = variables.clone();
+            if directory.is_some() {
+                for name in [CONFIG_PATH_VARIABLE_NAME, INLINE_CONFIG_VARIABLE_NAME] {
+                    dependency_variables.insert(name.to_string(), String::new());
+                }
+            }
+
+            let mut args = self.forwarded_args.args(directory.is_none());
+            args.extend(command.split_whitespace().map(String::from));
+            let working_directory = directory.map(String::from);
+
+            self.execute_nested(
+                command,
+                |variables| {
+                    self.command_executor
+                        .execute_plz(&args, working_directory, variables)
+                },
+                delegate_depth(),
+                &dependency_variables,
+            )
+            .map_err(|err| ActionError::Dependency {
+                dependency: dependency.clone(),
+                source: Box::new(err),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes the provided `on_interrupt` [`ActionConfig`] if plz was interrupted while
+    /// executing a command, returning the exit code plz should exit with, or `None` if it wasn't
+    /// interrupted.
+    /// The interrupt is cleared first so the handler's actions can be executed. Interrupting the
+    /// handler will stop it from executing any further actions.
+    pub fn execute_on_interrupt(
+        &self,
+        on_interrupt: Option<&ActionConfig>,
+        variables: &VariableMap,
+    ) -> Result<Option<i32>, ActionError> {
+        if !interrupt::is_interrupted() {
+            return Ok(None);
         }
+
+        interrupt::clear();
+        if let Some(on_interrupt) = on_interrupt {
+            self.execute(on_interrupt, variables)?;
+        }
+
+        Ok(Some(interrupt::INTERRUPTED_EXIT_CODE))
+    }
+
+    /// Executes the provided `on_failure` [`ActionConfig`] after `action_config` has failed with
+    /// `error`. The name (or index) of the failing action and its exit code are made available to
+    /// the handler as the `PLZ_FAILED_STEP` and `PLZ_EXIT_CODE` variables.
+    pub fn execute_on_failure(
+        &self,
+        on_failure: &ActionConfig,
+        action_config: &ActionConfig,
+        error: &ActionError,
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        let handler_variables = failure_variables(action_config, error, variables);
+        self.execute(on_failure, &handler_variables)
     }
 
     fn execute_actions(
         &self,
-        exec_configs: Vec<ExecutionConfigVariant>,
+        steps: &[StepConfig],
         variables: &VariableMap,
     ) -> Result<(), ActionError> {
-        for (idx, execution_config) in exec_configs.iter().enumerate() {
-            let result = self.command_executor.execute(&execution_config, &variables);
+        let mut step_results = StepResultMap::new();
+
+        for (idx, step) in steps.iter().enumerate() {
+            // Don't start any more actions once the user has asked to stop.
+            if interrupt::is_interrupted() {
+                return Err(ActionError::Interrupted { index: idx });
+            }
+
+            if let Some(condition) = &step.condition {
+                let context = ConditionContext {
+                    steps: &step_results,
+                    variables,
+                };
+
+                let should_execute = conditions::evaluate(condition, &context).map_err(|err| {
+                    ActionError::Condition {
+                        index: idx,
+                        source: err,
+                    }
+                })?;
+
+                if !should_execute {
+                    if let Some(name) = &step.name {
+                        step_results.insert(name.clone(), StepResult::skipped());
+                    }
+
+                    self.print_step_header(step, &StepEvent::Skipped);
+                    continue;
+                }
+            }
+
+            // Steps can be executed somewhere other than the local machine (e.g. a container)
+            let step_command_executor = step
+                .executor
+                .as_ref()
+                .map(|executor| self.command_executor.with_executor(executor));
+            let command_executor = step_command_executor
+                .as_deref()
+                .unwrap_or(self.command_executor.as_ref());
+
+            // Steps can filter their output before it's displayed or captured
+            let filtered_command_executor = if step.filters.is_empty() {
+                None
+            } else {
+                let output_filter =
+                    OutputFilter::new(&step.filters).map_err(|err| ActionError::OutputFilter {
+                        index: idx,
+                        source: err,
+                    })?;
+                Some(command_executor.with_output_filter(output_filter))
+            };
+            let command_executor = filtered_command_executor
+                .as_deref()
+                .unwrap_or(command_executor);
+
+            // Steps can add or override variables without affecting the other steps
+            let step_variables = step_variables(step, variables);
+            let variables = step_variables.as_ref().unwrap_or(variables);
+
+            // Steps can write their output to files instead of the terminal
+            let redirected_command_executor =
+                if step.stdout_file.is_none() && step.stderr_file.is_none() {
+                    None
+                } else {
+                    let redirected_command_executor = redirect_output(
+                        command_executor,
+                        step.stdout_file.as_deref(),
+                        step.stderr_file.as_deref(),
+                        variables,
+                    )
+                    .map_err(|err| ActionError::OutputFiles {
+                        index: idx,
+                        source: err,
+                    })?;
+                    Some(redirected_command_executor)
+                };
+            let command_executor = redirected_command_executor
+                .as_deref()
+                .unwrap_or(command_executor);
+
+            self.print_step_header(step, &StepEvent::Started);
+            if let Some(description) = &step.description {
+                command_executor.describe_step(description);
+            }
+            let started = Instant::now();
+
+            // Named steps have their output captured so it can be referenced in later conditions.
+            let result = if let Some(name) = &step.name {
+                command_executor
+                    .execute_and_capture(&step.execution, variables)
+                    .map(|output| {
+                        step_results.insert(
+                            name.clone(),
+                            StepResult {
+                                status: Some(output.status.clone()),
+                                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+                            },
+                        );
+                        output.status
+                    })
+            } else {
+                command_executor.execute(&step.execution, variables)
+            };
+
+            match &result {
+                Ok(ExitStatus::Success) => {
+                    self.print_step_header(step, &StepEvent::Succeeded(started.elapsed()))
+                }
+                Ok(status) => self.print_step_header(step, &StepEvent::Failed(Some(status))),
+                Err(_) => self.print_step_header(step, &StepEvent::Failed(None)),
+            }
 
             match result {
                 Ok(status) => {
                     match status {
                         ExitStatus::Success => continue,
+                        _ if step.continue_on_error => continue,
 
                         // Re-map non-zero exit codes to errors
                         _ => return Err(ActionError::StatusCode { index: idx, status }),
@@ -59,22 +332,95 @@ impl ActionExecutor {
         return Ok(());
     }
 
+    /// Prints a header for the provided step if step headers are enabled.
+    fn print_step_header(&self, step: &StepConfig, event: &StepEvent) {
+        if let Some(symbols) = self.step_symbols {
+            let label = step
+                .description
+                .as_deref()
+                .or(step.name.as_deref())
+                .unwrap_or_else(|| step.execution.command());
+            step_headers::print(symbols, label, event);
+        }
+    }
+
+    fn execute_session(
+        &self,
+        steps: &[StepConfig],
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        // Steps are executed by a single shell, so plz can't evaluate anything between them.
+        if let Some(idx) = steps.iter().position(|step| {
+            step.condition.is_some()
+                || step.continue_on_error
+                || step.executor.is_some()
+                || !step.env.is_empty()
+                || !step.filters.is_empty()
+                || step.stdout_file.is_some()
+                || step.stderr_file.is_some()
+        }) {
+            return Err(ActionError::UnsupportedInSession { index: idx });
+        }
+
+        let exec_configs: Vec<ExecutionConfigVariant> =
+            steps.iter().map(|step| step.execution.clone()).collect();
+
+        let result = self
+            .command_executor
+            .execute_session(&exec_configs, variables)
+            .map_err(|err| ActionError::Execution {
+                index: 0,
+                source: err,
+            })?;
+
+        // Re-map non-zero exit codes to errors
+        if let Some((index, status)) = result {
+            return Err(ActionError::StatusCode { index, status });
+        }
+
+        Ok(())
+    }
+
+    fn execute_services(
+        &self,
+        services_action: &ServicesActionConfig,
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        let services: Vec<Service> = services_action
+            .services
+            .iter()
+            .map(|(name, service)| Service {
+                name: name.clone(),
+                execution: service.execution.clone(),
+                variables: env_variables(&service.env, variables)
+                    .unwrap_or_else(|| variables.clone()),
+                restart: service.restart,
+                watch: service.watch.clone(),
+            })
+            .collect();
+
+        let result = self
+            .command_executor
+            .execute_services(&services, services_action.output)
+            .map_err(|err| ActionError::Execution {
+                index: 0,
+                source: err,
+            })?;
+
+        match result {
+            Some((service, status)) if status != ExitStatus::Success => {
+                Err(ActionError::Service { service, status })
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn execute_alias(
         &self,
         alias_action_config: &AliasActionConfig,
         variables: &VariableMap,
     ) -> Result<(), ActionError> {
-        // Replace variables in the alias text
-        let alias_text = substitute_variables(alias_action_config.alias.as_str(), variables);
-
-        // Get the args and append them to the alias
-        let command_text =
-            if let Some(args) = self.arg_resolver.get_many(&ALIAS_ARGS_NAME.to_string()) {
-                let joined_args: String = args.join(" ");
-                format!("{} {}", alias_text, joined_args)
-            } else {
-                alias_text
-            };
+        let command_text = self.alias_command_text(alias_action_config, variables);
 
         // Execute it!
         let exec = ExecutionConfigVariant::RawCommand(Shorthand(command_text));
@@ -87,6 +433,258 @@ impl ActionExecutor {
 
         return Ok(());
     }
+
+    /// Executes another plz command, forwarding any trailing arguments to it.
+    /// `depth` is the number of delegate actions that led to the current process.
+    fn execute_delegate(
+        &self,
+        delegate_action_config: &DelegateActionConfig,
+        depth: usize,
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        check_depth(&delegate_action_config.delegate, depth)?;
+
+        let mut args = self.forwarded_args.args(true);
+        args.extend(self.delegate_args(delegate_action_config, variables));
+        self.execute_nested(
+            &delegate_action_config.delegate,
+            |variables| self.command_executor.execute_plz(&args, None, variables),
+            depth,
+            variables,
+        )
+    }
+
+    /// Executes another plz process using `execute`, which is given the variables to execute it
+    /// with.
+    /// `command` is the path of the command it executes, and `depth` is the number of delegate
+    /// actions and dependencies that led to the current process.
+    fn execute_nested(
+        &self,
+        command: &str,
+        execute: impl FnOnce(&VariableMap) -> ExecutionResult,
+        depth: usize,
+        variables: &VariableMap,
+    ) -> Result<(), ActionError> {
+        check_depth(command, depth)?;
+
+        let mut nested_variables = variables.clone();
+        nested_variables.insert(
+            DELEGATE_DEPTH_VARIABLE_NAME.to_string(),
+            (depth + 1).to_string(),
+        );
+
+        let status = execute(&nested_variables).map_err(|err| ActionError::Execution {
+            index: 0,
+            source: err,
+        })?;
+
+        if !matches!(status, ExitStatus::Success) {
+            return Err(ActionError::StatusCode { index: 0, status });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the command text the provided [`ActionConfig`] would execute with the provided
+    /// [`VariableMap`], with any variables substituted.
+    /// Each step of a multi-step action is placed on its own line, regardless of its condition.
+    pub fn command_text(&self, action_config: &ActionConfig, variables: &VariableMap) -> String {
+        match action_config {
+            ActionConfig::SingleStep(single_command_action) => {
+                substitute_variables(single_command_action.action.command(), variables)
+            }
+
+            ActionConfig::MultiStep(multi_command_action) => multi_command_action
+                .actions
+                .iter()
+                .map(|step| {
+                    let step_variables = step_variables(step, variables);
+                    let variables = step_variables.as_ref().unwrap_or(variables);
+                    substitute_variables(step.execution.command(), variables)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+
+            ActionConfig::Alias(alias_action) => self.alias_command_text(alias_action, variables),
+
+            ActionConfig::Delegate(delegate_action) => {
+                format!(
+                    "{} {}",
+                    self.binary_name,
+                    self.delegate_args(delegate_action, variables).join(" ")
+                )
+            }
+
+            ActionConfig::Services(services_action) => services_action
+                .services
+                .iter()
+                .map(|(name, service)| {
+                    let service_variables = env_variables(&service.env, variables);
+                    let variables = service_variables.as_ref().unwrap_or(variables);
+                    format!(
+                        "{}: {}",
+                        name,
+                        substitute_variables(service.execution.command(), variables)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    fn alias_command_text(
+        &self,
+        alias_action_config: &AliasActionConfig,
+        variables: &VariableMap,
+    ) -> String {
+        let args = self.arg_resolver.get_many(&ALIAS_ARGS_NAME.to_string());
+        let alias = alias_action_config.alias.as_str();
+
+        // Replace variables in the alias text, including the args if the alias references them
+        let args_variable = format!("${}", ALIAS_ARGS_VARIABLE_NAME);
+        let places_args = alias.contains(&args_variable)
+            || template::references(alias)
+                .iter()
+                .any(|name| name == template::ARGS_NAME || name == ALIAS_ARGS_VARIABLE_NAME);
+        let mut alias_variables = variables.clone();
+        alias_variables.insert(
+            ALIAS_ARGS_VARIABLE_NAME.to_string(),
+            args.clone().unwrap_or_default().join(" "),
+        );
+        let alias_text = substitute_variables_with_args(
+            alias,
+            &alias_variables,
+            &args.clone().unwrap_or_default(),
+        );
+
+        // Otherwise, append the args to the alias
+        match args {
+            Some(args) if !places_args => format!("{} {}", alias_text, args.join(" ")),
+            _ => alias_text,
+        }
+    }
+
+    /// Returns the arguments for the plz process executed by the provided delegate action: the
+    /// path of the command to execute, followed by any trailing arguments.
+    fn delegate_args(
+        &self,
+        delegate_action_config: &DelegateActionConfig,
+        variables: &VariableMap,
+    ) -> Vec<String> {
+        let delegate_text =
+            substitute_variables(delegate_action_config.delegate.as_str(), variables);
+        let mut args: Vec<String> = delegate_text.split_whitespace().map(String::from).collect();
+        args.extend(
+            self.arg_resolver
+                .get_many(&ALIAS_ARGS_NAME.to_string())
+                .unwrap_or_default(),
+        );
+
+        args
+    }
+}
+
+/// Returns the number of delegate actions and dependencies that led to the current process.
+fn delegate_depth() -> usize {
+    env::var(DELEGATE_DEPTH_VARIABLE_NAME)
+        .ok()
+        .and_then(|depth| depth.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Fails if executing `command` would nest delegate actions and dependencies too deeply.
+fn check_depth(command: &str, depth: usize) -> Result<(), ActionError> {
+    if depth >= MAX_DELEGATE_DEPTH {
+        return Err(ActionError::DelegateDepthExceeded {
+            command: command.to_string(),
+            max_depth: MAX_DELEGATE_DEPTH,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns a copy of `variables` including the environment variables defined by the provided
+/// [`StepConfig`], or `None` if it doesn't define any.
+/// Each value can reference any variable, including those defined earlier in the step's `env`.
+fn step_variables(step: &StepConfig, variables: &VariableMap) -> Option<VariableMap> {
+    env_variables(&step.env, variables)
+}
+
+/// Returns a copy of `variables` with the provided environment variables added, or `None` if
+/// there aren't any. Values can reference other variables.
+fn env_variables(
+    env: &LinkedHashMap<String, String>,
+    variables: &VariableMap,
+) -> Option<VariableMap> {
+    if env.is_empty() {
+        return None;
+    }
+
+    let mut env_variables = variables.clone();
+    for (name, value) in env {
+        let value = substitute_variables(value, &env_variables);
+        env_variables.insert(name.clone(), value);
+    }
+
+    Some(env_variables)
+}
+
+/// Returns a copy of `variables` including the name (or index) of the action in `action_config`
+/// that failed with `error` and its exit code, as the `PLZ_FAILED_STEP` and `PLZ_EXIT_CODE`
+/// variables.
+pub fn failure_variables(
+    action_config: &ActionConfig,
+    error: &ActionError,
+    variables: &VariableMap,
+) -> VariableMap {
+    let index = match error {
+        ActionError::StatusCode { index, .. }
+        | ActionError::Execution { index, .. }
+        | ActionError::Condition { index, .. }
+        | ActionError::UnsupportedInSession { index }
+        | ActionError::OutputFilter { index, .. }
+        | ActionError::OutputFiles { index, .. }
+        | ActionError::Interrupted { index } => *index,
+        ActionError::DelegateDepthExceeded { .. }
+        | ActionError::Dependency { .. }
+        | ActionError::Service { .. } => 0,
+    };
+
+    let step_name = match (error, action_config) {
+        (ActionError::Dependency { dependency, .. }, _) => Some(dependency.clone()),
+        (ActionError::Service { service, .. }, _) => Some(service.clone()),
+        (_, ActionConfig::MultiStep(multi_command_action)) => multi_command_action
+            .actions
+            .get(index)
+            .and_then(|step| step.name.clone()),
+        _ => None,
+    }
+    .unwrap_or(index.to_string());
+
+    let mut failure_variables = variables.clone();
+    failure_variables.insert(FAILED_STEP_VARIABLE_NAME.to_string(), step_name);
+    failure_variables.insert(
+        EXIT_CODE_VARIABLE_NAME.to_string(),
+        error
+            .exit_code()
+            .map(|code| code.to_string())
+            .unwrap_or_default(),
+    );
+
+    failure_variables
+}
+
+/// Creates a [`CommandExecutor`] which writes stdout and stderr to the provided files, after
+/// substituting variables into their paths.
+pub fn redirect_output(
+    command_executor: &dyn CommandExecutor,
+    stdout_file: Option<&str>,
+    stderr_file: Option<&str>,
+    variables: &VariableMap,
+) -> Result<Box<dyn CommandExecutor>, ExecutionError> {
+    let substitute = |path: &str| PathBuf::from(substitute_variables(path, variables));
+    command_executor.with_output_files(stdout_file.map(substitute), stderr_file.map(substitute))
 }
 
 #[derive(Error, Debug)]
@@ -100,6 +698,54 @@ pub enum ActionError {
     // TODO: Reconsider whether a non-zero exit codes should be treated as errors
     #[error("failed to execute action {index}: {status}")]
     StatusCode { index: usize, status: ExitStatus },
+
+    #[error("failed to evaluate condition for action {index}")]
+    Condition {
+        index: usize,
+        source: ConditionError,
+    },
+
+    #[error(
+        "action {index} uses a condition, continue_on_error, executor, env, filters, or output files, which are not supported in sessions"
+    )]
+    UnsupportedInSession { index: usize },
+
+    #[error("invalid output filter for action {index}")]
+    OutputFilter { index: usize, source: FilterError },
+
+    #[error("failed to open the output files for action {index}")]
+    OutputFiles {
+        index: usize,
+        source: ExecutionError,
+    },
+
+    #[error("interrupted before executing action {index}")]
+    Interrupted { index: usize },
+
+    #[error("could not execute \"{command}\", delegate actions and dependencies can only be nested {max_depth} times")]
+    DelegateDepthExceeded { command: String, max_depth: usize },
+
+    #[error("service {service} stopped: {status}")]
+    Service { service: String, status: ExitStatus },
+
+    #[error("dependency {dependency} failed")]
+    Dependency {
+        dependency: String,
+        source: Box<ActionError>, // Need to box this so the size isn't infinite
+    },
+}
+
+impl ActionError {
+    /// Returns the exit code of the step that failed, if it ran to completion.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            ActionError::StatusCode { status, .. } | ActionError::Service { status, .. } => {
+                status.code()
+            }
+            ActionError::Dependency { source, .. } => source.exit_code(),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -107,11 +753,23 @@ mod tests {
     use super::*;
     use crate::{
         args::MockArgumentResolver,
-        config::{MultiActionConfig, RawCommandConfigVariant, SingleActionConfig},
-        exec::MockCommandExecutor,
+        config::Options,
+        config::{
+            BashCommandConfig, DockerExecutorConfig, ExecutorConfig, MultiActionConfig,
+            RawCommandConfigVariant, ShellCommandConfigVariant, SingleActionConfig,
+        },
+        exec::{create_command_executor, MockCommandExecutor, Output},
     };
+    use linked_hash_map::LinkedHashMap;
     use mockall::{predicate::eq, Sequence};
 
+    /// The name of the variable containing the file [`record_dependency`] records to.
+    const DEPENDENCY_TEST_VARIABLE: &str = "PLZ_TEST_DEPENDENCY_RECORD";
+
+    /// The name of the variable used to run [`interrupt_command`].
+    #[cfg(unix)]
+    const INTERRUPT_TEST_VARIABLE: &str = "PLZ_TEST_INTERRUPT_COMMAND";
+
     #[test]
     fn execute_single_step() {
         // Arrange
@@ -145,6 +803,9 @@ mod tests {
         let action_executor = ActionExecutor {
             command_executor: Box::new(command_executor),
             arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
         };
 
         let result = action_executor.execute(&action, &variables.clone());
@@ -190,19 +851,26 @@ mod tests {
             actions: vec![
                 ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
                     command_text_1.to_string(),
-                )),
+                ))
+                .into(),
                 ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
                     command_text_2.to_string(),
-                )),
+                ))
+                .into(),
                 ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
                     command_text_3.to_string(),
-                )),
+                ))
+                .into(),
             ],
+            session: false,
         });
 
         let action_executor = ActionExecutor {
             command_executor: Box::new(command_executor),
             arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
         };
 
         let result = action_executor.execute(&action, &variables.clone());
@@ -212,46 +880,962 @@ mod tests {
     }
 
     #[test]
-    fn execute_alias() {
+    fn execute_multi_step_session() {
         // Arrange
-        let mut variables = VariableMap::new();
-        variables.insert("name".to_string(), "Alice".to_string());
+        let variables = VariableMap::new();
 
-        let command_text = "docker compose";
+        let actions = vec![
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "export NAME=Alice".to_string(),
+            )),
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "exit 3".to_string(),
+            )),
+        ];
 
         let mut command_executor = MockCommandExecutor::new();
+        command_executor.expect_execute().times(0);
         command_executor
-            .expect_execute()
-            .times(1)
-            .with(
-                eq(ExecutionConfigVariant::RawCommand(
-                    RawCommandConfigVariant::Shorthand("docker compose up -d".to_string()),
-                )),
-                eq(variables.clone()),
-            )
-            .returning(|_, _| Ok(ExitStatus::Success));
+            .expect_execute_session()
+            .once()
+            .with(eq(actions.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(Some((1, ExitStatus::Fail(3)))));
 
-        let alias_text = "up -d";
         let mut arg_resolver = MockArgumentResolver::new();
-        arg_resolver
-            .expect_get_many()
-            .with(eq(ALIAS_ARGS_NAME.to_string()))
-            .once()
-            .returning(|_| Some(vec![alias_text.to_string()]));
+        arg_resolver.expect_get_many().times(0).returning(|_| None);
 
         // Act
-        let action = ActionConfig::Alias(AliasActionConfig {
-            alias: command_text.to_string(),
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: actions.iter().cloned().map(StepConfig::from).collect(),
+            session: true,
         });
 
         let action_executor = ActionExecutor {
             command_executor: Box::new(command_executor),
             arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
         };
 
-        let result = action_executor.execute(&action, &variables.clone());
+        let result = action_executor.execute(&action, &variables);
 
         // Assert
-        assert!(result.is_ok())
+        assert!(matches!(
+            result,
+            Err(ActionError::StatusCode {
+                index: 1,
+                status: ExitStatus::Fail(3)
+            })
+        ));
+    }
+
+    #[test]
+    fn execute_multi_step_with_conditions() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let check = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "git status --porcelain".to_string(),
+        ));
+        let clean = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "echo clean".to_string(),
+        ));
+        let dirty = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "echo dirty".to_string(),
+        ));
+
+        let mut seq = Sequence::new();
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute_and_capture()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(check.clone()), eq(variables.clone()))
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Fail(1),
+                    stdout: "M src/main.rs\n".as_bytes().to_vec(),
+                    stderr: vec![],
+                })
+            });
+        command_executor
+            .expect_execute()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(dirty.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver.expect_get_many().times(0).returning(|_| None);
+
+        // Act
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                StepConfig {
+                    name: Some("check".to_string()),
+                    description: None,
+                    condition: None,
+                    continue_on_error: true,
+                    executor: None,
+                    env: Default::default(),
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: check,
+                },
+                StepConfig {
+                    name: None,
+                    description: None,
+                    condition: Some("steps.check.stdout == \"\"".to_string()),
+                    continue_on_error: false,
+                    executor: None,
+                    env: Default::default(),
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: clean,
+                },
+                StepConfig {
+                    name: None,
+                    description: None,
+                    condition: Some(
+                        "steps.check.failure && steps.check.stdout contains \"main.rs\""
+                            .to_string(),
+                    ),
+                    continue_on_error: false,
+                    executor: None,
+                    env: Default::default(),
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: dirty,
+                },
+            ],
+            session: false,
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_multi_step_with_step_env() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("level".to_string(), "debug".to_string());
+
+        let mut step_variables = variables.clone();
+        step_variables.insert("RUST_LOG".to_string(), "debug".to_string());
+
+        let test = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "cargo test".to_string(),
+        ));
+        let build = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "cargo build".to_string(),
+        ));
+
+        let mut seq = Sequence::new();
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(test.clone()), eq(step_variables))
+            .returning(|_, _| Ok(ExitStatus::Success));
+        command_executor
+            .expect_execute()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(build.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let mut env = LinkedHashMap::new();
+        env.insert("RUST_LOG".to_string(), "$level".to_string());
+
+        // Act
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                StepConfig {
+                    name: None,
+                    description: None,
+                    condition: None,
+                    continue_on_error: false,
+                    executor: None,
+                    env,
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: test,
+                },
+                StepConfig::from(build),
+            ],
+            session: false,
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_multi_step_describes_steps_with_descriptions() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let migrate = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "docker compose run --rm api ./manage.py migrate --noinput".to_string(),
+        ));
+        let build = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "cargo build".to_string(),
+        ));
+
+        let mut seq = Sequence::new();
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_describe_step()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq("Run the database migrations"))
+            .return_const(());
+        command_executor
+            .expect_execute()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(migrate.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+        command_executor
+            .expect_execute()
+            .once()
+            .in_sequence(&mut seq)
+            .with(eq(build.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        // Act
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                StepConfig {
+                    description: Some("Run the database migrations".to_string()),
+                    ..StepConfig::from(migrate)
+                },
+                StepConfig::from(build),
+            ],
+            session: false,
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn command_text_substitutes_variables_in_each_step() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("level".to_string(), "debug".to_string());
+
+        let mut env = LinkedHashMap::new();
+        env.insert("RUST_LOG".to_string(), "$level".to_string());
+
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                StepConfig {
+                    name: None,
+                    description: None,
+                    condition: None,
+                    continue_on_error: false,
+                    executor: None,
+                    env,
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: ExecutionConfigVariant::RawCommand(
+                        RawCommandConfigVariant::Shorthand(
+                            "cargo test --log $RUST_LOG".to_string(),
+                        ),
+                    ),
+                },
+                StepConfig::from(ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::Shorthand("cargo build --log $level".to_string()),
+                )),
+            ],
+            session: false,
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let command_text = action_executor.command_text(&action, &variables);
+
+        // Assert
+        assert_eq!(
+            command_text,
+            "cargo test --log debug\ncargo build --log debug"
+        );
+    }
+
+    #[test]
+    fn command_text_uses_binary_name_for_delegate() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("target".to_string(), "staging".to_string());
+
+        let action = ActionConfig::Delegate(DelegateActionConfig {
+            delegate: "deploy $target".to_string(),
+        });
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver
+            .expect_get_many()
+            .with(eq(ALIAS_ARGS_NAME.to_string()))
+            .returning(|_| Some(vec!["--force".to_string()]));
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "acme".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let command_text = action_executor.command_text(&action, &variables);
+
+        // Assert
+        assert_eq!(command_text, "acme deploy staging --force");
+    }
+
+    #[test]
+    fn failure_variables_include_failed_step() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("env".to_string(), "prod".to_string());
+
+        let action = ActionConfig::SingleStep(SingleActionConfig {
+            action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "./deploy.sh".to_string(),
+            )),
+        });
+        let error = ActionError::StatusCode {
+            index: 0,
+            status: ExitStatus::Fail(3),
+        };
+
+        // Act
+        let failure_variables = failure_variables(&action, &error, &variables);
+
+        // Assert
+        assert_eq!(failure_variables["env"], "prod");
+        assert_eq!(failure_variables[FAILED_STEP_VARIABLE_NAME], "0");
+        assert_eq!(failure_variables[EXIT_CODE_VARIABLE_NAME], "3");
+    }
+
+    #[test]
+    fn execute_uses_step_executor() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let local = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "docker compose up -d".to_string(),
+        ));
+        let containerised = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "cargo test".to_string(),
+        ));
+        let executor_config = ExecutorConfig::Docker(DockerExecutorConfig {
+            docker: "app".to_string(),
+        });
+
+        let mut docker_command_executor = MockCommandExecutor::new();
+        docker_command_executor
+            .expect_execute()
+            .once()
+            .with(eq(containerised.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+        let mut docker_command_executor = Some(docker_command_executor);
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .once()
+            .with(eq(local.clone()), eq(variables.clone()))
+            .returning(|_, _| Ok(ExitStatus::Success));
+        command_executor
+            .expect_with_executor()
+            .once()
+            .with(eq(executor_config.clone()))
+            .returning(move |_| Box::new(docker_command_executor.take().unwrap()));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver.expect_get_many().times(0).returning(|_| None);
+
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                local.into(),
+                StepConfig {
+                    name: None,
+                    description: None,
+                    condition: None,
+                    continue_on_error: false,
+                    executor: Some(executor_config),
+                    env: Default::default(),
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: containerised,
+                },
+            ],
+            session: false,
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_on_failure_receives_failed_step() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let mut expected_variables = variables.clone();
+        expected_variables.insert(FAILED_STEP_VARIABLE_NAME.to_string(), "build".to_string());
+        expected_variables.insert(EXIT_CODE_VARIABLE_NAME.to_string(), "2".to_string());
+
+        let on_failure_exec = ExecutionConfigVariant::RawCommand(
+            RawCommandConfigVariant::Shorthand("docker compose logs".to_string()),
+        );
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .once()
+            .with(eq(on_failure_exec.clone()), eq(expected_variables))
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver.expect_get_many().times(0).returning(|_| None);
+
+        let action = ActionConfig::MultiStep(MultiActionConfig {
+            actions: vec![
+                ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                    "docker compose up -d".to_string(),
+                ))
+                .into(),
+                StepConfig {
+                    name: Some("build".to_string()),
+                    description: None,
+                    condition: None,
+                    continue_on_error: false,
+                    executor: None,
+                    env: Default::default(),
+                    filters: Vec::new(),
+                    stdout_file: None,
+                    stderr_file: None,
+                    execution: ExecutionConfigVariant::RawCommand(
+                        RawCommandConfigVariant::Shorthand("make".to_string()),
+                    ),
+                },
+            ],
+            session: false,
+        });
+        let on_failure = ActionConfig::SingleStep(SingleActionConfig {
+            action: on_failure_exec,
+        });
+        let error = ActionError::StatusCode {
+            index: 1,
+            status: ExitStatus::Fail(2),
+        };
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let result = action_executor.execute_on_failure(&on_failure, &action, &error, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_alias() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("name".to_string(), "Alice".to_string());
+
+        let command_text = "docker compose";
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .times(1)
+            .with(
+                eq(ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::Shorthand("docker compose up -d".to_string()),
+                )),
+                eq(variables.clone()),
+            )
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let alias_text = "up -d";
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver
+            .expect_get_many()
+            .with(eq(ALIAS_ARGS_NAME.to_string()))
+            .once()
+            .returning(|_| Some(vec![alias_text.to_string()]));
+
+        // Act
+        let action = ActionConfig::Alias(AliasActionConfig {
+            alias: command_text.to_string(),
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables.clone());
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_alias_places_args_where_referenced() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("project".to_string(), "deps".to_string());
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .times(1)
+            .with(
+                eq(ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::Shorthand(
+                        "docker compose up -d --project-name deps".to_string(),
+                    ),
+                )),
+                eq(variables.clone()),
+            )
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver
+            .expect_get_many()
+            .with(eq(ALIAS_ARGS_NAME.to_string()))
+            .once()
+            .returning(|_| Some(vec!["up".to_string(), "-d".to_string()]));
+
+        // Act
+        let action = ActionConfig::Alias(AliasActionConfig {
+            alias: "docker compose $PLZ_ARGS --project-name $project".to_string(),
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_alias_places_args_where_referenced_by_tags() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute()
+            .times(1)
+            .with(
+                eq(ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::Shorthand("echo [up,-d] [up -d] end".to_string()),
+                )),
+                eq(variables.clone()),
+            )
+            .returning(|_, _| Ok(ExitStatus::Success));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver
+            .expect_get_many()
+            .with(eq(ALIAS_ARGS_NAME.to_string()))
+            .once()
+            .returning(|_| Some(vec!["up".to_string(), "-d".to_string()]));
+
+        // Act
+        let action = ActionConfig::Alias(AliasActionConfig {
+            alias: "echo [{{ args | join(sep=\",\") }}] [{{ plz.args }}] end".to_string(),
+        });
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        let result = action_executor.execute(&action, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_delegate() {
+        // Arrange
+        let variables = VariableMap::new();
+
+        let mut expected_variables = variables.clone();
+        expected_variables.insert(DELEGATE_DEPTH_VARIABLE_NAME.to_string(), "2".to_string());
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_execute_plz()
+            .times(1)
+            .with(
+                eq(vec![
+                    "--config".to_string(),
+                    "/work/plz.yaml".to_string(),
+                    "--env".to_string(),
+                    "staging".to_string(),
+                    "deploy".to_string(),
+                    "staging".to_string(),
+                    "--message".to_string(),
+                    "fix the build".to_string(),
+                ]),
+                eq(None),
+                eq(expected_variables),
+            )
+            .returning(|_, _, _| Ok(ExitStatus::Success));
+
+        let mut arg_resolver = MockArgumentResolver::new();
+        arg_resolver
+            .expect_get_many()
+            .with(eq(ALIAS_ARGS_NAME.to_string()))
+            .once()
+            .returning(|_| Some(vec!["--message".to_string(), "fix the build".to_string()]));
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(arg_resolver),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs {
+                config_paths: vec![PathBuf::from("/work/plz.yaml")],
+                environment: Some("staging".to_string()),
+            },
+        };
+
+        // Act
+        let delegate_action = DelegateActionConfig {
+            delegate: "deploy staging".to_string(),
+        };
+        let result = action_executor.execute_delegate(&delegate_action, 1, &variables);
+
+        // Assert
+        assert!(result.is_ok())
+    }
+
+    #[test]
+    fn execute_dependencies_executes_commands_in_their_directories() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("lib".to_string(), "shared-lib".to_string());
+
+        let mut command_executor = MockCommandExecutor::new();
+        let mut seq = Sequence::new();
+        command_executor
+            .expect_execute_plz()
+            .once()
+            .in_sequence(&mut seq)
+            .withf(|args, working_directory, variables| {
+                args == ["--env", "staging", "build"]
+                    && working_directory.as_deref() == Some("../shared-lib")
+                    && variables.get(CONFIG_PATH_VARIABLE_NAME) == Some(&String::new())
+            })
+            .returning(|_, _, _| Ok(ExitStatus::Success));
+        command_executor
+            .expect_execute_plz()
+            .once()
+            .in_sequence(&mut seq)
+            .withf(|args, working_directory, variables| {
+                args == [
+                    "--config",
+                    "/work/plz.yaml",
+                    "--env",
+                    "staging",
+                    "db:migrate",
+                    "--seed",
+                ] && working_directory.is_none()
+                    && !variables.contains_key(CONFIG_PATH_VARIABLE_NAME)
+            })
+            .returning(|_, _, _| Ok(ExitStatus::Fail(2)));
+
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(command_executor),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs {
+                config_paths: vec![PathBuf::from("/work/plz.yaml")],
+                environment: Some("staging".to_string()),
+            },
+        };
+
+        // Act
+        let result = action_executor.execute_dependencies(
+            &[
+                "../$lib:build".to_string(),
+                "db:migrate --seed".to_string(),
+                "never".to_string(),
+            ],
+            &variables,
+        );
+
+        // Assert
+        let err = result.unwrap_err();
+        assert!(matches!(
+            &err,
+            ActionError::Dependency { dependency, .. } if dependency == "db:migrate --seed"
+        ));
+        assert_eq!(err.exit_code(), Some(2));
+    }
+
+    /// Executes an action which interrupts plz, followed by its `on_interrupt` action, then exits
+    /// with the exit code plz would exit with.
+    /// Interrupts affect the whole process, so this is only run in a separate process by
+    /// [`execute_on_interrupt_runs_handler_after_interrupt`].
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn interrupt_command() {
+        if env::var(INTERRUPT_TEST_VARIABLE).is_err() {
+            return;
+        }
+
+        interrupt::install_handler().unwrap();
+        let action_executor = ActionExecutor {
+            command_executor: create_command_executor(&Options::default()),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+        let bash = |command: String| {
+            ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+                    BashCommandConfig {
+                        working_directory: None,
+                        command,
+                    },
+                )),
+            })
+        };
+
+        // Sent to plz alone, as if the command had been interrupted from another terminal
+        let action = bash(format!("kill -INT {} && sleep 1", std::process::id()));
+        let on_interrupt = bash("echo cleaned up".to_string());
+
+        let result = action_executor.execute(&action, &VariableMap::new());
+        let exit_code = action_executor
+            .execute_on_interrupt(Some(&on_interrupt), &VariableMap::new())
+            .unwrap();
+
+        assert!(result.is_ok());
+        std::process::exit(exit_code.unwrap_or(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn execute_on_interrupt_runs_handler_after_interrupt() {
+        // Act
+        let output = std::process::Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "actions::tests::interrupt_command",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(INTERRUPT_TEST_VARIABLE, "1")
+            .output()
+            .unwrap();
+
+        // Assert
+        assert_eq!(output.status.code(), Some(interrupt::INTERRUPTED_EXIT_CODE));
+        assert!(String::from_utf8_lossy(&output.stdout).contains("cleaned up"));
+    }
+
+    #[test]
+    fn execute_on_interrupt_does_nothing_without_interrupt() {
+        // Arrange
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+        let on_interrupt = ActionConfig::SingleStep(SingleActionConfig {
+            action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "./cleanup.sh".to_string(),
+            )),
+        });
+
+        // Act
+        let result = action_executor.execute_on_interrupt(Some(&on_interrupt), &VariableMap::new());
+
+        // Assert
+        assert!(matches!(result, Ok(None)));
+    }
+
+    /// Records the arguments, working directory, and delegate depth of the process it's run by to
+    /// the file named by [`DEPENDENCY_TEST_VARIABLE`].
+    /// Only run by [`execute_dependencies_executes_the_current_executable`].
+    #[test]
+    #[ignore]
+    fn record_dependency() {
+        let Ok(output_path) = env::var(DEPENDENCY_TEST_VARIABLE) else {
+            return;
+        };
+
+        let record = format!(
+            "{}\n{}\n{}",
+            env::args().skip(1).collect::<Vec<_>>().join(" "),
+            env::current_dir().unwrap().display(),
+            env::var(DELEGATE_DEPTH_VARIABLE_NAME).unwrap(),
+        );
+        std::fs::write(output_path, record).unwrap();
+    }
+
+    #[test]
+    fn execute_dependencies_executes_the_current_executable() {
+        // Arrange
+        // The test binary stands in for plz, and runs a test which records how it was executed
+        let directory = tempfile::tempdir().unwrap();
+        let directory = directory.path().canonicalize().unwrap();
+        let output_path = directory.join("record");
+
+        let mut variables = VariableMap::new();
+        variables.insert(
+            DEPENDENCY_TEST_VARIABLE.to_string(),
+            output_path.to_string_lossy().to_string(),
+        );
+
+        let action_executor = ActionExecutor {
+            command_executor: create_command_executor(&Options::default()),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let result = action_executor.execute_dependencies(
+            &[format!(
+                "{}:--exact actions::tests::record_dependency --ignored --test-threads=1",
+                directory.display()
+            )],
+            &variables,
+        );
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(
+            std::fs::read_to_string(&output_path).unwrap(),
+            format!(
+                "--exact actions::tests::record_dependency --ignored --test-threads=1\n{}\n1",
+                directory.display()
+            )
+        );
+    }
+
+    #[test]
+    fn execute_delegate_fails_when_nested_too_deeply() {
+        // Arrange
+        let action_executor = ActionExecutor {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            arg_resolver: Box::new(MockArgumentResolver::new()),
+            step_symbols: None,
+            binary_name: "plz".to_string(),
+            forwarded_args: ForwardedArgs::default(),
+        };
+
+        // Act
+        let delegate_action = DelegateActionConfig {
+            delegate: "d".to_string(),
+        };
+        let result = action_executor.execute_delegate(
+            &delegate_action,
+            MAX_DELEGATE_DEPTH,
+            &VariableMap::new(),
+        );
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(ActionError::DelegateDepthExceeded { .. })
+        ));
     }
 }
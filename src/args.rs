@@ -1,8 +1,16 @@
+use clap::parser::ValueSource;
 use clap::ArgMatches;
 use mockall::automock;
+use std::sync::Arc;
 
 pub const ALIAS_ARGS_NAME: &str = "ARGS";
 
+/// Returns the ID of the argument used for the named form of a variable that is bound to both a
+/// positional argument and a named argument.
+pub fn flag_arg_id(key: &str) -> String {
+    format!("plz-flag-{}", key)
+}
+
 /// Capable of resolving command-line argument values.
 #[automock]
 pub trait ArgumentResolver {
@@ -13,26 +21,62 @@ pub trait ArgumentResolver {
     /// For a given `key`, this will return `Some(Vec<String>)` with the argument values matching
     /// the key, otherwise `None` is returned.
     fn get_many(&self, key: &String) -> Option<Vec<String>>;
+
+    /// Returns `true` if the argument matching the `key` was provided on the command-line, rather
+    /// than taking its default value.
+    fn was_provided(&self, key: &str) -> bool;
+
+    /// For a given `key`, this will return `Some(bool)` if the argument matching the key is a flag,
+    /// otherwise `None` is returned.
+    fn get_bool(&self, key: &str) -> Option<bool>;
+
+    /// For a given `key`, this will return `Some(u8)` with the number of times the counted flag
+    /// matching the key was provided (e.g. `3` for `-vvv`), otherwise `None` is returned.
+    fn get_count(&self, key: &str) -> Option<u8>;
 }
 
+/// An [`ArgumentResolver`] backed by clap's [`ArgMatches`].
+/// The [`ArgMatches`] are shared so that multiple resolvers can be created without cloning them.
 pub struct ClapArgumentResolver {
-    arg_matches: ArgMatches,
+    arg_matches: Arc<ArgMatches>,
 }
 
 impl ClapArgumentResolver {
-    pub fn from_arg_matches(arg_matches: &ArgMatches) -> ClapArgumentResolver {
-        return ClapArgumentResolver {
-            arg_matches: arg_matches.clone(),
-        };
+    pub fn from_arg_matches(arg_matches: Arc<ArgMatches>) -> ClapArgumentResolver {
+        return ClapArgumentResolver { arg_matches };
+    }
+
+    /// Returns where the value of the argument with the provided ID came from, or `None` if the
+    /// argument doesn't exist.
+    fn value_source(&self, id: &str) -> Option<ValueSource> {
+        // Clap panics when looking up the source of an argument that doesn't exist
+        self.arg_matches.try_contains_id(id).ok()?;
+        self.arg_matches.value_source(id)
     }
 }
 
 impl ArgumentResolver for ClapArgumentResolver {
     fn get(&self, key: &String) -> Option<String> {
-        if let Some(found_value) = self.arg_matches.get_one::<String>(key) {
+        // Variables bound to both a positional and named argument may have been provided using the
+        // named argument
+        if let Ok(Some(found_value)) = self.arg_matches.try_get_one::<String>(&flag_arg_id(key)) {
+            return Some(found_value.clone());
+        }
+
+        // Variables without an argument won't be present in the matches
+        if let Ok(Some(found_value)) = self.arg_matches.try_get_one::<String>(key) {
             return Some(found_value.clone());
         }
 
+        // Flags and counted flags resolve to their value as text (e.g. `true` or `3`)
+        if let Some(count) = self.get_count(key) {
+            return Some(count.to_string());
+        }
+
+        if let Some(flag) = self.get_bool(key) {
+            return Some(flag.to_string());
+        }
+
         return None;
     }
 
@@ -49,6 +93,20 @@ impl ArgumentResolver for ClapArgumentResolver {
 
         return None;
     }
+
+    fn was_provided(&self, key: &str) -> bool {
+        [flag_arg_id(key), key.to_string()]
+            .iter()
+            .any(|id| self.value_source(id) == Some(ValueSource::CommandLine))
+    }
+
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        self.arg_matches.try_get_one::<bool>(key).ok()?.copied()
+    }
+
+    fn get_count(&self, key: &str) -> Option<u8> {
+        self.arg_matches.try_get_one::<u8>(key).ok()?.copied()
+    }
 }
 
 #[cfg(test)]
@@ -67,7 +125,7 @@ mod tests {
             .arg(arg)
             .get_matches_from(vec!["plz", "--name", value]);
 
-        let arg_resolver = ClapArgumentResolver::from_arg_matches(&matches);
+        let arg_resolver = ClapArgumentResolver::from_arg_matches(Arc::new(matches));
 
         // Assert
         let found_value = arg_resolver.get(&"name".to_string());
@@ -87,7 +145,8 @@ mod tests {
         let (subcommand_name, subcommand_matches) = root_matches.subcommand().unwrap();
         assert_eq!(subcommand_name, "greet");
 
-        let arg_resolver = ClapArgumentResolver::from_arg_matches(&subcommand_matches);
+        let arg_resolver =
+            ClapArgumentResolver::from_arg_matches(Arc::new(subcommand_matches.clone()));
 
         // Assert
         let found_value = arg_resolver.get(&"name".to_string());
@@ -110,7 +169,8 @@ mod tests {
         let (subcommand_name, subcommand_matches) = root_matches.subcommand().unwrap();
         assert_eq!(subcommand_name, "print");
 
-        let arg_resolver = ClapArgumentResolver::from_arg_matches(&subcommand_matches);
+        let arg_resolver =
+            ClapArgumentResolver::from_arg_matches(Arc::new(subcommand_matches.clone()));
 
         // Assert
         let found_file_names = arg_resolver.get_many(&"file".to_string());
@@ -120,6 +180,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn argresolver_distinguishes_provided_args_from_defaults() {
+        // Arrange
+        let command = Command::new("plz")
+            .arg(single_arg(&"env".to_string()).default_value("dev"))
+            .arg(single_arg(&"region".to_string()).default_value("eu"))
+            .arg(
+                Arg::new("dry-run")
+                    .long("dry-run")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("verbose").short('v').action(ArgAction::Count));
+
+        // Act
+        let matches = command.get_matches_from(vec!["plz", "--env", "prod", "-vvv"]);
+        let arg_resolver = ClapArgumentResolver::from_arg_matches(Arc::new(matches));
+
+        // Assert
+        assert!(arg_resolver.was_provided("env"));
+        assert!(!arg_resolver.was_provided("region"));
+        assert!(!arg_resolver.was_provided("missing"));
+        assert_eq!(
+            arg_resolver.get(&"region".to_string()),
+            Some("eu".to_string())
+        );
+        assert_eq!(arg_resolver.get_bool("dry-run"), Some(false));
+        assert_eq!(arg_resolver.get_count("verbose"), Some(3));
+        assert_eq!(arg_resolver.get_count("missing"), None);
+        assert_eq!(
+            arg_resolver.get(&"verbose".to_string()),
+            Some("3".to_string())
+        );
+    }
+
     fn single_arg(name: &String) -> Arg {
         return Arg::new(name.clone())
             .long(name.clone())
@@ -0,0 +1,179 @@
+use crate::mask::mask;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("failed to open the audit log at {path}")]
+    OpenFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to write to the audit log at {path}")]
+    WriteFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A record of a command being executed.
+#[derive(Serialize, PartialEq, Debug)]
+pub struct Receipt {
+    pub user: Option<String>,
+    pub host: Option<String>,
+
+    /// When the command finished executing, in seconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// The full path of the command (e.g. `deploy store`).
+    pub command: String,
+
+    /// The command-line arguments, with any sensitive values masked.
+    pub args: Vec<String>,
+
+    /// The exit code of the command, or `None` if it failed without one (e.g. it couldn't be
+    /// started).
+    pub exit_code: Option<i32>,
+}
+
+impl Receipt {
+    /// Creates a [`Receipt`] for the current user and host, masking the sensitive values within
+    /// the provided arguments.
+    pub fn new(command: String, args: &[String], exit_code: Option<i32>) -> Receipt {
+        Receipt {
+            user: current_user(),
+            host: current_host(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            command,
+            args: args.iter().map(|arg| mask(arg)).collect(),
+            exit_code,
+        }
+    }
+}
+
+/// An append-only log of [`Receipt`]s, stored as JSON lines.
+pub struct AuditLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl AuditLog {
+    /// Opens the audit log at the provided path, creating it if necessary.
+    /// The log is opened before the command is executed so a command is never executed without a
+    /// receipt being written.
+    pub fn open(path: &Path) -> Result<AuditLog, AuditError> {
+        let map_err = |source| AuditError::OpenFailed {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(map_err)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(map_err)?;
+
+        Ok(AuditLog {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// Appends the provided [`Receipt`] to the log.
+    pub fn record(&mut self, receipt: &Receipt) -> Result<(), AuditError> {
+        let map_err = |source| AuditError::WriteFailed {
+            path: self.path.clone(),
+            source,
+        };
+
+        let mut line = serde_json::to_string(receipt).map_err(|err| map_err(err.into()))?;
+        line.push('\n');
+
+        // Written in a single call so concurrent executions don't interleave their receipts
+        self.file.write_all(line.as_bytes()).map_err(map_err)
+    }
+}
+
+/// Returns the name of the user executing plz.
+pub fn current_user() -> Option<String> {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .ok()
+        .filter(|user| !user.is_empty())
+}
+
+/// Returns the hostname of the machine executing plz.
+/// Falls back to the `hostname` command, since macOS doesn't have an `/etc/hostname` file.
+pub fn current_host() -> Option<String> {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| {
+            process::Command::new("hostname")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        })
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mask;
+    use tempfile::TempDir;
+
+    #[test]
+    fn record_appends_receipts() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logs").join("audit.jsonl");
+        mask::register("audit-test-token");
+        mask::register("audit-test-token-suffix");
+        let receipt = Receipt {
+            user: Some("alice".to_string()),
+            host: Some("build-01".to_string()),
+            timestamp: 1700000000,
+            ..Receipt::new(
+                "deploy".to_string(),
+                &[
+                    "--token=audit-test-token-suffix".to_string(),
+                    "prod".to_string(),
+                ],
+                Some(0),
+            )
+        };
+
+        // Act
+        AuditLog::open(&path).unwrap().record(&receipt).unwrap();
+        AuditLog::open(&path).unwrap().record(&receipt).unwrap();
+
+        // Assert
+        let line = "{\"user\":\"alice\",\"host\":\"build-01\",\"timestamp\":1700000000,\"command\":\"deploy\",\"args\":[\"--token=***\",\"prod\"],\"exit_code\":0}\n";
+        assert_eq!(fs::read_to_string(path).unwrap(), line.repeat(2));
+    }
+}
@@ -0,0 +1,424 @@
+use crate::config::{Config, Platform};
+use crate::locale;
+use crate::state::seconds_since_epoch;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use std::{env, io};
+use thiserror::Error;
+
+/// The name of the directory (within the state directory) where cached data is stored.
+pub const CACHE_DIR_NAME: &str = "cache";
+
+/// The name of the file (within the cache directory) where the config with all imports parsed is
+/// cached.
+const CONFIG_CACHE_FILE_NAME: &str = "config.json";
+
+/// The name of the file (within the state directory) where the output of execution variables is
+/// cached.
+pub const VARIABLES_CACHE_FILE_NAME: &str = "variables.json";
+
+/// A previously parsed [`Config`], along with the information required to determine whether it's
+/// still up to date.
+#[derive(Serialize, Deserialize)]
+struct ConfigCache {
+    /// A SHA-256 hash of the config file, the contents of all the files it imports, and anything
+    /// else that affects parsing.
+    hash: String,
+
+    /// The paths of all the files imported while parsing the config.
+    imports: Vec<PathBuf>,
+
+    /// The parsed [`Config`], with the imports that were parsed merged.
+    config: Config,
+}
+
+/// Returns the cached [`Config`] for the provided config file, along with the paths of the files it
+/// imports, if the config file and all the files it imports are unchanged since the cache was
+/// written.
+/// The config with all imports parsed is preferred. Otherwise, the config parsed for
+/// `requested_command` (which only has the imports that command needs) is used.
+pub fn load_config(
+    cache_dir: &Path,
+    config_text: &str,
+    requested_command: Option<&str>,
+    current_platform: &Platform,
+) -> Option<(Config, Vec<PathBuf>)> {
+    load_config_file(
+        &config_cache_path(cache_dir, None),
+        config_text,
+        current_platform,
+    )
+    .or_else(|| {
+        load_config_file(
+            &config_cache_path(cache_dir, Some(requested_command?)),
+            config_text,
+            current_platform,
+        )
+    })
+}
+
+fn load_config_file(
+    path: &Path,
+    config_text: &str,
+    current_platform: &Platform,
+) -> Option<(Config, Vec<PathBuf>)> {
+    let cache_text = fs::read_to_string(path).ok()?;
+    let cache: ConfigCache = serde_json::from_str(&cache_text).ok()?;
+
+    let hash = hash_config(config_text, &cache.imports, current_platform).ok()?;
+    if hash != cache.hash {
+        return None;
+    }
+
+    Some((cache.config, cache.imports))
+}
+
+/// Writes the provided [`Config`] to the cache, so it can be loaded by [`load_config`] until the
+/// config file or any of its imports change.
+/// If the config was parsed for `requested_command`, it's cached separately so it's only used for
+/// that command.
+pub fn save_config(
+    cache_dir: &Path,
+    config_text: &str,
+    imports: &[PathBuf],
+    requested_command: Option<&str>,
+    current_platform: &Platform,
+    config: &Config,
+) -> Result<(), CacheError> {
+    let cache = ConfigCache {
+        hash: hash_config(config_text, imports, current_platform).map_err(CacheError::IO)?,
+        imports: imports.to_vec(),
+        config: config.clone(),
+    };
+
+    let cache_text = serde_json::to_string(&cache).map_err(CacheError::Serialize)?;
+
+    fs::create_dir_all(cache_dir).map_err(CacheError::IO)?;
+    fs::write(config_cache_path(cache_dir, requested_command), cache_text).map_err(CacheError::IO)
+}
+
+/// Returns the path of the file the config parsed for the provided command is cached in.
+/// Command names are hashed, since they may contain characters which can't be used in file names.
+fn config_cache_path(cache_dir: &Path, requested_command: Option<&str>) -> PathBuf {
+    match requested_command {
+        Some(command) => cache_dir.join(format!("config-{}.json", hash(&[command.as_bytes()]))),
+        None => cache_dir.join(CONFIG_CACHE_FILE_NAME),
+    }
+}
+
+fn hash_config(
+    config_text: &str,
+    imports: &[PathBuf],
+    current_platform: &Platform,
+) -> Result<String, io::Error> {
+    // Descriptions and prompts are translated into the current language while parsing
+    let language = locale::current_language(|name| env::var(name).ok());
+    let mut parts = vec![
+        env!("CARGO_PKG_VERSION").as_bytes().to_vec(),
+        format!("{:?}", current_platform).into_bytes(),
+        format!("{:?}", language).into_bytes(),
+        config_text.as_bytes().to_vec(),
+    ];
+
+    // Imports are checked using their contents, since their metadata may not change when they do
+    // (e.g. when they're edited within the same second)
+    for import in imports {
+        parts.push(import.to_string_lossy().into_owned().into_bytes());
+        parts.push(fs::read(import)?);
+    }
+
+    // Options can be set using environment variables, which are read while parsing
+    let mut plz_variables: Vec<(String, String)> = env::vars()
+        .filter(|(key, _)| key.starts_with("PLZ_"))
+        .collect();
+    plz_variables.sort();
+    for (key, value) in plz_variables {
+        parts.push(key.into_bytes());
+        parts.push(value.into_bytes());
+    }
+
+    Ok(hash(&parts.iter().map(Vec::as_slice).collect::<Vec<_>>()))
+}
+
+/// Returns a SHA-256 hash of the provided parts.
+/// Each part is prefixed with its length, so moving bytes between parts changes the hash.
+fn hash(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// The output of an execution variable's command, reused until it expires.
+#[derive(Serialize, Deserialize)]
+struct CachedOutput {
+    output: String,
+
+    /// When the output expires, in seconds since the Unix epoch.
+    expires: u64,
+}
+
+/// Returns the key the output of the provided command is cached with when it's executed from the
+/// provided directory.
+/// Commands are hashed, since they may contain sensitive values.
+pub fn variable_key(command: &str, working_dir: &Path) -> String {
+    hash(&[command.as_bytes(), working_dir.to_string_lossy().as_bytes()])
+}
+
+/// Returns the cached output for the provided key, unless it has expired by `now`.
+pub fn load_variable(state_dir: &Path, key: &str, now: SystemTime) -> Option<String> {
+    load_variables(state_dir)
+        .remove(key)
+        .filter(|cached| cached.expires > seconds_since_epoch(now))
+        .map(|cached| cached.output)
+}
+
+/// Caches the output for the provided key, so it's returned by [`load_variable`] until `ttl` has
+/// passed. Any outputs that have already expired are removed.
+pub fn save_variable(
+    state_dir: &Path,
+    key: &str,
+    output: &str,
+    ttl: Duration,
+    now: SystemTime,
+) -> Result<(), CacheError> {
+    let now = seconds_since_epoch(now);
+    let mut variables = load_variables(state_dir);
+    variables.retain(|_, cached| cached.expires > now);
+    variables.insert(
+        key.to_string(),
+        CachedOutput {
+            output: output.to_string(),
+            expires: now + ttl.as_secs(),
+        },
+    );
+
+    let cache_text = serde_json::to_string(&variables).map_err(CacheError::Serialize)?;
+
+    fs::create_dir_all(state_dir).map_err(CacheError::IO)?;
+    fs::write(state_dir.join(VARIABLES_CACHE_FILE_NAME), cache_text).map_err(CacheError::IO)
+}
+
+fn load_variables(state_dir: &Path) -> HashMap<String, CachedOutput> {
+    fs::read_to_string(state_dir.join(VARIABLES_CACHE_FILE_NAME))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("failed to write cache")]
+    IO(#[source] io::Error),
+
+    #[error("failed to serialize cache")]
+    Serialize(#[source] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const CONFIG_TEXT: &str = "variables:
+    name: Alice
+    age:
+        value: \"42\"
+        arg: age
+        env: AGE
+    branch:
+        execute:
+            bash: git branch --show-current
+    color:
+        prompt:
+            message: Pick a color
+            options:
+                - Red
+                - Blue
+commands:
+    greet:
+        description: Say hello
+        platform: linux
+        action: echo \"Hello, $name!\"
+    deploy:
+        cooldown: 5m
+        lock: true
+        actions:
+            - name: build
+              bash: cargo build
+            - if: steps.build.success
+              command: ./deploy.sh
+              workdir: ./scripts
+        on_failure:
+            action: echo $PLZ_FAILED_STEP
+    deps:
+        alias: docker compose";
+
+    #[test]
+    fn load_config_returns_saved_config() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let config: Config = serde_yaml::from_str(CONFIG_TEXT).unwrap();
+
+        // Act
+        save_config(
+            dir.path(),
+            CONFIG_TEXT,
+            &[],
+            None,
+            &Platform::Linux,
+            &config,
+        )
+        .unwrap();
+        let (cached_config, imports) =
+            load_config(dir.path(), CONFIG_TEXT, None, &Platform::Linux).unwrap();
+
+        // Assert
+        assert_eq!(cached_config.variables, config.variables);
+        assert_eq!(cached_config.commands, config.commands);
+        assert_eq!(cached_config.options, config.options);
+        assert!(imports.is_empty());
+    }
+
+    #[test]
+    fn load_variable_returns_output_until_it_expires() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        let key = variable_key("aws sts get-caller-identity", Path::new("/repo"));
+        let other_dir_key = variable_key("aws sts get-caller-identity", Path::new("/other"));
+
+        // Act
+        save_variable(
+            dir.path(),
+            &key,
+            "123456789012",
+            Duration::from_secs(600),
+            now,
+        )
+        .unwrap();
+        let cached = load_variable(dir.path(), &key, now + Duration::from_secs(599));
+        let expired = load_variable(dir.path(), &key, now + Duration::from_secs(600));
+        let other_dir = load_variable(dir.path(), &other_dir_key, now);
+
+        // Assert
+        assert_eq!(cached.as_deref(), Some("123456789012"));
+        assert!(expired.is_none());
+        assert!(other_dir.is_none());
+    }
+
+    #[test]
+    fn load_config_only_uses_command_configs_for_their_command() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let config: Config = serde_yaml::from_str(CONFIG_TEXT).unwrap();
+        let load = |requested_command| {
+            load_config(dir.path(), CONFIG_TEXT, requested_command, &Platform::Linux)
+        };
+
+        // Act
+        save_config(
+            dir.path(),
+            CONFIG_TEXT,
+            &[],
+            Some("deploy"),
+            &Platform::Linux,
+            &config,
+        )
+        .unwrap();
+        let same_command = load(Some("deploy"));
+        let other_command = load(Some("greet"));
+        let all_commands = load(None);
+
+        save_config(
+            dir.path(),
+            CONFIG_TEXT,
+            &[],
+            None,
+            &Platform::Linux,
+            &config,
+        )
+        .unwrap();
+        let after_full_parse = load(Some("greet"));
+
+        // Assert
+        assert!(same_command.is_some());
+        assert!(other_command.is_none());
+        assert!(all_commands.is_none());
+        assert!(after_full_parse.is_some());
+    }
+
+    #[test]
+    fn variable_key_is_a_sha256_hash() {
+        // Act
+        let key = variable_key("git branch --show-current", Path::new("/repo"));
+        let other_dir = variable_key("git branch --show-current", Path::new("/other"));
+
+        // Assert
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(key, other_dir);
+    }
+
+    #[test]
+    fn load_config_is_invalidated_by_changes() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let import_path = dir.path().join("import.yaml");
+        fs::write(&import_path, "commands: {}").unwrap();
+
+        let config: Config = serde_yaml::from_str(CONFIG_TEXT).unwrap();
+        save_config(
+            dir.path(),
+            CONFIG_TEXT,
+            std::slice::from_ref(&import_path),
+            None,
+            &Platform::Linux,
+            &config,
+        )
+        .unwrap();
+
+        // Act
+        let changed_config = load_config(dir.path(), "commands: {}", None, &Platform::Linux);
+        let changed_platform = load_config(dir.path(), CONFIG_TEXT, None, &Platform::MacOS);
+
+        fs::write(&import_path, "commands: { demo: { action: echo hi } }").unwrap();
+        let changed_import = load_config(dir.path(), CONFIG_TEXT, None, &Platform::Linux);
+
+        // Edits which keep the size and modification time still need to be noticed
+        let write_import = |text: &str| {
+            fs::write(&import_path, text).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&import_path)
+                .unwrap()
+                .set_modified(SystemTime::UNIX_EPOCH)
+                .unwrap();
+        };
+        write_import("commands: { demo: { action: echo yo } }");
+        save_config(
+            dir.path(),
+            CONFIG_TEXT,
+            std::slice::from_ref(&import_path),
+            None,
+            &Platform::Linux,
+            &config,
+        )
+        .unwrap();
+        write_import("commands: { demo: { action: echo hi } }");
+        let edited_import = load_config(dir.path(), CONFIG_TEXT, None, &Platform::Linux);
+
+        // Assert
+        assert!(changed_config.is_none());
+        assert!(changed_platform.is_none());
+        assert!(changed_import.is_none());
+        assert!(edited_import.is_none());
+    }
+}
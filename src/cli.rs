@@ -1,18 +1,269 @@
-use crate::args::ALIAS_ARGS_NAME;
+use crate::args::{flag_arg_id, ALIAS_ARGS_NAME};
 use crate::config::{
-    ActionConfig, ArgumentConfigVariant, CommandConfig, CommandConfigMap, Config,
+    option_flag_name, ActionConfig, ArgumentConfigVariant, CommandConfig, CommandConfigMap, Config,
     ExecutionConfigVariant, NamedArgumentConfig, Options, RawCommandConfigVariant, VariableConfig,
-    VariableConfigMap,
+    VariableConfigMap, OPTION_OVERRIDES,
 };
+use crate::config_tests::DEFAULT_SPEC_FILE_NAME;
+use crate::hook::HookShell;
 use crate::platform::{is_current_platform, PlatformProvider};
-use clap::{Arg, ArgMatches, Command, ValueHint};
+use crate::storage::StorageKind;
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
+use clap_complete::Shell;
+use linked_hash_map::LinkedHashMap;
+use std::ffi::OsStr;
+use std::iter;
+use std::path::Path;
+
+/// The name of the root command when plz is invoked without a name (e.g. from a test) and the
+/// config file doesn't set one.
+pub const DEFAULT_BINARY_NAME: &str = "plz";
+
+/// The ID of the argument used to ignore a command's cooldown.
+pub const FORCE_ARG_NAME: &str = "plz-force";
+
+/// The ID of the argument used to execute a command which requires a clean git working tree
+/// anyway.
+pub const ALLOW_DIRTY_ARG_NAME: &str = "plz-allow-dirty";
+
+/// The ID of the argument used to execute a command which its guard doesn't allow anyway.
+pub const IGNORE_GUARD_ARG_NAME: &str = "plz-ignore-guard";
+
+/// The ID of the argument used to enable timings.
+const TIMINGS_ARG_NAME: &str = "plz-timings";
+const REFRESH_IMPORTS_ARG_NAME: &str = "plz-refresh-imports";
+
+/// The ID of the argument used to execute an untrusted config without being prompted.
+pub const TRUST_ARG_NAME: &str = "plz-trust";
+
+/// The ID of the argument used to choose the config file.
+const CONFIG_ARG_NAME: &str = "plz-config";
+
+/// The ID of the argument used to execute the last command that failed again.
+const RETRY_FAILED_ARG_NAME: &str = "plz-retry-failed";
+
+/// The ID of the argument used to select an environment.
+const ENVIRONMENT_ARG_NAME: &str = "plz-environment";
+
+/// The name of the built-in command used to print the environment of another command.
+pub const ENV_COMMAND_NAME: &str = "env";
+
+/// The ID of the argument containing the path of the command to print the environment of.
+const ENV_TARGET_ARG_NAME: &str = "plz-env-target";
+
+/// The ID of the argument used to select the format of the printed environment.
+const ENV_FORMAT_ARG_NAME: &str = "plz-env-format";
+
+/// The name of the built-in command used to copy the command text of another command.
+pub const COPY_COMMAND_NAME: &str = "copy";
+
+/// The ID of the argument containing the path of the command to copy.
+const COPY_TARGET_ARG_NAME: &str = "plz-copy-target";
+
+/// The name of the built-in command used to print the command tree.
+pub const TREE_COMMAND_NAME: &str = "tree";
+
+/// The ID of the argument used to include variables in the printed command tree.
+const TREE_VARS_ARG_NAME: &str = "plz-tree-vars";
+
+/// The name of the built-in command used to check the config against the lint rules.
+pub const LINT_COMMAND_NAME: &str = "lint";
+
+/// The name of the built-in command used to print a JSON Schema for the config file format.
+pub const SCHEMA_COMMAND_NAME: &str = "schema";
+
+/// The name of the built-in command used to print and install shell completions.
+pub const COMPLETIONS_COMMAND_NAME: &str = "completions";
+
+/// The name of the `completions` subcommand used to install shell completions.
+const COMPLETIONS_INSTALL_COMMAND_NAME: &str = "install";
+
+/// The ID of the argument used to select the shell to generate completions for.
+const COMPLETIONS_SHELL_ARG_NAME: &str = "plz-completions-shell";
+
+/// The name of the built-in command used to manage the data plz stores on disk.
+pub const CACHE_COMMAND_NAME: &str = "cache";
+
+/// The name of the `cache` subcommand used to print how much disk space is used.
+const CACHE_USAGE_COMMAND_NAME: &str = "usage";
+
+/// The name of the `cache` subcommand used to print where data is stored.
+const CACHE_PATHS_COMMAND_NAME: &str = "paths";
+
+/// The name of the `cache` subcommand used to remove stored data.
+const CACHE_CLEAN_COMMAND_NAME: &str = "clean";
+
+/// The ID of the argument used to select the kinds of data to remove.
+const CACHE_KINDS_ARG_NAME: &str = "plz-cache-kinds";
+
+/// The name of the built-in command used to manage the defaults saved by the user.
+pub const CONFIG_COMMAND_NAME: &str = "config";
+
+/// The name of the `config` subcommand used to save a default.
+const CONFIG_SET_COMMAND_NAME: &str = "set";
+
+/// The name of the `config` subcommand used to remove a saved default.
+const CONFIG_UNSET_COMMAND_NAME: &str = "unset";
+
+/// The name of the `config` subcommand used to print the saved defaults.
+const CONFIG_LIST_COMMAND_NAME: &str = "list";
+
+/// The ID of the argument containing the key of a saved default.
+const CONFIG_KEY_ARG_NAME: &str = "plz-config-key";
+
+/// The ID of the argument containing the value of a saved default.
+const CONFIG_VALUE_ARG_NAME: &str = "plz-config-value";
+
+/// The name of the built-in command used to update the versions of remote imports in the lock
+/// file.
+pub const UPDATE_COMMAND_NAME: &str = "update";
+
+/// The name of the built-in command used to print the shell hook which defines functions for the
+/// current directory's commands.
+pub const HOOK_COMMAND_NAME: &str = "hook";
+
+/// The ID of the argument used to select the shell to print the hook for.
+const HOOK_SHELL_ARG_NAME: &str = "plz-hook-shell";
+
+/// The ID of the flag used by the hook to list the commands to define functions for.
+const HOOK_COMMANDS_ARG_NAME: &str = "plz-hook-commands";
+
+/// The name of the built-in command used to check the commands invocations resolve to against a
+/// spec.
+pub const TEST_CONFIG_COMMAND_NAME: &str = "test-config";
+
+/// The ID of the argument containing the path of the spec to check.
+const TEST_CONFIG_SPEC_ARG_NAME: &str = "plz-test-config-spec";
+
+/// The name of the built-in command used to migrate the config file to the current version of the
+/// config format.
+pub const MIGRATE_COMMAND_NAME: &str = "migrate";
+
+/// The ID of the flag used to check whether the config file needs to be migrated without
+/// migrating it.
+const MIGRATE_CHECK_ARG_NAME: &str = "plz-migrate-check";
+
+/// A function which creates a built-in command.
+type CreateCommand = fn() -> Command;
+
+/// The built-in commands, in the order they're listed in the help text, along with the functions
+/// used to create them.
+const BUILT_IN_COMMANDS: [(&str, CreateCommand); 12] = [
+    (ENV_COMMAND_NAME, create_env_command),
+    (COPY_COMMAND_NAME, create_copy_command),
+    (TREE_COMMAND_NAME, create_tree_command),
+    (LINT_COMMAND_NAME, create_lint_command),
+    (SCHEMA_COMMAND_NAME, create_schema_command),
+    (COMPLETIONS_COMMAND_NAME, create_completions_command),
+    (CACHE_COMMAND_NAME, create_cache_command),
+    (CONFIG_COMMAND_NAME, create_config_command),
+    (UPDATE_COMMAND_NAME, create_update_command),
+    (HOOK_COMMAND_NAME, create_hook_command),
+    (TEST_CONFIG_COMMAND_NAME, create_test_config_command),
+    (MIGRATE_COMMAND_NAME, create_migrate_command),
+];
+
+/// The name of the subcommand clap adds to print the help text.
+const HELP_COMMAND_NAME: &str = "help";
+
+/// Returns `true` if the provided name is the name of a built-in command (including `help`).
+pub fn is_built_in_command(name: &str) -> bool {
+    name == HELP_COMMAND_NAME
+        || BUILT_IN_COMMANDS
+            .iter()
+            .any(|(built_in, _)| *built_in == name)
+}
+
+/// The ID of the flag used to migrate the config file without asking for confirmation.
+const MIGRATE_YES_ARG_NAME: &str = "plz-migrate-yes";
+
+/// The formats the built-in `env` command can print the environment in.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnvFormat {
+    Dotenv,
+    Json,
+}
+
+/// What the built-in `completions` command was asked to do.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CompletionsRequest {
+    /// The shell to generate completions for, or `None` if it should be detected.
+    pub shell: Option<Shell>,
+
+    /// Whether the completions should be installed rather than printed.
+    pub install: bool,
+}
+
+/// What the built-in `cache` command was asked to do.
+#[derive(Clone, PartialEq, Debug)]
+pub enum CacheRequest {
+    /// Print how much disk space each kind of data uses.
+    Usage,
+
+    /// Print where each kind of data is stored.
+    Paths,
+
+    /// Remove the provided kinds of data.
+    Clean(Vec<StorageKind>),
+}
+
+/// What the built-in `config` command was asked to do.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ConfigRequest {
+    /// Save a default value for a variable.
+    Set { key: String, value: String },
+
+    /// Remove a saved default.
+    Unset { key: String },
+
+    /// Print the saved defaults.
+    List,
+}
+
+/// What the built-in `hook` command was asked to do.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HookRequest {
+    /// The shell to print the hook for, or `None` if it should be detected.
+    pub shell: Option<HookShell>,
+
+    /// Whether the names of the commands should be printed rather than the hook.
+    pub list_commands: bool,
+}
+
+/// What the built-in `migrate` command was asked to do.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MigrateRequest {
+    /// Whether the config file should only be checked, rather than migrated.
+    pub check: bool,
+
+    /// Whether the config file should be migrated without asking for confirmation.
+    pub yes: bool,
+}
+
+/// Returns the name plz was invoked as, which is used for the root command so usage strings,
+/// completions, and the shell hook refer to it by the right name.
+/// `options.binary_name` takes precedence, followed by the name of the binary that was invoked
+/// (`argv[0]`), so a renamed or symlinked binary is referred to by its own name.
+pub fn binary_name(configured: Option<&str>, argv0: Option<&OsStr>) -> String {
+    configured
+        .map(str::to_string)
+        .or_else(|| {
+            argv0
+                .map(Path::new)
+                .and_then(Path::file_stem)
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_BINARY_NAME.to_string())
+}
 
 /// Creates a root-level [`Command`] for the provided [`Config`].
 pub fn create_root_command(
     config: &Config,
     platform_provider: &Box<dyn PlatformProvider>,
 ) -> Command {
-    let root_args = create_args(&config.options, &config.variables);
+    let root_args = create_args(config.options.auto_args, &config.variables);
     let subcommands = create_commands(
         &config.options,
         &config.commands,
@@ -20,12 +271,110 @@ pub fn create_root_command(
         &platform_provider,
     );
 
-    let mut root_command = Command::new("plz")
+    let binary_name = config
+        .options
+        .binary_name
+        .clone()
+        .unwrap_or_else(|| DEFAULT_BINARY_NAME.to_string());
+    let mut root_command = Command::new(binary_name.clone())
+        .bin_name(binary_name)
         .version(env!("CARGO_PKG_VERSION"))
         .subcommands(subcommands)
         .subcommand_required(true)
         .arg_required_else_help(true)
-        .args(root_args);
+        .args(root_args)
+        .arg(
+            Arg::new(TIMINGS_ARG_NAME)
+                .long("timings")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Report how long each stage of the invocation took."),
+        )
+        .arg(
+            Arg::new(REFRESH_IMPORTS_ARG_NAME)
+                .long("refresh-imports")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Download remote imports again, even if they're cached."),
+        )
+        .arg(
+            Arg::new(TRUST_ARG_NAME)
+                .long("trust")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Execute the config without asking whether it should be trusted."),
+        )
+        .arg(
+            Arg::new(RETRY_FAILED_ARG_NAME)
+                .long("retry-failed")
+                .action(ArgAction::SetTrue)
+                .help("Execute the last command that failed again, with the same arguments."),
+        );
+
+    // Options can be overridden from the command-line.
+    // These are applied before the arguments are parsed, so clap only needs to accept them.
+    for (field, is_bool, help) in OPTION_OVERRIDES {
+        let flag = option_flag_name(field);
+        let mut arg = Arg::new(format!("plz-{}", flag))
+            .long(flag)
+            .global(true)
+            .help(help);
+        if is_bool {
+            arg = arg
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true")
+                .value_name("BOOL");
+        } else {
+            arg = arg.value_name(field.to_uppercase());
+        }
+
+        root_command = root_command.arg(arg);
+    }
+
+    // The config file is chosen before the config is loaded, so clap only needs to accept it.
+    // Root-level variables with a `config` argument take precedence.
+    let has_config_arg = root_command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some("config"));
+    if !has_config_arg {
+        let mut config_arg = Arg::new(CONFIG_ARG_NAME)
+            .long("config")
+            .value_name("PATH")
+            .value_hint(ValueHint::FilePath)
+            .action(ArgAction::Append)
+            .help("The config file to use, rather than searching for one. Later config files are merged over earlier ones.");
+        if !root_command
+            .get_arguments()
+            .any(|arg| arg.get_short() == Some('f'))
+        {
+            config_arg = config_arg.short('f');
+        }
+
+        root_command = root_command.arg(config_arg);
+    }
+
+    // Environments are applied before the arguments are created, so clap only needs to accept it.
+    // Root-level variables with an `env` argument take precedence.
+    let has_env_arg = root_command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some("env"));
+    if !config.environments.is_empty() && !has_env_arg {
+        root_command = root_command.arg(
+            Arg::new(ENVIRONMENT_ARG_NAME)
+                .long("env")
+                .value_name("ENVIRONMENT")
+                .value_parser(PossibleValuesParser::new(config.environments.keys()))
+                .help("The environment to override variables and options for."),
+        );
+    }
+
+    // Commands defined in the config take precedence over the built-in commands
+    for (name, create_command) in BUILT_IN_COMMANDS {
+        if !has_command_named(&config.commands, name) {
+            root_command = root_command.subcommand(create_command());
+        }
+    }
 
     if let Some(description) = &config.description {
         root_command = root_command.about(description)
@@ -34,11 +383,514 @@ pub fn create_root_command(
     return root_command;
 }
 
+/// Returns `true` if any of the provided commands resolves to the provided name.
+fn has_command_named(commands: &CommandConfigMap, name: &str) -> bool {
+    commands
+        .iter()
+        .any(|(key, command_config)| command_config.name.as_ref().unwrap_or(key) == name)
+}
+
+/// Creates the built-in `env` command, which prints the environment another command's action
+/// would receive.
+fn create_env_command() -> Command {
+    Command::new(ENV_COMMAND_NAME)
+        .about("Print the environment a command would be executed with.")
+        .arg(
+            Arg::new(ENV_FORMAT_ARG_NAME)
+                .long("format")
+                .value_parser(["dotenv", "json"])
+                .default_value("dotenv")
+                .help("The format to print the environment in."),
+        )
+        .arg(
+            Arg::new(ENV_TARGET_ARG_NAME)
+                .value_name("COMMAND")
+                .num_args(1..)
+                .required(true)
+                .allow_hyphen_values(true)
+                .trailing_var_arg(true)
+                .help("The command (and any arguments) to print the environment of."),
+        )
+}
+
+/// Returns the target command (and its arguments) and the format if the built-in `env` command
+/// was matched, or [`None`] if a different command was matched.
+pub fn find_env_command(arg_matches: &ArgMatches) -> Option<(Vec<String>, EnvFormat)> {
+    let (name, env_matches) = arg_matches.subcommand()?;
+    if name != ENV_COMMAND_NAME {
+        return None;
+    }
+
+    // The matched command may be an env command defined in the config
+    let target = env_matches
+        .try_get_many::<String>(ENV_TARGET_ARG_NAME)
+        .ok()??
+        .cloned()
+        .collect();
+
+    let format = match env_matches
+        .get_one::<String>(ENV_FORMAT_ARG_NAME)
+        .map(String::as_str)
+    {
+        Some("json") => EnvFormat::Json,
+        _ => EnvFormat::Dotenv,
+    };
+
+    Some((target, format))
+}
+
+/// Creates the built-in `copy` command, which copies the command text another command's action
+/// would execute to the clipboard.
+fn create_copy_command() -> Command {
+    Command::new(COPY_COMMAND_NAME)
+        .about("Copy the command a command would execute to the clipboard, without executing it.")
+        .arg(
+            Arg::new(COPY_TARGET_ARG_NAME)
+                .value_name("COMMAND")
+                .num_args(1..)
+                .required(true)
+                .allow_hyphen_values(true)
+                .trailing_var_arg(true)
+                .help("The command (and any arguments) to copy."),
+        )
+}
+
+/// Returns the target command (and its arguments) if the built-in `copy` command was matched, or
+/// [`None`] if a different command was matched.
+pub fn find_copy_command(arg_matches: &ArgMatches) -> Option<Vec<String>> {
+    let (name, copy_matches) = arg_matches.subcommand()?;
+    if name != COPY_COMMAND_NAME {
+        return None;
+    }
+
+    // The matched command may be a copy command defined in the config
+    Some(
+        copy_matches
+            .try_get_many::<String>(COPY_TARGET_ARG_NAME)
+            .ok()??
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Creates the built-in `tree` command, which prints the available commands as a tree.
+fn create_tree_command() -> Command {
+    Command::new(TREE_COMMAND_NAME)
+        .about("Print the available commands as a tree.")
+        .arg(
+            Arg::new(TREE_VARS_ARG_NAME)
+                .long("vars")
+                .action(ArgAction::SetTrue)
+                .help("Show the variables available to each command, and where they were defined."),
+        )
+}
+
+/// Returns whether variables should be shown if the built-in `tree` command was matched, or
+/// [`None`] if a different command was matched.
+pub fn find_tree_command(arg_matches: &ArgMatches) -> Option<bool> {
+    let (name, tree_matches) = arg_matches.subcommand()?;
+    if name != TREE_COMMAND_NAME {
+        return None;
+    }
+
+    // The matched command may be a tree command defined in the config
+    tree_matches
+        .try_get_one::<bool>(TREE_VARS_ARG_NAME)
+        .ok()?
+        .copied()
+}
+
+/// Creates the built-in `lint` command, which checks the config against the lint rules.
+fn create_lint_command() -> Command {
+    Command::new(LINT_COMMAND_NAME).about("Check the config file for common problems.")
+}
+
+/// Returns `true` if the built-in `lint` command was matched, rather than a lint command defined in
+/// the provided commands.
+pub fn find_lint_command(arg_matches: &ArgMatches, commands: &CommandConfigMap) -> bool {
+    arg_matches.subcommand_name() == Some(LINT_COMMAND_NAME)
+        && !has_command_named(commands, LINT_COMMAND_NAME)
+}
+
+/// Creates the built-in `schema` command, which prints a JSON Schema for the config file format.
+fn create_schema_command() -> Command {
+    Command::new(SCHEMA_COMMAND_NAME)
+        .about("Print a JSON Schema for the config file, for editor autocompletion and validation.")
+}
+
+/// Returns `true` if the built-in `schema` command was matched, rather than a schema command
+/// defined in the provided commands.
+pub fn find_schema_command(arg_matches: &ArgMatches, commands: &CommandConfigMap) -> bool {
+    arg_matches.subcommand_name() == Some(SCHEMA_COMMAND_NAME)
+        && !has_command_named(commands, SCHEMA_COMMAND_NAME)
+}
+
+/// Creates the built-in `completions` command, which prints or installs shell completions.
+fn create_completions_command() -> Command {
+    let shell_arg = Arg::new(COMPLETIONS_SHELL_ARG_NAME)
+        .value_name("SHELL")
+        .value_parser(value_parser!(Shell))
+        .help("The shell to generate completions for. Detected from $SHELL if not specified.");
+
+    Command::new(COMPLETIONS_COMMAND_NAME)
+        .about("Print the completion script for a shell.")
+        .args_conflicts_with_subcommands(true)
+        .arg(shell_arg.clone())
+        .subcommand(
+            Command::new(COMPLETIONS_INSTALL_COMMAND_NAME)
+                .about("Install completions for a shell, and print what was changed.")
+                .arg(shell_arg.long("shell")),
+        )
+}
+
+/// Returns the [`CompletionsRequest`] if the built-in `completions` command was matched, or
+/// [`None`] if a different command was matched.
+pub fn find_completions_command(arg_matches: &ArgMatches) -> Option<CompletionsRequest> {
+    let (name, completions_matches) = arg_matches.subcommand()?;
+    if name != COMPLETIONS_COMMAND_NAME {
+        return None;
+    }
+
+    if let Some((COMPLETIONS_INSTALL_COMMAND_NAME, install_matches)) =
+        completions_matches.subcommand()
+    {
+        let shell = install_matches
+            .get_one::<Shell>(COMPLETIONS_SHELL_ARG_NAME)
+            .copied();
+        return Some(CompletionsRequest {
+            shell,
+            install: true,
+        });
+    }
+
+    // The matched command may be a completions command defined in the config
+    let shell = completions_matches
+        .try_get_one::<Shell>(COMPLETIONS_SHELL_ARG_NAME)
+        .ok()?
+        .copied();
+    Some(CompletionsRequest {
+        shell,
+        install: false,
+    })
+}
+
+/// Creates the built-in `cache` command, which manages the data plz stores on disk.
+fn create_cache_command() -> Command {
+    let kind_names: Vec<&str> = StorageKind::ALL.iter().map(|kind| kind.name()).collect();
+
+    Command::new(CACHE_COMMAND_NAME)
+        .about("Manage the data plz stores between executions.")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new(CACHE_USAGE_COMMAND_NAME)
+                .about("Print how much disk space each kind of data uses."),
+        )
+        .subcommand(
+            Command::new(CACHE_PATHS_COMMAND_NAME)
+                .about("Print where each kind of data is stored."),
+        )
+        .subcommand(
+            Command::new(CACHE_CLEAN_COMMAND_NAME)
+                .visible_alias("clear")
+                .about("Remove stored data.")
+                .arg(
+                    Arg::new(CACHE_KINDS_ARG_NAME)
+                        .value_name("KIND")
+                        .num_args(0..)
+                        .value_parser(kind_names)
+                        .help(
+                            "The kinds of data to remove. \
+                            Removes everything except trusted configs and saved defaults if not specified.",
+                        ),
+                ),
+        )
+}
+
+/// Returns the [`CacheRequest`] if the built-in `cache` command was matched, or [`None`] if a
+/// different command was matched.
+pub fn find_cache_command(
+    arg_matches: &ArgMatches,
+    commands: &CommandConfigMap,
+) -> Option<CacheRequest> {
+    let (name, cache_matches) = arg_matches.subcommand()?;
+    if name != CACHE_COMMAND_NAME || has_command_named(commands, CACHE_COMMAND_NAME) {
+        return None;
+    }
+
+    match cache_matches.subcommand()? {
+        (CACHE_USAGE_COMMAND_NAME, _) => Some(CacheRequest::Usage),
+        (CACHE_PATHS_COMMAND_NAME, _) => Some(CacheRequest::Paths),
+        (CACHE_CLEAN_COMMAND_NAME, clean_matches) => {
+            let kinds: Vec<StorageKind> = clean_matches
+                .get_many::<String>(CACHE_KINDS_ARG_NAME)
+                .map(|names| {
+                    names
+                        .filter_map(|name| StorageKind::from_name(name))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            // Trusted configs and saved defaults are only removed when asked for, since they were
+            // chosen by the user rather than cached
+            if kinds.is_empty() {
+                return Some(CacheRequest::Clean(
+                    StorageKind::ALL
+                        .into_iter()
+                        .filter(|kind| {
+                            !matches!(kind, StorageKind::Trusted | StorageKind::Defaults)
+                        })
+                        .collect(),
+                ));
+            }
+
+            Some(CacheRequest::Clean(kinds))
+        }
+        _ => None,
+    }
+}
+
+/// Creates the built-in `config` command, which manages the defaults saved by the user.
+fn create_config_command() -> Command {
+    let key_arg = Arg::new(CONFIG_KEY_ARG_NAME)
+        .value_name("KEY")
+        .required(true)
+        .help("The command path and variable name (e.g. deploy.region).");
+
+    Command::new(CONFIG_COMMAND_NAME)
+        .about("Manage your own default argument values for this config.")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new(CONFIG_SET_COMMAND_NAME)
+                .about("Save a default value for a variable.")
+                .arg(key_arg.clone())
+                .arg(
+                    Arg::new(CONFIG_VALUE_ARG_NAME)
+                        .value_name("VALUE")
+                        .required(true)
+                        .allow_hyphen_values(true)
+                        .help("The value to use when the argument isn't provided."),
+                ),
+        )
+        .subcommand(
+            Command::new(CONFIG_UNSET_COMMAND_NAME)
+                .about("Remove a saved default.")
+                .arg(key_arg),
+        )
+        .subcommand(Command::new(CONFIG_LIST_COMMAND_NAME).about("Print the saved defaults."))
+}
+
+/// Returns the [`ConfigRequest`] if the built-in `config` command was matched, or [`None`] if a
+/// different command was matched.
+pub fn find_config_command(
+    arg_matches: &ArgMatches,
+    commands: &CommandConfigMap,
+) -> Option<ConfigRequest> {
+    let (name, config_matches) = arg_matches.subcommand()?;
+    if name != CONFIG_COMMAND_NAME || has_command_named(commands, CONFIG_COMMAND_NAME) {
+        return None;
+    }
+
+    let key = |matches: &ArgMatches| {
+        matches
+            .get_one::<String>(CONFIG_KEY_ARG_NAME)
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    match config_matches.subcommand()? {
+        (CONFIG_SET_COMMAND_NAME, set_matches) => Some(ConfigRequest::Set {
+            key: key(set_matches),
+            value: set_matches
+                .get_one::<String>(CONFIG_VALUE_ARG_NAME)
+                .cloned()
+                .unwrap_or_default(),
+        }),
+        (CONFIG_UNSET_COMMAND_NAME, unset_matches) => Some(ConfigRequest::Unset {
+            key: key(unset_matches),
+        }),
+        (CONFIG_LIST_COMMAND_NAME, _) => Some(ConfigRequest::List),
+        _ => None,
+    }
+}
+
+/// Creates the built-in `update` command, which updates the versions of remote imports in the lock
+/// file.
+fn create_update_command() -> Command {
+    Command::new(UPDATE_COMMAND_NAME)
+        .about("Update remote and git imports to their latest versions, and lock them in plz.lock.")
+}
+
+/// Returns `true` if the built-in `update` command was matched.
+pub fn find_update_command(arg_matches: &ArgMatches, commands: &CommandConfigMap) -> bool {
+    arg_matches.subcommand_name() == Some(UPDATE_COMMAND_NAME)
+        && !has_command_named(commands, UPDATE_COMMAND_NAME)
+}
+
+/// Creates the built-in `hook` command, which prints the shell hook.
+fn create_hook_command() -> Command {
+    Command::new(HOOK_COMMAND_NAME)
+        .about("Print a shell hook which defines a function for each command in the current directory.")
+        .arg(
+            Arg::new(HOOK_SHELL_ARG_NAME)
+                .value_name("SHELL")
+                .value_parser(HookShell::NAMES)
+                .help("The shell to print the hook for. Detected from $SHELL if not specified."),
+        )
+        .arg(
+            Arg::new(HOOK_COMMANDS_ARG_NAME)
+                .long("commands")
+                .action(ArgAction::SetTrue)
+                .hide(true)
+                .help("Print the names of the commands to define functions for."),
+        )
+}
+
+/// Returns the [`HookRequest`] if the built-in `hook` command was matched, or [`None`] if a
+/// different command was matched.
+pub fn find_hook_command(
+    arg_matches: &ArgMatches,
+    commands: &CommandConfigMap,
+) -> Option<HookRequest> {
+    let (name, hook_matches) = arg_matches.subcommand()?;
+    if name != HOOK_COMMAND_NAME || has_command_named(commands, HOOK_COMMAND_NAME) {
+        return None;
+    }
+
+    Some(HookRequest {
+        shell: hook_matches
+            .get_one::<String>(HOOK_SHELL_ARG_NAME)
+            .and_then(|shell| HookShell::from_name(shell)),
+        list_commands: hook_matches.get_flag(HOOK_COMMANDS_ARG_NAME),
+    })
+}
+
+/// Returns the [`HookRequest`] if the provided arguments invoke the built-in `hook` command.
+/// The hook is evaluated in every directory the user changes to, so this is used when there isn't
+/// a config file to create the root [`Command`] from.
+/// This will exit if the `hook` command's arguments are invalid.
+pub fn parse_hook_command(args: &[String]) -> Option<HookRequest> {
+    if args.get(1).map(String::as_str) != Some(HOOK_COMMAND_NAME) {
+        return None;
+    }
+
+    let arg_matches = Command::new("plz")
+        .subcommand(create_hook_command())
+        .get_matches_from(args);
+    find_hook_command(&arg_matches, &CommandConfigMap::new())
+}
+
+/// Creates the built-in `test-config` command, which checks the commands invocations resolve to
+/// against a spec.
+fn create_test_config_command() -> Command {
+    Command::new(TEST_CONFIG_COMMAND_NAME)
+        .about("Check the commands invocations resolve to against a spec, without executing them.")
+        .arg(
+            Arg::new(TEST_CONFIG_SPEC_ARG_NAME)
+                .value_name("SPEC")
+                .value_hint(ValueHint::FilePath)
+                .help(format!(
+                    "The spec containing the test cases. Defaults to {} next to the config file.",
+                    DEFAULT_SPEC_FILE_NAME
+                )),
+        )
+}
+
+/// Returns the path of the spec (or `None` if the default spec should be used) if the built-in
+/// `test-config` command was matched, or [`None`] if a different command was matched.
+pub fn find_test_config_command(
+    arg_matches: &ArgMatches,
+    commands: &CommandConfigMap,
+) -> Option<Option<String>> {
+    let (name, test_matches) = arg_matches.subcommand()?;
+    if name != TEST_CONFIG_COMMAND_NAME || has_command_named(commands, TEST_CONFIG_COMMAND_NAME) {
+        return None;
+    }
+
+    Some(
+        test_matches
+            .get_one::<String>(TEST_CONFIG_SPEC_ARG_NAME)
+            .cloned(),
+    )
+}
+
+/// Creates the built-in `migrate` command, which migrates the config file to the current version of
+/// the config format.
+fn create_migrate_command() -> Command {
+    Command::new(MIGRATE_COMMAND_NAME)
+        .about("Update the config file to the current version of the config format.")
+        .arg(
+            Arg::new(MIGRATE_CHECK_ARG_NAME)
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .help("Fail if the config file needs to be migrated, without changing it."),
+        )
+        .arg(
+            Arg::new(MIGRATE_YES_ARG_NAME)
+                .long("yes")
+                .short('y')
+                .action(ArgAction::SetTrue)
+                .conflicts_with(MIGRATE_CHECK_ARG_NAME)
+                .help("Write the migrated config file without asking for confirmation."),
+        )
+}
+
+/// Returns the [`MigrateRequest`] if the built-in `migrate` command was matched, or [`None`] if a
+/// different command was matched.
+pub fn find_migrate_command(
+    arg_matches: &ArgMatches,
+    commands: &CommandConfigMap,
+) -> Option<MigrateRequest> {
+    let (name, migrate_matches) = arg_matches.subcommand()?;
+    if name != MIGRATE_COMMAND_NAME || has_command_named(commands, MIGRATE_COMMAND_NAME) {
+        return None;
+    }
+
+    Some(MigrateRequest {
+        check: migrate_matches.get_flag(MIGRATE_CHECK_ARG_NAME),
+        yes: migrate_matches.get_flag(MIGRATE_YES_ARG_NAME),
+    })
+}
+
+/// Returns the [`MigrateRequest`] if the provided arguments invoke the built-in `migrate` command.
+/// Older config files may not load at all, so this is used when the root [`Command`] can't be
+/// created from the config.
+/// This will exit if the `migrate` command's arguments are invalid.
+pub fn parse_migrate_command(args: &[String]) -> Option<MigrateRequest> {
+    if args.get(1).map(String::as_str) != Some(MIGRATE_COMMAND_NAME) {
+        return None;
+    }
+
+    let arg_matches = Command::new("plz")
+        .subcommand(create_migrate_command())
+        .get_matches_from(args);
+    find_migrate_command(&arg_matches, &CommandConfigMap::new())
+}
+
+/// A view of one or more [`VariableConfigMap`]s that borrows the [`VariableConfig`]s rather than
+/// cloning them. Later entries override earlier entries with the same key.
+type VariableConfigRefs<'a> = LinkedHashMap<&'a String, &'a VariableConfig>;
+
 fn create_commands(
     options: &Options,
     commands: &CommandConfigMap,
     parent_variables: &VariableConfigMap,
     platform_provider: &Box<dyn PlatformProvider>,
+) -> Vec<Command> {
+    create_commands_with_refs(
+        options.auto_args,
+        commands,
+        &parent_variables.iter().collect(),
+        platform_provider.as_ref(),
+    )
+}
+
+fn create_commands_with_refs<'a>(
+    auto_args: bool,
+    commands: &'a CommandConfigMap,
+    parent_variables: &VariableConfigRefs<'a>,
+    platform_provider: &dyn PlatformProvider,
 ) -> Vec<Command> {
     commands
         .iter()
@@ -50,6 +902,10 @@ fn create_commands(
                 }
             }
 
+            if !command_config.is_available() {
+                return false;
+            }
+
             return true;
         })
         .map(|(key, command_config)| -> Command {
@@ -62,15 +918,18 @@ fn create_commands(
             // configs from the current command.
             // This lets us inherit variables from the root config/parent commands.
             let mut variables = parent_variables.clone();
-            variables.extend(command_config.variables.clone());
+            variables.extend(command_config.variables.iter());
+
+            // Commands can override the auto_args option for themselves and their subcommands
+            let auto_args = command_config.auto_args.unwrap_or(auto_args);
 
-            let args = create_args(options, &variables);
+            let args = create_args(auto_args, variables.iter().map(|(key, var)| (*key, *var)));
 
-            let subcommands = create_commands(
-                options,
+            let subcommands = create_commands_with_refs(
+                auto_args,
                 &command_config.commands,
                 &variables,
-                &platform_provider,
+                platform_provider,
             );
 
             // If this command doesn't have any action, then it needs a subcommand
@@ -84,8 +943,10 @@ fn create_commands(
                 .args(args)
                 .hide(command_config.hidden);
 
-            // If the action is an alias, then we use a special argument for the arguments to pass through to the alias
-            if let Some(ActionConfig::Alias(_)) = command_config.action.clone() {
+            // If the action is an alias (or delegates to another command), then we use a special argument for the
+            // arguments to pass through to the alias
+            if let Some(ActionConfig::Alias(_) | ActionConfig::Delegate(_)) = &command_config.action
+            {
                 let raw_args = Arg::new(ALIAS_ARGS_NAME)
                     .num_args(1..)
                     .allow_hyphen_values(true)
@@ -96,7 +957,39 @@ fn create_commands(
                 command = command.arg(raw_args)
             }
 
-            if let Some(description) = command_config.description.clone() {
+            // Commands with a cooldown can be forced to execute within the cooldown window
+            if command_config.cooldown.is_some() {
+                let force_arg = Arg::new(FORCE_ARG_NAME)
+                    .long("force")
+                    .action(ArgAction::SetTrue)
+                    .help("Execute the command even if its cooldown hasn't elapsed.");
+
+                command = command.arg(force_arg)
+            }
+
+            // Commands which require a clean working tree can be executed with uncommitted changes
+            if command_config.require_clean_git {
+                let allow_dirty_arg = Arg::new(ALLOW_DIRTY_ARG_NAME)
+                    .long("allow-dirty")
+                    .action(ArgAction::SetTrue)
+                    .help(
+                        "Execute the command even if the git working tree has uncommitted changes.",
+                    );
+
+                command = command.arg(allow_dirty_arg)
+            }
+
+            // Guarded commands can be executed when the guard doesn't allow it
+            if command_config.guard.is_some() {
+                let ignore_guard_arg = Arg::new(IGNORE_GUARD_ARG_NAME)
+                    .long("ignore-guard")
+                    .action(ArgAction::SetTrue)
+                    .help("Execute the command even if its guard doesn't allow it.");
+
+                command = command.arg(ignore_guard_arg)
+            }
+
+            if let Some(description) = &command_config.description {
                 command = command.about(description)
             }
 
@@ -105,20 +998,26 @@ fn create_commands(
         .collect()
 }
 
-fn create_args(options: &Options, variable_config_map: &VariableConfigMap) -> Vec<Arg> {
-    variable_config_map
-        .iter()
-        .map(|(key, var_config)| -> Option<Arg> {
+fn create_args<'a>(
+    auto_args: bool,
+    variable_configs: impl IntoIterator<Item = (&'a String, &'a VariableConfig)>,
+) -> Vec<Arg> {
+    variable_configs
+        .into_iter()
+        .flat_map(|(key, var_config)| -> Vec<Arg> {
             let mut arg_config = match var_config {
                 VariableConfig::ShorthandLiteral(_) => None,
-                VariableConfig::Literal(literal) => literal.clone().argument,
-                VariableConfig::Execution(exec) => exec.clone().argument,
-                VariableConfig::Prompt(prompt) => prompt.clone().argument,
-                VariableConfig::Argument(argument) => Some(argument.clone().argument),
+                VariableConfig::Literal(literal) => literal.argument.clone(),
+                VariableConfig::Execution(exec) => exec.argument.clone(),
+                VariableConfig::Prompt(prompt) => prompt.argument.clone(),
+                VariableConfig::Env(env) => env.argument.clone(),
+                VariableConfig::Keyring(keyring) => keyring.argument.clone(),
+                VariableConfig::Secret(secret) => secret.argument.clone(),
+                VariableConfig::Argument(argument) => Some(argument.argument.clone()),
             };
 
             // Automatically create an argument if the auto_args option is enabled
-            if options.auto_args && arg_config == None {
+            if auto_args && arg_config == None && !var_config.no_arg() {
                 arg_config = Some(ArgumentConfigVariant::Shorthand(key.clone()));
             }
 
@@ -126,6 +1025,10 @@ fn create_args(options: &Options, variable_config_map: &VariableConfigMap) -> Ve
                 // Use the variable key as the ID so we can link this arg to the variable
                 let mut arg = Arg::new(key.clone());
 
+                // Clap arguments can't be both positional and named, so variables bound to both
+                // have a separate argument for the named form
+                let mut flag_arg = None;
+
                 arg = match arg_config {
                     // Shorthand args only set the long version
                     ArgumentConfigVariant::Shorthand(arg_name) => arg.long(arg_name),
@@ -137,11 +1040,31 @@ fn create_args(options: &Options, variable_config_map: &VariableConfigMap) -> Ve
                             arg = arg.short(short_arg_name)
                         }
 
-                        if let Some(description) = named_arg_config.description {
+                        if let Some(description) = &named_arg_config.description {
                             arg = arg.help(description)
                         }
 
-                        arg
+                        arg = arg.hide(named_arg_config.hidden);
+
+                        // Named arguments with a position can be provided either way
+                        if let Some(position) = named_arg_config.position {
+                            flag_arg = Some(
+                                arg.id(flag_arg_id(key))
+                                    .value_name(key.clone())
+                                    .conflicts_with(key.clone()),
+                            );
+
+                            let mut positional_arg = Arg::new(key.clone())
+                                .index(position)
+                                .hide(named_arg_config.hidden);
+                            if let Some(description) = named_arg_config.description {
+                                positional_arg = positional_arg.help(description)
+                            }
+
+                            positional_arg
+                        } else {
+                            arg
+                        }
                     }
 
                     // Positional arguments only set the position and description
@@ -152,7 +1075,7 @@ fn create_args(options: &Options, variable_config_map: &VariableConfigMap) -> Ve
                             arg = arg.help(description)
                         }
 
-                        arg
+                        arg.hide(positional_arg_config.hidden)
                     }
                 };
 
@@ -163,39 +1086,96 @@ fn create_args(options: &Options, variable_config_map: &VariableConfigMap) -> Ve
                     _ => {}
                 }
 
-                return Some(arg);
+                return iter::once(arg).chain(flag_arg).collect();
             }
 
-            return None;
+            return Vec::new();
+        })
+        .collect()
+}
+
+/// Returns the flags (e.g. `--profile` and `-p`) of the arguments created for the provided
+/// variables.
+pub fn variable_flags(auto_args: bool, variable_configs: &VariableConfigMap) -> Vec<String> {
+    create_args(auto_args, variable_configs)
+        .iter()
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{}", long));
+            let short = arg.get_short().map(|short| format!("-{}", short));
+            long.into_iter().chain(short)
         })
-        .filter(|arg| arg.is_some())
-        .map(|arg| arg.unwrap())
         .collect()
 }
 
 /// Finds the [`CommandConfig`], [`VariableConfigMap`], and [`ArgMatches`], matching the provided `arg_matches`.
 /// This essentially returns the command to invoke (and it's relevent [`ArgMatches`]), all the variables available to the command.
-pub fn find_subcommand(
+pub fn find_subcommand<'a>(
+    arg_matches: &'a ArgMatches,
+    parent_command: &Command,
+    available_commands: &'a CommandConfigMap,
+    parent_variables: &'a VariableConfigMap,
+) -> Option<SubcommandSearchResult<'a>> {
+    let (command_config, available_variables, subcommand_matches) = find_subcommand_with_refs(
+        arg_matches,
+        parent_command,
+        available_commands,
+        &parent_variables.iter().collect(),
+    )?;
+
+    // Only the variables available to the matched command need to be cloned
+    let available_variables: VariableConfigMap = available_variables
+        .into_iter()
+        .map(|(key, var_config)| (key.clone(), var_config.clone()))
+        .collect();
+
+    Some((command_config, available_variables, subcommand_matches))
+}
+
+/// Returns the [`CommandConfig`]s matching the provided `arg_matches`, from the top-level command
+/// down to the command to invoke.
+pub fn find_command_path<'a>(
     arg_matches: &ArgMatches,
+    available_commands: &'a CommandConfigMap,
+) -> Vec<&'a CommandConfig> {
+    let mut path = Vec::new();
+    let mut arg_matches = arg_matches;
+    let mut available_commands = available_commands;
+
+    while let Some((subcommand_name, subcommand_matches)) = arg_matches.subcommand() {
+        let Some(command_config) =
+            find_command_by_name(&subcommand_name.to_string(), available_commands)
+        else {
+            break;
+        };
+
+        path.push(command_config);
+        arg_matches = subcommand_matches;
+        available_commands = &command_config.commands;
+    }
+
+    path
+}
+
+fn find_subcommand_with_refs<'a>(
+    arg_matches: &'a ArgMatches,
     parent_command: &Command,
-    available_commands: &CommandConfigMap,
-    parent_variables: &VariableConfigMap,
-) -> Option<SubcommandSearchResult> {
+    available_commands: &'a CommandConfigMap,
+    parent_variables: &VariableConfigRefs<'a>,
+) -> Option<(&'a CommandConfig, VariableConfigRefs<'a>, &'a ArgMatches)> {
     if let Some((subcommand_name, subcommand_matches)) = arg_matches.subcommand() {
         // Safe to unwrap: we wouldn't have matched on anything if the command didn't exist
         let subcommand = parent_command.find_subcommand(subcommand_name).unwrap();
-        let command_config = find_command_by_name(&subcommand_name.to_string(), available_commands)
-            .unwrap()
-            .to_owned();
+        let command_config =
+            find_command_by_name(&subcommand_name.to_string(), available_commands).unwrap();
 
         // Add the subcommands variables to the variables provided by the parent
         let mut available_variables = parent_variables.clone();
-        available_variables.extend(command_config.variables.clone());
+        available_variables.extend(command_config.variables.iter());
 
         // If we've matched another subcommand, return that one instead
-        let matched_subcommand = find_subcommand(
-            &subcommand_matches,
-            &subcommand,
+        let matched_subcommand = find_subcommand_with_refs(
+            subcommand_matches,
+            subcommand,
             &command_config.commands,
             &available_variables,
         );
@@ -204,21 +1184,29 @@ pub fn find_subcommand(
         }
 
         // If no more subcommand matches exist, then return the current subcommand
-        let result: SubcommandSearchResult = (
-            command_config.clone(),
-            available_variables,
-            subcommand_matches.clone(),
-        );
-        return Some(result);
+        return Some((command_config, available_variables, subcommand_matches));
     }
 
     return None;
 }
 
-fn find_command_by_name(
+/// Returns the names of the subcommands matched by the provided `arg_matches`, from the top-level
+/// command down (e.g. `["deploy", "store"]`).
+pub fn command_path(arg_matches: &ArgMatches) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut current_matches = arg_matches;
+    while let Some((subcommand_name, subcommand_matches)) = current_matches.subcommand() {
+        path.push(subcommand_name.to_string());
+        current_matches = subcommand_matches;
+    }
+
+    path
+}
+
+fn find_command_by_name<'a>(
     command_name: &String,
-    available_commands: &CommandConfigMap,
-) -> Option<CommandConfig> {
+    available_commands: &'a CommandConfigMap,
+) -> Option<&'a CommandConfig> {
     let found_command = available_commands.iter().find(|(key, command_config)| {
         if let Some(overridden_name) = &command_config.name {
             if command_name == overridden_name {
@@ -234,27 +1222,30 @@ fn find_command_by_name(
     });
 
     if let Some((_, found_command)) = found_command {
-        return Some(found_command.clone());
+        return Some(found_command);
     }
 
     return None;
 }
 
-type SubcommandSearchResult = (CommandConfig, VariableConfigMap, ArgMatches);
+type SubcommandSearchResult<'a> = (&'a CommandConfig, VariableConfigMap, &'a ArgMatches);
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::args::{ArgumentResolver, ClapArgumentResolver};
+    use crate::config::parse_config;
     use crate::config::ArgumentConfigVariant::Named;
     use crate::config::OneOrManyPlatforms::{Many, One};
     use crate::config::RawCommandConfigVariant::Shorthand;
     use crate::config::{
-        ActionConfig, AliasActionConfig, CommandConfig, ExecutionVariableConfig,
-        LiteralVariableConfig, ManyPlatforms, OnePlatform, Options, Platform,
-        PositionalArgumentConfig, PromptConfig, PromptVariableConfig, SingleActionConfig,
+        ActionConfig, AliasActionConfig, CommandConfig, ExecutionErrorPolicy,
+        ExecutionVariableConfig, LiteralVariableConfig, ManyPlatforms, OnePlatform, Options,
+        Platform, PositionalArgumentConfig, PromptConfig, PromptVariableConfig, SingleActionConfig,
         VariableConfig,
     };
     use crate::platform::MockPlatformProvider;
+    use std::sync::Arc;
 
     fn mock_platform_provider() -> Box<dyn PlatformProvider> {
         let mut platform_provider = MockPlatformProvider::new();
@@ -276,6 +1267,24 @@ mod tests {
                 platform: None,
                 description: Some("Sub 1 description".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -299,6 +1308,24 @@ mod tests {
                 platform: None,
                 description: Some("Sub 2 description".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subcommand_variables,
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -357,6 +1384,13 @@ mod tests {
                 )),
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
             }),
         );
         subcommand_variables.insert(
@@ -366,10 +1400,14 @@ mod tests {
                     description: Some("Sub arg 2".to_string()),
                     long: "sub-arg-2".to_string(),
                     short: None,
+                    position: None,
+                    hidden: false,
                 })),
                 environment_variable_name: None,
+                no_arg: false,
                 prompt: PromptConfig {
                     message: "What's your name?".to_string(),
+                    default: None,
                     options: Default::default(),
                 },
             }),
@@ -383,6 +1421,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subcommand_variables,
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -404,6 +1460,8 @@ mod tests {
                 value: "bar".to_string(),
                 argument: Some(ArgumentConfigVariant::Shorthand("parent-arg-2".to_string())),
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
@@ -456,10 +1514,14 @@ mod tests {
                     description: Some("Sub arg 2".to_string()),
                     long: "sub-arg-2".to_string(),
                     short: None,
+                    position: None,
+                    hidden: false,
                 })),
                 environment_variable_name: None,
+                no_arg: false,
                 prompt: PromptConfig {
                     message: "What's your name?".to_string(),
+                    default: None,
                     options: Default::default(),
                 },
             }),
@@ -473,6 +1535,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subsubcommand_variables,
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -492,6 +1572,13 @@ mod tests {
                 )),
                 argument: Some(ArgumentConfigVariant::Shorthand("sub-arg-1".to_string())),
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
             }),
         );
 
@@ -503,6 +1590,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subcommand_variables,
                 commands: subsubcommands,
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -556,6 +1661,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -574,6 +1697,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: subsubcommands,
                 action: None,
@@ -610,6 +1751,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::Alias(AliasActionConfig {
@@ -656,6 +1815,24 @@ mod tests {
                 platform: None,
                 description: None,
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -694,6 +1871,24 @@ mod tests {
                 })),
                 description: Some("Demo command on Linux.".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -713,6 +1908,24 @@ mod tests {
                 })),
                 description: Some("Demo command on macOS.".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -732,6 +1945,24 @@ mod tests {
                 })),
                 description: Some("Demo command on Unix.".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -751,6 +1982,24 @@ mod tests {
                 })),
                 description: Some("Demo command on Windows.".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -794,6 +2043,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn create_commands_excludes_commands_whose_condition_is_false() {
+        // Arrange
+        let dir = tempfile::TempDir::new().unwrap();
+        let manifest_path = dir.path().join("Cargo.toml");
+        std::fs::write(&manifest_path, "").unwrap();
+        let yaml = format!(
+            "commands:
+    cargo:
+        when: exists('{}')
+        action: cargo build
+    npm:
+        when: exists('{}')
+        action: npm run build
+    broken:
+        when: exists(
+        action: echo broken",
+            manifest_path.display(),
+            dir.path().join("package.json").display()
+        );
+        let config = parse_config(&yaml, Platform::Linux, None).unwrap();
+
+        // Act
+        let created_subcommands = create_commands(
+            &config.options,
+            &config.commands,
+            &config.variables,
+            &Box::new(mock_platform_provider()),
+        );
+
+        // Assert
+        let names: Vec<&str> = created_subcommands
+            .iter()
+            .map(|command| command.get_name())
+            .collect();
+        assert_eq!(names, vec!["cargo"]);
+    }
+
     #[test]
     fn create_args_creates_correct_args() {
         // Arrange
@@ -810,6 +2097,8 @@ mod tests {
                 value: "bar".to_string(),
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
         variables.insert(
@@ -820,6 +2109,13 @@ mod tests {
                 )),
                 argument: Some(ArgumentConfigVariant::Shorthand("var-3".to_string())),
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
             }),
         );
         variables.insert(
@@ -829,10 +2125,14 @@ mod tests {
                     description: Some("Fourth variable".to_string()),
                     long: "name".to_string(),
                     short: Some('v'),
+                    position: None,
+                    hidden: false,
                 })),
                 environment_variable_name: None,
+                no_arg: false,
                 prompt: PromptConfig {
                     message: "What's your name?".to_string(),
+                    default: None,
                     options: Default::default(),
                 },
             }),
@@ -844,18 +2144,21 @@ mod tests {
                     PositionalArgumentConfig {
                         description: Some("Fifth variable".to_string()),
                         position: 1,
+                        hidden: false,
                     },
                 )),
                 environment_variable_name: None,
+                no_arg: false,
                 prompt: PromptConfig {
                     message: "What's your age?".to_string(),
+                    default: None,
                     options: Default::default(),
                 },
             }),
         );
 
         // Act
-        let args = create_args(&options, &variables);
+        let args = create_args(options.auto_args, &variables);
 
         // Assert
         let var1 = args.iter().find(|v| v.get_id() == "var-1");
@@ -881,9 +2184,8 @@ mod tests {
     fn auto_args_creates_correct_args() {
         // Arrange
         let options = Options {
-            print_commands: false,
-            print_variables: false,
             auto_args: true,
+            ..Options::default()
         };
 
         let mut variables = VariableConfigMap::new();
@@ -893,6 +2195,8 @@ mod tests {
                 value: "foo".to_string(),
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
@@ -902,11 +2206,13 @@ mod tests {
                 value: "bar".to_string(),
                 argument: Some(ArgumentConfigVariant::Shorthand("existing".to_string())),
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
         // Act
-        let args = create_args(&options, &variables);
+        let args = create_args(options.auto_args, &variables);
 
         // Assert
         let var1 = args.iter().find(|v| v.get_id() == "var-1").unwrap();
@@ -919,6 +2225,157 @@ mod tests {
         assert_eq!(var2.get_default_values(), ["bar"]);
     }
 
+    #[test]
+    fn literal_defaults_are_rendered_in_help() {
+        // Arrange
+        let mut variables = VariableConfigMap::new();
+        variables.insert(
+            "target".to_string(),
+            VariableConfig::Literal(LiteralVariableConfig {
+                value: "debug".to_string(),
+                argument: Some(ArgumentConfigVariant::Named(NamedArgumentConfig {
+                    long: "target".to_string(),
+                    short: None,
+                    position: None,
+                    description: Some("The build target".to_string()),
+                    hidden: false,
+                })),
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+            }),
+        );
+
+        let args = create_args(false, &variables);
+
+        // Act
+        let help = Command::new("build").args(args).render_help().to_string();
+
+        // Assert
+        assert!(help.contains("The build target [default: debug]"));
+    }
+
+    #[test]
+    fn hidden_args_are_not_rendered_in_help() {
+        // Arrange
+        let mut variables = VariableConfigMap::new();
+        variables.insert(
+            "batch-size".to_string(),
+            VariableConfig::Literal(LiteralVariableConfig {
+                value: "100".to_string(),
+                argument: Some(ArgumentConfigVariant::Named(NamedArgumentConfig {
+                    long: "batch-size".to_string(),
+                    short: None,
+                    position: None,
+                    description: None,
+                    hidden: true,
+                })),
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+            }),
+        );
+
+        let args = create_args(false, &variables);
+
+        // Act
+        let command = Command::new("import").args(args);
+        let help = command.clone().render_help().to_string();
+        let arg_matches = command.try_get_matches_from(["import", "--batch-size", "5"]);
+
+        // Assert
+        assert!(!help.contains("--batch-size"));
+        assert_eq!(
+            arg_matches
+                .unwrap()
+                .get_one::<String>("batch-size")
+                .unwrap(),
+            "5"
+        );
+    }
+
+    #[test]
+    fn positional_named_args_can_be_provided_either_way() {
+        // Arrange
+        let mut variables = VariableConfigMap::new();
+        variables.insert(
+            "env".to_string(),
+            VariableConfig::Literal(LiteralVariableConfig {
+                value: "dev".to_string(),
+                argument: Some(ArgumentConfigVariant::Named(NamedArgumentConfig {
+                    long: "env".to_string(),
+                    short: None,
+                    position: Some(1),
+                    description: None,
+                    hidden: false,
+                })),
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+            }),
+        );
+
+        let command = Command::new("deploy").args(create_args(false, &variables));
+        let resolve = |args: &[&str]| {
+            let arg_matches = command.clone().try_get_matches_from(args).unwrap();
+            ClapArgumentResolver::from_arg_matches(Arc::new(arg_matches)).get(&"env".to_string())
+        };
+
+        // Act / Assert
+        assert_eq!(resolve(&["deploy", "prod"]), Some("prod".to_string()));
+        assert_eq!(
+            resolve(&["deploy", "--env", "prod"]),
+            Some("prod".to_string())
+        );
+        assert_eq!(resolve(&["deploy"]), Some("dev".to_string()));
+        assert!(command
+            .clone()
+            .try_get_matches_from(["deploy", "prod", "--env", "prod"])
+            .is_err());
+    }
+
+    #[test]
+    fn auto_args_can_be_disabled_per_command_and_variable() {
+        // Arrange
+        let yaml = "options:
+    auto_args: true
+variables:
+    region: us-east-1
+    token:
+        prompt:
+            message: Token?
+        no_arg: true
+commands:
+    build:
+        action: cargo build
+    deploy:
+        auto_args: false
+        commands:
+            store:
+                action: ./deploy.sh";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let root_command = create_root_command(&config, &mock_platform_provider());
+
+        // Assert
+        let build_command = root_command.find_subcommand("build").unwrap();
+        assert!(build_command
+            .get_arguments()
+            .any(|arg| arg.get_id() == "region"));
+        assert!(!build_command
+            .get_arguments()
+            .any(|arg| arg.get_id() == "token"));
+
+        let store_command = root_command
+            .find_subcommand("deploy")
+            .and_then(|deploy_command| deploy_command.find_subcommand("store"))
+            .unwrap();
+        assert!(!store_command
+            .get_arguments()
+            .any(|arg| arg.get_id() == "region"));
+    }
+
     #[test]
     fn find_subcommand_finds_top_level_command() {
         // Arrange
@@ -942,6 +2399,24 @@ mod tests {
                 platform: None,
                 description: Some("Top-level command".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subcommand_variables,
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -958,6 +2433,9 @@ mod tests {
             variables: root_variables,
             commands: commands,
             options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
         };
 
         let platform_provider = mock_platform_provider();
@@ -1013,6 +2491,24 @@ mod tests {
                 platform: None,
                 description: Some("Subcommand".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: subcommand_variables,
                 commands: CommandConfigMap::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1031,6 +2527,24 @@ mod tests {
                 platform: None,
                 description: Some("Mid-level command".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: command_variables,
                 commands: subcommands,
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1049,6 +2563,24 @@ mod tests {
                 platform: None,
                 description: Some("Top-level command".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: parent_command_variables,
                 commands: target_commands,
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1065,6 +2597,9 @@ mod tests {
             variables: root_variables,
             commands: parent_commands,
             options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
         };
 
         let platform_provider = mock_platform_provider();
@@ -1118,6 +2653,24 @@ mod tests {
                 platform: None,
                 description: Some("Bottom-level command".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: command_variables,
                 commands: CommandConfigMap::new(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1136,6 +2689,24 @@ mod tests {
                 platform: None,
                 description: Some("Top-level command".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: parent_command_variables,
                 commands: target_commands,
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1152,6 +2723,9 @@ mod tests {
             variables: root_variables,
             commands: parent_commands,
             options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
         };
 
         let platform_provider = mock_platform_provider();
@@ -1173,6 +2747,7 @@ mod tests {
         assert!(found_variables.contains_key("root-var-1"));
         assert!(found_variables.contains_key("parent-var-1"));
         assert!(found_variables.contains_key("sub-var-1"));
+        assert_eq!(command_path(&matches), vec!["parent", "subcommand"]);
     }
 
     #[test]
@@ -1185,6 +2760,24 @@ mod tests {
                 platform: None,
                 description: Some("Command with custom name".to_string()),
                 hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 variables: Default::default(),
                 commands: Default::default(),
                 action: Some(ActionConfig::SingleStep(SingleActionConfig {
@@ -1201,6 +2794,9 @@ mod tests {
             variables: Default::default(),
             commands: commands,
             options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
         };
 
         let platform_provider = mock_platform_provider();
@@ -1229,6 +2825,24 @@ mod tests {
             CommandConfig {
                 name: Some("command".to_string()),
                 hidden: true,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
                 platform: None,
                 description: Some("Command with custom name".to_string()),
                 variables: Default::default(),
@@ -1247,6 +2861,9 @@ mod tests {
             variables: Default::default(),
             commands: commands,
             options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
         };
 
         let platform_provider = mock_platform_provider();
@@ -1266,4 +2883,134 @@ mod tests {
             Some("Command with custom name".to_string())
         );
     }
+
+    #[test]
+    fn find_env_command_finds_target_command() {
+        // Arrange
+        let config = Config {
+            imports: Default::default(),
+            description: None,
+            variables: Default::default(),
+            commands: Default::default(),
+            options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
+        };
+
+        let root_command = create_root_command(&config, &Box::new(mock_platform_provider()));
+
+        // Act
+        let matches = root_command.clone().get_matches_from(vec![
+            "plz", "env", "--format", "json", "deploy", "--env", "staging",
+        ]);
+        let env_command = find_env_command(&matches);
+
+        // Assert
+        assert_eq!(
+            env_command,
+            Some((
+                vec![
+                    "deploy".to_string(),
+                    "--env".to_string(),
+                    "staging".to_string()
+                ],
+                EnvFormat::Json
+            ))
+        );
+    }
+
+    #[test]
+    fn find_copy_command_finds_target_command() {
+        // Arrange
+        let config = Config {
+            imports: Default::default(),
+            description: None,
+            variables: Default::default(),
+            commands: Default::default(),
+            options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
+        };
+
+        let root_command = create_root_command(&config, &Box::new(mock_platform_provider()));
+
+        // Act
+        let matches = root_command
+            .clone()
+            .get_matches_from(vec!["plz", "copy", "deploy", "--env", "staging"]);
+        let copy_command = find_copy_command(&matches);
+
+        // Assert
+        assert_eq!(
+            copy_command,
+            Some(vec![
+                "deploy".to_string(),
+                "--env".to_string(),
+                "staging".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn find_completions_command_finds_shell() {
+        // Arrange
+        let config = Config {
+            imports: Default::default(),
+            description: None,
+            variables: Default::default(),
+            commands: Default::default(),
+            options: Options::default(),
+            variable_sets: Default::default(),
+            environments: Default::default(),
+            env_files: Vec::new(),
+        };
+
+        let root_command = create_root_command(&config, &Box::new(mock_platform_provider()));
+
+        // Act
+        let print_matches =
+            root_command
+                .clone()
+                .get_matches_from(vec!["plz", "completions", "fish"]);
+        let install_matches = root_command.clone().get_matches_from(vec![
+            "plz",
+            "completions",
+            "install",
+            "--shell",
+            "zsh",
+        ]);
+
+        // Assert
+        assert_eq!(
+            find_completions_command(&print_matches),
+            Some(CompletionsRequest {
+                shell: Some(Shell::Fish),
+                install: false
+            })
+        );
+        assert_eq!(
+            find_completions_command(&install_matches),
+            Some(CompletionsRequest {
+                shell: Some(Shell::Zsh),
+                install: true
+            })
+        );
+    }
+
+    #[test]
+    fn binary_name_prefers_configured_name_then_invoked_name() {
+        // Act
+        let configured = binary_name(Some("acme"), Some(OsStr::new("/usr/local/bin/plz")));
+        let symlinked = binary_name(None, Some(OsStr::new("/usr/local/bin/acme")));
+        let windows = binary_name(None, Some(OsStr::new("acme.exe")));
+        let unknown = binary_name(None, None);
+
+        // Assert
+        assert_eq!(configured, "acme");
+        assert_eq!(symlinked, "acme");
+        assert_eq!(windows, "acme");
+        assert_eq!(unknown, "plz");
+    }
 }
@@ -0,0 +1,13 @@
+use arboard::Clipboard;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("failed to copy to the clipboard")]
+pub struct ClipboardError(#[source] arboard::Error);
+
+/// Replaces the contents of the system clipboard with the provided text.
+pub fn copy(text: &str) -> Result<(), ClipboardError> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(ClipboardError)
+}
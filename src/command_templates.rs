@@ -0,0 +1,442 @@
+use linked_hash_map::LinkedHashMap;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+/// The prefix of the placeholders that are replaced with a template's parameters (e.g.
+/// `{{ params.service }}`).
+const PARAMS_PREFIX: &str = "params.";
+
+/// A reusable command defined in the `templates` section, which commands can be created from with
+/// `template` and `params`.
+struct CommandTemplate {
+    /// The parameters of the template, and their default values. Parameters without a default
+    /// value must be provided by every command using the template.
+    params: LinkedHashMap<String, Option<Value>>,
+
+    /// The command's fields, which may contain placeholders for the parameters.
+    command: Value,
+}
+
+/// Expands any commands created from a template in the provided config, and removes the
+/// `templates` section.
+/// Templates are expanded before the config is parsed, so the rest of plz only ever sees regular
+/// commands.
+/// Returns `true` if the config was changed.
+pub fn expand(config: &mut Value) -> Result<bool, CommandTemplateError> {
+    let Some(mapping) = config.as_mapping_mut() else {
+        return Ok(false);
+    };
+
+    let templates = match mapping.remove("templates") {
+        Some(templates) => parse_templates(templates)?,
+        None => LinkedHashMap::new(),
+    };
+    let expanded = expand_commands(mapping, "", &templates, &mut Vec::new())?;
+
+    Ok(expanded || !templates.is_empty())
+}
+
+fn parse_templates(
+    value: Value,
+) -> Result<LinkedHashMap<String, CommandTemplate>, CommandTemplateError> {
+    let mut templates = LinkedHashMap::new();
+    let Value::Mapping(mapping) = value else {
+        return Ok(templates);
+    };
+
+    for (name, template) in mapping {
+        let name = key_text(&name);
+        let Value::Mapping(mut template) = template else {
+            return Err(CommandTemplateError::MissingCommand { template: name });
+        };
+
+        let command =
+            template
+                .remove("command")
+                .ok_or_else(|| CommandTemplateError::MissingCommand {
+                    template: name.clone(),
+                })?;
+
+        // Parameters can be listed by name if none of them have a default value
+        let params = match template.remove("params") {
+            Some(Value::Mapping(params)) => params
+                .into_iter()
+                .map(|(param, default)| (key_text(&param), Some(default).filter(|v| !v.is_null())))
+                .collect(),
+            Some(Value::Sequence(params)) => {
+                params.iter().map(|param| (key_text(param), None)).collect()
+            }
+            _ => LinkedHashMap::new(),
+        };
+
+        templates.insert(name, CommandTemplate { params, command });
+    }
+
+    Ok(templates)
+}
+
+/// Expands the templates used by the commands in the provided config or command.
+/// `stack` contains the templates currently being expanded, so templates can't use themselves.
+fn expand_commands(
+    mapping: &mut Mapping,
+    path: &str,
+    templates: &LinkedHashMap<String, CommandTemplate>,
+    stack: &mut Vec<String>,
+) -> Result<bool, CommandTemplateError> {
+    let mut expanded = false;
+    for key in ["commands", "cmds"] {
+        let Some(Value::Mapping(commands)) = mapping.get_mut(key) else {
+            continue;
+        };
+
+        for (name, command) in commands.iter_mut() {
+            let path = join(path, &format!("commands.{}", key_text(name)));
+            expanded |= expand_command(command, &path, templates, stack)?;
+        }
+    }
+
+    Ok(expanded)
+}
+
+fn expand_command(
+    command: &mut Value,
+    path: &str,
+    templates: &LinkedHashMap<String, CommandTemplate>,
+    stack: &mut Vec<String>,
+) -> Result<bool, CommandTemplateError> {
+    let Some(mapping) = command.as_mapping_mut() else {
+        return Ok(false);
+    };
+
+    let Some(template_name) = mapping.remove("template") else {
+        return expand_commands(mapping, path, templates, stack);
+    };
+
+    let template_name = key_text(&template_name);
+    if stack.contains(&template_name) {
+        return Err(CommandTemplateError::Recursive {
+            template: template_name,
+        });
+    }
+
+    let template =
+        templates
+            .get(&template_name)
+            .ok_or_else(|| CommandTemplateError::UnknownTemplate {
+                command: path.to_string(),
+                template: template_name.clone(),
+            })?;
+
+    let provided = match mapping.remove("params") {
+        Some(Value::Mapping(provided)) => provided,
+        _ => Mapping::new(),
+    };
+    let params = resolve_params(template, &template_name, path, provided)?;
+
+    // Any fields set on the command itself take precedence over the template's
+    let mut expanded = substitute(&template.command, &template_name, &params)?;
+    merge(&mut expanded, Value::Mapping(mapping.clone()));
+    *command = expanded;
+
+    // The template's subcommands may use other templates
+    stack.push(template_name);
+    if let Some(mapping) = command.as_mapping_mut() {
+        expand_commands(mapping, path, templates, stack)?;
+    }
+    stack.pop();
+
+    Ok(true)
+}
+
+/// Returns the value of each of the template's parameters, using the default value for any
+/// parameters that weren't provided.
+fn resolve_params(
+    template: &CommandTemplate,
+    template_name: &str,
+    path: &str,
+    provided: Mapping,
+) -> Result<LinkedHashMap<String, Value>, CommandTemplateError> {
+    let mut provided: LinkedHashMap<String, Value> = provided
+        .into_iter()
+        .map(|(param, value)| (key_text(&param), value))
+        .collect();
+
+    if let Some(param) = provided
+        .keys()
+        .find(|param| !template.params.contains_key(*param))
+    {
+        return Err(CommandTemplateError::UnknownParam {
+            command: path.to_string(),
+            template: template_name.to_string(),
+            param: param.clone(),
+        });
+    }
+
+    template
+        .params
+        .iter()
+        .map(|(param, default)| {
+            let value = provided
+                .remove(param)
+                .or_else(|| default.clone())
+                .ok_or_else(|| CommandTemplateError::MissingParam {
+                    command: path.to_string(),
+                    template: template_name.to_string(),
+                    param: param.clone(),
+                })?;
+            Ok((param.clone(), value))
+        })
+        .collect()
+}
+
+/// Replaces the placeholders in the provided value (including any mapping keys) with the
+/// parameters.
+fn substitute(
+    value: &Value,
+    template_name: &str,
+    params: &LinkedHashMap<String, Value>,
+) -> Result<Value, CommandTemplateError> {
+    Ok(match value {
+        Value::String(text) => substitute_text(text, template_name, params)?,
+        Value::Sequence(sequence) => Value::Sequence(
+            sequence
+                .iter()
+                .map(|value| substitute(value, template_name, params))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Mapping(mapping) => {
+            let mut substituted = Mapping::new();
+            for (key, value) in mapping {
+                substituted.insert(
+                    substitute(key, template_name, params)?,
+                    substitute(value, template_name, params)?,
+                );
+            }
+            Value::Mapping(substituted)
+        }
+        _ => value.clone(),
+    })
+}
+
+/// Replaces the placeholders in the provided text with the parameters.
+/// If the text is a single placeholder, the parameter's value is used as is, so parameters can be
+/// used for fields which aren't strings (e.g. `hidden: "{{ params.hidden }}"`).
+/// Any other `{{ ... }}` tags are left as is.
+fn substitute_text(
+    text: &str,
+    template_name: &str,
+    params: &LinkedHashMap<String, Value>,
+) -> Result<Value, CommandTemplateError> {
+    let param = |name: &str| {
+        params
+            .get(name)
+            .ok_or_else(|| CommandTemplateError::UndeclaredParam {
+                template: template_name.to_string(),
+                param: name.to_string(),
+            })
+    };
+
+    if let Some(name) = placeholder_name(text.trim()) {
+        return param(name).cloned();
+    }
+
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}").map(|end| end + 2) else {
+            break;
+        };
+
+        let tag = &rest[start..start + length];
+        result.push_str(&rest[..start]);
+        match placeholder_name(tag) {
+            Some(name) => result.push_str(&value_text(param(name)?)),
+            None => result.push_str(tag),
+        }
+        rest = &rest[start + length..];
+    }
+    result.push_str(rest);
+
+    Ok(Value::String(result))
+}
+
+/// Returns the name of the parameter if the provided text is a placeholder (e.g.
+/// `{{ params.service }}`).
+fn placeholder_name(text: &str) -> Option<&str> {
+    let inner = text.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    inner
+        .strip_prefix(PARAMS_PREFIX)
+        .filter(|name| !name.contains(char::is_whitespace))
+}
+
+/// Merges the fields of `overrides` into `base`, replacing any fields that aren't mappings.
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Mapping(base), Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => serde_yaml::to_string(value)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn key_text(key: &Value) -> String {
+    match key {
+        Value::String(text) => text.clone(),
+        key => value_text(key),
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CommandTemplateError {
+    #[error("{command} uses the template \"{template}\", which doesn't exist")]
+    UnknownTemplate { command: String, template: String },
+
+    #[error("the template \"{template}\" doesn't have a command")]
+    MissingCommand { template: String },
+
+    #[error("{command} doesn't provide the \"{param}\" parameter of the template \"{template}\"")]
+    MissingParam {
+        command: String,
+        template: String,
+        param: String,
+    },
+
+    #[error("{command} provides the \"{param}\" parameter, which the template \"{template}\" doesn't have")]
+    UnknownParam {
+        command: String,
+        template: String,
+        param: String,
+    },
+
+    #[error(
+        "the template \"{template}\" uses the \"{param}\" parameter without declaring it in params"
+    )]
+    UndeclaredParam { template: String, param: String },
+
+    #[error("the template \"{template}\" uses itself")]
+    Recursive { template: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, ActionConfig, Platform};
+
+    #[test]
+    fn expand_creates_commands_from_templates() {
+        // Arrange
+        let yaml = r#"
+templates:
+  docker-service:
+    params:
+      service:
+      port: 8080
+    command:
+      description: Manage the {{ params.service }} service
+      commands:
+        build:
+          action: docker build -t {{ params.service }} ./services/{{ params.service }}
+        run:
+          action: docker run -p {{ params.port }}:{{ params.port }} {{ params.service }}
+
+commands:
+  api:
+    template: docker-service
+    params:
+      service: api
+  web:
+    template: docker-service
+    params:
+      service: web
+      port: 3000
+    description: Manage the website
+"#;
+
+        // Act
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Assert
+        let command_text = |command: &str, subcommand: &str| match &config.commands[command]
+            .commands[subcommand]
+            .action
+        {
+            Some(ActionConfig::SingleStep(action)) => action.action.command().to_string(),
+            _ => panic!("expected a single step action"),
+        };
+        assert_eq!(
+            config.commands["api"].description.as_deref(),
+            Some("Manage the api service")
+        );
+        assert_eq!(
+            config.commands["web"].description.as_deref(),
+            Some("Manage the website")
+        );
+        assert_eq!(
+            command_text("api", "build"),
+            "docker build -t api ./services/api"
+        );
+        assert_eq!(command_text("api", "run"), "docker run -p 8080:8080 api");
+        assert_eq!(command_text("web", "run"), "docker run -p 3000:3000 web");
+    }
+
+    #[test]
+    fn expand_rejects_missing_and_unknown_params() {
+        // Arrange
+        let config = |params: &str| {
+            serde_yaml::from_str::<Value>(&format!(
+                r#"
+templates:
+  service:
+    params: [name]
+    command:
+      action: echo {{{{ params.name }}}}
+commands:
+  api:
+    template: service
+    params: {}
+"#,
+                params
+            ))
+            .unwrap()
+        };
+
+        // Act
+        let missing = expand(&mut config("{}"));
+        let unknown = expand(&mut config("{ name: api, port: 80 }"));
+
+        // Assert
+        assert!(matches!(
+            missing,
+            Err(CommandTemplateError::MissingParam { param, .. }) if param == "name"
+        ));
+        assert!(matches!(
+            unknown,
+            Err(CommandTemplateError::UnknownParam { param, .. }) if param == "port"
+        ));
+    }
+}
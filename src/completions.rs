@@ -0,0 +1,215 @@
+use clap::Command;
+use clap_complete::Shell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CompletionsError {
+    #[error("couldn't detect the current shell, try specifying one explicitly")]
+    UnknownShell,
+
+    #[error(
+        "installing completions for {shell} isn't supported, try `plz completions {shell}` instead"
+    )]
+    UnsupportedShell { shell: Shell },
+
+    #[error("couldn't determine the home directory")]
+    NoHomeDirectory,
+
+    #[error("failed to write to {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// A change made to the filesystem while installing completions.
+#[derive(PartialEq, Debug)]
+pub enum InstallChange {
+    /// A completion script was written to the path.
+    Wrote(PathBuf),
+
+    /// A line loading the completion script was appended to the path (usually a shell rc file).
+    Appended(PathBuf),
+
+    /// The completion script was already loaded from the path, so nothing was changed.
+    Unchanged(PathBuf),
+}
+
+/// Determines the current shell from the `SHELL` environment variable.
+pub fn detect_shell(get_env: impl Fn(&str) -> Option<String>) -> Option<Shell> {
+    get_env("SHELL").and_then(Shell::from_shell_path)
+}
+
+/// Generates the completion script for the provided [`Shell`] and root [`Command`].
+pub fn generate(shell: Shell, root_command: &mut Command) -> String {
+    let mut buf = Vec::new();
+    let bin_name = root_command.get_name().to_string();
+    clap_complete::generate(shell, root_command, bin_name, &mut buf);
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// Installs completions for the provided [`Shell`], returning the changes that were made.
+/// Rather than writing the completion script itself, a loader which runs `plz completions` is
+/// installed, so completions reflect whichever config file is in use when they're loaded.
+/// `bin_name` is the name plz is invoked as, which completions are registered for.
+pub fn install(
+    shell: Shell,
+    bin_name: &str,
+    get_env: impl Fn(&str) -> Option<String>,
+) -> Result<InstallChange, CompletionsError> {
+    let home = get_env("HOME")
+        .or_else(|| get_env("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or(CompletionsError::NoHomeDirectory)?;
+
+    match shell {
+        // bash-completion loads scripts from this directory the first time the binary is completed
+        Shell::Bash => {
+            let data_home = get_env("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .unwrap_or(home.join(".local").join("share"));
+            let path = data_home
+                .join("bash-completion")
+                .join("completions")
+                .join(bin_name);
+            write(&path, &format!("source <({bin_name} completions bash)\n"))
+        }
+
+        // fish loads scripts from this directory the first time the binary is completed
+        Shell::Fish => {
+            let config_home = get_env("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .unwrap_or(home.join(".config"));
+            let path = config_home
+                .join("fish")
+                .join("completions")
+                .join(format!("{bin_name}.fish"));
+            write(&path, &format!("{bin_name} completions fish | source\n"))
+        }
+
+        // zsh doesn't have a conventional location for user completions, so use the rc file
+        Shell::Zsh => {
+            let zdotdir = get_env("ZDOTDIR").map(PathBuf::from).unwrap_or(home);
+            append(
+                &zdotdir.join(".zshrc"),
+                &format!("source <({bin_name} completions zsh)"),
+            )
+        }
+
+        _ => Err(CompletionsError::UnsupportedShell { shell }),
+    }
+}
+
+fn write(path: &Path, contents: &str) -> Result<InstallChange, CompletionsError> {
+    let map_err = |source| CompletionsError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(InstallChange::Unchanged(path.to_path_buf()));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(map_err)?;
+    }
+    fs::write(path, contents).map_err(map_err)?;
+
+    Ok(InstallChange::Wrote(path.to_path_buf()))
+}
+
+fn append(path: &Path, line: &str) -> Result<InstallChange, CompletionsError> {
+    let map_err = |source| CompletionsError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    if existing
+        .lines()
+        .any(|existing_line| existing_line.trim() == line)
+    {
+        return Ok(InstallChange::Unchanged(path.to_path_buf()));
+    }
+
+    let mut contents = existing;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(line);
+    contents.push('\n');
+    fs::write(path, contents).map_err(map_err)?;
+
+    Ok(InstallChange::Appended(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detect_shell_uses_shell_env_var() {
+        // Act
+        let shell = detect_shell(|name| match name {
+            "SHELL" => Some("/usr/bin/zsh".to_string()),
+            _ => None,
+        });
+
+        // Assert
+        assert_eq!(shell, Some(Shell::Zsh));
+    }
+
+    #[test]
+    fn install_writes_loader_for_bash() {
+        // Arrange
+        let home = TempDir::new().unwrap();
+        let get_env = |name: &str| match name {
+            "HOME" => Some(home.path().to_string_lossy().to_string()),
+            _ => None,
+        };
+
+        // Act
+        let first = install(Shell::Bash, "plz", get_env).unwrap();
+        let second = install(Shell::Bash, "plz", get_env).unwrap();
+
+        // Assert
+        let path = home
+            .path()
+            .join(".local/share/bash-completion/completions/plz");
+        assert_eq!(first, InstallChange::Wrote(path.clone()));
+        assert_eq!(second, InstallChange::Unchanged(path.clone()));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "source <(plz completions bash)\n"
+        );
+    }
+
+    #[test]
+    fn install_appends_loader_to_zshrc() {
+        // Arrange
+        let home = TempDir::new().unwrap();
+        let zshrc = home.path().join(".zshrc");
+        fs::write(&zshrc, "export EDITOR=vim").unwrap();
+        let get_env = |name: &str| match name {
+            "HOME" => Some(home.path().to_string_lossy().to_string()),
+            _ => None,
+        };
+
+        // Act
+        let first = install(Shell::Zsh, "acme", get_env).unwrap();
+        let second = install(Shell::Zsh, "acme", get_env).unwrap();
+
+        // Assert
+        assert_eq!(first, InstallChange::Appended(zshrc.clone()));
+        assert_eq!(second, InstallChange::Unchanged(zshrc.clone()));
+        assert_eq!(
+            fs::read_to_string(zshrc).unwrap(),
+            "export EDITOR=vim\nsource <(acme completions zsh)\n"
+        );
+    }
+}
@@ -0,0 +1,645 @@
+use crate::actions::{EXIT_CODE_VARIABLE_NAME, FAILED_STEP_VARIABLE_NAME};
+use crate::exec::ExitStatus;
+use crate::variables::{
+    substitute_variables, VariableMap, HOSTNAME_VARIABLE_NAME, PLATFORM_VARIABLE_NAME,
+    USER_VARIABLE_NAME,
+};
+use std::collections::HashMap;
+use std::env;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+use thiserror::Error;
+
+/// The result of a step that has been evaluated.
+#[derive(PartialEq, Debug, Clone)]
+pub struct StepResult {
+    /// The [`ExitStatus`] of the step, or `None` if the step was skipped.
+    pub status: Option<ExitStatus>,
+
+    /// The output the step wrote to stdout.
+    pub stdout: String,
+}
+
+impl StepResult {
+    pub fn skipped() -> StepResult {
+        StepResult {
+            status: None,
+            stdout: String::new(),
+        }
+    }
+}
+
+/// A [`HashMap`] where the key is the step name, and the value is that steps [`StepResult`].
+pub type StepResultMap = HashMap<String, StepResult>;
+
+/// The values that can be referenced from within a condition.
+pub struct ConditionContext<'a> {
+    pub steps: &'a StepResultMap,
+    pub variables: &'a VariableMap,
+}
+
+/// Evaluates a condition such as `steps.build.success` or `steps.check.stdout contains "dirty"`.
+///
+/// Conditions support the following syntax:
+/// - `steps.<name>.success`, `steps.<name>.failure`, and `steps.<name>.skipped` evaluate to a boolean.
+/// - `steps.<name>.stdout` and `steps.<name>.exit_code` evaluate to a string.
+/// - `env.<name>` evaluates to the value of an environment variable, or an empty string if it isn't
+///   set.
+/// - `plz.exit_code` and `plz.failed_step` evaluate to the exit code and name (or index) of the
+///   action that failed, or an empty string outside of an `on_failure` handler.
+/// - `plz.user` and `plz.hostname` evaluate to the name of the user executing plz and the hostname
+///   of the machine.
+/// - `plz.platform` evaluates to the platform plz is executing on (e.g. `Windows`).
+/// - `<name>` evaluates to the value of a variable (e.g. `profile == "prod"`).
+/// - `"text"` and `'text'` are string literals. Variables are substituted within string literals.
+/// - References can also be wrapped in braces, like variables (e.g. `{{ env.CI }} == 'true'`).
+/// - `exists("<path>")` evaluates to `true` if the file or directory exists.
+/// - `on_path("<name>")` evaluates to `true` if an executable with the provided name is on the
+///   `PATH`.
+/// - `==`, `!=`, and `contains` compare two strings.
+/// - `!`, `&&`, `||`, and parentheses can be used to combine conditions.
+pub fn evaluate(condition: &str, context: &ConditionContext) -> Result<bool, ConditionError> {
+    let tokens = tokenize(condition)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        context,
+    };
+
+    let value = parser.parse_or()?;
+    if let Some(token) = parser.tokens.get(parser.position) {
+        return Err(ConditionError::UnexpectedToken(token.to_string()));
+    }
+
+    value.as_bool()
+}
+
+/// Evaluates a condition which is checked before any variables or steps exist (e.g. the `when`
+/// condition of a command), so only environment variables can be referenced.
+pub fn evaluate_without_context(condition: &str) -> Result<bool, ConditionError> {
+    evaluate_with_variables(condition, &VariableMap::new())
+}
+
+/// Evaluates a condition which is checked before any steps exist (e.g. the `when` condition of an
+/// import), so only the provided variables and environment variables can be referenced.
+pub fn evaluate_with_variables(
+    condition: &str,
+    variables: &VariableMap,
+) -> Result<bool, ConditionError> {
+    let context = ConditionContext {
+        steps: &StepResultMap::new(),
+        variables,
+    };
+
+    evaluate(condition, &context)
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum Token {
+    Identifier(String),
+    String(String),
+    Equal,
+    NotEqual,
+    Not,
+    And,
+    Or,
+    OpenParen,
+    CloseParen,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(identifier) => write!(f, "{}", identifier),
+            Token::String(string) => write!(f, "\"{}\"", string),
+            Token::Equal => write!(f, "=="),
+            Token::NotEqual => write!(f, "!="),
+            Token::Not => write!(f, "!"),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(condition: &str) -> Result<Vec<Token>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut chars = condition.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        match ch {
+            ch if ch.is_whitespace() => {
+                chars.next();
+            }
+            '"' | '\'' => {
+                chars.next();
+                tokens.push(Token::String(read_string(&mut chars, ch)?));
+            }
+            '{' => {
+                chars.next();
+                tokens.push(Token::Identifier(read_placeholder(&mut chars)?));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::OpenParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::CloseParen);
+            }
+            '=' | '!' | '&' | '|' => {
+                chars.next();
+                let token = match (ch, chars.peek()) {
+                    ('=', Some('=')) => Token::Equal,
+                    ('!', Some('=')) => Token::NotEqual,
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    ('!', _) => Token::Not,
+                    _ => return Err(ConditionError::UnexpectedToken(ch.to_string())),
+                };
+
+                if token != Token::Not {
+                    chars.next();
+                }
+
+                tokens.push(token);
+            }
+            _ => {
+                let mut identifier = String::new();
+                while let Some(&next_ch) = chars.peek() {
+                    if next_ch.is_alphanumeric() || "_-.".contains(next_ch) {
+                        identifier.push(next_ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                if identifier.is_empty() {
+                    return Err(ConditionError::UnexpectedToken(ch.to_string()));
+                }
+
+                tokens.push(Token::Identifier(identifier));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn read_string(chars: &mut Peekable<Chars>, quote: char) -> Result<String, ConditionError> {
+    let mut string = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            ch if ch == quote => return Ok(string),
+            '\\' => match chars.next() {
+                Some('n') => string.push('\n'),
+                Some(escaped) => string.push(escaped),
+                None => break,
+            },
+            ch => string.push(ch),
+        }
+    }
+
+    Err(ConditionError::UnterminatedString)
+}
+
+/// Reads a reference wrapped in braces (e.g. `{{ env.CI }}`), so references can be written the
+/// same way as variables.
+fn read_placeholder(chars: &mut Peekable<Chars>) -> Result<String, ConditionError> {
+    if chars.next() != Some('{') {
+        return Err(ConditionError::UnexpectedToken("{".to_string()));
+    }
+
+    let mut reference = String::new();
+    while let Some(ch) = chars.next() {
+        if ch == '}' {
+            if chars.next() != Some('}') {
+                break;
+            }
+
+            return Ok(reference.trim().to_string());
+        }
+
+        reference.push(ch);
+    }
+
+    Err(ConditionError::UnexpectedToken(format!(
+        "{{{{{}",
+        reference
+    )))
+}
+
+enum Value {
+    Bool(bool),
+    String(String),
+}
+
+impl Value {
+    fn as_bool(&self) -> Result<bool, ConditionError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            Value::String(value) => Err(ConditionError::ExpectedBoolean(value.clone())),
+        }
+    }
+
+    fn as_string(&self) -> String {
+        match self {
+            Value::Bool(value) => value.to_string(),
+            Value::String(value) => value.clone(),
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    context: &'a ConditionContext<'a>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Value, ConditionError> {
+        let mut value = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            let rhs = self.parse_and()?;
+            value = Value::Bool(value.as_bool()? || rhs.as_bool()?);
+        }
+
+        Ok(value)
+    }
+
+    fn parse_and(&mut self) -> Result<Value, ConditionError> {
+        let mut value = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            let rhs = self.parse_unary()?;
+            value = Value::Bool(value.as_bool()? && rhs.as_bool()?);
+        }
+
+        Ok(value)
+    }
+
+    fn parse_unary(&mut self) -> Result<Value, ConditionError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            let value = self.parse_unary()?;
+            return Ok(Value::Bool(!value.as_bool()?));
+        }
+
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Value, ConditionError> {
+        let lhs = self.parse_operand()?;
+
+        let value = match self.peek() {
+            Some(Token::Equal) => {
+                self.next();
+                let rhs = self.parse_operand()?;
+                Value::Bool(lhs.as_string() == rhs.as_string())
+            }
+            Some(Token::NotEqual) => {
+                self.next();
+                let rhs = self.parse_operand()?;
+                Value::Bool(lhs.as_string() != rhs.as_string())
+            }
+            Some(Token::Identifier(identifier)) if identifier == "contains" => {
+                self.next();
+                let rhs = self.parse_operand()?;
+                Value::Bool(lhs.as_string().contains(&rhs.as_string()))
+            }
+            _ => lhs,
+        };
+
+        Ok(value)
+    }
+
+    fn parse_operand(&mut self) -> Result<Value, ConditionError> {
+        match self.next() {
+            Some(Token::OpenParen) => {
+                let value = self.parse_or()?;
+                match self.next() {
+                    Some(Token::CloseParen) => Ok(value),
+                    Some(token) => Err(ConditionError::UnexpectedToken(token.to_string())),
+                    None => Err(ConditionError::UnexpectedEnd),
+                }
+            }
+            Some(Token::String(string)) => Ok(Value::String(substitute_variables(
+                &string,
+                self.context.variables,
+            ))),
+            Some(Token::Identifier(identifier)) if self.peek() == Some(&Token::OpenParen) => {
+                self.call_function(&identifier)
+            }
+            Some(Token::Identifier(identifier)) => self.resolve_reference(&identifier),
+            Some(token) => Err(ConditionError::UnexpectedToken(token.to_string())),
+            None => Err(ConditionError::UnexpectedEnd),
+        }
+    }
+
+    fn call_function(&mut self, name: &str) -> Result<Value, ConditionError> {
+        // The opening parenthesis has already been peeked
+        self.next();
+        let argument = self.parse_or()?.as_string();
+        match self.next() {
+            Some(Token::CloseParen) => {}
+            Some(token) => return Err(ConditionError::UnexpectedToken(token.to_string())),
+            None => return Err(ConditionError::UnexpectedEnd),
+        }
+
+        match name {
+            "exists" => Ok(Value::Bool(Path::new(&argument).exists())),
+            "on_path" => Ok(Value::Bool(is_on_path(&argument))),
+            _ => Err(ConditionError::UnknownFunction(name.to_string())),
+        }
+    }
+
+    /// Returns the value of the provided variable, or an empty string if it isn't set (e.g. the
+    /// `PLZ_EXIT_CODE` variable outside of an `on_failure` handler).
+    fn variable(&self, name: &str) -> Value {
+        Value::String(
+            self.context
+                .variables
+                .get(name)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+
+    fn resolve_reference(&self, reference: &str) -> Result<Value, ConditionError> {
+        match reference {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            _ => {}
+        }
+
+        let parts: Vec<&str> = reference.split('.').collect();
+        match parts.as_slice() {
+            ["env", name] => Ok(Value::String(env::var(name).unwrap_or_default())),
+            ["plz", "exit_code"] => Ok(self.variable(EXIT_CODE_VARIABLE_NAME)),
+            ["plz", "failed_step"] => Ok(self.variable(FAILED_STEP_VARIABLE_NAME)),
+            ["plz", "user"] => Ok(self.variable(USER_VARIABLE_NAME)),
+            ["plz", "hostname"] => Ok(self.variable(HOSTNAME_VARIABLE_NAME)),
+            ["plz", "platform"] => Ok(self.variable(PLATFORM_VARIABLE_NAME)),
+            ["steps", step_name, field] => {
+                let step_result =
+                    self.context
+                        .steps
+                        .get(*step_name)
+                        .ok_or(ConditionError::UnknownStep {
+                            name: step_name.to_string(),
+                        })?;
+
+                match *field {
+                    "success" => Ok(Value::Bool(step_result.status == Some(ExitStatus::Success))),
+                    "failure" => Ok(Value::Bool(matches!(
+                        step_result.status,
+                        Some(ExitStatus::Fail(_)) | Some(ExitStatus::Unknown)
+                    ))),
+                    "skipped" => Ok(Value::Bool(step_result.status.is_none())),
+                    "stdout" => Ok(Value::String(step_result.stdout.trim_end().to_string())),
+                    "exit_code" => Ok(Value::String(
+                        step_result
+                            .status
+                            .as_ref()
+                            .and_then(ExitStatus::code)
+                            .map(|code| code.to_string())
+                            .unwrap_or_default(),
+                    )),
+                    _ => Err(ConditionError::UnknownReference(reference.to_string())),
+                }
+            }
+            [name] if self.context.variables.contains_key(*name) => Ok(self.variable(name)),
+            _ => Err(ConditionError::UnknownReference(reference.to_string())),
+        }
+    }
+}
+
+/// Returns `true` if an executable with the provided name can be found in one of the directories
+/// on the `PATH`. On Windows, the extensions in `PATHEXT` (e.g. `.exe`) are also checked.
+fn is_on_path(name: &str) -> bool {
+    let extensions: Vec<String> = if cfg!(windows) {
+        env::var("PATHEXT")
+            .unwrap_or(".COM;.EXE;.BAT;.CMD".to_string())
+            .split(';')
+            .map(String::from)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+
+    env::split_paths(&paths).any(|directory| {
+        directory.join(name).is_file()
+            || extensions
+                .iter()
+                .any(|extension| directory.join(format!("{name}{extension}")).is_file())
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum ConditionError {
+    #[error("unexpected \"{0}\" in condition")]
+    UnexpectedToken(String),
+
+    #[error("unexpected end of condition")]
+    UnexpectedEnd,
+
+    #[error("unterminated string in condition")]
+    UnterminatedString,
+
+    #[error("expected a boolean but found \"{0}\"")]
+    ExpectedBoolean(String),
+
+    #[error("unknown reference \"{0}\" in condition")]
+    UnknownReference(String),
+
+    #[error("step \"{name}\" does not exist or has not been executed yet")]
+    UnknownStep { name: String },
+
+    #[error("unknown function \"{0}\" in condition")]
+    UnknownFunction(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step_results() -> StepResultMap {
+        let mut steps = StepResultMap::new();
+        steps.insert(
+            "build".to_string(),
+            StepResult {
+                status: Some(ExitStatus::Success),
+                stdout: "Build complete\n".to_string(),
+            },
+        );
+        steps.insert(
+            "check".to_string(),
+            StepResult {
+                status: Some(ExitStatus::Fail(2)),
+                stdout: "M src/main.rs (dirty)\n".to_string(),
+            },
+        );
+        steps.insert("deploy".to_string(), StepResult::skipped());
+        steps
+    }
+
+    fn evaluate_with_steps(condition: &str) -> Result<bool, ConditionError> {
+        let steps = step_results();
+        let mut variables = VariableMap::new();
+        variables.insert("state".to_string(), "dirty".to_string());
+
+        let context = ConditionContext {
+            steps: &steps,
+            variables: &variables,
+        };
+
+        evaluate(condition, &context)
+    }
+
+    #[test]
+    fn evaluate_step_status() {
+        assert!(evaluate_with_steps("steps.build.success").unwrap());
+        assert!(!evaluate_with_steps("steps.build.failure").unwrap());
+        assert!(evaluate_with_steps("steps.check.failure").unwrap());
+        assert!(evaluate_with_steps("steps.deploy.skipped").unwrap());
+        assert!(!evaluate_with_steps("steps.deploy.success").unwrap());
+    }
+
+    #[test]
+    fn evaluate_step_output() {
+        assert!(evaluate_with_steps("steps.check.stdout contains \"dirty\"").unwrap());
+        assert!(evaluate_with_steps("steps.build.stdout == \"Build complete\"").unwrap());
+        assert!(evaluate_with_steps("steps.build.stdout != \"\"").unwrap());
+        assert!(evaluate_with_steps("steps.check.exit_code == \"2\"").unwrap());
+    }
+
+    #[test]
+    fn evaluate_substitutes_variables_in_strings() {
+        assert!(evaluate_with_steps("steps.check.stdout contains \"($state)\"").unwrap());
+    }
+
+    #[test]
+    fn evaluate_combines_conditions() {
+        assert!(evaluate_with_steps("steps.build.success && steps.check.failure").unwrap());
+        assert!(evaluate_with_steps("steps.build.failure || steps.check.failure").unwrap());
+        assert!(evaluate_with_steps("!steps.build.failure").unwrap());
+        assert!(!evaluate_with_steps(
+            "!(steps.build.success && steps.check.stdout contains \"dirty\")"
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn evaluate_without_context_checks_environment() {
+        // Arrange
+        let dir = tempfile::TempDir::new().unwrap();
+        let existing_path = dir.path().to_string_lossy().replace('\\', "/");
+        unsafe {
+            env::set_var("PLZ_TEST_CONDITION_CI", "true");
+        }
+
+        // Act
+        let results = [
+            "{{ env.PLZ_TEST_CONDITION_CI }} == 'true'",
+            "env.PLZ_TEST_CONDITION_MISSING == \"\"",
+            &format!("exists(\"{}\")", existing_path),
+            &format!("!exists(\"{}/missing\")", existing_path),
+            "on_path(\"cargo\") && !on_path(\"plz-test-missing-binary\")",
+        ]
+        .map(|condition| evaluate_without_context(condition).unwrap());
+
+        // Assert
+        assert_eq!(results, [true; 5]);
+    }
+
+    #[test]
+    fn evaluate_with_variables_checks_variables() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("profile".to_string(), "prod".to_string());
+
+        // Act
+        let results = [
+            "{{ profile }} == 'prod'",
+            "profile != \"dev\"",
+            "\"$profile\" == 'prod'",
+        ]
+        .map(|condition| evaluate_with_variables(condition, &variables).unwrap());
+        let missing = evaluate_with_variables("{{ region }} == 'us'", &variables);
+
+        // Assert
+        assert_eq!(results, [true; 3]);
+        assert!(matches!(
+            missing,
+            Err(ConditionError::UnknownReference(reference)) if reference == "region"
+        ));
+    }
+
+    #[test]
+    fn evaluate_failure_references() {
+        // Arrange
+        let steps = StepResultMap::new();
+        let mut variables = VariableMap::new();
+        variables.insert(EXIT_CODE_VARIABLE_NAME.to_string(), "2".to_string());
+        variables.insert(FAILED_STEP_VARIABLE_NAME.to_string(), "migrate".to_string());
+        let context = ConditionContext {
+            steps: &steps,
+            variables: &variables,
+        };
+
+        // Act
+        let results = [
+            "plz.exit_code == '2'",
+            "{{ plz.failed_step }} == 'migrate'",
+            "plz.failed_step != 'seed'",
+        ]
+        .map(|condition| evaluate(condition, &context).unwrap());
+
+        // Assert
+        assert_eq!(results, [true; 3]);
+        assert!(evaluate_with_steps("plz.exit_code == ''").unwrap());
+    }
+
+    #[test]
+    fn evaluate_fails_for_unknown_function() {
+        let result = evaluate_with_steps("installed(\"docker\")");
+        assert!(matches!(result, Err(ConditionError::UnknownFunction(_))));
+    }
+
+    #[test]
+    fn evaluate_fails_for_unknown_step() {
+        let result = evaluate_with_steps("steps.test.success");
+        assert!(matches!(result, Err(ConditionError::UnknownStep { .. })));
+    }
+
+    #[test]
+    fn evaluate_fails_for_non_boolean_result() {
+        let result = evaluate_with_steps("steps.build.stdout");
+        assert!(matches!(result, Err(ConditionError::ExpectedBoolean(_))));
+    }
+
+    #[test]
+    fn evaluate_fails_for_invalid_syntax() {
+        assert!(evaluate_with_steps("steps.build.success &&").is_err());
+        assert!(evaluate_with_steps("steps.build.stdout == \"unterminated").is_err());
+        assert!(evaluate_with_steps("steps.build.success steps.check.success").is_err());
+    }
+}
@@ -1,14 +1,85 @@
+use crate::cache;
+use crate::cache::CACHE_DIR_NAME;
+use crate::cli;
+use crate::command_templates;
+use crate::command_templates::CommandTemplateError;
+use crate::conditions;
+use crate::conditions::ConditionError;
+use crate::import_lock;
+use crate::import_lock::{ImportLockError, LOCK_FILE_NAME};
+use crate::locale::{deserialize_localized, deserialize_optional_localized, LocalizedText};
+use crate::migrate;
+use crate::migrate::MigrateError;
 use crate::platform::{current_platform_provider, is_current_platform};
+use crate::remote;
+use crate::remote::RemoteError;
+use crate::schema::add_aliases;
+use crate::snippets;
+use crate::snippets::SnippetError;
+use crate::state::STATE_DIR_NAME;
+use crate::timings;
+use crate::unknown_fields;
+use crate::variables::VariableMap;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use colored::Colorize;
 use linked_hash_map::LinkedHashMap;
+use schemars::{JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::IsTerminal;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::{env, fs, io};
+use std::{env, fs, io, iter, mem};
 use thiserror::Error;
 
-const CONFIG_FILE_NAMES: [&str; 4] = ["plz.yaml", "Plz.yaml", "plz.yml", "Plz.yml"];
+/// The names of the config files to search for, in order of precedence.
+/// YAML files are preferred over JSON files.
+const CONFIG_FILE_NAMES: [&str; 11] = [
+    "plz.yaml",
+    "Plz.yaml",
+    "plz.yml",
+    "Plz.yml",
+    "plzfile.yaml",
+    "plzfile.yml",
+    ".plz.yaml",
+    ".plz.yml",
+    "plz.json",
+    "plzfile.json",
+    ".plz.json",
+];
+
+/// The name of the environment variable containing the path to the config file to use.
+/// When set, the config file is not searched for.
+pub const CONFIG_PATH_VARIABLE_NAME: &str = "PLZ_CONFIG";
+
+/// The name of the environment variable containing the config itself, either as YAML or JSON, or
+/// base64 encoded. When set, it takes precedence over `PLZ_CONFIG`, stdin, and the config files in
+/// the current directory.
+pub const INLINE_CONFIG_VARIABLE_NAME: &str = "PLZ_CONFIG_INLINE";
+
+/// The argument used to choose the config file to use, taking precedence over `PLZ_CONFIG`.
+/// Only the arguments before the command are checked, since commands may have their own `--config`
+/// argument.
+/// It can be provided more than once, in which case the later config files are merged over the
+/// earlier ones.
+pub const CONFIG_ARG: &str = "--config";
+
+/// The short form of [`CONFIG_ARG`].
+const CONFIG_SHORT_ARG: &str = "-f";
+
+/// The argument used to choose the environment to apply, taking precedence over `PLZ_ENV`.
+const ENV_ARG: &str = "--env";
+
+/// The name of the environment variable containing a comma-separated list of config file names to
+/// search for, overriding [`CONFIG_FILE_NAMES`].
+const CONFIG_FILE_NAMES_VARIABLE_NAME: &str = "PLZ_CONFIG_NAMES";
+
+/// The name of the environment variable containing the environment to apply when `--env` isn't
+/// provided.
+const ENVIRONMENT_VARIABLE_NAME: &str = "PLZ_ENV";
+
+const DEFAULT_PRINT_COMMANDS_FORMAT: &str = "Executing: {command:green}";
 
 const DEFAULT_CONFIG_FILE: &str = "description: My plzfile
 
@@ -20,60 +91,132 @@ commands:
     action: echo \"Hello, $name!\"";
 
 pub enum Source {
-    Unknown,
     Stdin,
+    Inline,
     File(PathBuf),
 }
 
 pub struct FoundConfig {
     pub source: Source,
     pub config: Config,
+
+    /// The text the config was loaded from, including any parent and override config files it was
+    /// merged with.
+    pub text: String,
+
+    /// The paths of the files imported while loading the config.
+    pub imports: Vec<PathBuf>,
 }
 
-/// Loads the [`Config`] from stdin, or a file in the current directory.
-pub fn load() -> Result<FoundConfig, ConfigError> {
-    let input = io::stdin();
+/// The config files found for the current invocation.
+struct ConfigFilePaths {
+    /// The config file to load.
+    config: PathBuf,
 
-    let mut source = Source::Unknown;
-    let mut config_text = String::new();
+    /// Config files from parent directories, which are merged into the config file.
+    parents: Vec<PathBuf>,
 
-    if input.is_terminal() {
-        let mut found = false;
-        let mut directory = env::current_dir().unwrap();
-        while !found {
-            for config_file_name in CONFIG_FILE_NAMES {
-                let config_file_path = directory.join(config_file_name);
-                if !config_file_path.exists() {
-                    continue;
-                }
+    /// Config files provided after the first `--config`, which are merged over the config file.
+    overrides: Vec<PathBuf>,
+}
 
-                source = Source::File(config_file_path.clone());
-                config_text = fs::read_to_string(config_file_path)
-                    .map_err(|err| ConfigError::ReadFailed(err))?;
-                found = true;
-                break;
-            }
+/// Returns the path of the config file that would be loaded, or `None` if the config would be read
+/// from `PLZ_CONFIG_INLINE` or stdin.
+pub fn config_file_path() -> Result<Option<PathBuf>, ConfigError> {
+    Ok(find_config_file_paths()?.map(|paths| paths.config))
+}
 
-            if found {
-                break;
-            }
+/// Finds the config files to load from the `--config` arguments, `PLZ_CONFIG`, or the current
+/// directory and its parents.
+/// Returns `None` if the config should be read from `PLZ_CONFIG_INLINE` or stdin.
+fn find_config_file_paths() -> Result<Option<ConfigFilePaths>, ConfigError> {
+    let mut config_paths: Vec<PathBuf> = config_path_args(&env::args().collect::<Vec<_>>())
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    if config_paths.is_empty() {
+        if inline_config().is_some() {
+            return Ok(None);
+        }
 
-            if let Some(parent) = directory.parent() {
-                directory = parent.to_owned();
-            } else {
-                break;
+        config_paths.extend(env::var_os(CONFIG_PATH_VARIABLE_NAME).map(PathBuf::from));
+    }
+    config_paths.retain(|path| !path.as_os_str().is_empty());
+
+    if !config_paths.is_empty() {
+        // The working directory is changed to the config file's directory, so it needs to be
+        // absolute
+        let current_dir = env::current_dir().map_err(ConfigError::ReadFailed)?;
+        let mut config_file_paths = Vec::new();
+        for config_path in config_paths {
+            let config_file_path = current_dir.join(config_path);
+            if !config_file_path.is_file() {
+                return Err(ConfigError::ConfigFileNotFound {
+                    path: config_file_path.to_string_lossy().to_string(),
+                });
             }
+
+            config_file_paths.push(config_file_path);
         }
 
-        if !found {
-            return Err(ConfigError::FileNotFound);
+        let config_file_path = config_file_paths.remove(0);
+        return Ok(Some(ConfigFilePaths {
+            config: config_file_path,
+            parents: Vec::new(),
+            overrides: config_file_paths,
+        }));
+    }
+
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+
+    let config_file_names = config_file_names(env::var(CONFIG_FILE_NAMES_VARIABLE_NAME).ok());
+    let mut config_file_paths =
+        find_config_files(&env::current_dir().unwrap(), &config_file_names)?;
+    if config_file_paths.is_empty() {
+        return Err(ConfigError::FileNotFound);
+    }
+
+    let config_file_path = config_file_paths.remove(0);
+    Ok(Some(ConfigFilePaths {
+        config: config_file_path,
+        parents: config_file_paths,
+        overrides: Vec::new(),
+    }))
+}
+
+/// Loads the [`Config`] from `PLZ_CONFIG_INLINE`, stdin, or a file in the current directory.
+pub fn load() -> Result<FoundConfig, ConfigError> {
+    let input = io::stdin();
+
+    // Config files from parent directories are merged into the closest one, and any config files
+    // provided after the first --config are merged over it
+    let (config_file_path, parent_config_paths, override_config_paths) =
+        match find_config_file_paths()? {
+            Some(paths) => (Some(paths.config), paths.parents, paths.overrides),
+            None => (None, Vec::new(), Vec::new()),
+        };
+
+    let mut config_text = String::new();
+    let source = match config_file_path {
+        Some(config_file_path) => {
+            config_text = read_config_file(&config_file_path)?;
+            Source::File(config_file_path)
         }
-    } else {
-        source = Source::Stdin;
-        input
-            .lock()
-            .read_to_string(&mut config_text)
-            .map_err(|err| ConfigError::ReadFailed(err))?;
+        None => match inline_config() {
+            Some(inline_config) => {
+                config_text = decode_inline_config(&inline_config)?;
+                Source::Inline
+            }
+            None => {
+                input
+                    .lock()
+                    .read_to_string(&mut config_text)
+                    .map_err(ConfigError::ReadFailed)?;
+                Source::Stdin
+            }
+        },
     };
 
     let current_platform = current_platform_provider().get_platform();
@@ -81,1636 +224,6185 @@ pub fn load() -> Result<FoundConfig, ConfigError> {
         Source::File(path) => path.parent().map(|p| p.to_path_buf()),
         _ => None,
     };
-    let config = parse_config(&config_text, current_platform, base_dir.as_deref())?;
-    Ok(FoundConfig { source, config })
-}
 
-/// Creates a new config file in the current directory.
-pub fn init() -> Result<String, ConfigError> {
-    let file_name = CONFIG_FILE_NAMES[0];
+    // Config files are cached alongside the config file since they can't be cached for stdin or
+    // inline configs
+    let args: Vec<String> = env::args().collect();
+    let Some(base_dir) = base_dir else {
+        let mut imports = Vec::new();
+        let variable_flags = root_variable_flags(&[&config_text], &args);
+        let config = parse_config_tracked(
+            &config_text,
+            current_platform,
+            None,
+            requested_command(&args, &variable_flags).as_deref(),
+            &[],
+            &mut imports,
+        )?;
+        return Ok(FoundConfig {
+            source,
+            config,
+            text: config_text,
+            imports,
+        });
+    };
 
-    fs::write(file_name, DEFAULT_CONFIG_FILE).map_err(|io_err| ConfigError::WriteFailed(io_err))?;
-    Ok(file_name.to_string())
-}
+    // The parent config files are part of the cached config too, so changing them needs to
+    // invalidate the cache
+    let mut parent_configs = Vec::new();
+    let mut cache_text = config_text.clone();
+    for path in parent_config_paths {
+        let text = read_config_file(&path)?;
+        cache_text.push_str(&format!("\n# {}\n{}", path.to_string_lossy(), text));
+        parent_configs.push((path, text));
+    }
 
-fn parse_config_from(path: &Path, current_platform: Platform) -> Result<Config, ConfigError> {
-    let config_text = fs::read_to_string(path).map_err(|err| ConfigError::ReadFailed(err))?;
-    let base_dir = path.parent();
-    parse_config(&config_text, current_platform, base_dir)
-}
+    let mut override_configs = Vec::new();
+    for path in override_config_paths {
+        let text = read_config_file(&path)?;
+        cache_text.push_str(&format!("\n# {}\n{}", path.to_string_lossy(), text));
+        override_configs.push((path, text));
+    }
 
-fn parse_config(
-    text: &String,
-    current_platform: Platform,
-    base_dir: Option<&Path>,
-) -> Result<Config, ConfigError> {
-    // Parse the base config
-    let mut base_config: Config =
-        serde_yaml::from_str(text.as_str()).map_err(|err| ConfigError::ParseFailed(err))?;
+    // Root variables' arguments can be provided before the command, so their values need to be
+    // skipped over to find it
+    let config_texts: Vec<&str> = iter::once(&config_text)
+        .chain(parent_configs.iter().map(|(_, text)| text))
+        .chain(override_configs.iter().map(|(_, text)| text))
+        .map(String::as_str)
+        .collect();
+    let variable_flags = root_variable_flags(&config_texts, &args);
+    let requested_command = requested_command(&args, &variable_flags);
+
+    let cache_dir = base_dir.join(STATE_DIR_NAME).join(CACHE_DIR_NAME);
+    // Cached configs have already been parsed, so they can't be checked for unknown fields when
+    // strict mode is enabled from the command-line
+    let cached_config = match is_strict(&serde_yaml::Value::Null) {
+        true => None,
+        false => cache::load_config(
+            &cache_dir,
+            &cache_text,
+            requested_command.as_deref(),
+            &current_platform,
+        ),
+    };
+    if let Some((config, imports)) = cached_config {
+        return Ok(FoundConfig {
+            source,
+            config,
+            text: cache_text,
+            imports,
+        });
+    }
 
-    // Parse the imports too
-    for import in &base_config.imports {
-        // Don't even try parsing the import if it's not for the current platform
-        if let Some(import_platform) = &import.platform {
-            if !is_current_platform(current_platform.clone(), import_platform) {
-                continue;
-            }
-        }
+    // Remote imports are locked next to the config file, so everyone using it gets the same
+    // versions
+    let updating_imports = args
+        .get(leading_args(&args).len() + 1)
+        .is_some_and(|arg| arg == cli::UPDATE_COMMAND_NAME);
+    import_lock::activate(base_dir.join(LOCK_FILE_NAME), updating_imports)
+        .map_err(ConfigError::ImportLockFailed)?;
+
+    let mut imports = Vec::new();
+    let config_file_path = match &source {
+        Source::File(path) => vec![path.clone()],
+        Source::Stdin | Source::Inline => Vec::new(),
+    };
+    let mut config = parse_config_tracked(
+        &config_text,
+        current_platform.clone(),
+        Some(&base_dir),
+        requested_command.as_deref(),
+        &config_file_path,
+        &mut imports,
+    )?;
+
+    for (path, text) in parent_configs {
+        let parent_dir = path.parent().unwrap_or(&base_dir);
+        let mut parent_config = parse_config_tracked(
+            &text,
+            current_platform.clone(),
+            Some(parent_dir),
+            requested_command.as_deref(),
+            std::slice::from_ref(&path),
+            &mut imports,
+        )?;
+
+        // Commands from parent config files still run relative to their own config file
+        resolve_variable_working_dirs(&mut parent_config.variables, parent_dir);
+        resolve_command_working_dirs(&mut parent_config.commands, parent_dir);
+        resolve_env_files(&mut parent_config.env_files, parent_dir);
+
+        config = merge_parent_config(config, parent_config);
+    }
 
-        let import_path = {
-            let raw = PathBuf::from(&import.source);
-            if raw.is_relative() {
-                if let Some(dir) = base_dir {
-                    normalize_path(&dir.join(&raw))
-                } else {
-                    raw
-                }
-            } else {
-                raw
-            }
-        };
+    for (path, text) in override_configs {
+        let override_dir = path.parent().unwrap_or(&base_dir);
+        let mut override_config = parse_config_tracked(
+            &text,
+            current_platform.clone(),
+            Some(override_dir),
+            requested_command.as_deref(),
+            std::slice::from_ref(&path),
+            &mut imports,
+        )?;
+
+        // Commands from override config files also run relative to their own config file
+        resolve_variable_working_dirs(&mut override_config.variables, override_dir);
+        resolve_command_working_dirs(&mut override_config.commands, override_dir);
+        resolve_env_files(&mut override_config.env_files, override_dir);
+
+        config = merge_override_config(config, override_config, &text)?;
+    }
+    check_command_names(&config.commands, &current_platform, None)?;
 
-        let mut child_config =
-            parse_config_from(&import_path, current_platform.clone()).map_err(|err| {
-                ConfigError::ImportFailed {
-                    alias: import.alias.clone(),
-                    source: Box::new(err),
-                }
-            })?;
+    // Any newly resolved imports are locked, but the lock file is only rewritten with the latest
+    // versions by the update command.
+    // Failing to write the lock file shouldn't prevent the command from executing.
+    if !updating_imports {
+        let _ = import_lock::save(false);
+    }
 
-        // Resolve working directories in the imported config relative to its location
-        if let Some(import_dir) = import_path.parent() {
-            resolve_variable_working_dirs(&mut child_config.variables, import_dir);
-            resolve_command_working_dirs(&mut child_config.commands, import_dir);
-        }
+    // Configs which only have the imports the requested command needs are cached separately, so
+    // they're only used for that command.
+    // Failing to write the cache shouldn't prevent the command from executing.
+    if !has_volatile_imports(&config, &imports) {
+        let _ = cache::save_config(
+            &cache_dir,
+            &cache_text,
+            &imports,
+            requested_command.as_deref(),
+            &current_platform,
+            &config,
+        );
+    }
 
-        // Create a top-level command for every import
-        let command = CommandConfig {
-            name: None,
-            description: child_config.description,
-            hidden: import.hidden,
-            platform: import.platform.clone(),
-            variables: child_config.variables,
-            commands: child_config.commands,
-            action: None,
+    Ok(FoundConfig {
+        source,
+        config,
+        text: cache_text,
+        imports,
+    })
+}
+
+/// Returns the name of the top-level command being invoked, if it can be determined from the
+/// command-line arguments without parsing them.
+/// `variable_flags` are the flags of the root variables' arguments (see [`root_variable_flags`]),
+/// whose values are skipped over.
+/// Returns `None` if no command is provided, if help is requested before the command (e.g.
+/// `--help deploy`), or if the command is a built-in command (e.g. `help`, `env`, or `tree`).
+fn requested_command(args: &[String], variable_flags: &[String]) -> Option<String> {
+    let leading_args = leading_args_with_flags(args, variable_flags);
+    if leading_args
+        .iter()
+        .any(|arg| arg == "--help" || arg == "-h")
+    {
+        return None;
+    }
+
+    args.get(leading_args.len() + 1)
+        .filter(|arg| *arg != "--" && !cli::is_built_in_command(arg))
+        .cloned()
+}
+
+/// Returns the flags of the arguments created for the root variables of the provided config texts
+/// (e.g. `--profile`), which all take a value.
+/// The variables are read without parsing the rest of the config, so any config which can't be
+/// read this way is left out.
+fn root_variable_flags(config_texts: &[&str], args: &[String]) -> Vec<String> {
+    /// The parts of a config which determine the arguments of the root command.
+    #[derive(Deserialize)]
+    struct RootArguments {
+        #[serde(default, alias = "vars")]
+        variables: VariableConfigMap,
+
+        #[serde(default, alias = "opts")]
+        options: Option<Options>,
+    }
+
+    let mut flags = Vec::new();
+    for text in config_texts {
+        let Ok(root_arguments) = serde_yaml::from_str::<RootArguments>(text) else {
+            continue;
         };
 
-        base_config.commands.insert(import.alias.clone(), command);
+        let mut options = root_arguments.options.unwrap_or_default();
+        let _ = options.apply_overrides(args, |name| env::var(name).ok());
+        flags.extend(cli::variable_flags(
+            options.auto_args,
+            &root_arguments.variables,
+        ));
     }
 
-    Ok(base_config)
+    flags
 }
 
-/// Normalizes a path by resolving `.` and `..` components without touching the filesystem.
-fn normalize_path(path: &Path) -> PathBuf {
-    let mut normalized = PathBuf::new();
-    for component in path.components() {
-        match component {
-            std::path::Component::CurDir => {}
-            std::path::Component::ParentDir => {
-                normalized.pop();
-            }
-            c => normalized.push(c),
+/// Returns the arguments before the command (excluding the binary name), such as `--config` or
+/// `--exec-timeout`, which plz reads itself before the arguments are parsed.
+/// Arguments after the command are left out, since commands may have their own arguments with the
+/// same names. The values of `--config`, `--env`, and options which aren't booleans are skipped
+/// over, so they aren't mistaken for the command.
+pub fn leading_args(args: &[String]) -> &[String] {
+    leading_args_with_flags(args, &[])
+}
+
+/// Returns the arguments before the command, like [`leading_args`], also skipping over the values
+/// of the provided flags (e.g. the arguments of root variables).
+fn leading_args_with_flags<'a>(args: &'a [String], value_flags: &[String]) -> &'a [String] {
+    let args = args.get(1..).unwrap_or_default();
+    let takes_value = |arg: &str| {
+        arg == CONFIG_ARG
+            || arg == CONFIG_SHORT_ARG
+            || arg == ENV_ARG
+            || value_flags.iter().any(|flag| flag == arg)
+            || OPTION_OVERRIDES.iter().any(|(field, is_bool, _)| {
+                !is_bool && arg.strip_prefix("--") == Some(option_flag_name(field).as_str())
+            })
+    };
+
+    let mut index = 0;
+    while let Some(arg) = args.get(index) {
+        if arg == "--" || !arg.starts_with('-') {
+            break;
         }
+
+        index += if takes_value(arg) { 2 } else { 1 };
     }
-    normalized
+
+    &args[..index.min(args.len())]
 }
 
-/// Resolves the working directory for a single execution config relative to `base_dir`.
-/// - If the execution has no working directory, sets it to `base_dir`.
-/// - If the execution has a relative working directory, resolves it against `base_dir`.
-/// - Absolute working directories are left unchanged.
-fn resolve_exec_workdir(exec: &mut ExecutionConfigVariant, base_dir: &Path) {
-    match exec {
-        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(bash)) => {
-            bash.working_directory = Some(resolve_dir(bash.working_directory.as_deref(), base_dir));
+/// Returns the paths provided with `--config` (or `-f`), in the order they were provided.
+/// Only the arguments before the command are checked.
+pub fn config_path_args(args: &[String]) -> Vec<String> {
+    let mut args = leading_args(args).iter();
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        if let Some(value) = config_arg_value(arg) {
+            paths.push(value.to_string());
+        } else if arg == CONFIG_ARG || arg == CONFIG_SHORT_ARG {
+            paths.extend(args.next().cloned());
         }
-        ExecutionConfigVariant::RawCommand(raw) => match raw {
-            RawCommandConfigVariant::Shorthand(cmd) => {
-                *raw = RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
-                    command: cmd.clone(),
-                    working_directory: Some(base_dir.to_string_lossy().to_string()),
-                });
-            }
-            RawCommandConfigVariant::RawCommandConfig(config) => {
-                config.working_directory =
-                    Some(resolve_dir(config.working_directory.as_deref(), base_dir));
-            }
-        },
     }
+
+    paths
 }
 
-/// Returns an absolute working directory.
-/// If `workdir` is none, then the `base_dir` is returned.
-/// If `workdir` is a relative path, it is joined with `base_dir`.
-/// If `workdir` is an absolute path, it is returned as-is.
-fn resolve_dir(workdir: Option<&str>, base_dir: &Path) -> String {
-    match workdir {
-        None => base_dir.to_string_lossy().to_string(),
-        Some(wd) => {
-            let path = PathBuf::from(wd);
-            if path.is_relative() {
-                normalize_path(&base_dir.join(path))
-                    .to_string_lossy()
-                    .to_string()
-            } else {
-                wd.to_string()
-            }
+/// Returns the value of a `--config=<path>` (or `-f=<path>`) argument.
+fn config_arg_value(arg: &str) -> Option<&str> {
+    arg.strip_prefix(CONFIG_ARG)
+        .or_else(|| arg.strip_prefix(CONFIG_SHORT_ARG))
+        .and_then(|rest| rest.strip_prefix('='))
+}
+
+/// Returns the command-line arguments (excluding the binary name), without the `--config`
+/// arguments and their values.
+pub fn command_args() -> Vec<String> {
+    let mut args = env::args().skip(1);
+    let mut command_args: Vec<String> = Vec::new();
+    while let Some(arg) = args.next() {
+        let is_before_command = command_args.iter().all(|arg| arg.starts_with('-'));
+        if is_before_command && (arg == CONFIG_ARG || arg == CONFIG_SHORT_ARG) {
+            args.next();
+        } else if !is_before_command || config_arg_value(&arg).is_none() {
+            command_args.push(arg);
         }
     }
+
+    command_args
 }
 
-/// Resolves working directories in execution-based variables relative to `base_dir`.
-fn resolve_variable_working_dirs(variables: &mut VariableConfigMap, base_dir: &Path) {
-    for (_, variable) in variables.iter_mut() {
-        match variable {
-            VariableConfig::Execution(exec_conf) => {
-                resolve_exec_workdir(&mut exec_conf.execution, base_dir);
-            }
-            VariableConfig::Prompt(prompt_conf) => {
-                if let PromptOptionsVariant::Select(select_opts) = &mut prompt_conf.prompt.options {
-                    if let SelectOptionsConfig::Execution(exec_select_opts) =
-                        &mut select_opts.options
-                    {
-                        resolve_exec_workdir(&mut exec_select_opts.execution, base_dir);
-                    }
-                }
-            }
-            _ => {}
-        }
+/// Returns the names of the config files to search for, in order of precedence.
+/// If `overridden_names` is provided, it is treated as a comma-separated list of names to use
+/// instead of [`CONFIG_FILE_NAMES`].
+fn config_file_names(overridden_names: Option<String>) -> Vec<String> {
+    let overridden_names: Vec<String> = overridden_names
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect();
+
+    if overridden_names.is_empty() {
+        return CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
     }
+
+    overridden_names
 }
 
-/// Recursively resolves working directories for all executions in a command map relative to `base_dir`.
-fn resolve_command_working_dirs(commands: &mut CommandConfigMap, base_dir: &Path) {
-    for (_, command) in commands.iter_mut() {
-        resolve_command_working_dirs(&mut command.commands, base_dir);
-        resolve_variable_working_dirs(&mut command.variables, base_dir);
+/// Returns the path of the first config file in `directory` matching one of the provided names.
+/// Returns the value of `PLZ_CONFIG_INLINE`, if it's set.
+fn inline_config() -> Option<String> {
+    env::var(INLINE_CONFIG_VARIABLE_NAME)
+        .ok()
+        .filter(|inline_config| !inline_config.trim().is_empty())
+}
 
-        if let Some(action) = &mut command.action {
-            match action {
-                ActionConfig::SingleStep(single) => {
-                    resolve_exec_workdir(&mut single.action, base_dir);
-                }
-                ActionConfig::MultiStep(multi) => {
-                    for exec in &mut multi.actions {
-                        resolve_exec_workdir(exec, base_dir);
-                    }
-                }
-                ActionConfig::Alias(_) => {}
-            }
+/// Decodes the config provided by `PLZ_CONFIG_INLINE`.
+/// YAML and JSON configs always contain a `:`, which base64 never does, so anything else is
+/// decoded as base64.
+fn decode_inline_config(inline_config: &str) -> Result<String, ConfigError> {
+    if inline_config.contains(':') {
+        return Ok(inline_config.to_string());
+    }
+
+    let encoded: String = inline_config.split_whitespace().collect();
+    let decoded =
+        BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|err| ConfigError::InvalidInlineConfig {
+                reason: err.to_string(),
+            })?;
+
+    String::from_utf8(decoded).map_err(|err| ConfigError::InvalidInlineConfig {
+        reason: err.to_string(),
+    })
+}
+
+fn find_config_file(directory: &Path, config_file_names: &[String]) -> Option<PathBuf> {
+    config_file_names
+        .iter()
+        .map(|config_file_name| directory.join(config_file_name))
+        .find(|config_file_path| config_file_path.is_file())
+}
+
+/// Returns the paths of the config files to load, starting with the closest one to `directory`.
+/// Within a git repository, the config files in each of the parent directories up to the root of
+/// the repository are included too, so they can be merged. Otherwise, only the closest config file
+/// is included.
+fn find_config_files(
+    directory: &Path,
+    config_file_names: &[String],
+) -> Result<Vec<PathBuf>, ConfigError> {
+    let mut config_file_paths = Vec::new();
+    for ancestor in directory.ancestors() {
+        if let Some(config_file_path) = find_config_file(ancestor, config_file_names) {
+            ensure_unambiguous(&config_file_path, config_file_names)?;
+            config_file_paths.push(config_file_path);
+        }
+
+        let is_repository_root = ancestor.join(".git").exists();
+        if is_repository_root && !config_file_paths.is_empty() {
+            return Ok(config_file_paths);
         }
     }
+
+    config_file_paths.truncate(1);
+    Ok(config_file_paths)
 }
 
-#[derive(Error, Debug)]
-pub enum ConfigError {
-    #[error("config file not found")]
-    FileNotFound,
+/// Merges a config file from a parent directory into `config`.
+/// Commands, variables, and the description from `config` take precedence over those from the
+/// parent, and the options from the parent are ignored.
+fn merge_parent_config(mut config: Config, parent: Config) -> Config {
+    for (key, command) in parent.commands {
+        config.commands.entry(key).or_insert(command);
+    }
 
-    #[error("failed to read config")]
-    ReadFailed(#[source] io::Error),
+    // Variables from the parent come first so they can be referenced by the closer variables
+    let mut variables = parent.variables;
+    for (key, variable) in config.variables {
+        variables.remove(&key);
+        variables.insert(key, variable);
+    }
+    config.variables = variables;
 
-    #[error("failed to write config file")]
-    WriteFailed(#[source] io::Error),
+    for (key, environment) in parent.environments {
+        config.environments.entry(key).or_insert(environment);
+    }
 
-    #[error("failed to parse config file")]
-    ParseFailed(#[source] serde_yaml::Error),
+    // Dotenv files from the parent are loaded first, so the closer files take precedence
+    let mut env_files = parent.env_files;
+    env_files.append(&mut config.env_files);
+    config.env_files = env_files;
 
-    #[error("failed to import {alias}")]
-    ImportFailed {
-        alias: String,
-        source: Box<ConfigError>, // Need to box this so the size isn't infinite
-    },
+    config.description = config.description.or(parent.description);
+    config.imports.extend(parent.imports);
+
+    config
 }
 
-/// The root-level of the Configuration.
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Config {
-    /// A list of additional config files to import.
-    #[serde(default = "default_imports")]
-    pub imports: Vec<Import>,
+/// Merges a config file provided with a later `--config` over `config`.
+/// The override's commands, variables, and environments take precedence, and only the options set
+/// in `override_text` are overridden.
+fn merge_override_config(
+    config: Config,
+    override_config: Config,
+    override_text: &str,
+) -> Result<Config, ConfigError> {
+    let override_value: serde_yaml::Value =
+        serde_yaml::from_str(override_text).map_err(ConfigError::ParseFailed)?;
+    let override_options = override_value
+        .get("options")
+        .or_else(|| override_value.get("opts"))
+        .and_then(serde_yaml::Value::as_mapping);
+
+    let options = match override_options {
+        Some(override_options) => {
+            let mut options =
+                serde_yaml::to_value(&config.options).map_err(ConfigError::ParseFailed)?;
+            if let Some(options) = options.as_mapping_mut() {
+                options.extend(override_options.clone());
+            }
+            serde_yaml::from_value(options).map_err(ConfigError::ParseFailed)?
+        }
+        None => config.options.clone(),
+    };
 
-    /// A user-friendly description.
-    #[serde(alias = "desc")]
-    pub description: Option<String>,
+    let mut merged = merge_parent_config(override_config, config);
+    merged.options = options;
+    Ok(merged)
+}
 
-    /// Root-level [`VariableConfig`]s that are available to all subsequent commands.
-    #[serde(default = "default_variables")]
-    #[serde(alias = "vars")]
-    pub variables: VariableConfigMap,
+/// Returns an error if the directory containing `config_file_path` also contains a config file
+/// with the same name in a different format (e.g. `plz.yaml` and `plz.json`), since it isn't clear
+/// which of them is meant to be used.
+fn ensure_unambiguous(
+    config_file_path: &Path,
+    config_file_names: &[String],
+) -> Result<(), ConfigError> {
+    let Some(directory) = config_file_path.parent() else {
+        return Ok(());
+    };
 
-    /// Top-level [`CommandConfig`]s.
-    #[serde(alias = "cmds")]
-    pub commands: CommandConfigMap,
+    let stem = config_file_stem(config_file_path);
+    let format = ConfigFormat::from_path(config_file_path);
+    let other_path = config_file_names
+        .iter()
+        .map(|config_file_name| directory.join(config_file_name))
+        .filter(|other_path| other_path.is_file())
+        .find(|other_path| {
+            config_file_stem(other_path) == stem && ConfigFormat::from_path(other_path) != format
+        });
 
-    #[serde(default)]
-    #[serde(alias = "opts")]
-    pub options: Options,
+    match other_path {
+        Some(other_path) => Err(ConfigError::AmbiguousConfigFile {
+            first: config_file_path.to_string_lossy().to_string(),
+            second: other_path.to_string_lossy().to_string(),
+        }),
+        None => Ok(()),
+    }
 }
 
-fn default_imports() -> Vec<Import> {
-    Vec::new()
+fn config_file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
 }
 
-fn default_variables() -> VariableConfigMap {
-    VariableConfigMap::new()
+/// The formats config files can be written in.
+#[derive(PartialEq, Debug)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
 }
 
-fn default_commands() -> CommandConfigMap {
-    CommandConfigMap::new()
+impl ConfigFormat {
+    /// Determines the format of the config file at the provided path from its extension.
+    /// Files without a recognised extension are assumed to be YAML.
+    pub fn from_path(path: &Path) -> ConfigFormat {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Import {
-    pub alias: String,
-    pub source: String, // TODO: Separate types for path, url, etc.
+/// Reads the config file at the provided path, converting it to YAML if it's written in another
+/// format.
+fn read_config_file(path: &Path) -> Result<String, ConfigError> {
+    let text = fs::read_to_string(path).map_err(ConfigError::ReadFailed)?;
+    match ConfigFormat::from_path(path) {
+        ConfigFormat::Yaml => Ok(text),
+        ConfigFormat::Json => {
+            let value: serde_yaml::Value =
+                serde_json::from_str(&text).map_err(ConfigError::ParseJsonFailed)?;
+            serde_yaml::to_string(&value).map_err(ConfigError::ParseFailed)
+        }
+    }
+}
 
-    /// Whether the imported commands should be hidden from the --help output.
-    #[serde(default = "default_hidden")]
-    pub hidden: bool,
+/// Creates a new config file in the current directory.
+pub fn init() -> Result<String, ConfigError> {
+    let file_name = config_file_names(env::var(CONFIG_FILE_NAMES_VARIABLE_NAME).ok()).remove(0);
 
-    /// An optional platform to restrict this import to.
-    /// When specified, the config will only be imported on the specified platforms.
-    #[serde(flatten)]
-    pub platform: Option<OneOrManyPlatforms>,
+    fs::write(&file_name, DEFAULT_CONFIG_FILE)
+        .map_err(|io_err| ConfigError::WriteFailed(io_err))?;
+    Ok(file_name)
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct Options {
-    /// When set to `true`, commands will be printed to stdout before executing them.
-    /// Defaults to `false`.
-    #[serde(default = "default_print_commands")]
-    pub print_commands: bool,
-
-    /// When set to `true`, variables will be printed to stdout once they've been resolved.
-    /// Defaults to `false`.
-    #[serde(default = "default_print_variables")]
-    pub print_variables: bool,
+/// Parses the config file at the provided path, recording the path of it and every file it
+/// imports in `imports`.
+///
+/// `chain` contains the config files that (transitively) imported this one, so circular imports
+/// can be reported rather than recursing forever.
+fn parse_config_from(
+    path: &Path,
+    current_platform: Platform,
+    chain: &[PathBuf],
+    imports: &mut Vec<PathBuf>,
+) -> Result<Config, ConfigError> {
+    let chain = [chain, &[path.to_path_buf()]].concat();
+    if chain[..chain.len() - 1]
+        .iter()
+        .any(|importer| is_same_file(importer, path))
+    {
+        return Err(ConfigError::CircularImport {
+            chain: import_chain_text(&chain),
+        });
+    }
 
-    /// When set to `true`, arguments will automatically be created for all variables.
-    /// Defaults to `false`.
-    #[serde(default = "default_auto_args")]
-    pub auto_args: bool,
+    imports.push(path.to_path_buf());
+    let config_text = read_config_file(path)?;
+    let base_dir = path.parent();
+    parse_config_tracked(
+        &config_text,
+        current_platform,
+        base_dir,
+        None,
+        &chain,
+        imports,
+    )
 }
 
-impl Default for Options {
-    fn default() -> Self {
-        Options {
-            print_commands: default_print_commands(),
-            print_variables: default_print_variables(),
-            auto_args: default_auto_args(),
-        }
+/// Returns `true` if both paths refer to the same file, even if one of them is relative or goes
+/// through a symlink.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
     }
 }
 
-fn default_print_commands() -> bool {
-    match env::var("PLZ_PRINT_COMMANDS") {
-        Ok(str) => is_truthy(str),
-        Err(_) => false,
-    }
+/// Formats a chain of imports (e.g. `a.yaml -> b.yaml -> a.yaml`), with paths relative to the
+/// directory of the first config file where possible.
+fn import_chain_text(chain: &[PathBuf]) -> String {
+    let root_dir = chain
+        .first()
+        .and_then(|path| path.parent())
+        .map(normalize_path)
+        .unwrap_or_default();
+
+    chain
+        .iter()
+        .map(|path| {
+            let path = normalize_path(path);
+            path.strip_prefix(&root_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(" -> ")
 }
 
-fn default_print_variables() -> bool {
-    match env::var("PLZ_PRINT_VARIABLES") {
-        Ok(str) => is_truthy(str),
-        Err(_) => false,
-    }
+/// Parses the provided config text, including all imports.
+#[cfg(test)]
+pub fn parse_config(
+    text: &String,
+    current_platform: Platform,
+    base_dir: Option<&Path>,
+) -> Result<Config, ConfigError> {
+    parse_config_tracked(text, current_platform, base_dir, None, &[], &mut Vec::new())
 }
 
-fn default_auto_args() -> bool {
-    match env::var("PLZ_AUTO_ARGS") {
-        Ok(str) => is_truthy(str),
-        Err(_) => false,
+/// Parses the provided config text, recording the path of every imported file in `imports`.
+///
+/// If `requested_command` is provided, only the imports whose alias matches it will be parsed.
+/// Placeholder commands are created for the remaining imports.
+///
+/// `chain` contains the config file the text was read from (if any), and the config files that
+/// imported it.
+fn parse_config_tracked(
+    text: &String,
+    current_platform: Platform,
+    base_dir: Option<&Path>,
+    requested_command: Option<&str>,
+    chain: &[PathBuf],
+    imports: &mut Vec<PathBuf>,
+) -> Result<Config, ConfigError> {
+    let mut value: Option<serde_yaml::Value> = serde_yaml::from_str(text.as_str()).ok();
+    let mut expanded = false;
+    if let Some(value) = value.as_mut() {
+        // Older config files are migrated in memory, so they keep working until they're migrated
+        let version = migrate::version(value).map_err(ConfigError::VersionCheckFailed)?;
+        let applied = migrate::upgrade(value).map_err(ConfigError::VersionCheckFailed)?;
+        if !applied.is_empty() {
+            let source = chain
+                .last()
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|| "the config file".to_string());
+            eprintln!(
+                "{} {} uses version {} of the config format, run `plz migrate` to update it",
+                "warning:".yellow().bold(),
+                source,
+                version
+            );
+            expanded = true;
+        }
+
+        // Create any commands that use a template, so they're parsed like any other command
+        expanded |= command_templates::expand(value).map_err(ConfigError::ExpandFailed)?;
+
+        // Snippets are expanded after templates, so templates can use them too
+        expanded |= snippets::expand(value).map_err(ConfigError::SnippetExpandFailed)?;
     }
-}
 
-fn is_truthy(s: String) -> bool {
-    s == "true" || s == "TRUE" || s == "t" || s == "T"
-}
+    // Warn about any fields we don't recognise, since serde will silently ignore most of them
+    // In strict mode they're rejected instead, so a typo can't go unnoticed
+    if let Some(value) = &value {
+        let unknown_fields = unknown_fields::find(value);
+        if !unknown_fields.is_empty() && is_strict(value) {
+            return Err(ConfigError::UnknownFields {
+                fields: unknown_fields_text(&unknown_fields, text, chain.last()),
+            });
+        }
 
-/// A set of [`VariableConfig`].
-/// Note that this uses a [`LinkedHashMap`] so that the order of insertion is retained.
-pub type VariableConfigMap = LinkedHashMap<String, VariableConfig>;
+        for unknown_field in unknown_fields {
+            eprintln!("{} {}", "warning:".yellow().bold(), unknown_field);
+        }
+    }
 
-/// The kind of variable.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum VariableConfig {
-    /// Denotes a shorthand literal variable.
-    ShorthandLiteral(String),
+    // Parse the base config
+    // Parsed from the text where possible, since errors from the value don't have a location
+    let mut base_config: Config = match value.filter(|_| expanded) {
+        Some(value) => serde_yaml::from_value(value),
+        None => serde_yaml::from_str(text.as_str()),
+    }
+    .map_err(|err| ConfigError::ParseFailed(err))?;
 
-    /// Encapsulates a [`LiteralVariableConfig`].
-    Literal(LiteralVariableConfig),
+    // Parse the imports too
+    let condition_variables = if base_config
+        .imports
+        .iter()
+        .any(|import| import.when.is_some())
+    {
+        import_condition_variables(&base_config, &env::args().collect::<Vec<_>>(), |name| {
+            env::var(name).ok()
+        })
+    } else {
+        VariableMap::new()
+    };
+    // The requested command is found without parsing the arguments, so it may not be a command at
+    // all (e.g. the value of an argument plz doesn't know about). Every import is parsed unless
+    // it's one of the commands or imports in this config.
+    let requested_command = requested_command.filter(|requested_command| {
+        base_config.commands.contains_key(*requested_command)
+            || base_config
+                .imports
+                .iter()
+                .any(|import| import.alias == *requested_command)
+    });
+    for import in &base_config.imports {
+        // Don't even try parsing the import if it's not for the current platform
+        if let Some(import_platform) = &import.platform {
+            if !is_current_platform(current_platform.clone(), import_platform) {
+                continue;
+            }
+        }
 
-    /// Encapsulates a [`ExecutionVariableConfig`].
-    Execution(ExecutionVariableConfig),
+        // Or if its condition isn't met
+        if let Some(condition) = &import.when {
+            if !conditions::evaluate_with_variables(condition, &condition_variables).map_err(
+                |err| ConfigError::InvalidImportCondition {
+                    alias: import.alias.clone(),
+                    source: err,
+                },
+            )? {
+                continue;
+            }
+        }
 
-    /// Encapsulates a [`PromptVariableConfig`].
-    Prompt(PromptVariableConfig),
+        // Imports would otherwise silently replace commands with the same name
+        if base_config.commands.contains_key(&import.alias) {
+            return Err(ConfigError::DuplicateCommandName {
+                name: import.alias.clone(),
+                first: format!("the command \"{}\"", import.alias),
+                second: format!("the import of {}", import.display_source()),
+            });
+        }
 
-    /// Encapsulates a [`ArgumentVariableConfig`].
-    Argument(ArgumentVariableConfig),
-}
+        // Optional imports are skipped if there's nothing to import
+        let is_remote = import.git.is_some() || remote::is_remote(&import.source);
+        if import.optional && !is_remote && !import.source.is_empty() {
+            let import_path = resolve_import_path(&import.source, base_dir);
+            let missing_reason = match is_glob(&import.source) {
+                true => expand_glob(&import_path, imports)?
+                    .is_empty()
+                    .then_some("nothing matches it"),
+                false => (!import_path.exists()).then_some("it doesn't exist"),
+            };
+
+            if let Some(reason) = missing_reason {
+                // Recorded so the cached config is invalidated once the file is created
+                imports.extend(
+                    import_path
+                        .parent()
+                        .filter(|dir| dir.is_dir())
+                        .map(Path::to_path_buf),
+                );
+                warn_skipped_import(import, reason);
+                continue;
+            }
+        }
 
-impl VariableConfig {
-    pub fn environment_variable_name(&self, key: &str) -> String {
-        match self {
-            VariableConfig::ShorthandLiteral(_) => None,
-            VariableConfig::Literal(literal_conf) => literal_conf.clone().environment_variable_name,
-            VariableConfig::Execution(execution_conf) => {
-                execution_conf.clone().environment_variable_name
+        // Don't parse the import if it can't contain the command being invoked
+        if requested_command.is_some_and(|requested_command| requested_command != import.alias) {
+            base_config
+                .commands
+                .insert(import.alias.clone(), create_import_command(import, None));
+            continue;
+        }
+
+        let fetched = match &import.git {
+            Some(git) => Some(remote::load_git(git, remote::refresh_from_args())),
+            None if is_remote => Some(remote::load(&import.source, remote::refresh_from_args())),
+            None => None,
+        };
+        let import_path = match fetched {
+            Some(Ok(path)) => path,
+            Some(Err(err)) if import.optional => {
+                warn_skipped_import(import, &format!("it couldn't be fetched: {}", err));
+                continue;
             }
-            VariableConfig::Prompt(prompt_conf) => prompt_conf.clone().environment_variable_name,
-            VariableConfig::Argument(argument_conf) => {
-                argument_conf.clone().environment_variable_name
+            Some(Err(err)) => {
+                return Err(ConfigError::ImportFailed {
+                    alias: import.alias.clone(),
+                    source: Box::new(ConfigError::FetchFailed(err)),
+                })
             }
-        }
-        .unwrap_or(key.to_string())
-    }
-}
+            None if import.source.is_empty() => {
+                return Err(ConfigError::MissingImportSource {
+                    alias: import.alias.clone(),
+                })
+            }
+            None => resolve_import_path(&import.source, base_dir),
+        };
 
-/// Denotes a literal variable where the value is hard-coded.
-///
-/// Example:
-/// ```yaml
-/// name:
-///     arg: name
-///     value: Alice
-/// ```
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct LiteralVariableConfig {
-    /// An optional argument configuration.
-    #[serde(rename(deserialize = "argument"))]
-    #[serde(alias = "arg")]
-    pub argument: Option<ArgumentConfigVariant>,
+        let child_config = timings::measure("import resolution", || {
+            if is_remote || !is_glob(&import.source) {
+                return parse_import_from(
+                    &import_path,
+                    is_remote,
+                    current_platform.clone(),
+                    chain,
+                    imports,
+                );
+            }
 
-    /// An optional environment variable name.
-    /// If specified, the environment variable for this variable will have the specified name.
-    ///
-    /// This is **not** the name of the environment variable to source the value from.
-    /// If you want to source a variables value from an environment variable,
-    /// use an [`ExecutionVariableConfig`].
-    #[serde(rename(deserialize = "environment_variable"))]
-    #[serde(alias = "env")]
-    pub environment_variable_name: Option<String>,
+            // Every file matching the pattern is merged into the same command
+            let mut merged_config: Option<Config> = None;
+            let mut sources = HashMap::new();
+            for path in expand_glob(&import_path, imports)? {
+                let child_config =
+                    parse_import_from(&path, is_remote, current_platform.clone(), chain, imports)?;
+                merged_config = Some(match merged_config {
+                    Some(merged_config) => {
+                        merge_import(merged_config, child_config, &path, &mut sources)?
+                    }
+                    None => {
+                        record_sources(&child_config, &path, &mut sources);
+                        child_config
+                    }
+                });
+            }
 
-    /// The value of the variable
-    pub value: String,
-}
+            merged_config.ok_or(ConfigError::NoImportMatches {
+                pattern: import.source.clone(),
+            })
+        })
+        .map_err(|err| ConfigError::ImportFailed {
+            alias: import.alias.clone(),
+            source: Box::new(err),
+        })?;
 
-/// Denotes a variable whose value is determined by the output of a command.
-///
-/// Example:
-/// ```yaml
-/// name:
-///     arg: name
-///     exec: cat name.txt
-/// ```
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct ExecutionVariableConfig {
-    /// An optional argument configuration.
-    #[serde(rename(deserialize = "argument"))]
-    #[serde(alias = "arg")]
-    pub argument: Option<ArgumentConfigVariant>,
+        // Create a top-level command for every import
+        let command = create_import_command(import, Some(child_config));
+        base_config.commands.insert(import.alias.clone(), command);
+    }
 
-    /// An optional environment variable name.
-    /// If specified, the environment variable for this variable will have the specified name.
-    ///
-    /// This is **not** the name of the environment variable to source the value from.
-    /// If you want to source a variables value from an environment variable,
-    /// use an [`ExecutionVariableConfig`].
-    #[serde(rename(deserialize = "environment_variable"))]
-    #[serde(alias = "env")]
-    pub environment_variable_name: Option<String>,
+    // Imported commands have already had their own variable sets added
+    add_variable_sets(&mut base_config.commands, &base_config.variable_sets, None)?;
+    check_command_names(&base_config.commands, &current_platform, None)?;
 
-    /// The [`ExecutionConfigVariant`] to use to determine the value of this variable.
-    #[serde(rename = "execute")]
-    #[serde(alias = "exec")]
-    pub execution: ExecutionConfigVariant,
+    Ok(base_config)
 }
 
-/// Denotes a variable whose value is determined by prompting the user for input.
-///
-/// Example:
-/// ```yaml
-/// name:
-///     arg: name
-///     prompt:
-///         message: What is your name?
-/// ```
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct PromptVariableConfig {
-    /// An optional argument configuration.
-    #[serde(rename(deserialize = "argument"))]
-    #[serde(alias = "arg")]
-    pub argument: Option<ArgumentConfigVariant>,
-
-    /// An optional environment variable name.
-    /// If specified, the environment variable for this variable will have the specified name.
-    ///
-    /// This is **not** the name of the environment variable to source the value from.
-    /// If you want to source a variables value from an environment variable,
-    /// use an [`ExecutionVariableConfig`].
-    #[serde(rename(deserialize = "environment_variable"))]
-    #[serde(alias = "env")]
-    pub environment_variable_name: Option<String>,
+/// Returns `true` if unknown fields should be rejected when parsing the provided config, either
+/// because it sets `options.strict` or because strict mode was enabled with `--strict` or
+/// `PLZ_STRICT`.
+fn is_strict(config: &serde_yaml::Value) -> bool {
+    let configured = ["options", "opts"]
+        .into_iter()
+        .filter_map(|key| config.get(key))
+        .find_map(|options| options.get("strict"))
+        .and_then(serde_yaml::Value::as_bool);
+
+    let mut options = Options {
+        strict: configured.unwrap_or_else(default_strict),
+        ..Options::default()
+    };
+    let args: Vec<String> = env::args().collect();
+    options
+        .apply_override("strict", true, &args, |name| env::var(name).ok())
+        .is_ok_and(|_| options.strict)
+}
 
-    /// The [`PromptConfig`] to use for the prompt.
-    pub prompt: PromptConfig,
+/// Formats the provided unknown fields, one per line, with the file and line they're on where
+/// possible (e.g. `plz.yaml:3: unknown field "decription" in commands.build`).
+fn unknown_fields_text(
+    unknown_fields: &[unknown_fields::UnknownField],
+    text: &str,
+    path: Option<&PathBuf>,
+) -> String {
+    let file = path
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|| "<stdin>".to_string());
+
+    // Converted JSON config files don't have the same lines as the original file
+    let is_json = path.is_some_and(|path| ConfigFormat::from_path(path) == ConfigFormat::Json);
+
+    unknown_fields
+        .iter()
+        .map(|unknown_field| {
+            match unknown_fields::find_line(text, unknown_field).filter(|_| !is_json) {
+                Some(line) => format!("  {}:{}: {}", file, line, unknown_field),
+                None => format!("  {}: {}", file, unknown_field),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-/// Denotes a variable whose value is sourced from command-line arguments.
-///
-/// Example:
-/// ```yaml
-/// name:
-///     arg:
-///         long: name
-///         short: n
-///         description: Your name
-/// ```
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct ArgumentVariableConfig {
-    /// An optional argument configuration.
-    #[serde(rename(deserialize = "argument"))]
-    #[serde(alias = "arg")]
-    pub argument: ArgumentConfigVariant,
+/// Parses the imported config file at the provided path.
+fn parse_import_from(
+    path: &Path,
+    is_remote: bool,
+    current_platform: Platform,
+    chain: &[PathBuf],
+    imports: &mut Vec<PathBuf>,
+) -> Result<Config, ConfigError> {
+    let mut config = parse_config_from(path, current_platform, chain, imports)?;
+
+    // Resolve working directories in the imported config relative to its location
+    // Remote configs don't have a meaningful location, so they're left relative to this one
+    if let Some(import_dir) = path.parent().filter(|_| !is_remote) {
+        resolve_variable_working_dirs(&mut config.variables, import_dir);
+        resolve_command_working_dirs(&mut config.commands, import_dir);
+        resolve_env_files(&mut config.env_files, import_dir);
+    }
 
-    /// An optional environment variable name.
-    /// If specified, the environment variable for this variable will have the specified name.
-    ///
-    /// This is **not** the name of the environment variable to source the value from.
-    /// If you want to source a variables value from an environment variable,
-    /// use an [`ExecutionVariableConfig`].
-    #[serde(rename(deserialize = "environment_variable"))]
-    #[serde(alias = "env")]
-    pub environment_variable_name: Option<String>,
+    Ok(config)
 }
 
-/// The kind of argument configuration.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum ArgumentConfigVariant {
-    Shorthand(String),
-    Named(NamedArgumentConfig),
-    Positional(PositionalArgumentConfig),
+/// Returns the path of a local import, resolving relative sources against `base_dir`.
+fn resolve_import_path(source: &str, base_dir: Option<&Path>) -> PathBuf {
+    let raw = PathBuf::from(source);
+    match base_dir {
+        Some(dir) if raw.is_relative() => normalize_path(&dir.join(&raw)),
+        _ => raw,
+    }
 }
 
-/// The configuration for a command-line argument.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct NamedArgumentConfig {
-    /// An optional description for the variable.
-    #[serde(alias = "desc")]
-    pub description: Option<String>,
+/// Warns that an optional import is being skipped, unless it's `quiet`.
+fn warn_skipped_import(import: &Import, reason: &str) {
+    if !import.quiet {
+        eprintln!(
+            "{} skipping the optional import of {} because {}",
+            "warning:".yellow().bold(),
+            import.display_source(),
+            reason
+        );
+    }
+}
 
-    /// The long version of the argument without the preceding `--`.
-    pub long: String,
+/// Returns `true` if the provided import source is a glob pattern (e.g. `./plz.d/*.yaml`).
+fn is_glob(source: &str) -> bool {
+    source.contains(['*', '?', '['])
+}
 
-    /// The short version of the argument without the preceding `-`.
-    pub short: Option<char>,
+/// Returns the paths of the files matching the provided glob pattern, in sorted order.
+/// The directories containing the matches are recorded in `imports`, so adding or removing a file
+/// invalidates the cache.
+fn expand_glob(pattern: &Path, imports: &mut Vec<PathBuf>) -> Result<Vec<PathBuf>, ConfigError> {
+    let pattern_text = pattern.to_string_lossy();
+    let invalid_pattern = || ConfigError::InvalidImportPattern {
+        pattern: pattern_text.to_string(),
+    };
+
+    let mut paths: Vec<PathBuf> = glob::glob(&pattern_text)
+        .map_err(|_| invalid_pattern())?
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    // The deepest directory without any wildcards is where new matches are most likely to appear
+    let base_dir = pattern
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| !is_glob(&ancestor.to_string_lossy()));
+    let mut directories: Vec<PathBuf> = base_dir
+        .into_iter()
+        .chain(paths.iter().filter_map(|path| path.parent()))
+        .map(Path::to_path_buf)
+        .collect();
+    directories.sort();
+    directories.dedup();
+    imports.extend(
+        directories
+            .into_iter()
+            .filter(|directory| directory.is_dir()),
+    );
+
+    Ok(paths)
 }
 
-/// The configuration for a positional command-line argument.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct PositionalArgumentConfig {
-    /// An optional description for the variable.
-    #[serde(alias = "desc")]
-    pub description: Option<String>,
-
-    /// The position of the argument.
-    /// This refers to position according to other positional argument.
-    /// It does not define the position in the argument list as a whole.
-    /// https://docs.rs/clap/latest/clap/struct.Arg.html#method.index
-    pub position: usize,
+/// Records the file each of the commands and variables in the provided config came from.
+fn record_sources(config: &Config, path: &Path, sources: &mut HashMap<String, PathBuf>) {
+    let keys = config
+        .commands
+        .keys()
+        .map(|key| format!("commands.{}", key))
+        .chain(
+            config
+                .variables
+                .keys()
+                .map(|key| format!("variables.{}", key)),
+        );
+    for key in keys {
+        sources.insert(key, path.to_path_buf());
+    }
 }
 
-/// The configuration for a prompt to the user for input.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct PromptConfig {
-    /// The message to display to the user.
-    pub message: String,
+/// Merges the commands and variables from `other` (which was parsed from `path`) into `config`.
+/// Returns an error naming both files if they define the same command or variable.
+fn merge_import(
+    mut config: Config,
+    other: Config,
+    path: &Path,
+    sources: &mut HashMap<String, PathBuf>,
+) -> Result<Config, ConfigError> {
+    let conflict = other
+        .commands
+        .keys()
+        .map(|key| format!("commands.{}", key))
+        .chain(
+            other
+                .variables
+                .keys()
+                .map(|key| format!("variables.{}", key)),
+        )
+        .find_map(|key| sources.get(&key).map(|first| (key, first)));
+    if let Some((key, first)) = conflict {
+        return Err(ConfigError::ConflictingImports {
+            key,
+            first: first.to_string_lossy().to_string(),
+            second: path.to_string_lossy().to_string(),
+        });
+    }
 
-    /// Additional, type-specific options for the prompt.
-    #[serde(flatten)]
-    pub options: PromptOptionsVariant,
+    record_sources(&other, path, sources);
+    config.description = config.description.or(other.description);
+    config.commands.extend(other.commands);
+    config.variables.extend(other.variables);
+
+    Ok(config)
 }
 
-impl Default for PromptOptionsVariant {
-    fn default() -> Self {
-        return PromptOptionsVariant::Text(TextPromptOptions {
-            multi_line: false,
-            sensitive: false,
-        });
+/// Returns `true` if the provided config, or any of the config files it imports, has an import
+/// which can change without any of the config files changing. These are imports with a `when`
+/// condition, which depends on the environment, and remote or git imports, which can expire.
+fn has_volatile_imports(config: &Config, imports: &[PathBuf]) -> bool {
+    let is_volatile = |import: &Import| {
+        import.when.is_some() || import.git.is_some() || remote::is_remote(&import.source)
+    };
+    if config.imports.iter().any(is_volatile) {
+        return true;
     }
+
+    imports.iter().any(|path| {
+        read_config_file(path)
+            .ok()
+            .and_then(|text| serde_yaml::from_str::<Config>(&text).ok())
+            .is_some_and(|config| config.imports.iter().any(is_volatile))
+    })
 }
 
-/// The kind of prompt options.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum PromptOptionsVariant {
-    // Note: Select needs to come first here because SelectPromptOptions is the most specific.
-    // Serde will use the type it matches on.
-    /// Encapsulates a [`SelectPromptOptions]`, indicating that the prompt should be a select-style
-    /// prompt.
-    Select(SelectPromptOptions),
+/// Ensures that no two commands available on the current platform resolve to the same name (taking
+/// `name` overrides into account) at the same level.
+fn check_command_names(
+    commands: &CommandConfigMap,
+    current_platform: &Platform,
+    parent_path: Option<&str>,
+) -> Result<(), ConfigError> {
+    // Sort the keys so the same error is reported regardless of the order of the map
+    let mut keys: Vec<&String> = commands.keys().collect();
+    keys.sort();
+
+    let mut names: HashMap<&String, String> = HashMap::new();
+    for key in keys {
+        let command = &commands[key];
+        if let Some(platform) = &command.platform {
+            if !is_current_platform(current_platform.clone(), platform) {
+                continue;
+            }
+        }
 
-    /// Encapsulates a [`TextPromptOptions]`, indicating that the prompt should be a text prompt.
-    Text(TextPromptOptions),
-}
+        let path = match parent_path {
+            Some(parent_path) => format!("{} {}", parent_path, key),
+            None => key.clone(),
+        };
 
-/// The options for a text prompt
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct TextPromptOptions {
-    /// Whether the prompt should be multi-line.
-    #[serde(default = "default_multi_line")]
-    pub multi_line: bool,
+        let name = command.name.as_ref().unwrap_or(key);
+        if let Some(existing_path) = names.get(name) {
+            return Err(ConfigError::DuplicateCommandName {
+                name: name.clone(),
+                first: format!("the command \"{}\"", existing_path),
+                second: format!("the command \"{}\"", path),
+            });
+        }
 
-    /// Whether the prompt is for a sensitive value.
-    /// When set to `true`, the input value will be obscured.
-    #[serde(default = "default_sensitive")]
-    pub sensitive: bool,
-}
+        check_command_names(&command.commands, current_platform, Some(&path))?;
+        names.insert(name, path);
+    }
 
-fn default_multi_line() -> bool {
-    false
+    Ok(())
 }
 
-fn default_sensitive() -> bool {
-    false
+/// Adds the variables from the variable sets used by each command (and subcommand) to the
+/// command's own variables. Sets are added in order, and the command's own variables are added
+/// last so they take precedence.
+fn add_variable_sets(
+    commands: &mut CommandConfigMap,
+    variable_sets: &LinkedHashMap<String, VariableConfigMap>,
+    parent_path: Option<&str>,
+) -> Result<(), ConfigError> {
+    for (key, command) in commands.iter_mut() {
+        let path = match parent_path {
+            Some(parent_path) => format!("{} {}", parent_path, key),
+            None => key.clone(),
+        };
+
+        if !command.use_variables.is_empty() {
+            let mut variables = VariableConfigMap::new();
+            for name in command.use_variables.drain(..) {
+                let Some(variable_set) = variable_sets.get(&name) else {
+                    return Err(ConfigError::UnknownVariableSet {
+                        command: path,
+                        name,
+                    });
+                };
+
+                for (key, variable) in variable_set {
+                    variables.remove(key);
+                    variables.insert(key.clone(), variable.clone());
+                }
+            }
+
+            for (key, variable) in mem::take(&mut command.variables) {
+                variables.remove(&key);
+                variables.insert(key, variable);
+            }
+            command.variables = variables;
+        }
+
+        add_variable_sets(&mut command.commands, variable_sets, Some(&path))?;
+    }
+
+    Ok(())
 }
 
-/// The options for a select prompt.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct SelectPromptOptions {
-    /// The [`SelectOptionsConfig`] for determining the options the user can choose from.
-    #[serde(alias = "opts")]
-    pub options: SelectOptionsConfig,
+/// Creates the top-level [`CommandConfig`] for an import.
+/// If `child_config` is `None` (i.e. the import hasn't been parsed), the command will be empty.
+fn create_import_command(import: &Import, child_config: Option<Config>) -> CommandConfig {
+    let (description, variables, commands, env_files) = match child_config {
+        Some(child_config) => (
+            child_config.description,
+            child_config.variables,
+            child_config.commands,
+            child_config.env_files,
+        ),
+        None => (None, default_variables(), default_commands(), Vec::new()),
+    };
+
+    CommandConfig {
+        name: None,
+        description,
+        hidden: import.hidden,
+        platform: import.platform.clone(),
+        python_env: None,
+        save_env: None,
+        on_failure: None,
+        on_interrupt: None,
+        cooldown: None,
+        require_clean_git: false,
+        guard: None,
+        env_files,
+        depends_on: Vec::new(),
+        use_variables: Vec::new(),
+        stdout_file: None,
+        stderr_file: None,
+        lock: false,
+        lock_wait: None,
+        auto_args: None,
+        on_success_message: None,
+        on_failure_message: None,
+        when: None,
+        variables,
+        commands,
+        action: None,
+    }
 }
 
-/// The kind of select prompt options.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum SelectOptionsConfig {
-    /// Encapsulates an [`ExecutionSelectOptionsConfig`], indicating that the options should be
-    /// sourced from the output of a command.
-    Execution(ExecutionSelectOptionsConfig),
+/// Normalizes a path by resolving `.` and `..` components without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
 
-    /// Encapsulates a `Vec<String>` where each element is an option that the user can choose.
-    Literal(Vec<String>),
+/// Resolves the working directory for a single execution config relative to `base_dir`.
+/// - If the execution has no working directory, sets it to `base_dir`.
+/// - If the execution has a relative working directory, resolves it against `base_dir`.
+/// - Absolute working directories are left unchanged.
+fn resolve_exec_workdir(exec: &mut ExecutionConfigVariant, base_dir: &Path) {
+    match exec {
+        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(bash)) => {
+            bash.working_directory = Some(resolve_dir(bash.working_directory.as_deref(), base_dir));
+        }
+        ExecutionConfigVariant::RawCommand(raw) => match raw {
+            RawCommandConfigVariant::Shorthand(cmd) => {
+                *raw = RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                    command: cmd.clone(),
+                    working_directory: Some(base_dir.to_string_lossy().to_string()),
+                });
+            }
+            RawCommandConfigVariant::RawCommandConfig(config) => {
+                config.working_directory =
+                    Some(resolve_dir(config.working_directory.as_deref(), base_dir));
+            }
+        },
+    }
 }
 
-/// Encapsulates a [`ExecutionConfigVariant`] for use in [`SelectOptionsConfig::Execution`].
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct ExecutionSelectOptionsConfig {
-    /// The [`ExecutionConfigVariant`] to use to determine the options.
-    #[serde(rename = "execute")]
-    #[serde(alias = "exec")]
-    pub execution: ExecutionConfigVariant,
+/// Returns an absolute working directory.
+/// If `workdir` is none, then the `base_dir` is returned.
+/// If `workdir` is a relative path, it is joined with `base_dir`.
+/// If `workdir` is an absolute path, or starts with a variable, it is returned as-is.
+fn resolve_dir(workdir: Option<&str>, base_dir: &Path) -> String {
+    match workdir {
+        None => base_dir.to_string_lossy().to_string(),
+        Some(wd) => {
+            // Whether a path starting with a variable is relative depends on the variable's value,
+            // which isn't known until the variables have been resolved.
+            let path = PathBuf::from(wd);
+            if path.is_relative() && !wd.starts_with('$') {
+                normalize_path(&base_dir.join(path))
+                    .to_string_lossy()
+                    .to_string()
+            } else {
+                wd.to_string()
+            }
+        }
+    }
 }
 
-pub type CommandConfigMap = HashMap<String, CommandConfig>;
+/// Resolves working directories in execution-based variables relative to `base_dir`.
+fn resolve_variable_working_dirs(variables: &mut VariableConfigMap, base_dir: &Path) {
+    for (_, variable) in variables.iter_mut() {
+        match variable {
+            VariableConfig::Execution(exec_conf) => {
+                resolve_exec_workdir(&mut exec_conf.execution, base_dir);
+            }
+            VariableConfig::Prompt(prompt_conf) => {
+                if let PromptOptionsVariant::Select(select_opts) = &mut prompt_conf.prompt.options {
+                    if let SelectOptionsConfig::Execution(exec_select_opts) =
+                        &mut select_opts.options
+                    {
+                        resolve_exec_workdir(&mut exec_select_opts.execution, base_dir);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
 
-/// The configuration for a command.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct CommandConfig {
-    /// An optional name for the command. Setting this will override the name provided by the key.
-    pub name: Option<String>,
+/// Recursively resolves working directories for all executions in a command map relative to `base_dir`.
+fn resolve_command_working_dirs(commands: &mut CommandConfigMap, base_dir: &Path) {
+    for (_, command) in commands.iter_mut() {
+        resolve_command_working_dirs(&mut command.commands, base_dir);
+        resolve_variable_working_dirs(&mut command.variables, base_dir);
 
-    /// An optional description for the command.
-    #[serde(alias = "desc")]
-    pub description: Option<String>,
+        if let Some(PythonEnvConfig::Venv(venv_path)) = &mut command.python_env {
+            *venv_path = resolve_dir(Some(venv_path), base_dir);
+        }
 
-    /// Whether the command should be hidden from the --help output.
-    #[serde(default = "default_hidden")]
-    pub hidden: bool,
+        if let Some(save_env) = &mut command.save_env {
+            save_env.path = resolve_dir(Some(&save_env.path), base_dir);
+        }
 
-    /// An optional platform to restrict this command to.
-    /// When specified, the command will only be available on the specified platforms.
-    #[serde(flatten)]
-    pub platform: Option<OneOrManyPlatforms>,
+        resolve_env_files(&mut command.env_files, base_dir);
+        resolve_output_files(&mut command.stdout_file, &mut command.stderr_file, base_dir);
+        resolve_dependencies(&mut command.depends_on, base_dir);
 
-    /// The [`VariableConfig`]s associated with this [`CommandConfig`] and it's subcommands.
-    #[serde(default = "default_variables")]
-    #[serde(alias = "vars")]
-    pub variables: VariableConfigMap,
+        if let Some(action) = &mut command.action {
+            resolve_action_working_dirs(action, base_dir);
+        }
 
-    // TODO: Need to enforce an invariant here:
-    // - If no action exists, then one or more subcommands _must_ exist.
-    /// Any sub-[`CommandConfig`]s.
-    #[serde(default = "default_commands")]
-    #[serde(alias = "cmds")]
-    pub commands: CommandConfigMap,
+        if let Some(on_failure) = &mut command.on_failure {
+            resolve_action_working_dirs(on_failure, base_dir);
+        }
 
-    /// The [`ActionConfig`] that this command will perform when executed.
-    #[serde(flatten)]
-    pub action: Option<ActionConfig>,
+        if let Some(on_interrupt) = &mut command.on_interrupt {
+            resolve_action_working_dirs(on_interrupt, base_dir);
+        }
+    }
 }
 
-fn default_hidden() -> bool {
-    false
+/// Resolves the provided dotenv file paths relative to `base_dir`.
+fn resolve_env_files(env_files: &mut [String], base_dir: &Path) {
+    for env_file in env_files {
+        *env_file = resolve_dir(Some(env_file), base_dir);
+    }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum OneOrManyPlatforms {
-    One(OnePlatform),
-    Many(ManyPlatforms),
+/// Resolves the directories of the provided dependencies relative to `base_dir`.
+fn resolve_dependencies(depends_on: &mut [String], base_dir: &Path) {
+    for dependency in depends_on {
+        if let (Some(directory), command) = split_dependency(dependency) {
+            *dependency = format!("{}:{}", resolve_dir(Some(directory), base_dir), command);
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct OnePlatform {
-    pub platform: Platform,
-}
+/// Splits a dependency into the directory containing the config file defining the command (if
+/// it's defined by another config file), and the path of the command.
+/// Directories are separated from the command by the first `:` after the last path separator, so
+/// command names can contain `:` too (e.g. `db:migrate` or `../api:db:migrate`).
+pub fn split_dependency(dependency: &str) -> (Option<&str>, &str) {
+    let Some(last_separator) = dependency.rfind(['/', '\\']) else {
+        return (None, dependency);
+    };
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct ManyPlatforms {
-    pub platforms: Vec<Platform>,
+    match dependency[last_separator..].find(':') {
+        Some(index) => {
+            let (directory, command) = dependency.split_at(last_separator + index);
+            (Some(directory), &command[1..])
+        }
+        None => (None, dependency),
+    }
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub enum Platform {
-    MacOS,
-    Windows,
-    Linux,
+/// Resolves the provided output file paths relative to `base_dir`.
+fn resolve_output_files(
+    stdout_file: &mut Option<String>,
+    stderr_file: &mut Option<String>,
+    base_dir: &Path,
+) {
+    for file in [stdout_file, stderr_file].into_iter().flatten() {
+        *file = resolve_dir(Some(file), base_dir);
+    }
 }
 
-/// Encapsulates either a single [`ExecutionConfigVariant`] ([`ActionConfig::SingleStep`] with a [`SingleActionConfig`])
-/// or multiple [`ExecutionConfigVariant`] ([`ActionConfig::MultiStep`] with a [`MultiActionConfig`]).
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum ActionConfig {
-    SingleStep(SingleActionConfig),
-    MultiStep(MultiActionConfig),
-    Alias(AliasActionConfig),
+fn resolve_action_working_dirs(action: &mut ActionConfig, base_dir: &Path) {
+    match action {
+        ActionConfig::SingleStep(single) => {
+            resolve_exec_workdir(&mut single.action, base_dir);
+        }
+        ActionConfig::MultiStep(multi) => {
+            for step in &mut multi.actions {
+                resolve_exec_workdir(&mut step.execution, base_dir);
+                resolve_output_files(&mut step.stdout_file, &mut step.stderr_file, base_dir);
+            }
+        }
+        ActionConfig::Services(services) => {
+            for (_, service) in services.services.iter_mut() {
+                resolve_exec_workdir(&mut service.execution, base_dir);
+            }
+        }
+        ActionConfig::Alias(_) | ActionConfig::Delegate(_) => {}
+    }
 }
 
-/// Contains the prefix for a command to execute.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct AliasActionConfig {
-    pub alias: String,
-}
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("config file not found")]
+    FileNotFound,
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct SingleActionConfig {
-    pub action: ExecutionConfigVariant,
-}
+    #[error("config file {path} not found")]
+    ConfigFileNotFound { path: String },
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct MultiActionConfig {
-    pub actions: Vec<ExecutionConfigVariant>,
-}
+    #[error("{INLINE_CONFIG_VARIABLE_NAME} must contain a YAML or JSON config, or a base64 encoded one: {reason}")]
+    InvalidInlineConfig { reason: String },
 
-/// The kind of command to execute.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum ExecutionConfigVariant {
-    /// Encapsulates a [`ShellCommandConfigVariant`].
-    ShellCommand(ShellCommandConfigVariant),
+    #[error("failed to read config")]
+    ReadFailed(#[source] io::Error),
 
-    /// Encapsulates a [`RawCommandConfigVariant`].
-    RawCommand(RawCommandConfigVariant),
-}
+    #[error("failed to write config file")]
+    WriteFailed(#[source] io::Error),
 
-/// The configuration for a raw command.
-/// Raw commands are simply commands executed without a shell.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum RawCommandConfigVariant {
-    /// Denotes a shorthand execution.
-    ///
-    /// Example:
-    /// ```yaml
-    /// exec: cat example.txt
-    /// ```
-    Shorthand(String),
+    #[error("failed to parse config file")]
+    ParseFailed(#[source] serde_yaml::Error),
 
-    /// Encapsulates a [`RawCommandConfig`].
-    RawCommandConfig(RawCommandConfig),
-}
+    #[error("failed to parse config file")]
+    ParseJsonFailed(#[source] serde_json::Error),
 
-/// The configuration for a raw command.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct RawCommandConfig {
-    /// An optional working directory for the command to be executed in.
-    /// If not specified, then the command will be executed in the current directory.
-    #[serde(rename = "workdir")]
-    #[serde(alias = "wd")]
-    pub working_directory: Option<String>,
+    #[error("failed to expand command templates")]
+    ExpandFailed(#[source] CommandTemplateError),
 
-    /// The command to execute.
-    #[serde(alias = "cmd")]
-    pub command: String,
-}
+    #[error("failed to expand snippets")]
+    SnippetExpandFailed(#[source] SnippetError),
 
-/// The configuration for a shell command.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-#[serde(untagged)]
-pub enum ShellCommandConfigVariant {
-    /// Encapsulates a [`BashCommandConfig`].
-    Bash(BashCommandConfig),
+    #[error("found fields plz doesn't recognise in strict mode\n{fields}")]
+    UnknownFields { fields: String },
+
+    #[error("failed to check the config file's version")]
+    VersionCheckFailed(#[source] MigrateError),
+
+    #[error("found both {first} and {second}, remove one of them or choose one with PLZ_CONFIG")]
+    AmbiguousConfigFile { first: String, second: String },
+
+    #[error("\"{name}\" is defined by both {first} and {second}")]
+    DuplicateCommandName {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("invalid value \"{value}\" for option {field}")]
+    InvalidOption { field: String, value: String },
+
+    #[error("unknown environment \"{name}\", expected one of: {available}")]
+    UnknownEnvironment { name: String, available: String },
+
+    #[error("invalid import pattern {pattern}")]
+    InvalidImportPattern { pattern: String },
+
+    #[error("no files match the import pattern {pattern}")]
+    NoImportMatches { pattern: String },
+
+    #[error("{key} is defined by both {first} and {second}")]
+    ConflictingImports {
+        key: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("import {alias} needs either a source or a git repository")]
+    MissingImportSource { alias: String },
+
+    #[error("failed to fetch remote config")]
+    FetchFailed(#[source] RemoteError),
+
+    #[error("failed to evaluate the condition for import {alias}")]
+    InvalidImportCondition {
+        alias: String,
+        source: ConditionError,
+    },
+
+    #[error("\"{key}\" doesn't match a variable, expected something like deploy.region")]
+    UnknownDefault { key: String },
+
+    #[error("the command \"{command}\" uses the variable set \"{name}\", which doesn't exist")]
+    UnknownVariableSet { command: String, name: String },
+
+    #[error("failed to load the import lock file")]
+    ImportLockFailed(#[source] ImportLockError),
+
+    #[error("circular import: {chain}")]
+    CircularImport { chain: String },
+
+    #[error("failed to import {alias}")]
+    ImportFailed {
+        alias: String,
+        source: Box<ConfigError>, // Need to box this so the size isn't infinite
+    },
 }
 
-/// The configuration for a bash command.
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct BashCommandConfig {
-    /// An optional working directory for the command to be executed in.
-    /// If not specified, then the command will be executed in the current directory.
-    #[serde(rename = "workdir")]
-    #[serde(alias = "wd")]
-    pub working_directory: Option<String>,
+/// The root-level of the Configuration.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct Config {
+    /// A list of additional config files to import.
+    #[serde(default = "default_imports")]
+    pub imports: Vec<Import>,
 
-    /// The command to execute.
-    #[serde(rename = "bash")]
-    #[serde(alias = "sh")]
-    pub command: String,
+    /// A user-friendly description.
+    #[serde(alias = "desc")]
+    #[serde(default, deserialize_with = "deserialize_optional_localized")]
+    #[schemars(with = "Option<LocalizedText>")]
+    pub description: Option<String>,
+
+    /// Root-level [`VariableConfig`]s that are available to all subsequent commands.
+    #[serde(default = "default_variables")]
+    #[serde(alias = "vars")]
+    #[schemars(with = "HashMap<String, VariableConfig>")]
+    pub variables: VariableConfigMap,
+
+    /// Named sets of [`VariableConfig`]s that commands can include with `use_variables`, so they
+    /// don't need to be repeated or defined at the root-level.
+    #[serde(default)]
+    #[schemars(with = "HashMap<String, HashMap<String, VariableConfig>>")]
+    pub variable_sets: LinkedHashMap<String, VariableConfigMap>,
+
+    /// Top-level [`CommandConfig`]s.
+    #[serde(alias = "cmds")]
+    pub commands: CommandConfigMap,
+
+    #[serde(default)]
+    #[serde(alias = "opts")]
+    pub options: Options,
+
+    /// [`EnvironmentConfig`]s that can be selected with `--env` or `PLZ_ENV` to override
+    /// variables and options.
+    #[serde(default)]
+    #[schemars(with = "HashMap<String, EnvironmentConfig>")]
+    pub environments: LinkedHashMap<String, EnvironmentConfig>,
+
+    /// Dotenv files to load variables from before any command is executed, relative to the config
+    /// file. Files that don't exist are skipped.
+    #[serde(default)]
+    pub env_files: Vec<String>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::OneOrManyPlatforms::{Many, One};
-    use crate::config::Platform::Linux;
-    use crate::config::RawCommandConfigVariant::Shorthand;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+impl Config {
+    /// Returns the name of the environment selected by the `--env` argument, falling back to the
+    /// `PLZ_ENV` environment variable.
+    /// Only the arguments before the first top-level command are checked, since commands may have
+    /// their own `--env` argument.
+    pub fn selected_environment(
+        &self,
+        args: &[String],
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        let is_command = |arg: &String| {
+            self.commands
+                .iter()
+                .any(|(key, command)| command.name.as_ref().unwrap_or(key) == arg)
+        };
 
-    fn bash_exec(command: &str, workdir: Option<String>) -> ExecutionConfigVariant {
-        return ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
-            BashCommandConfig {
-                working_directory: workdir,
-                command: command.to_string(),
-            },
-        ));
+        let mut args = args
+            .iter()
+            .skip(1)
+            .take_while(|arg| *arg != "--" && !is_command(arg));
+        let mut selected = None;
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg
+                .strip_prefix(ENV_ARG)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                selected = Some(name.to_string());
+            } else if arg == ENV_ARG {
+                selected = args.next().cloned();
+            }
+        }
+
+        selected
+            .or_else(|| get_env(ENVIRONMENT_VARIABLE_NAME))
+            .filter(|name| !name.is_empty())
     }
 
-    fn raw_exec(command: &str) -> ExecutionConfigVariant {
-        return ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-            command.to_string(),
-        ));
+    /// Overrides the variables and options with those from the named [`EnvironmentConfig`].
+    /// This needs to happen before the arguments are created so the overridden values are used as
+    /// the argument defaults.
+    pub fn apply_environment(&mut self, name: &str) -> Result<(), ConfigError> {
+        let Some(environment) = self.environments.get(name).cloned() else {
+            return Err(ConfigError::UnknownEnvironment {
+                name: name.to_string(),
+                available: self
+                    .environments
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            });
+        };
+
+        for (field, value) in &environment.options {
+            let value = match value {
+                serde_yaml::Value::Bool(value) => value.to_string(),
+                serde_yaml::Value::Number(value) => value.to_string(),
+                serde_yaml::Value::String(value) => value.clone(),
+                _ => {
+                    return Err(ConfigError::InvalidOption {
+                        field: field.clone(),
+                        value: serde_yaml::to_string(value)
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string(),
+                    })
+                }
+            };
+            self.options.set(field, &value)?;
+        }
+
+        override_variables(&mut self.variables, &environment.variables, true);
+        override_command_variables(&mut self.commands, &environment.variables);
+
+        Ok(())
     }
 
-    #[test]
-    fn empty_root_variables_allowed() {
-        let yaml = "commands:
-    demo:
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    /// Overrides the default value of a variable with a default saved by the user.
+    /// The key is the path of the command followed by the name of the variable (e.g.
+    /// `deploy.region`), or just the name of a root-level variable. Variables inherited from a
+    /// parent command are only overridden for the command in the key (and its subcommands).
+    pub fn apply_default(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let segments: Vec<&str> = key.split('.').collect();
+        let Some((variable, command_path)) = segments.split_last() else {
+            return Err(ConfigError::UnknownDefault {
+                key: key.to_string(),
+            });
+        };
 
-        assert!(config.variables.is_empty());
+        let applied = match command_path {
+            [] => match self.variables.get_mut(*variable) {
+                Some(existing) => {
+                    *existing = existing.with_value(value);
+                    true
+                }
+                None => false,
+            },
+            _ => apply_command_default(
+                &mut self.commands,
+                command_path,
+                variable,
+                value,
+                self.variables.get(*variable),
+            ),
+        };
+
+        if !applied {
+            return Err(ConfigError::UnknownDefault {
+                key: key.to_string(),
+            });
+        }
+
+        Ok(())
     }
+}
 
-    #[test]
-    fn shorthand_literal_variable_parsed() {
-        let yaml = "variables:
-    my-root-var: My root value
-commands:
-    demo:
-        variables:
-            my-command-var: My command value
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+/// Overrides the default value of `variable` for the command at `command_path`, returning `false`
+/// if the command doesn't exist or can't access the variable.
+/// Commands are matched by name, so every platform-specific variant of a command is overridden.
+fn apply_command_default(
+    commands: &mut CommandConfigMap,
+    command_path: &[&str],
+    variable: &str,
+    value: &str,
+    inherited: Option<&VariableConfig>,
+) -> bool {
+    let Some((name, command_path)) = command_path.split_first() else {
+        return false;
+    };
 
-        assert!(!config.variables.is_empty());
+    let mut applied = false;
+    for (key, command) in commands.iter_mut() {
+        if command.name.as_ref().unwrap_or(key) != name {
+            continue;
+        }
 
-        let root_variable = config.variables.get("my-root-var").unwrap();
-        assert_eq!(
-            root_variable,
-            &VariableConfig::ShorthandLiteral("My root value".to_string())
-        );
+        let inherited = command.variables.get(variable).or(inherited).cloned();
+        if !command_path.is_empty() {
+            applied |= apply_command_default(
+                &mut command.commands,
+                command_path,
+                variable,
+                value,
+                inherited.as_ref(),
+            );
+            continue;
+        }
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let command_variable = demo_command.variables.get("my-command-var").unwrap();
-        assert_eq!(
-            command_variable,
-            &VariableConfig::ShorthandLiteral("My command value".to_string())
-        )
+        let Some(inherited) = inherited else {
+            continue;
+        };
+
+        match command.variables.get_mut(variable) {
+            Some(existing) => *existing = inherited.with_value(value),
+            None => {
+                command
+                    .variables
+                    .insert(variable.to_string(), inherited.with_value(value));
+            }
+        }
+        applied = true;
     }
 
-    #[test]
-    fn literal_variable_parsed() {
-        let yaml = "variables:
-    my-root-var:
-        value: My root value
-commands:
-    demo:
-        variables:
-            my-command-var:
-                value: My command value
-                arg: command-arg
-                env: MY_VAR
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    applied
+}
 
-        assert!(!config.variables.is_empty());
+/// Overrides any variables in `variables` that are also defined in `overrides`.
+/// Shorthand overrides of literal variables only replace the value, so the variable keeps its
+/// argument and environment variable settings.
+fn override_variables(
+    variables: &mut VariableConfigMap,
+    overrides: &VariableConfigMap,
+    add_missing: bool,
+) {
+    for (key, variable) in overrides {
+        match (variables.get_mut(key), variable) {
+            (Some(VariableConfig::Literal(literal)), VariableConfig::ShorthandLiteral(value)) => {
+                literal.value = value.clone();
+            }
+            (Some(existing), _) => *existing = variable.clone(),
+            (None, _) if add_missing => {
+                variables.insert(key.clone(), variable.clone());
+            }
+            (None, _) => {}
+        }
+    }
+}
 
-        let root_variable = config.variables.get("my-root-var").unwrap();
-        assert_eq!(
-            root_variable,
-            &VariableConfig::Literal(LiteralVariableConfig {
-                value: "My root value".to_string(),
-                argument: None,
-                environment_variable_name: None,
-            })
-        );
+/// Returns the values of the provided config's root-level literal variables, which can be
+/// referenced by the `when` conditions of its imports, with the environment selected by `--env`
+/// (or `PLZ_ENV`) applied.
+/// Imports are resolved before any other variables, so variables that need to be resolved (e.g.
+/// prompts) can't be referenced.
+fn import_condition_variables(
+    config: &Config,
+    args: &[String],
+    get_env: impl Fn(&str) -> Option<String>,
+) -> VariableMap {
+    let mut variables = config.variables.clone();
+    if let Some(environment) = config
+        .selected_environment(args, get_env)
+        .and_then(|name| config.environments.get(&name))
+    {
+        override_variables(&mut variables, &environment.variables, true);
+    }
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let command_variable = demo_command.variables.get("my-command-var").unwrap();
-        assert_eq!(
-            command_variable,
-            &VariableConfig::Literal(LiteralVariableConfig {
-                value: "My command value".to_string(),
-                argument: Some(ArgumentConfigVariant::Shorthand("command-arg".to_string())),
-                environment_variable_name: Some("MY_VAR".to_string()),
-            })
-        )
+    variables
+        .iter()
+        .filter_map(|(name, variable)| match variable {
+            VariableConfig::ShorthandLiteral(value) => Some((name.clone(), value.clone())),
+            VariableConfig::Literal(literal) => Some((name.clone(), literal.value.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn override_command_variables(commands: &mut CommandConfigMap, overrides: &VariableConfigMap) {
+    for command in commands.values_mut() {
+        override_variables(&mut command.variables, overrides, false);
+        override_command_variables(&mut command.commands, overrides);
     }
+}
 
-    #[test]
-    fn exec_variable_parsed() {
-        let yaml = "variables:
-    my-root-var:
-        exec:
-            sh: echo \"My root value\"
-            workdir: ../
-commands:
+/// The variables and options to override when an environment is selected.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[schemars(transform = add_aliases)]
+pub struct EnvironmentConfig {
+    /// [`VariableConfig`]s that override the variables with the same name.
+    /// Variables that don't exist at the root-level are added to it.
+    #[serde(default = "default_variables")]
+    #[serde(alias = "vars")]
+    #[schemars(with = "HashMap<String, VariableConfig>")]
+    pub variables: VariableConfigMap,
+
+    /// Values that override the [`Options`] with the same name.
+    #[serde(default)]
+    #[serde(alias = "opts")]
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub options: LinkedHashMap<String, serde_yaml::Value>,
+}
+
+fn default_imports() -> Vec<Import> {
+    Vec::new()
+}
+
+fn default_variables() -> VariableConfigMap {
+    VariableConfigMap::new()
+}
+
+fn default_commands() -> CommandConfigMap {
+    CommandConfigMap::new()
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Import {
+    pub alias: String,
+
+    /// The path or URL of the config file to import.
+    /// Not required when importing from a git repository.
+    #[serde(default)]
+    pub source: String,
+
+    /// An optional git repository to import the config file from, instead of `source`.
+    #[serde(default)]
+    pub git: Option<GitImport>,
+
+    /// Whether the imported commands should be hidden from the --help output.
+    #[serde(default = "default_hidden")]
+    pub hidden: bool,
+
+    /// An optional platform to restrict this import to.
+    /// When specified, the config will only be imported on the specified platforms.
+    #[serde(flatten)]
+    pub platform: Option<OneOrManyPlatforms>,
+
+    /// An optional condition to restrict this import to.
+    /// When specified, the config will only be imported when the condition is true.
+    #[serde(default)]
+    pub when: Option<String>,
+
+    /// Whether the import can be missing.
+    /// When set to `true`, a warning is printed if the file doesn't exist, nothing matches the
+    /// pattern, or the config file can't be fetched, rather than failing.
+    #[serde(default)]
+    pub optional: bool,
+
+    /// Whether to skip a missing optional import without printing a warning.
+    #[serde(default)]
+    pub quiet: bool,
+}
+
+impl Import {
+    /// Returns a description of where the config file is imported from, for use in messages.
+    pub fn display_source(&self) -> String {
+        match &self.git {
+            Some(git) => match &git.reference {
+                Some(reference) => format!("{}@{}:{}", git.repo, reference, git.path),
+                None => format!("{}:{}", git.repo, git.path),
+            },
+            None => self.source.clone(),
+        }
+    }
+}
+
+/// A config file imported from a git repository.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GitImport {
+    /// The URL of the repository.
+    pub repo: String,
+
+    /// The branch, tag, or commit to import.
+    /// Defaults to the repository's default branch.
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+
+    /// The path to the config file within the repository.
+    /// Defaults to `plz.yaml`.
+    #[serde(default = "default_git_import_path")]
+    pub path: String,
+}
+
+fn default_git_import_path() -> String {
+    CONFIG_FILE_NAMES[0].to_string()
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Options {
+    /// When set to `true`, commands will be printed to stdout before executing them.
+    /// Defaults to `false`.
+    #[serde(default = "default_print_commands")]
+    pub print_commands: bool,
+
+    /// The template used to print commands when `print_commands` is enabled.
+    /// Defaults to `Executing: {command:green}`.
+    #[serde(default = "default_print_commands_format")]
+    pub print_commands_format: String,
+
+    /// When set to `true`, variables will be printed to stdout once they've been resolved.
+    /// Defaults to `false`.
+    #[serde(default = "default_print_variables")]
+    pub print_variables: bool,
+
+    /// When set to `true`, arguments will automatically be created for all variables.
+    /// Defaults to `false`.
+    #[serde(default = "default_auto_args")]
+    pub auto_args: bool,
+
+    /// When set to `true`, environment managers such as mise, asdf, and direnv will be detected
+    /// in the config file's directory, and the environment they export will be applied to actions.
+    /// Defaults to `false`.
+    #[serde(default = "default_activate_env")]
+    pub activate_env: bool,
+
+    /// An optional maximum amount of time (e.g. `30s`) to wait for commands whose output is
+    /// captured, such as execution variables, before giving up.
+    /// Defaults to no timeout.
+    #[serde(default = "default_exec_timeout")]
+    pub exec_timeout: Option<String>,
+
+    /// When set to `true`, a header will be printed to stderr before and after each action of a
+    /// multi-step command.
+    /// Defaults to `false`.
+    #[serde(default = "default_step_headers")]
+    pub step_headers: bool,
+
+    /// When set to `true`, ASCII symbols will be used in place of Unicode symbols in step headers.
+    /// ASCII symbols are always used when `TERM` is `dumb`.
+    /// Defaults to `false`.
+    #[serde(default = "default_ascii_symbols")]
+    pub ascii_symbols: bool,
+
+    /// When set to `true`, escape sequences marking where the command's output starts and how it
+    /// finished (OSC 133, and iTerm2's `PLZ_LAST_STATUS` user variable) will be written to stderr
+    /// when it's a terminal.
+    /// Defaults to `false`.
+    #[serde(default = "default_terminal_marks")]
+    pub terminal_marks: bool,
+
+    /// The order in which the sources of a variable's value are checked. The first source to
+    /// provide a value is used.
+    /// Defaults to `[cli, answers, defaults, prompt]`.
+    #[serde(default = "default_variable_precedence")]
+    pub variable_precedence: Vec<VariableSource>,
+
+    /// When set to `true`, each variable will be logged to stderr as it's resolved, along with
+    /// where its value came from and how long it took.
+    /// Defaults to `false`.
+    #[serde(default = "default_trace_vars")]
+    pub trace_vars: bool,
+
+    /// When set to `true`, execution variables are only executed if the command references them,
+    /// either directly or through another variable it references.
+    /// Defaults to `false`.
+    #[serde(default = "default_lazy_vars")]
+    pub lazy_vars: bool,
+
+    /// When set to `true`, variables without an `environment_variable` will be exposed using their
+    /// key in `UPPER_SNAKE_CASE` (e.g. `deployTarget` becomes `DEPLOY_TARGET`).
+    /// Defaults to `false`.
+    #[serde(default = "default_auto_env")]
+    pub auto_env: bool,
+
+    /// An optional prefix added to the names generated by `auto_env` (e.g. `APP_`).
+    /// Defaults to no prefix.
+    #[serde(default = "default_auto_env_prefix")]
+    pub auto_env_prefix: Option<String>,
+
+    /// An optional path to append a receipt to every time a command is executed.
+    /// Relative paths are resolved from the directory containing the config file.
+    /// Defaults to no audit log.
+    #[serde(default = "default_audit_log")]
+    pub audit_log: Option<String>,
+
+    /// When set to `true`, plz won't execute anything (including execution variables), and will
+    /// print what it would have executed instead.
+    /// Useful for reviewing a config file from an untrusted source.
+    /// Defaults to `false`.
+    #[serde(default = "default_inspect")]
+    pub inspect: bool,
+
+    /// When set to `true`, the config file fails to load if it contains any fields plz doesn't
+    /// recognise, rather than warning about them.
+    /// Defaults to `false`.
+    #[serde(default = "default_strict")]
+    pub strict: bool,
+
+    /// The name plz is invoked as, used in usage strings, help text, completions, and the shell
+    /// hook (e.g. `acme` when plz is shipped as a renamed binary).
+    /// Defaults to the name of the binary that was invoked.
+    #[serde(default = "default_binary_name")]
+    pub binary_name: Option<String>,
+
+    /// The severity of each of the rules checked by `plz lint`.
+    #[serde(default)]
+    pub lint: LintOptions,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            print_commands: default_print_commands(),
+            print_commands_format: default_print_commands_format(),
+            print_variables: default_print_variables(),
+            auto_args: default_auto_args(),
+            activate_env: default_activate_env(),
+            exec_timeout: default_exec_timeout(),
+            step_headers: default_step_headers(),
+            ascii_symbols: default_ascii_symbols(),
+            terminal_marks: default_terminal_marks(),
+            variable_precedence: default_variable_precedence(),
+            trace_vars: default_trace_vars(),
+            lazy_vars: default_lazy_vars(),
+            auto_env: default_auto_env(),
+            auto_env_prefix: default_auto_env_prefix(),
+            audit_log: default_audit_log(),
+            inspect: default_inspect(),
+            strict: default_strict(),
+            binary_name: default_binary_name(),
+            lint: LintOptions::default(),
+        }
+    }
+}
+
+/// How the findings of a lint rule are treated.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The rule isn't checked.
+    Off,
+
+    /// Findings are reported, but don't cause `plz lint` to fail.
+    Warn,
+
+    /// Findings are reported, and cause `plz lint` to fail.
+    Error,
+}
+
+/// The severity of each of the rules checked by `plz lint`.
+/// All rules default to [`Severity::Warn`].
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LintOptions {
+    /// Variables that aren't referenced by any action, variable, or subcommand.
+    #[serde(default = "default_lint_severity")]
+    pub unused_variables: Severity,
+
+    /// Commands without a description.
+    #[serde(default = "default_lint_severity")]
+    pub missing_descriptions: Severity,
+
+    /// Actions which run destructive commands, such as `rm -rf`.
+    #[serde(default = "default_lint_severity")]
+    pub dangerous_commands: Severity,
+
+    /// Platform-specific commands which aren't available on every platform.
+    #[serde(default = "default_lint_severity")]
+    pub missing_platform_counterparts: Severity,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions {
+            unused_variables: default_lint_severity(),
+            missing_descriptions: default_lint_severity(),
+            dangerous_commands: default_lint_severity(),
+            missing_platform_counterparts: default_lint_severity(),
+        }
+    }
+}
+
+fn default_lint_severity() -> Severity {
+    Severity::Warn
+}
+
+/// A source that a variable's value can be taken from.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableSource {
+    /// Command-line arguments.
+    Cli,
+
+    /// An environment variable with the same name as the variable's environment variable.
+    Env,
+
+    /// Scripted answers from `PLZ_PROMPT_ANSWERS`. Only applies to prompt variables.
+    Answers,
+
+    /// The value defined in the config, for literal and execution variables.
+    Defaults,
+
+    /// Prompting the user. Only applies to prompt variables.
+    Prompt,
+}
+
+impl VariableSource {
+    fn parse(s: &str) -> Option<VariableSource> {
+        match s.trim() {
+            "cli" => Some(VariableSource::Cli),
+            "env" => Some(VariableSource::Env),
+            "answers" => Some(VariableSource::Answers),
+            "defaults" => Some(VariableSource::Defaults),
+            "prompt" => Some(VariableSource::Prompt),
+            _ => None,
+        }
+    }
+}
+
+/// The fields of [`Options`] that can be overridden with environment variables and command-line
+/// flags, along with whether they're boolean and a description for the flag.
+/// The environment variable for each option is `PLZ_` followed by the field name in uppercase, and
+/// the flag is the field name with `_` replaced by `-` (e.g. `PLZ_PRINT_COMMANDS` and
+/// `--print-commands` for `print_commands`).
+pub const OPTION_OVERRIDES: [(&str, bool, &str); 17] = [
+    (
+        "print_commands",
+        true,
+        "Print commands before executing them.",
+    ),
+    (
+        "print_commands_format",
+        false,
+        "The template used to print commands.",
+    ),
+    (
+        "print_variables",
+        true,
+        "Print variables once they've been resolved.",
+    ),
+    ("auto_args", true, "Create arguments for all variables."),
+    (
+        "activate_env",
+        true,
+        "Apply the environment from environment managers (mise, direnv, etc.).",
+    ),
+    (
+        "exec_timeout",
+        false,
+        "The maximum amount of time to wait for execution variables.",
+    ),
+    (
+        "step_headers",
+        true,
+        "Print a header before and after each action.",
+    ),
+    (
+        "ascii_symbols",
+        true,
+        "Use ASCII symbols instead of Unicode symbols in step headers.",
+    ),
+    (
+        "terminal_marks",
+        true,
+        "Mark where the output of the command starts and how it finished for the terminal.",
+    ),
+    (
+        "variable_precedence",
+        false,
+        "The order in which variable sources are checked (e.g. cli,env,defaults).",
+    ),
+    (
+        "trace_vars",
+        true,
+        "Log where each variable's value came from as it's resolved.",
+    ),
+    (
+        "lazy_vars",
+        true,
+        "Only execute the execution variables referenced by the command.",
+    ),
+    (
+        "auto_env",
+        true,
+        "Expose variables as environment variables named after their key in UPPER_SNAKE_CASE.",
+    ),
+    (
+        "auto_env_prefix",
+        false,
+        "The prefix added to environment variable names generated by auto_env.",
+    ),
+    (
+        "audit_log",
+        false,
+        "The path to append a receipt to every time a command is executed.",
+    ),
+    (
+        "inspect",
+        true,
+        "Print what would be executed instead of executing anything.",
+    ),
+    (
+        "strict",
+        true,
+        "Fail if the config file contains any fields plz doesn't recognise.",
+    ),
+];
+
+/// Returns the name of the command-line flag used to override the provided [`Options`] field.
+pub fn option_flag_name(field: &str) -> String {
+    field.replace('_', "-")
+}
+
+/// Returns the name of the environment variable used to override the provided [`Options`] field.
+fn option_env_var_name(field: &str) -> String {
+    format!("PLZ_{}", field.to_uppercase())
+}
+
+impl Options {
+    /// Overrides any options set via environment variables (using `get_env`) or command-line flags
+    /// (in `args`).
+    /// Command-line flags take precedence over environment variables, which take precedence over the
+    /// config file.
+    /// This needs to happen before the clap tree is created, so it can't wait for clap to parse the
+    /// arguments.
+    pub fn apply_overrides(
+        &mut self,
+        args: &[String],
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        for (field, is_bool, _) in OPTION_OVERRIDES {
+            self.apply_override(field, is_bool, args, &get_env)?;
+        }
+
+        Ok(())
+    }
+
+    /// Overrides the provided field if it's set via an environment variable or command-line flag.
+    fn apply_override(
+        &mut self,
+        field: &str,
+        is_bool: bool,
+        args: &[String],
+        get_env: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        if let Some(value) = get_env(&option_env_var_name(field)) {
+            self.set(field, &value)?;
+        }
+
+        let flag = format!("--{}", option_flag_name(field));
+        let flag_with_value = format!("{}=", flag);
+        let mut args = leading_args(args).iter();
+        while let Some(arg) = args.next() {
+            if let Some(value) = arg.strip_prefix(&flag_with_value) {
+                self.set(field, value)?;
+            } else if *arg == flag && is_bool {
+                self.set(field, "true")?;
+            } else if *arg == flag {
+                if let Some(value) = args.next() {
+                    self.set(field, value)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
+        let parse_bool = |value: &str| {
+            parse_bool(value).ok_or(ConfigError::InvalidOption {
+                field: field.to_string(),
+                value: value.to_string(),
+            })
+        };
+
+        match field {
+            "print_commands" => self.print_commands = parse_bool(value)?,
+            "print_commands_format" => self.print_commands_format = value.to_string(),
+            "print_variables" => self.print_variables = parse_bool(value)?,
+            "auto_args" => self.auto_args = parse_bool(value)?,
+            "activate_env" => self.activate_env = parse_bool(value)?,
+            "exec_timeout" => self.exec_timeout = Some(value.to_string()),
+            "step_headers" => self.step_headers = parse_bool(value)?,
+            "ascii_symbols" => self.ascii_symbols = parse_bool(value)?,
+            "terminal_marks" => self.terminal_marks = parse_bool(value)?,
+            "trace_vars" => self.trace_vars = parse_bool(value)?,
+            "lazy_vars" => self.lazy_vars = parse_bool(value)?,
+            "auto_env" => self.auto_env = parse_bool(value)?,
+            "auto_env_prefix" => self.auto_env_prefix = Some(value.to_string()),
+            "audit_log" => self.audit_log = Some(value.to_string()),
+            "inspect" => self.inspect = parse_bool(value)?,
+            "strict" => self.strict = parse_bool(value)?,
+            "variable_precedence" => {
+                self.variable_precedence = value
+                    .split(',')
+                    .map(VariableSource::parse)
+                    .collect::<Option<Vec<VariableSource>>>()
+                    .ok_or(ConfigError::InvalidOption {
+                        field: field.to_string(),
+                        value: value.to_string(),
+                    })?
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+fn default_print_commands() -> bool {
+    match env::var("PLZ_PRINT_COMMANDS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_print_commands_format() -> String {
+    env::var("PLZ_PRINT_COMMANDS_FORMAT").unwrap_or(DEFAULT_PRINT_COMMANDS_FORMAT.to_string())
+}
+
+fn default_print_variables() -> bool {
+    match env::var("PLZ_PRINT_VARIABLES") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_auto_args() -> bool {
+    match env::var("PLZ_AUTO_ARGS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_activate_env() -> bool {
+    match env::var("PLZ_ACTIVATE_ENV") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_exec_timeout() -> Option<String> {
+    env::var("PLZ_EXEC_TIMEOUT").ok()
+}
+
+fn default_step_headers() -> bool {
+    match env::var("PLZ_STEP_HEADERS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_ascii_symbols() -> bool {
+    match env::var("PLZ_ASCII_SYMBOLS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_terminal_marks() -> bool {
+    match env::var("PLZ_TERMINAL_MARKS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_variable_precedence() -> Vec<VariableSource> {
+    vec![
+        VariableSource::Cli,
+        VariableSource::Answers,
+        VariableSource::Defaults,
+        VariableSource::Prompt,
+    ]
+}
+
+fn default_trace_vars() -> bool {
+    match env::var("PLZ_TRACE_VARS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_lazy_vars() -> bool {
+    match env::var("PLZ_LAZY_VARS") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_auto_env() -> bool {
+    match env::var("PLZ_AUTO_ENV") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_auto_env_prefix() -> Option<String> {
+    env::var("PLZ_AUTO_ENV_PREFIX").ok()
+}
+
+fn default_audit_log() -> Option<String> {
+    env::var("PLZ_AUDIT_LOG").ok()
+}
+
+fn default_strict() -> bool {
+    match env::var("PLZ_STRICT") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+fn default_binary_name() -> Option<String> {
+    None
+}
+
+fn default_inspect() -> bool {
+    match env::var("PLZ_INSPECT") {
+        Ok(str) => is_truthy(str),
+        Err(_) => false,
+    }
+}
+
+/// Converts the provided key to `UPPER_SNAKE_CASE`, treating any non-alphanumeric characters and
+/// lowercase to uppercase transitions as word boundaries (e.g. `deployTarget` and `deploy-target`
+/// both become `DEPLOY_TARGET`).
+fn to_upper_snake_case(key: &str) -> String {
+    let mut result = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in key.chars() {
+        if !c.is_alphanumeric() {
+            result.push('_');
+        } else {
+            let is_boundary = previous.is_some_and(|previous| {
+                c.is_uppercase() && (previous.is_lowercase() || previous.is_numeric())
+            });
+            if is_boundary {
+                result.push('_');
+            }
+            result.extend(c.to_uppercase());
+        }
+        previous = Some(c);
+    }
+
+    result
+}
+
+fn is_truthy(s: String) -> bool {
+    parse_bool(&s) == Some(true)
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "true" | "TRUE" | "t" | "T" | "1" | "yes" => Some(true),
+        "false" | "FALSE" | "f" | "F" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// A set of [`VariableConfig`].
+/// Note that this uses a [`LinkedHashMap`] so that the order of insertion is retained.
+pub type VariableConfigMap = LinkedHashMap<String, VariableConfig>;
+
+/// The kind of variable.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum VariableConfig {
+    /// Denotes a shorthand literal variable.
+    ShorthandLiteral(String),
+
+    /// Encapsulates a [`LiteralVariableConfig`].
+    Literal(LiteralVariableConfig),
+
+    /// Encapsulates a [`ExecutionVariableConfig`].
+    Execution(ExecutionVariableConfig),
+
+    /// Encapsulates a [`PromptVariableConfig`].
+    Prompt(PromptVariableConfig),
+
+    /// Encapsulates a [`EnvVariableConfig`].
+    Env(EnvVariableConfig),
+
+    /// Encapsulates a [`KeyringVariableConfig`].
+    Keyring(KeyringVariableConfig),
+
+    /// Encapsulates a [`SecretVariableConfig`].
+    Secret(SecretVariableConfig),
+
+    /// Encapsulates a [`ArgumentVariableConfig`].
+    Argument(ArgumentVariableConfig),
+}
+
+impl VariableConfig {
+    /// Returns a copy of this variable with the provided value, keeping its argument and
+    /// environment variable settings.
+    pub fn with_value(&self, value: &str) -> VariableConfig {
+        let (argument, environment_variable_name, no_arg) = match self {
+            VariableConfig::ShorthandLiteral(_) => {
+                return VariableConfig::ShorthandLiteral(value.to_string())
+            }
+            VariableConfig::Literal(literal_conf) => (
+                literal_conf.argument.clone(),
+                literal_conf.environment_variable_name.clone(),
+                literal_conf.no_arg,
+            ),
+            VariableConfig::Execution(execution_conf) => (
+                execution_conf.argument.clone(),
+                execution_conf.environment_variable_name.clone(),
+                execution_conf.no_arg,
+            ),
+            VariableConfig::Prompt(prompt_conf) => (
+                prompt_conf.argument.clone(),
+                prompt_conf.environment_variable_name.clone(),
+                prompt_conf.no_arg,
+            ),
+            VariableConfig::Env(env_conf) => (
+                env_conf.argument.clone(),
+                env_conf.environment_variable_name.clone(),
+                env_conf.no_arg,
+            ),
+            VariableConfig::Keyring(keyring_conf) => (
+                keyring_conf.argument.clone(),
+                keyring_conf.environment_variable_name.clone(),
+                keyring_conf.no_arg,
+            ),
+            VariableConfig::Secret(secret_conf) => (
+                secret_conf.argument.clone(),
+                secret_conf.environment_variable_name.clone(),
+                secret_conf.no_arg,
+            ),
+            VariableConfig::Argument(argument_conf) => (
+                Some(argument_conf.argument.clone()),
+                argument_conf.environment_variable_name.clone(),
+                false,
+            ),
+        };
+
+        VariableConfig::Literal(LiteralVariableConfig {
+            argument,
+            environment_variable_name,
+            no_arg,
+            sensitive: self.sensitive(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Returns the name of the environment variable this variable is exposed as.
+    /// If one isn't configured, the key is used as-is, or converted to `UPPER_SNAKE_CASE` (and
+    /// prefixed with `auto_env_prefix`) if the `auto_env` option is enabled.
+    pub fn environment_variable_name(&self, key: &str, options: &Options) -> String {
+        let configured_name = match self {
+            VariableConfig::ShorthandLiteral(_) => None,
+            VariableConfig::Literal(literal_conf) => literal_conf.clone().environment_variable_name,
+            VariableConfig::Execution(execution_conf) => {
+                execution_conf.clone().environment_variable_name
+            }
+            VariableConfig::Prompt(prompt_conf) => prompt_conf.clone().environment_variable_name,
+            VariableConfig::Env(env_conf) => env_conf.clone().environment_variable_name,
+            VariableConfig::Keyring(keyring_conf) => keyring_conf.clone().environment_variable_name,
+            VariableConfig::Secret(secret_conf) => secret_conf.clone().environment_variable_name,
+            VariableConfig::Argument(argument_conf) => {
+                argument_conf.clone().environment_variable_name
+            }
+        };
+
+        match configured_name {
+            Some(name) => name,
+            None if options.auto_env => format!(
+                "{}{}",
+                options.auto_env_prefix.as_deref().unwrap_or_default(),
+                to_upper_snake_case(key)
+            ),
+            None => key.to_string(),
+        }
+    }
+
+    /// Returns `true` if an argument should not be created for this variable when the `auto_args`
+    /// option is enabled.
+    pub fn no_arg(&self) -> bool {
+        match self {
+            VariableConfig::Literal(literal_conf) => literal_conf.no_arg,
+            VariableConfig::Execution(execution_conf) => execution_conf.no_arg,
+            VariableConfig::Prompt(prompt_conf) => prompt_conf.no_arg,
+            VariableConfig::Env(env_conf) => env_conf.no_arg,
+            VariableConfig::Keyring(keyring_conf) => keyring_conf.no_arg,
+            VariableConfig::Secret(secret_conf) => secret_conf.no_arg,
+            VariableConfig::ShorthandLiteral(_) | VariableConfig::Argument(_) => false,
+        }
+    }
+
+    /// Returns `true` if the value of this variable is sensitive, and should be masked whenever
+    /// it's printed.
+    /// Keyring and secret variables are always sensitive.
+    pub fn sensitive(&self) -> bool {
+        match self {
+            VariableConfig::ShorthandLiteral(_) => false,
+            VariableConfig::Literal(literal_conf) => literal_conf.sensitive,
+            VariableConfig::Execution(execution_conf) => execution_conf.sensitive,
+            VariableConfig::Prompt(prompt_conf) => match &prompt_conf.prompt.options {
+                PromptOptionsVariant::Select(_) => false,
+                PromptOptionsVariant::Text(text_prompt_options) => text_prompt_options.sensitive,
+            },
+            VariableConfig::Env(env_conf) => env_conf.sensitive,
+            VariableConfig::Keyring(_) | VariableConfig::Secret(_) => true,
+            VariableConfig::Argument(argument_conf) => argument_conf.sensitive,
+        }
+    }
+}
+
+/// Denotes a literal variable where the value is hard-coded.
+///
+/// Example:
+/// ```yaml
+/// name:
+///     arg: name
+///     value: Alice
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct LiteralVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// Whether the value of the variable is sensitive.
+    /// When set to `true`, the value will be masked whenever plz prints it.
+    #[serde(default)]
+    pub sensitive: bool,
+
+    /// The value of the variable
+    pub value: String,
+}
+
+/// Denotes a variable whose value is determined by the output of a command.
+///
+/// Example:
+/// ```yaml
+/// name:
+///     arg: name
+///     exec: cat name.txt
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct ExecutionVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// Whether the value of the variable is sensitive.
+    /// When set to `true`, the value will be masked whenever plz prints it.
+    #[serde(default)]
+    pub sensitive: bool,
+
+    /// The [`ExecutionConfigVariant`] to use to determine the value of this variable.
+    #[serde(rename = "execute")]
+    #[serde(alias = "exec")]
+    pub execution: ExecutionConfigVariant,
+
+    /// An optional maximum amount of time to wait for the command (e.g. `30s`), overriding the
+    /// `exec_timeout` option.
+    #[serde(default)]
+    pub timeout: Option<String>,
+
+    /// What to do if the command fails, times out, or exits with a non-zero exit code.
+    /// Defaults to [`ExecutionErrorPolicy::Fail`].
+    #[serde(default)]
+    pub on_error: ExecutionErrorPolicy,
+
+    /// An optional [`ParseConfig`] describing how to extract the value from the command's output.
+    /// If not specified, the entire output is used.
+    pub parse: Option<ParseConfig>,
+
+    /// An optional [`VariableCacheConfig`] describing how long the command's output is reused for
+    /// by later executions of plz. If not specified, the command is executed every time.
+    #[serde(default)]
+    pub cache: Option<VariableCacheConfig>,
+
+    /// The value to use when the command fails and `on_error` is set to
+    /// [`ExecutionErrorPolicy::Default`].
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// Extracts the value of an [`ExecutionVariableConfig`] from the command's output, or the value of
+/// an [`EnvVariableConfig`] from the environment variable.
+///
+/// Example:
+/// ```yaml
+/// variables:
+///     pod:
+///         exec: kubectl get pods -o json
+///         parse:
+///             json: $.items[0].metadata.name
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ParseConfig {
+    /// Parses the output as JSON and extracts the values at a JSONPath (e.g. `$.items[0].id`) or a
+    /// jq-style path (e.g. `.items[0].id`). Multiple values are placed on separate lines.
+    Json { json: String },
+
+    /// Extracts the first capture group of the first match of a regex (e.g. `version (\d+\.\d+)`),
+    /// or the entire match if the regex doesn't have any capture groups.
+    Regex { regex: String },
+}
+
+/// Caches the output of an [`ExecutionVariableConfig`] on disk, so it's reused until it expires
+/// rather than being executed every time.
+/// The output is cached for each command (after substituting variables) and working directory.
+///
+/// Example:
+/// ```yaml
+/// variables:
+///     account_id:
+///         exec: aws sts get-caller-identity --query Account --output text
+///         cache:
+///             ttl: 1h
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct VariableCacheConfig {
+    /// How long the output is reused for (e.g. `10m`).
+    pub ttl: String,
+}
+
+/// What to do when the command for an [`ExecutionVariableConfig`] fails.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionErrorPolicy {
+    /// Stop and report the error.
+    #[default]
+    Fail,
+
+    /// Use the variable's `default` value instead.
+    Default,
+
+    /// Use an empty value instead.
+    Empty,
+}
+
+/// Denotes a variable whose value is determined by prompting the user for input.
+///
+/// Example:
+/// ```yaml
+/// name:
+///     arg: name
+///     prompt:
+///         message: What is your name?
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct PromptVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// The [`PromptConfig`] to use for the prompt.
+    pub prompt: PromptConfig,
+}
+
+/// Denotes a variable whose value is read from an environment variable.
+///
+/// Example:
+/// ```yaml
+/// token:
+///     from_env: GITHUB_TOKEN
+///     required: true
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct EnvVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from, that's
+    /// `from_env`.
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// Whether the value of the variable is sensitive.
+    /// When set to `true`, the value will be masked whenever plz prints it.
+    #[serde(default)]
+    pub sensitive: bool,
+
+    /// The name of the environment variable to read the value from.
+    pub from_env: String,
+
+    /// An optional [`ParseConfig`] describing how to extract the value from the environment
+    /// variable. If not specified, the entire value is used.
+    pub parse: Option<ParseConfig>,
+
+    /// The value to use when the environment variable isn't set.
+    #[serde(default)]
+    pub default: Option<String>,
+
+    /// Whether to fail if the environment variable isn't set and there isn't a default value.
+    /// Otherwise, the variable is empty.
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Denotes a variable whose value is a secret read from the platform's credential store (the
+/// macOS Keychain, the Windows Credential Manager, or the Secret Service).
+/// Keyring variables are always sensitive, so their values are never printed.
+///
+/// Example:
+/// ```yaml
+/// token:
+///     keyring:
+///         service: github
+///         account: octocat
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct KeyringVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// The [`KeyringConfig`] identifying the secret.
+    pub keyring: KeyringConfig,
+}
+
+/// Identifies a secret in the platform's credential store.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct KeyringConfig {
+    /// The name of the service the secret belongs to.
+    /// Variables defined above this one can be referenced.
+    pub service: String,
+
+    /// The name of the account the secret belongs to.
+    /// Variables defined above this one can be referenced.
+    pub account: String,
+}
+
+/// Denotes a variable whose value is a secret read from a secret backend, like HashiCorp Vault or
+/// AWS SSM Parameter Store.
+/// Secret variables are always sensitive, so their values are never printed.
+///
+/// Example:
+/// ```yaml
+/// db_password:
+///     secret:
+///         backend: vault
+///         path: secret/db
+///         field: password
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct SecretVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: Option<ArgumentConfigVariant>,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether to prevent an argument from being created for this variable when the `auto_args`
+    /// option is enabled.
+    #[serde(default = "default_no_arg")]
+    pub no_arg: bool,
+
+    /// The [`SecretConfig`] identifying the secret.
+    pub secret: SecretConfig,
+}
+
+/// Identifies a secret in a secret backend.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SecretConfig {
+    /// The [`SecretBackendKind`] to read the secret from.
+    pub backend: SecretBackendKind,
+
+    /// The path of the secret (e.g. `secret/db` for Vault, or `/prod/db/password` for SSM).
+    /// Variables defined above this one can be referenced.
+    pub path: String,
+
+    /// The field of the secret to read. Vault only, defaults to `value`.
+    pub field: Option<String>,
+
+    /// The AWS region the parameter is stored in. SSM only, defaults to the AWS CLI's region.
+    pub region: Option<String>,
+}
+
+/// The secret backends secret variables can be read from.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretBackendKind {
+    /// HashiCorp Vault's key/value secrets engine, read with the `vault` CLI.
+    Vault,
+
+    /// AWS Systems Manager Parameter Store, read with the `aws` CLI.
+    Ssm,
+}
+
+/// Denotes a variable whose value is sourced from command-line arguments.
+///
+/// Example:
+/// ```yaml
+/// name:
+///     arg:
+///         long: name
+///         short: n
+///         description: Your name
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct ArgumentVariableConfig {
+    /// An optional argument configuration.
+    #[serde(rename(deserialize = "argument"))]
+    #[serde(alias = "arg")]
+    pub argument: ArgumentConfigVariant,
+
+    /// An optional environment variable name.
+    /// If specified, the environment variable for this variable will have the specified name.
+    ///
+    /// This is **not** the name of the environment variable to source the value from.
+    /// If you want to source a variables value from an environment variable,
+    /// use an [`EnvVariableConfig`].
+    #[serde(rename = "environment_variable")]
+    #[serde(alias = "env")]
+    pub environment_variable_name: Option<String>,
+
+    /// Whether the value of the variable is sensitive.
+    /// When set to `true`, the value will be masked whenever plz prints it.
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+/// The kind of argument configuration.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ArgumentConfigVariant {
+    Shorthand(String),
+    Named(NamedArgumentConfig),
+    Positional(PositionalArgumentConfig),
+}
+
+/// The configuration for a command-line argument.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct NamedArgumentConfig {
+    /// An optional description for the variable.
+    #[serde(alias = "desc")]
+    #[serde(default, deserialize_with = "deserialize_optional_localized")]
+    #[schemars(with = "Option<LocalizedText>")]
+    pub description: Option<String>,
+
+    /// The long version of the argument without the preceding `--`.
+    pub long: String,
+
+    /// The short version of the argument without the preceding `-`.
+    pub short: Option<char>,
+
+    /// An optional position for the argument.
+    /// When set, the argument can be provided either as a positional argument or using its long
+    /// or short name.
+    pub position: Option<usize>,
+
+    /// Whether the argument should be hidden from the --help output.
+    #[serde(default = "default_hidden")]
+    pub hidden: bool,
+}
+
+/// The configuration for a positional command-line argument.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct PositionalArgumentConfig {
+    /// An optional description for the variable.
+    #[serde(alias = "desc")]
+    #[serde(default, deserialize_with = "deserialize_optional_localized")]
+    #[schemars(with = "Option<LocalizedText>")]
+    pub description: Option<String>,
+
+    /// The position of the argument.
+    /// This refers to position according to other positional argument.
+    /// It does not define the position in the argument list as a whole.
+    /// https://docs.rs/clap/latest/clap/struct.Arg.html#method.index
+    pub position: usize,
+
+    /// Whether the argument should be hidden from the --help output.
+    #[serde(default = "default_hidden")]
+    pub hidden: bool,
+}
+
+/// The configuration for a prompt to the user for input.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct PromptConfig {
+    /// The message to display to the user.
+    #[serde(deserialize_with = "deserialize_localized")]
+    #[schemars(with = "LocalizedText")]
+    pub message: String,
+
+    /// The answer to use when the user doesn't provide one.
+    /// For select prompts, this is the option selected initially.
+    #[serde(default)]
+    pub default: Option<String>,
+
+    /// Additional, type-specific options for the prompt.
+    #[serde(flatten)]
+    pub options: PromptOptionsVariant,
+}
+
+impl Default for PromptOptionsVariant {
+    fn default() -> Self {
+        return PromptOptionsVariant::Text(TextPromptOptions {
+            multi_line: false,
+            sensitive: false,
+        });
+    }
+}
+
+/// The kind of prompt options.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum PromptOptionsVariant {
+    // Note: Select needs to come first here because SelectPromptOptions is the most specific.
+    // Serde will use the type it matches on.
+    /// Encapsulates a [`SelectPromptOptions]`, indicating that the prompt should be a select-style
+    /// prompt.
+    Select(SelectPromptOptions),
+
+    /// Encapsulates a [`TextPromptOptions]`, indicating that the prompt should be a text prompt.
+    Text(TextPromptOptions),
+}
+
+/// The options for a text prompt
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct TextPromptOptions {
+    /// Whether the prompt should be multi-line.
+    #[serde(default = "default_multi_line")]
+    pub multi_line: bool,
+
+    /// Whether the prompt is for a sensitive value.
+    /// When set to `true`, the input value will be obscured.
+    #[serde(default = "default_sensitive")]
+    pub sensitive: bool,
+}
+
+fn default_multi_line() -> bool {
+    false
+}
+
+fn default_sensitive() -> bool {
+    false
+}
+
+/// The options for a select prompt.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct SelectPromptOptions {
+    /// The [`SelectOptionsConfig`] for determining the options the user can choose from.
+    #[serde(alias = "opts")]
+    pub options: SelectOptionsConfig,
+}
+
+/// The kind of select prompt options.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum SelectOptionsConfig {
+    /// Encapsulates an [`ExecutionSelectOptionsConfig`], indicating that the options should be
+    /// sourced from the output of a command.
+    Execution(ExecutionSelectOptionsConfig),
+
+    /// Encapsulates a `Vec<String>` where each element is an option that the user can choose.
+    Literal(Vec<String>),
+}
+
+/// Encapsulates a [`ExecutionConfigVariant`] for use in [`SelectOptionsConfig::Execution`].
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct ExecutionSelectOptionsConfig {
+    /// The [`ExecutionConfigVariant`] to use to determine the options.
+    #[serde(rename = "execute")]
+    #[serde(alias = "exec")]
+    pub execution: ExecutionConfigVariant,
+}
+
+pub type CommandConfigMap = HashMap<String, CommandConfig>;
+
+/// The configuration for a command.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct CommandConfig {
+    /// An optional name for the command. Setting this will override the name provided by the key.
+    pub name: Option<String>,
+
+    /// An optional description for the command.
+    #[serde(alias = "desc")]
+    #[serde(default, deserialize_with = "deserialize_optional_localized")]
+    #[schemars(with = "Option<LocalizedText>")]
+    pub description: Option<String>,
+
+    /// Whether the command should be hidden from the --help output.
+    #[serde(default = "default_hidden")]
+    pub hidden: bool,
+
+    /// An optional platform to restrict this command to.
+    /// When specified, the command will only be available on the specified platforms.
+    #[serde(flatten)]
+    pub platform: Option<OneOrManyPlatforms>,
+
+    /// An optional condition to restrict this command to.
+    /// When specified, the command will only be available when the condition is true.
+    pub when: Option<String>,
+
+    /// An optional Python environment to activate when executing the command's actions.
+    pub python_env: Option<PythonEnvConfig>,
+
+    /// An optional [`SaveEnvConfig`] for writing resolved variables to a dotenv file.
+    pub save_env: Option<SaveEnvConfig>,
+
+    /// An optional [`ActionConfig`] to perform when this command's action fails.
+    pub on_failure: Option<ActionConfig>,
+
+    /// An optional [`ActionConfig`] to perform when this command is interrupted (Ctrl+C).
+    pub on_interrupt: Option<ActionConfig>,
+
+    /// An optional minimum interval between successful executions of this command (e.g. `5m`).
+    pub cooldown: Option<String>,
+
+    /// Whether to refuse to execute this command if the git working tree has uncommitted changes,
+    /// unless `--allow-dirty` is passed.
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub require_clean_git: bool,
+
+    /// An optional [`GuardConfig`] restricting when this command can be executed, unless
+    /// `--ignore-guard` is passed.
+    pub guard: Option<GuardConfig>,
+
+    /// Dotenv files to load variables from before this command is executed, relative to the config
+    /// file. Files that don't exist are skipped.
+    #[serde(default)]
+    pub env_files: Vec<String>,
+
+    /// Commands to execute before this command's action, in order. Each one is either the path of
+    /// a command in this config (e.g. `build` or `db migrate`), or a directory and the path of a
+    /// command in the config file found there (e.g. `../shared-lib:build`).
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+
+    /// An optional file to write the stdout of this command's action to, instead of the terminal.
+    /// Relative paths are resolved relative to the config file, missing parent directories are
+    /// created, and variables are substituted into the path.
+    pub stdout_file: Option<String>,
+
+    /// An optional file to write the stderr of this command's action to, instead of the terminal.
+    /// Can be the same file as `stdout_file`.
+    pub stderr_file: Option<String>,
+
+    /// Whether to prevent this command from being executed by multiple processes at the same time.
+    #[serde(default = "default_lock")]
+    pub lock: bool,
+
+    /// An optional amount of time to wait for the lock to be released (e.g. `60s`) if this command
+    /// is already being executed by another process. Setting this implies `lock`.
+    pub lock_wait: Option<String>,
+
+    /// Overrides the `auto_args` option for this command and its subcommands.
+    pub auto_args: Option<bool>,
+
+    /// An optional message to print once the command's action has succeeded.
+    /// Variables are substituted into the message.
+    pub on_success_message: Option<String>,
+
+    /// An optional message to print once the command's action has failed.
+    /// Variables are substituted into the message, including `PLZ_FAILED_STEP` and
+    /// `PLZ_EXIT_CODE`.
+    pub on_failure_message: Option<String>,
+
+    /// The names of the variable sets whose [`VariableConfig`]s are added to this command's
+    /// variables. Variables defined by the command take precedence.
+    #[serde(default)]
+    #[serde(alias = "use_vars")]
+    pub use_variables: Vec<String>,
+
+    /// The [`VariableConfig`]s associated with this [`CommandConfig`] and it's subcommands.
+    #[serde(default = "default_variables")]
+    #[serde(alias = "vars")]
+    #[schemars(with = "HashMap<String, VariableConfig>")]
+    pub variables: VariableConfigMap,
+
+    // TODO: Need to enforce an invariant here:
+    // - If no action exists, then one or more subcommands _must_ exist.
+    /// Any sub-[`CommandConfig`]s.
+    #[serde(default = "default_commands")]
+    #[serde(alias = "cmds")]
+    pub commands: CommandConfigMap,
+
+    /// The [`ActionConfig`] that this command will perform when executed.
+    #[serde(flatten)]
+    pub action: Option<ActionConfig>,
+}
+
+impl CommandConfig {
+    /// Evaluates the command's `when` condition, returning `true` if the command has no condition.
+    /// Commands with an invalid condition are treated as unavailable, and a warning is printed.
+    pub fn is_available(&self) -> bool {
+        let Some(condition) = &self.when else {
+            return true;
+        };
+
+        conditions::evaluate_without_context(condition).unwrap_or_else(|error| {
+            eprintln!(
+                "{} invalid condition \"{}\": {}",
+                "warning:".yellow().bold(),
+                condition,
+                error
+            );
+            false
+        })
+    }
+}
+
+fn default_hidden() -> bool {
+    false
+}
+
+fn default_no_arg() -> bool {
+    false
+}
+
+fn default_lock() -> bool {
+    false
+}
+
+/// The kind of Python environment to activate.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum PythonEnvConfig {
+    /// Denotes the path to a virtual environment.
+    ///
+    /// Example:
+    /// ```yaml
+    /// python_env: .venv
+    /// ```
+    Venv(String),
+
+    /// Encapsulates a [`CondaEnvConfig`].
+    Conda(CondaEnvConfig),
+}
+
+/// The configuration for saving resolved variables to a dotenv file.
+///
+/// Example:
+/// ```yaml
+/// save_env:
+///     path: .plz.env
+///     vars:
+///         - version
+///         - build_id
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[schemars(transform = add_aliases)]
+pub struct SaveEnvConfig {
+    /// The path of the dotenv file to write.
+    pub path: String,
+
+    /// The names of the variables to write to the file.
+    #[serde(alias = "vars")]
+    pub variables: Vec<String>,
+}
+
+/// Restricts when a command can be executed, so policies like "no deploys after hours" can be
+/// kept with the command.
+///
+/// Example:
+/// ```yaml
+/// guard:
+///     not_on: [friday-after-16:00, weekends]
+///     only_if_env: CI
+///     message: Production deploys only happen in CI during working hours
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GuardConfig {
+    /// The times the command can't be executed at, in local time (e.g. `friday-after-16:00`,
+    /// `weekends`, or `22:00-06:00`).
+    pub not_on: Option<OneOrManyStrings>,
+
+    /// The environment variables that must be set for the command to be executed (e.g. `CI`), or
+    /// the values they must have (e.g. `DEPLOY_ENV=production`).
+    pub only_if_env: Option<OneOrManyStrings>,
+
+    /// An optional message explaining the guard, printed when it refuses to execute the command.
+    pub message: Option<String>,
+}
+
+/// Either a single string, or a list of strings.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum OneOrManyStrings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrManyStrings {
+    /// Returns the strings as a slice.
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            OneOrManyStrings::One(value) => std::slice::from_ref(value),
+            OneOrManyStrings::Many(values) => values,
+        }
+    }
+}
+
+/// The configuration for a conda environment.
+///
+/// Example:
+/// ```yaml
+/// python_env:
+///     conda: my-env
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct CondaEnvConfig {
+    /// The name of the conda environment.
+    pub conda: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum OneOrManyPlatforms {
+    One(OnePlatform),
+    Many(ManyPlatforms),
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct OnePlatform {
+    pub platform: Platform,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct ManyPlatforms {
+    pub platforms: Vec<Platform>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub enum Platform {
+    MacOS,
+    Windows,
+    WindowsArm64,
+    Linux,
+    FreeBSD,
+    OpenBSD,
+
+    /// A platform that plz doesn't recognise.
+    /// Commands restricted to specific platforms are never available on unknown platforms.
+    #[serde(skip_deserializing)]
+    Unknown,
+}
+
+impl Platform {
+    /// Determines whether this platform, when used in a config file, includes the provided platform.
+    /// [`Platform::Windows`] includes all Windows architectures, while [`Platform::WindowsArm64`]
+    /// only includes ARM64.
+    pub fn includes(&self, platform: &Platform) -> bool {
+        match (self, platform) {
+            (_, Platform::Unknown) => false,
+            (Platform::Windows, Platform::WindowsArm64) => true,
+            _ => self == platform,
+        }
+    }
+}
+
+/// Encapsulates either a single [`ExecutionConfigVariant`] ([`ActionConfig::SingleStep`] with a [`SingleActionConfig`])
+/// or multiple [`ExecutionConfigVariant`] ([`ActionConfig::MultiStep`] with a [`MultiActionConfig`]).
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ActionConfig {
+    SingleStep(SingleActionConfig),
+    MultiStep(MultiActionConfig),
+    Alias(AliasActionConfig),
+    Delegate(DelegateActionConfig),
+    Services(ServicesActionConfig),
+}
+
+/// Contains long-running services to execute together, with their output prefixed by their name.
+///
+/// Example:
+/// ```yaml
+/// services:
+///     api: cargo run
+///     web:
+///         command: npm run dev
+///         workdir: ./web
+///         env:
+///             PORT: "3000"
+///         restart: on_failure
+///         watch: [package.json]
+/// output: grouped
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct ServicesActionConfig {
+    #[schemars(with = "HashMap<String, ServiceConfig>")]
+    pub services: LinkedHashMap<String, ServiceConfig>,
+
+    /// How the output of the services is printed.
+    /// Defaults to [`ServicesOutput::Interleaved`].
+    #[serde(default)]
+    pub output: ServicesOutput,
+}
+
+/// How the output of the services within a [`ServicesActionConfig`] is printed.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ServicesOutput {
+    /// Each line is printed as soon as it's written, prefixed with the name of the service.
+    #[default]
+    Interleaved,
+
+    /// Each service's output is buffered, and printed all at once when the service exits (or is
+    /// restarted), so the output of different services isn't mixed together.
+    Grouped,
+}
+
+/// A long-running process within a [`ServicesActionConfig`].
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(from = "ServiceConfigVariant")]
+pub struct ServiceConfig {
+    /// Environment variables to add or override for this service only.
+    /// Values can reference other variables (e.g. `$name`).
+    #[serde(default)]
+    pub env: LinkedHashMap<String, String>,
+
+    /// When to restart the service after it exits.
+    /// Defaults to [`RestartPolicy::Never`], which stops every other service too.
+    #[serde(default)]
+    pub restart: RestartPolicy,
+
+    /// Glob patterns matching the files which restart the service when they change, relative to
+    /// the service's working directory.
+    #[serde(default)]
+    pub watch: Vec<String>,
+
+    /// The [`ExecutionConfigVariant`] to execute.
+    #[serde(flatten)]
+    pub execution: ExecutionConfigVariant,
+}
+
+// Services are deserialized from a ServiceConfigVariant, so that's what the schema needs to describe
+impl JsonSchema for ServiceConfig {
+    fn schema_name() -> Cow<'static, str> {
+        ServiceConfigVariant::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        ServiceConfigVariant::json_schema(generator)
+    }
+}
+
+/// The kind of service configuration. Only used for deserializing a [`ServiceConfig`].
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ServiceConfigVariant {
+    /// Denotes a shorthand service.
+    ///
+    /// Example:
+    /// ```yaml
+    /// services:
+    ///     api: cargo run
+    /// ```
+    Shorthand(String),
+
+    /// Denotes a service with additional options.
+    Service {
+        #[serde(default)]
+        #[schemars(with = "HashMap<String, String>")]
+        env: LinkedHashMap<String, String>,
+
+        #[serde(default)]
+        restart: RestartPolicy,
+
+        #[serde(default)]
+        watch: Vec<String>,
+
+        #[serde(flatten)]
+        execution: ExecutionConfigVariant,
+    },
+}
+
+impl From<ServiceConfigVariant> for ServiceConfig {
+    fn from(variant: ServiceConfigVariant) -> Self {
+        match variant {
+            ServiceConfigVariant::Shorthand(command) => ServiceConfig {
+                env: Default::default(),
+                restart: RestartPolicy::default(),
+                watch: Vec::new(),
+                execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                    command,
+                )),
+            },
+            ServiceConfigVariant::Service {
+                env,
+                restart,
+                watch,
+                execution,
+            } => ServiceConfig {
+                env,
+                restart,
+                watch,
+                execution,
+            },
+        }
+    }
+}
+
+/// When a service is restarted after it exits.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// The service isn't restarted, and every other service is stopped.
+    #[default]
+    Never,
+
+    /// The service is restarted if it exits with a non-zero exit code.
+    OnFailure,
+
+    /// The service is always restarted.
+    Always,
+}
+
+/// Contains the path of another plz command to execute.
+///
+/// Example:
+/// ```yaml
+/// delegate: deploy staging
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct DelegateActionConfig {
+    pub delegate: String,
+}
+
+/// Contains the prefix for a command to execute.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct AliasActionConfig {
+    pub alias: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct SingleActionConfig {
+    pub action: ExecutionConfigVariant,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct MultiActionConfig {
+    pub actions: Vec<StepConfig>,
+
+    /// When set to `true`, all actions will be executed in a single bash session, preserving
+    /// exported variables and the working directory between actions.
+    /// Defaults to `false`.
+    #[serde(default = "default_session")]
+    pub session: bool,
+}
+
+fn default_session() -> bool {
+    false
+}
+
+/// A single step within a [`MultiActionConfig`].
+///
+/// Example:
+/// ```yaml
+/// actions:
+///     - name: check
+///       bash: git status --porcelain
+///     - if: steps.check.stdout == ""
+///       command: ./release.sh
+/// ```
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+#[serde(from = "StepConfigVariant")]
+pub struct StepConfig {
+    /// An optional name for the step.
+    /// Named steps can be referenced in the conditions of subsequent steps.
+    pub name: Option<String>,
+
+    /// An optional description of what the step does, shown in step headers and when inspecting
+    /// instead of the command.
+    pub description: Option<String>,
+
+    /// An optional condition that must be met for the step to be executed.
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+
+    /// When set to `true`, a non-zero exit code will not prevent subsequent steps from executing.
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub continue_on_error: bool,
+
+    /// An optional [`ExecutorConfig`] describing where the step should be executed.
+    /// If not specified, then the step will be executed on the local machine.
+    pub executor: Option<ExecutorConfig>,
+
+    /// Environment variables to add or override for this step only.
+    /// Values can reference other variables (e.g. `$name`).
+    #[serde(default)]
+    pub env: LinkedHashMap<String, String>,
+
+    /// [`OutputFilterConfig`]s applied to the step's stdout, in order, before it's displayed or
+    /// captured.
+    #[serde(default)]
+    pub filters: Vec<OutputFilterConfig>,
+
+    /// An optional file to write the step's stdout to, instead of the terminal.
+    /// Relative paths are resolved relative to the config file, missing parent directories are
+    /// created, and variables are substituted into the path.
+    pub stdout_file: Option<String>,
+
+    /// An optional file to write the step's stderr to, instead of the terminal.
+    /// Can be the same file as `stdout_file`.
+    pub stderr_file: Option<String>,
+
+    /// The [`ExecutionConfigVariant`] to execute.
+    #[serde(flatten)]
+    pub execution: ExecutionConfigVariant,
+}
+
+impl From<ExecutionConfigVariant> for StepConfig {
+    fn from(execution: ExecutionConfigVariant) -> Self {
+        StepConfig {
+            name: None,
+            description: None,
+            condition: None,
+            continue_on_error: false,
+            executor: None,
+            env: Default::default(),
+            filters: Vec::new(),
+            stdout_file: None,
+            stderr_file: None,
+            execution,
+        }
+    }
+}
+
+// Steps are deserialized from a StepConfigVariant, so that's what the schema needs to describe
+impl JsonSchema for StepConfig {
+    fn schema_name() -> Cow<'static, str> {
+        StepConfigVariant::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        StepConfigVariant::json_schema(generator)
+    }
+}
+
+/// A filter applied to the output of a step.
+///
+/// Example:
+/// ```yaml
+/// actions:
+///     - command: ./gradlew build
+///       filters:
+///           - exclude: ^(DEBUG|TRACE)
+///           - replace: "\\x1b\\[[0-9;]*m"
+///             with: ""
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum OutputFilterConfig {
+    /// Only keeps the lines matching a regex.
+    Grep { grep: String },
+
+    /// Removes the lines matching a regex.
+    Exclude { exclude: String },
+
+    /// Replaces every match of a regex within each line. The replacement can reference capture
+    /// groups (e.g. `$1`).
+    Replace {
+        replace: String,
+
+        #[serde(default)]
+        with: String,
+    },
+
+    /// Parses the output as JSON and extracts the values at a jq-style path (e.g.
+    /// `.items[].name`). The entire output is read before it's filtered.
+    Jq { jq: String },
+}
+
+/// The environment to execute a step in.
+///
+/// Example:
+/// ```yaml
+/// actions:
+///     - command: cargo test
+///       executor:
+///           docker: my-container
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExecutorConfig {
+    /// Encapsulates a [`DockerExecutorConfig`].
+    Docker(DockerExecutorConfig),
+
+    /// Encapsulates a [`SshExecutorConfig`].
+    Ssh(SshExecutorConfig),
+
+    /// Encapsulates a [`WslExecutorConfig`].
+    Wsl(WslExecutorConfig),
+}
+
+/// Executes a step within a running docker container.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct DockerExecutorConfig {
+    /// The name or ID of the container.
+    pub docker: String,
+}
+
+/// Executes a step on a remote machine via SSH.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct SshExecutorConfig {
+    /// The destination to connect to (e.g. `user@example.com`).
+    pub ssh: String,
+}
+
+/// Executes a step within a Windows Subsystem for Linux distribution.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+pub struct WslExecutorConfig {
+    /// The name of the distribution.
+    pub wsl: String,
+}
+
+/// The kind of step configuration. Only used for deserializing a [`StepConfig`].
+// Steps are converted into a StepConfig as soon as they're deserialized, so the size doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum StepConfigVariant {
+    /// Denotes a shorthand step.
+    ///
+    /// Example:
+    /// ```yaml
+    /// actions:
+    ///     - cat example.txt
+    /// ```
+    Shorthand(String),
+
+    /// Denotes a step with additional options.
+    Step {
+        name: Option<String>,
+
+        #[serde(alias = "desc")]
+        #[serde(default, deserialize_with = "deserialize_optional_localized")]
+        #[schemars(with = "Option<LocalizedText>")]
+        description: Option<String>,
+
+        #[serde(rename = "if")]
+        condition: Option<String>,
+
+        #[serde(default)]
+        continue_on_error: bool,
+
+        executor: Option<ExecutorConfig>,
+
+        #[serde(default)]
+        #[schemars(with = "HashMap<String, String>")]
+        env: LinkedHashMap<String, String>,
+
+        #[serde(default)]
+        filters: Vec<OutputFilterConfig>,
+
+        stdout_file: Option<String>,
+
+        stderr_file: Option<String>,
+
+        #[serde(flatten)]
+        execution: ExecutionConfigVariant,
+    },
+}
+
+impl From<StepConfigVariant> for StepConfig {
+    fn from(variant: StepConfigVariant) -> Self {
+        match variant {
+            StepConfigVariant::Shorthand(command) => StepConfig::from(
+                ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(command)),
+            ),
+            StepConfigVariant::Step {
+                name,
+                description,
+                condition,
+                continue_on_error,
+                executor,
+                env,
+                filters,
+                stdout_file,
+                stderr_file,
+                execution,
+            } => StepConfig {
+                name,
+                description,
+                condition,
+                continue_on_error,
+                executor,
+                env,
+                filters,
+                stdout_file,
+                stderr_file,
+                execution,
+            },
+        }
+    }
+}
+
+/// The kind of command to execute.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExecutionConfigVariant {
+    /// Encapsulates a [`ShellCommandConfigVariant`].
+    ShellCommand(ShellCommandConfigVariant),
+
+    /// Encapsulates a [`RawCommandConfigVariant`].
+    RawCommand(RawCommandConfigVariant),
+}
+
+impl ExecutionConfigVariant {
+    /// Returns the command text, before any variables have been substituted.
+    pub fn command(&self) -> &str {
+        match self {
+            ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(bash_config)) => {
+                &bash_config.command
+            }
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(command)) => {
+                command
+            }
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::RawCommandConfig(
+                raw_config,
+            )) => &raw_config.command,
+        }
+    }
+
+    /// Returns the working directory the command is executed in, before any variables have been
+    /// substituted, or `None` if it's executed in the current directory.
+    pub fn working_directory(&self) -> Option<&str> {
+        match self {
+            ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(bash_config)) => {
+                bash_config.working_directory.as_deref()
+            }
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(_)) => None,
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::RawCommandConfig(
+                raw_config,
+            )) => raw_config.working_directory.as_deref(),
+        }
+    }
+}
+
+/// The configuration for a raw command.
+/// Raw commands are simply commands executed without a shell.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum RawCommandConfigVariant {
+    /// Denotes a shorthand execution.
+    ///
+    /// Example:
+    /// ```yaml
+    /// exec: cat example.txt
+    /// ```
+    Shorthand(String),
+
+    /// Encapsulates a [`RawCommandConfig`].
+    RawCommandConfig(RawCommandConfig),
+}
+
+/// The configuration for a raw command.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct RawCommandConfig {
+    /// An optional working directory for the command to be executed in.
+    /// If not specified, then the command will be executed in the current directory.
+    #[serde(rename = "workdir")]
+    #[serde(alias = "wd")]
+    pub working_directory: Option<String>,
+
+    /// The command to execute.
+    #[serde(alias = "cmd")]
+    pub command: String,
+}
+
+/// The configuration for a shell command.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub enum ShellCommandConfigVariant {
+    /// Encapsulates a [`BashCommandConfig`].
+    Bash(BashCommandConfig),
+}
+
+/// The configuration for a bash command.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug, Clone)]
+#[schemars(transform = add_aliases)]
+pub struct BashCommandConfig {
+    /// An optional working directory for the command to be executed in.
+    /// If not specified, then the command will be executed in the current directory.
+    #[serde(rename = "workdir")]
+    #[serde(alias = "wd")]
+    pub working_directory: Option<String>,
+
+    /// The command to execute.
+    #[serde(rename = "bash")]
+    #[serde(alias = "sh")]
+    pub command: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OneOrManyPlatforms::{Many, One};
+    use crate::config::Platform::Linux;
+    use crate::config::RawCommandConfigVariant::Shorthand;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn bash_exec(command: &str, workdir: Option<String>) -> ExecutionConfigVariant {
+        return ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+            BashCommandConfig {
+                working_directory: workdir,
+                command: command.to_string(),
+            },
+        ));
+    }
+
+    fn raw_exec(command: &str) -> ExecutionConfigVariant {
+        return ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            command.to_string(),
+        ));
+    }
+
+    #[test]
+    fn empty_root_variables_allowed() {
+        let yaml = "commands:
+    demo:
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(config.variables.is_empty());
+    }
+
+    #[test]
+    fn shorthand_literal_variable_parsed() {
+        let yaml = "variables:
+    my-root-var: My root value
+commands:
+    demo:
+        variables:
+            my-command-var: My command value
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(!config.variables.is_empty());
+
+        let root_variable = config.variables.get("my-root-var").unwrap();
+        assert_eq!(
+            root_variable,
+            &VariableConfig::ShorthandLiteral("My root value".to_string())
+        );
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let command_variable = demo_command.variables.get("my-command-var").unwrap();
+        assert_eq!(
+            command_variable,
+            &VariableConfig::ShorthandLiteral("My command value".to_string())
+        )
+    }
+
+    #[test]
+    fn literal_variable_parsed() {
+        let yaml = "variables:
+    my-root-var:
+        value: My root value
+commands:
+    demo:
+        variables:
+            my-command-var:
+                value: My command value
+                arg: command-arg
+                env: MY_VAR
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(!config.variables.is_empty());
+
+        let root_variable = config.variables.get("my-root-var").unwrap();
+        assert_eq!(
+            root_variable,
+            &VariableConfig::Literal(LiteralVariableConfig {
+                value: "My root value".to_string(),
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+            })
+        );
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let command_variable = demo_command.variables.get("my-command-var").unwrap();
+        assert_eq!(
+            command_variable,
+            &VariableConfig::Literal(LiteralVariableConfig {
+                value: "My command value".to_string(),
+                argument: Some(ArgumentConfigVariant::Shorthand("command-arg".to_string())),
+                environment_variable_name: Some("MY_VAR".to_string()),
+                no_arg: false,
+                sensitive: false,
+            })
+        )
+    }
+
+    #[test]
+    fn exec_variable_parsed() {
+        let yaml = "variables:
+    my-root-var:
+        exec:
+            sh: echo \"My root value\"
+            workdir: ../
+commands:
+    demo:
+        variables:
+            my-command-var-1:
+                exec:
+                    bash: echo \"My command value\"
+                arg: command-arg-1
+                env: MY_VAR_1
+            my-command-var-2:
+                exec:
+                    bash: echo \"My command value\"
+                arg:
+                    description: Command level variable
+                    long: command-arg-2
+                    short: c
+                env: MY_VAR_2
+            my-command-var-3:
+                exec:
+                    bash: echo \"My command value\"
+                arg:
+                    description: Command level variable
+                    position: 1
+                env: MY_VAR_3
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(!config.variables.is_empty());
+
+        let root_variable = config.variables.get("my-root-var").unwrap();
+        assert_eq!(
+            root_variable,
+            &VariableConfig::Execution(ExecutionVariableConfig {
+                execution: bash_exec("echo \"My root value\"", Some("../".to_string())),
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
+            })
+        );
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let command_variable_1 = demo_command.variables.get("my-command-var-1").unwrap();
+        assert_eq!(
+            command_variable_1,
+            &VariableConfig::Execution(ExecutionVariableConfig {
+                execution: bash_exec("echo \"My command value\"", None),
+                argument: Some(ArgumentConfigVariant::Shorthand(
+                    "command-arg-1".to_string()
+                )),
+                environment_variable_name: Some("MY_VAR_1".to_string()),
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
+            })
+        );
+
+        let command_variable_2 = demo_command.variables.get("my-command-var-2").unwrap();
+        assert_eq!(
+            command_variable_2,
+            &VariableConfig::Execution(ExecutionVariableConfig {
+                execution: bash_exec("echo \"My command value\"", None),
+                argument: Some(ArgumentConfigVariant::Named(NamedArgumentConfig {
+                    description: Some("Command level variable".to_string()),
+                    long: "command-arg-2".to_string(),
+                    short: Some('c'),
+                    position: None,
+                    hidden: false,
+                })),
+                environment_variable_name: Some("MY_VAR_2".to_string()),
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
+            })
+        );
+
+        let command_variable_3 = demo_command.variables.get("my-command-var-3").unwrap();
+        assert_eq!(
+            command_variable_3,
+            &VariableConfig::Execution(ExecutionVariableConfig {
+                execution: bash_exec("echo \"My command value\"", None),
+                argument: Some(ArgumentConfigVariant::Positional(
+                    PositionalArgumentConfig {
+                        description: Some("Command level variable".to_string()),
+                        position: 1,
+                        hidden: false,
+                    }
+                )),
+                environment_variable_name: Some("MY_VAR_3".to_string()),
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
+            })
+        )
+    }
+
+    #[test]
+    fn prompt_variable_parsed() {
+        let yaml = "variables:
+    name:
+        prompt:
+            message: What's your name?
+    food:
+        description: Favourite food
+        arg: food
+        env: FAV_FOOD
+        prompt:
+            message: What's your favourite food?
+            options:
+                - Burger
+                - Pizza
+                - Fries
+commands:
+    demo:
+        variables:
+            password:
+                prompt:
+                    message: What's your password?
+                    sensitive: true
+            life-story:
+                prompt:
+                    message: What's your life story?
+                    multi_line: true
+            favourite-line:
+                prompt:
+                    message: What's your favourite line?
+                    options:
+                        exec: cat example.txt
+
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(!config.variables.is_empty());
+
+        let name_variable = config.variables.get("name").unwrap();
+        assert_eq!(
+            name_variable,
+            &VariableConfig::Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "What's your name?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Text(TextPromptOptions {
+                        multi_line: false,
+                        sensitive: false,
+                    })
+                },
+            })
+        );
+
+        let food_variable = config.variables.get("food").unwrap();
+        assert_eq!(
+            food_variable,
+            &VariableConfig::Prompt(PromptVariableConfig {
+                argument: Some(ArgumentConfigVariant::Shorthand("food".to_string())),
+                environment_variable_name: Some("FAV_FOOD".to_string()),
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "What's your favourite food?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Select(SelectPromptOptions {
+                        options: SelectOptionsConfig::Literal(vec![
+                            "Burger".to_string(),
+                            "Pizza".to_string(),
+                            "Fries".to_string()
+                        ])
+                    })
+                },
+            })
+        );
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let password_variable = demo_command.variables.get("password").unwrap();
+        assert_eq!(
+            password_variable,
+            &VariableConfig::Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "What's your password?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Text(TextPromptOptions {
+                        multi_line: false,
+                        sensitive: true
+                    })
+                },
+            })
+        );
+
+        let life_story_variable = demo_command.variables.get("life-story").unwrap();
+        assert_eq!(
+            life_story_variable,
+            &VariableConfig::Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "What's your life story?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Text(TextPromptOptions {
+                        multi_line: true,
+                        sensitive: false
+                    })
+                },
+            })
+        );
+
+        let fav_line_variable = demo_command.variables.get("favourite-line").unwrap();
+        assert_eq!(
+            fav_line_variable,
+            &VariableConfig::Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "What's your favourite line?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Select(SelectPromptOptions {
+                        options: SelectOptionsConfig::Execution(ExecutionSelectOptionsConfig {
+                            execution: raw_exec("cat example.txt")
+                        }),
+                    })
+                }
+            })
+        )
+    }
+
+    #[test]
+    fn argument_variable_parsed() {
+        let yaml = "commands:
+    demo:
+        variables:
+            name:
+                argument:
+                    description: Your name.
+                    long: name
+                    short: n
+            age:
+                arg: age
+            food:
+                arg:
+                    description: Your favourite food.
+                    position: 1
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+
+        let name_variable = demo_command.variables.get("name").unwrap();
+        assert_eq!(
+            name_variable,
+            &VariableConfig::Argument(ArgumentVariableConfig {
+                argument: ArgumentConfigVariant::Named(NamedArgumentConfig {
+                    description: Some("Your name.".to_string()),
+                    long: "name".to_string(),
+                    short: Some('n'),
+                    position: None,
+                    hidden: false,
+                }),
+                environment_variable_name: None,
+                sensitive: false,
+            })
+        );
+
+        let age_variable = demo_command.variables.get("age").unwrap();
+        assert_eq!(
+            age_variable,
+            &VariableConfig::Argument(ArgumentVariableConfig {
+                argument: ArgumentConfigVariant::Shorthand("age".to_string()),
+                environment_variable_name: None,
+                sensitive: false,
+            })
+        );
+
+        let food_variable = demo_command.variables.get("food").unwrap();
+        assert_eq!(
+            food_variable,
+            &VariableConfig::Argument(ArgumentVariableConfig {
+                argument: ArgumentConfigVariant::Positional(PositionalArgumentConfig {
+                    description: Some("Your favourite food.".to_string()),
+                    position: 1,
+                    hidden: false,
+                }),
+                environment_variable_name: None,
+                sensitive: false,
+            })
+        );
+    }
+
+    #[test]
+    fn variable_order_is_preserved() {
+        let yaml = "variables:
+    root-var-3: Root value 3
+    root-var-2: Root value 2
+    root-var-1: Root value 1
+commands:
+    demo:
+        variables:
+            command-var-2: Command value 2
+            command-var-1: Command value 1
+            command-var-3: Command value 3
+        action: echo \"Hello, World!\"";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        assert!(!config.variables.is_empty());
+
+        let root_variable_names: Vec<String> =
+            config.variables.iter().map(|kv| kv.0.to_string()).collect();
+        assert_eq!(root_variable_names[0], "root-var-3".to_string());
+        assert_eq!(root_variable_names[1], "root-var-2".to_string());
+        assert_eq!(root_variable_names[2], "root-var-1".to_string());
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let command_variable_names: Vec<String> = demo_command
+            .variables
+            .iter()
+            .map(|kv| kv.0.to_string())
+            .collect();
+        assert_eq!(command_variable_names[0], "command-var-2".to_string());
+        assert_eq!(command_variable_names[1], "command-var-1".to_string());
+        assert_eq!(command_variable_names[2], "command-var-3".to_string());
+    }
+
+    // TODO: Command order is preserved
+
+    #[test]
+    fn single_action_command_parses() {
+        let yaml = "commands:
+    demo:
+        action: ls";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "ls".to_string()
+                    )),
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn alias_command_parses() {
+        let yaml = "commands:
+    deps:
+        alias: docker compose -f docker-compose.deps.yml";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("deps").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::Alias(AliasActionConfig {
+                    alias: "docker compose -f docker-compose.deps.yml".to_string()
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn delegate_command_parses() {
+        let yaml = "commands:
+    d:
+        delegate: deploy staging";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let d_command = config.commands.get("d").unwrap();
+        assert_eq!(
+            d_command.action,
+            Some(ActionConfig::Delegate(DelegateActionConfig {
+                delegate: "deploy staging".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn single_action_command_with_optional_fields_parses() {
+        let yaml = "commands:
+    demo:
+        description: Says hello.
+        action: ls";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                platform: None,
+                description: Some("Says hello.".to_string()),
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "ls".to_string()
+                    )),
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn action_with_subcommands_parses() {
+        let yaml = "commands:
+    demo:
+        commands:
+            gday:
+                action: ls
+        action: cat example.txt";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let gday_command = demo_command.commands.get("gday").unwrap();
+
+        assert_eq!(
+            gday_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "ls".to_string()
+                    )),
+                })),
+            }
+        );
+
+        let mut map = CommandConfigMap::new();
+        map.insert("gday".to_string(), gday_command.clone());
+
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: map,
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "cat example.txt".to_string()
+                    )),
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn command_with_subcommands_only_parses() {
+        let yaml = "commands:
     demo:
-        variables:
-            my-command-var-1:
-                exec:
-                    bash: echo \"My command value\"
-                arg: command-arg-1
-                env: MY_VAR_1
-            my-command-var-2:
-                exec:
-                    bash: echo \"My command value\"
-                arg:
-                    description: Command level variable
-                    long: command-arg-2
-                    short: c
-                env: MY_VAR_2
-            my-command-var-3:
-                exec:
-                    bash: echo \"My command value\"
-                arg:
-                    description: Command level variable
-                    position: 1
-                env: MY_VAR_3
-        action: echo \"Hello, World!\"";
+        commands:
+            gday:
+                action: ls";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        let gday_command = demo_command.commands.get("gday").unwrap();
+
+        assert_eq!(
+            gday_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "ls".to_string()
+                    )),
+                })),
+            }
+        );
+
+        let mut map = CommandConfigMap::new();
+        map.insert("gday".to_string(), gday_command.clone());
+
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: map,
+                action: None,
+            }
+        );
+    }
+
+    // TODO: Command with no subcommands or action - Fail
+
+    #[test]
+    fn command_with_multiple_actions_parses() {
+        let yaml = "commands:
+    demo:
+        actions:
+            - cat example.txt
+            - ls";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::MultiStep(MultiActionConfig {
+                    actions: vec![
+                        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                            "cat example.txt".to_string()
+                        ))
+                        .into(),
+                        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                            "ls".to_string()
+                        ))
+                        .into(),
+                    ],
+                    session: false,
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn commands_with_specific_platforms_parse() {
+        let yaml = "commands:
+    demo_nix:
+        platforms:
+            - Linux
+            - MacOS
+        action: cat example.txt
+    demo_win:
+        platform: Windows
+        action: Get-Content example.txt";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command_nix = config.commands.get("demo_nix").unwrap();
+        let demo_command_win = config.commands.get("demo_win").unwrap();
+        assert_eq!(
+            demo_command_nix,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: Some(Many(ManyPlatforms {
+                    platforms: vec![Platform::Linux, Platform::MacOS]
+                })),
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "cat example.txt".to_string()
+                    ))
+                })),
+            }
+        );
+
+        assert_eq!(
+            demo_command_win,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: Some(One(OnePlatform {
+                    platform: Platform::Windows
+                })),
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "Get-Content example.txt".to_string()
+                    ))
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn commands_with_name_parse() {
+        let yaml = "commands:
+    demo:
+        name: demonstration
+        action: cat example.txt";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: Some("demonstration".to_string()),
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::SingleStep(SingleActionConfig {
+                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                        "cat example.txt".to_string()
+                    ))
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn shell_action_parses() {
+        let yaml = "commands:
+    demo:
+        actions:
+            - bash: echo \"Hello, World!\"
+            - bash: pwd
+              workdir: /";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command,
+            &CommandConfig {
+                name: None,
+                description: None,
+                hidden: false,
+                python_env: None,
+                save_env: None,
+                on_failure: None,
+                on_interrupt: None,
+                cooldown: None,
+                require_clean_git: false,
+                guard: None,
+                env_files: Vec::new(),
+                depends_on: Vec::new(),
+                use_variables: Vec::new(),
+                stdout_file: None,
+                stderr_file: None,
+                lock: false,
+                lock_wait: None,
+                auto_args: None,
+                on_success_message: None,
+                on_failure_message: None,
+                when: None,
+                platform: None,
+                variables: Default::default(),
+                commands: Default::default(),
+                action: Some(ActionConfig::MultiStep(MultiActionConfig {
+                    actions: vec![
+                        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+                            BashCommandConfig {
+                                working_directory: None,
+                                command: "echo \"Hello, World!\"".to_string(),
+                            }
+                        ))
+                        .into(),
+                        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+                            BashCommandConfig {
+                                working_directory: Some("/".to_string()),
+                                command: "pwd".to_string(),
+                            }
+                        ))
+                        .into(),
+                    ],
+                    session: false,
+                })),
+            }
+        );
+    }
+
+    #[test]
+    fn step_with_condition_parses() {
+        let yaml = "commands:
+    demo:
+        actions:
+            - name: check
+              continue_on_error: true
+              bash: git status --porcelain
+            - if: steps.check.stdout == \"\"
+              command: ./release.sh";
         let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
 
-        assert!(!config.variables.is_empty());
+        let demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            demo_command.action,
+            Some(ActionConfig::MultiStep(MultiActionConfig {
+                actions: vec![
+                    StepConfig {
+                        name: Some("check".to_string()),
+                        description: None,
+                        condition: None,
+                        continue_on_error: true,
+                        executor: None,
+                        env: Default::default(),
+                        filters: Vec::new(),
+                        stdout_file: None,
+                        stderr_file: None,
+                        execution: bash_exec("git status --porcelain", None),
+                    },
+                    StepConfig {
+                        name: None,
+                        description: None,
+                        condition: Some("steps.check.stdout == \"\"".to_string()),
+                        continue_on_error: false,
+                        executor: None,
+                        env: Default::default(),
+                        filters: Vec::new(),
+                        stdout_file: None,
+                        stderr_file: None,
+                        execution: ExecutionConfigVariant::RawCommand(
+                            RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                                working_directory: None,
+                                command: "./release.sh".to_string(),
+                            })
+                        ),
+                    },
+                ],
+                session: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn step_with_executor_parses() {
+        let yaml = "commands:
+    demo:
+        actions:
+            - command: cargo test
+              executor:
+                  docker: app
+            - command: ./deploy.sh
+              executor:
+                  ssh: deploy@example.com
+            - command: ls
+              executor:
+                  wsl: Ubuntu";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
 
-        let root_variable = config.variables.get("my-root-var").unwrap();
+        let demo_command = config.commands.get("demo").unwrap();
+        let Some(ActionConfig::MultiStep(multi_action_config)) = &demo_command.action else {
+            panic!("expected a multi-step action");
+        };
+        let executors: Vec<Option<ExecutorConfig>> = multi_action_config
+            .actions
+            .iter()
+            .map(|step| step.executor.clone())
+            .collect();
         assert_eq!(
-            root_variable,
-            &VariableConfig::Execution(ExecutionVariableConfig {
-                execution: bash_exec("echo \"My root value\"", Some("../".to_string())),
-                argument: None,
-                environment_variable_name: None,
-            })
+            executors,
+            vec![
+                Some(ExecutorConfig::Docker(DockerExecutorConfig {
+                    docker: "app".to_string()
+                })),
+                Some(ExecutorConfig::Ssh(SshExecutorConfig {
+                    ssh: "deploy@example.com".to_string()
+                })),
+                Some(ExecutorConfig::Wsl(WslExecutorConfig {
+                    wsl: "Ubuntu".to_string()
+                })),
+            ]
         );
+    }
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let command_variable_1 = demo_command.variables.get("my-command-var-1").unwrap();
+    #[test]
+    fn import() {
+        let yaml3 = "variables:
+    age: Forty Two
+commands:
+    demo:
+        action: echo \"You are $age years old.\"";
+        let yaml3_file = create_temp_file(yaml3);
+        let yaml3_dir = yaml3_file
+            .path()
+            .parent()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let yaml2 = format!(
+            "imports:
+    - alias: level-3
+      source: {}
+      hidden: true
+variables:
+    last_name: Smith
+commands:
+    demo:
+        action: echo \"Your last name is $last_name!\"",
+            yaml3_file.path().to_str().unwrap()
+        );
+        let yaml2_file = create_temp_file(yaml2.as_str());
+        let yaml2_dir = yaml2_file
+            .path()
+            .parent()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let yaml1 = format!(
+            "imports:
+    - alias: level-2
+      source: {}
+      platform: Linux
+variables:
+    first_name: Alice
+commands:
+    demo:
+        action: echo \"Your first name is $first_name!\"",
+            yaml2_file.path().to_str().unwrap()
+        );
+
+        let config = parse_config(&yaml1.to_string(), Platform::Linux, None).unwrap();
+
+        let root_demo_command = config.commands.get("demo").unwrap();
         assert_eq!(
-            command_variable_1,
-            &VariableConfig::Execution(ExecutionVariableConfig {
-                execution: bash_exec("echo \"My command value\"", None),
-                argument: Some(ArgumentConfigVariant::Shorthand(
-                    "command-arg-1".to_string()
-                )),
-                environment_variable_name: Some("MY_VAR_1".to_string()),
-            })
+            root_demo_command.action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(Shorthand(
+                    "echo \"Your first name is $first_name!\"".to_string()
+                ))
+            }))
+        );
+        assert_eq!(
+            config.variables.get("first_name").unwrap(),
+            &VariableConfig::ShorthandLiteral("Alice".to_string())
         );
 
-        let command_variable_2 = demo_command.variables.get("my-command-var-2").unwrap();
+        let second_level_command = config.commands.get("level-2").unwrap();
         assert_eq!(
-            command_variable_2,
-            &VariableConfig::Execution(ExecutionVariableConfig {
-                execution: bash_exec("echo \"My command value\"", None),
-                argument: Some(ArgumentConfigVariant::Named(NamedArgumentConfig {
-                    description: Some("Command level variable".to_string()),
-                    long: "command-arg-2".to_string(),
-                    short: Some('c'),
-                })),
-                environment_variable_name: Some("MY_VAR_2".to_string()),
-            })
+            second_level_command.commands.get("demo").unwrap().action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                        command: "echo \"Your last name is $last_name!\"".to_string(),
+                        working_directory: Some(yaml2_dir),
+                    })
+                )
+            }))
+        );
+        assert_eq!(
+            second_level_command.platform,
+            Some(One(OnePlatform { platform: Linux }))
+        );
+        assert_eq!(
+            second_level_command.variables.get("last_name").unwrap(),
+            &VariableConfig::ShorthandLiteral("Smith".to_string())
         );
 
-        let command_variable_3 = demo_command.variables.get("my-command-var-3").unwrap();
+        let third_level_command = second_level_command.commands.get("level-3").unwrap();
         assert_eq!(
-            command_variable_3,
-            &VariableConfig::Execution(ExecutionVariableConfig {
-                execution: bash_exec("echo \"My command value\"", None),
-                argument: Some(ArgumentConfigVariant::Positional(
-                    PositionalArgumentConfig {
-                        description: Some("Command level variable".to_string()),
-                        position: 1,
-                    }
-                )),
-                environment_variable_name: Some("MY_VAR_3".to_string()),
-            })
-        )
+            third_level_command.commands.get("demo").unwrap().action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                        command: "echo \"You are $age years old.\"".to_string(),
+                        working_directory: Some(yaml3_dir),
+                    })
+                )
+            }))
+        );
+        assert_eq!(third_level_command.hidden, true);
+        assert_eq!(
+            third_level_command.variables.get("age").unwrap(),
+            &VariableConfig::ShorthandLiteral("Forty Two".to_string())
+        );
     }
 
     #[test]
-    fn prompt_variable_parsed() {
-        let yaml = "variables:
-    name:
-        prompt:
-            message: What's your name?
-    food:
-        description: Favourite food
-        arg: food
-        env: FAV_FOOD
-        prompt:
-            message: What's your favourite food?
-            options:
-                - Burger
-                - Pizza
-                - Fries
+    fn import_for_other_platform_is_ignored() {
+        let yaml2 = "commands:
+    demo:
+        action: echo \"Your last name is $last_name!\""
+            .to_string();
+        let yaml2_file = create_temp_file(yaml2.as_str());
+
+        let yaml1 = format!(
+            "imports:
+    - alias: other
+      source: {}
+      platform: Windows
+variables:
+    first_name: Alice
 commands:
     demo:
+        action: echo \"Your first name is $first_name!\"",
+            yaml2_file.path().to_str().unwrap()
+        );
+
+        let config = parse_config(&yaml1.to_string(), Platform::Linux, None).unwrap();
+
+        let root_demo_command = config.commands.get("demo").unwrap();
+        assert_eq!(
+            root_demo_command.action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(Shorthand(
+                    "echo \"Your first name is $first_name!\"".to_string()
+                ))
+            }))
+        );
+        assert_eq!(
+            config.variables.get("first_name").unwrap(),
+            &VariableConfig::ShorthandLiteral("Alice".to_string())
+        );
+
+        let second_level_command = config.commands.get("other");
+        assert_eq!(second_level_command, None);
+    }
+
+    #[test]
+    fn import_with_unmet_condition_is_ignored() {
+        unsafe {
+            env::set_var("PLZ_TEST_IMPORT_PROFILE", "dev");
+        }
+        let dev_file = create_temp_file("commands:\n    serve:\n        action: echo dev");
+        let prod_file = create_temp_file("commands:\n    deploy:\n        action: echo prod");
+
+        let yaml = format!(
+            "imports:
+    - alias: env
+      source: {}
+      when: '\"$PLZ_TEST_IMPORT_PROFILE\" == \"dev\"'
+    - alias: env
+      source: {}
+      when: '\"$PLZ_TEST_IMPORT_PROFILE\" == \"prod\"'
+commands: {{}}",
+            dev_file.path().to_str().unwrap(),
+            prod_file.path().to_str().unwrap()
+        );
+
+        let config = parse_config(&yaml, Platform::Linux, None).unwrap();
+
+        let env_command = config.commands.get("env").unwrap();
+        assert!(env_command.commands.contains_key("serve"));
+        assert!(!env_command.commands.contains_key("deploy"));
+    }
+
+    #[test]
+    fn import_condition_can_reference_root_variables() {
+        let dev_file = create_temp_file("commands:\n    serve:\n        action: echo dev");
+        let prod_file = create_temp_file("commands:\n    deploy:\n        action: echo prod");
+
+        let yaml = format!(
+            "variables:
+    profile: dev
+imports:
+    - alias: env
+      source: {}
+      when: \"{{{{ profile }}}} == 'dev'\"
+    - alias: env
+      source: {}
+      when: \"{{{{ profile }}}} == 'prod'\"
+commands: {{}}",
+            dev_file.path().to_str().unwrap(),
+            prod_file.path().to_str().unwrap()
+        );
+
+        let config = parse_config(&yaml, Platform::Linux, None).unwrap();
+
+        let env_command = config.commands.get("env").unwrap();
+        assert!(env_command.commands.contains_key("serve"));
+        assert!(!env_command.commands.contains_key("deploy"));
+    }
+
+    #[test]
+    fn import_condition_variables_apply_selected_environment() {
+        let config = parse_config(
+            &"variables:
+    profile: dev
+    region:
+        value: us-east-1
+    token:
+        prompt:
+            message: Token?
+environments:
+    prod:
         variables:
-            password:
-                prompt:
-                    message: What's your password?
-                    sensitive: true
-            life-story:
-                prompt:
-                    message: What's your life story?
-                    multi_line: true
-            favourite-line:
-                prompt:
-                    message: What's your favourite line?
-                    options:
-                        exec: cat example.txt
+            profile: prod
+            tier: gold
+commands:
+    deploy:
+        action: ./deploy.sh"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        let selected =
+            import_condition_variables(&config, &args(&["plz", "--env", "prod", "deploy"]), |_| {
+                None
+            });
+        let from_env = import_condition_variables(&config, &args(&["plz", "deploy"]), |name| {
+            (name == "PLZ_ENV").then(|| "prod".to_string())
+        });
+        let default = import_condition_variables(&config, &args(&["plz", "deploy"]), |_| None);
+
+        assert_eq!(selected["profile"], "prod");
+        assert_eq!(selected["region"], "us-east-1");
+        assert_eq!(selected["tier"], "gold");
+        assert!(!selected.contains_key("token"));
+        assert_eq!(from_env["profile"], "prod");
+        assert_eq!(default["profile"], "dev");
+        assert!(!default.contains_key("tier"));
+    }
+
+    #[test]
+    fn missing_optional_import_is_skipped() {
+        let dir = create_temp_dir();
+        write_file(
+            &dir.path().join("shared.yaml"),
+            "commands:\n    build:\n        action: cargo build",
+        );
+
+        let yaml = "imports:
+    - alias: shared
+      source: ./shared.yaml
+      optional: true
+    - alias: team
+      source: ./team.yaml
+      optional: true
+    - alias: extensions
+      source: ./plz.d/*.yaml
+      optional: true
+      quiet: true
+commands: {}";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, Some(dir.path())).unwrap();
+        let required = parse_config(
+            &yaml.replace("optional: true", "optional: false"),
+            Platform::Linux,
+            Some(dir.path()),
+        );
+
+        assert!(config.commands["shared"].commands.contains_key("build"));
+        assert!(!config.commands.contains_key("team"));
+        assert!(!config.commands.contains_key("extensions"));
+        assert!(matches!(
+            required,
+            Err(ConfigError::ImportFailed { alias, .. }) if alias == "team"
+        ));
+    }
 
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    #[test]
+    fn glob_import_merges_matching_files() {
+        let dir = create_temp_dir();
+        fs::create_dir(dir.path().join("plz.d")).unwrap();
+        write_file(
+            &dir.path().join("plz.d/build.yaml"),
+            "description: Build tasks\ncommands:\n    build:\n        action: cargo build",
+        );
+        write_file(
+            &dir.path().join("plz.d/test.yaml"),
+            "variables:\n    target: debug\ncommands:\n    test:\n        action: cargo test",
+        );
+        write_file(&dir.path().join("plz.d/notes.txt"), "not a config");
 
-        assert!(!config.variables.is_empty());
+        let yaml = "imports:\n    - alias: tasks\n      source: ./plz.d/*.yaml\ncommands: {}";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, Some(dir.path())).unwrap();
 
-        let name_variable = config.variables.get("name").unwrap();
-        assert_eq!(
-            name_variable,
-            &VariableConfig::Prompt(PromptVariableConfig {
-                argument: None,
-                environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "What's your name?".to_string(),
-                    options: PromptOptionsVariant::Text(TextPromptOptions {
-                        multi_line: false,
-                        sensitive: false,
-                    })
-                },
-            })
+        let tasks_command = config.commands.get("tasks").unwrap();
+        assert_eq!(tasks_command.description, Some("Build tasks".to_string()));
+        assert!(tasks_command.commands.contains_key("build"));
+        assert!(tasks_command.commands.contains_key("test"));
+        assert!(tasks_command.variables.contains_key("target"));
+    }
+
+    #[test]
+    fn glob_import_with_conflicting_keys_fails() {
+        let dir = create_temp_dir();
+        fs::create_dir(dir.path().join("plz.d")).unwrap();
+        write_file(
+            &dir.path().join("plz.d/a.yaml"),
+            "commands:\n    build:\n        action: cargo build",
+        );
+        write_file(
+            &dir.path().join("plz.d/b.yaml"),
+            "commands:\n    build:\n        action: make",
         );
 
-        let food_variable = config.variables.get("food").unwrap();
+        let yaml = "imports:\n    - alias: tasks\n      source: ./plz.d/*.yaml\ncommands: {}";
+        let result = parse_config(&yaml.to_string(), Platform::Linux, Some(dir.path()));
+
+        let Err(ConfigError::ImportFailed { source, .. }) = result else {
+            panic!("expected the import to fail");
+        };
         assert_eq!(
-            food_variable,
-            &VariableConfig::Prompt(PromptVariableConfig {
-                argument: Some(ArgumentConfigVariant::Shorthand("food".to_string())),
-                environment_variable_name: Some("FAV_FOOD".to_string()),
-                prompt: PromptConfig {
-                    message: "What's your favourite food?".to_string(),
-                    options: PromptOptionsVariant::Select(SelectPromptOptions {
-                        options: SelectOptionsConfig::Literal(vec![
-                            "Burger".to_string(),
-                            "Pizza".to_string(),
-                            "Fries".to_string()
-                        ])
-                    })
-                },
-            })
+            source.to_string(),
+            format!(
+                "commands.build is defined by both {} and {}",
+                dir.path().join("plz.d/a.yaml").display(),
+                dir.path().join("plz.d/b.yaml").display()
+            )
         );
+    }
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let password_variable = demo_command.variables.get("password").unwrap();
-        assert_eq!(
-            password_variable,
-            &VariableConfig::Prompt(PromptVariableConfig {
-                argument: None,
-                environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "What's your password?".to_string(),
-                    options: PromptOptionsVariant::Text(TextPromptOptions {
-                        multi_line: false,
-                        sensitive: true
-                    })
-                },
-            })
+    #[test]
+    fn import_only_parses_requested_command() {
+        let import_file = create_temp_file(
+            "commands:
+    demo:
+        action: echo \"Hello, World!\"",
         );
 
-        let life_story_variable = demo_command.variables.get("life-story").unwrap();
-        assert_eq!(
-            life_story_variable,
-            &VariableConfig::Prompt(PromptVariableConfig {
-                argument: None,
-                environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "What's your life story?".to_string(),
-                    options: PromptOptionsVariant::Text(TextPromptOptions {
-                        multi_line: true,
-                        sensitive: false
-                    })
-                },
-            })
+        let yaml = format!(
+            "imports:
+    - alias: requested
+      source: {}
+    - alias: missing
+      source: ./does-not-exist.yaml
+commands: {{}}",
+            import_file.path().to_str().unwrap()
         );
 
-        let fav_line_variable = demo_command.variables.get("favourite-line").unwrap();
-        assert_eq!(
-            fav_line_variable,
-            &VariableConfig::Prompt(PromptVariableConfig {
-                argument: None,
-                environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "What's your favourite line?".to_string(),
-                    options: PromptOptionsVariant::Select(SelectPromptOptions {
-                        options: SelectOptionsConfig::Execution(ExecutionSelectOptionsConfig {
-                            execution: raw_exec("cat example.txt")
-                        }),
-                    })
-                }
-            })
+        let mut imports = Vec::new();
+        let config = parse_config_tracked(
+            &yaml,
+            Platform::Linux,
+            None,
+            Some("requested"),
+            &[],
+            &mut imports,
         )
+        .unwrap();
+
+        let requested_command = config.commands.get("requested").unwrap();
+        assert!(requested_command.commands.contains_key("demo"));
+
+        let missing_command = config.commands.get("missing").unwrap();
+        assert!(missing_command.commands.is_empty());
+        assert_eq!(imports, vec![import_file.path().to_path_buf()]);
     }
 
     #[test]
-    fn argument_variable_parsed() {
-        let yaml = "commands:
-    demo:
-        variables:
-            name:
-                argument:
-                    description: Your name.
-                    long: name
-                    short: n
-            age:
-                arg: age
-            food:
-                arg:
-                    description: Your favourite food.
-                    position: 1
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn apply_overrides_prefers_flags_over_env_over_config() {
+        let mut options = Options {
+            print_commands: false,
+            print_variables: true,
+            auto_args: true,
+            exec_timeout: Some("1m".to_string()),
+            ..Options::default()
+        };
 
-        let demo_command = config.commands.get("demo").unwrap();
+        let args = vec![
+            "plz".to_string(),
+            "--auto-args".to_string(),
+            "--exec-timeout=30s".to_string(),
+            "build".to_string(),
+            "--".to_string(),
+            "--print-commands-format".to_string(),
+            "ignored".to_string(),
+        ];
+        let result = options.apply_overrides(&args, |name| match name {
+            "PLZ_PRINT_COMMANDS" => Some("1".to_string()),
+            "PLZ_PRINT_VARIABLES" => Some("false".to_string()),
+            "PLZ_AUTO_ARGS" => Some("0".to_string()),
+            "PLZ_EXEC_TIMEOUT" => Some("5s".to_string()),
+            _ => None,
+        });
 
-        let name_variable = demo_command.variables.get("name").unwrap();
-        assert_eq!(
-            name_variable,
-            &VariableConfig::Argument(ArgumentVariableConfig {
-                argument: ArgumentConfigVariant::Named(NamedArgumentConfig {
-                    description: Some("Your name.".to_string()),
-                    long: "name".to_string(),
-                    short: Some('n'),
-                }),
-                environment_variable_name: None,
-            })
+        assert!(result.is_ok());
+        assert!(options.print_commands);
+        assert!(!options.print_variables);
+        assert!(options.auto_args);
+        assert_eq!(options.exec_timeout, Some("30s".to_string()));
+        assert_ne!(options.print_commands_format, "ignored");
+    }
+
+    #[test]
+    fn apply_overrides_only_checks_arguments_before_the_command() {
+        // Arrange
+        let mut options = Options {
+            print_commands: false,
+            step_headers: false,
+            ..Options::default()
+        };
+        let args: Vec<String> = [
+            "plz",
+            "--env",
+            "staging",
+            "--exec-timeout",
+            "30s",
+            "deploy",
+            "--print-commands",
+            "--step-headers=true",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+        // Act
+        let result = options.apply_overrides(&args, |_| None);
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(options.exec_timeout, Some("30s".to_string()));
+        assert!(!options.print_commands);
+        assert!(!options.step_headers);
+    }
+
+    #[test]
+    fn requested_command_skips_leading_flags() {
+        // Arrange
+        let requested = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            requested_command(&args, &[])
+        };
+
+        // Act
+        let with_env = requested(&["plz", "--env", "staging", "deploy", "--help"]);
+        let with_config = requested(&["plz", "-f", "deploy.yaml", "--timings", "deploy"]);
+        let with_option = requested(&["plz", "--exec-timeout", "5", "deploy", "tree"]);
+        let built_in = [
+            requested(&["plz", "--env", "staging", "tree"]),
+            requested(&["plz", "help", "deploy"]),
+            requested(&["plz", "test-config", "spec.yaml"]),
+            requested(&["plz", "migrate"]),
+        ];
+        let help = requested(&["plz", "--help", "deploy"]);
+        let missing = requested(&["plz", "--env", "staging"]);
+
+        // Assert
+        assert_eq!(with_env.as_deref(), Some("deploy"));
+        assert_eq!(with_config.as_deref(), Some("deploy"));
+        assert_eq!(with_option.as_deref(), Some("deploy"));
+        assert_eq!(built_in, [None, None, None, None]);
+        assert_eq!(help, None);
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn requested_command_skips_root_variable_arguments() {
+        // Arrange
+        let config_text = "variables:
+    profile:
+        value: dev
+        arg:
+            long: profile
+            short: p
+    region:
+        from_env: REGION
+imports:
+    - alias: deploy
+      source: ./deploy.yaml
+commands: {}";
+        let args: Vec<String> = [
+            "plz",
+            "-f",
+            "plz.yaml",
+            "--profile",
+            "prod",
+            "deploy",
+            "app",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+
+        // Act
+        let variable_flags = root_variable_flags(&[config_text], &args);
+        let requested = requested_command(&args, &variable_flags);
+
+        // Assert
+        assert_eq!(variable_flags, ["--profile", "-p"]);
+        assert_eq!(requested.as_deref(), Some("deploy"));
+    }
+
+    #[test]
+    fn import_parses_every_import_when_requested_command_is_unknown() {
+        let import_file = create_temp_file(
+            "commands:
+    app:
+        action: echo \"Hello, World!\"",
         );
 
-        let age_variable = demo_command.variables.get("age").unwrap();
-        assert_eq!(
-            age_variable,
-            &VariableConfig::Argument(ArgumentVariableConfig {
-                argument: ArgumentConfigVariant::Shorthand("age".to_string()),
-                environment_variable_name: None,
-            })
+        let yaml = format!(
+            "imports:
+    - alias: deploy
+      source: {}
+commands: {{}}",
+            import_file.path().to_str().unwrap()
         );
 
-        let food_variable = demo_command.variables.get("food").unwrap();
+        // The value of an argument plz doesn't know about can be mistaken for the command
+        let mut imports = Vec::new();
+        let config = parse_config_tracked(
+            &yaml,
+            Platform::Linux,
+            None,
+            Some("prod"),
+            &[],
+            &mut imports,
+        )
+        .unwrap();
+
+        let deploy_command = config.commands.get("deploy").unwrap();
+        assert!(deploy_command.commands.contains_key("app"));
+        assert_eq!(imports, vec![import_file.path().to_path_buf()]);
+    }
+
+    #[test]
+    fn leading_args_skips_flag_values() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
         assert_eq!(
-            food_variable,
-            &VariableConfig::Argument(ArgumentVariableConfig {
-                argument: ArgumentConfigVariant::Positional(PositionalArgumentConfig {
-                    description: Some("Your favourite food.".to_string()),
-                    position: 1
-                }),
-                environment_variable_name: None,
-            })
+            leading_args(&args(&[
+                "plz",
+                "--env",
+                "staging",
+                "-f",
+                "plz.yaml",
+                "--print-commands",
+                "--exec-timeout",
+                "5s",
+                "deploy",
+                "--trust"
+            ])),
+            args(&[
+                "--env",
+                "staging",
+                "-f",
+                "plz.yaml",
+                "--print-commands",
+                "--exec-timeout",
+                "5s"
+            ])
+        );
+        assert_eq!(
+            leading_args(&args(&["plz", "--trust", "--", "deploy"])),
+            args(&["--trust"])
         );
+        assert_eq!(leading_args(&args(&["plz", "--env"])), args(&["--env"]));
+        assert!(leading_args(&args(&["plz"])).is_empty());
     }
 
     #[test]
-    fn variable_order_is_preserved() {
-        let yaml = "variables:
-    root-var-3: Root value 3
-    root-var-2: Root value 2
-    root-var-1: Root value 1
+    fn apply_environment_overrides_variables_and_options() {
+        let mut config = parse_config(
+            &"variables:
+    region:
+        value: us-east-1
+        argument:
+            long: region
 commands:
-    demo:
+    deploy:
         variables:
-            command-var-2: Command value 2
-            command-var-1: Command value 1
-            command-var-3: Command value 3
-        action: echo \"Hello, World!\"";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
-
-        assert!(!config.variables.is_empty());
+            replicas: '1'
+        action: ./deploy.sh
+environments:
+    production:
+        variables:
+            region: eu-west-1
+            replicas: '3'
+            approver: ops
+        options:
+            exec_timeout: 5m
+            print_commands: true"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
 
-        let root_variable_names: Vec<String> =
-            config.variables.iter().map(|kv| kv.0.to_string()).collect();
-        assert_eq!(root_variable_names[0], "root-var-3".to_string());
-        assert_eq!(root_variable_names[1], "root-var-2".to_string());
-        assert_eq!(root_variable_names[2], "root-var-1".to_string());
+        config.apply_environment("production").unwrap();
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let command_variable_names: Vec<String> = demo_command
-            .variables
-            .iter()
-            .map(|kv| kv.0.to_string())
-            .collect();
-        assert_eq!(command_variable_names[0], "command-var-2".to_string());
-        assert_eq!(command_variable_names[1], "command-var-1".to_string());
-        assert_eq!(command_variable_names[2], "command-var-3".to_string());
+        let VariableConfig::Literal(region) = &config.variables["region"] else {
+            panic!("expected a literal variable");
+        };
+        assert_eq!(region.value, "eu-west-1");
+        assert!(region.argument.is_some());
+        assert_eq!(
+            config.commands["deploy"].variables["replicas"],
+            VariableConfig::ShorthandLiteral("3".to_string())
+        );
+        assert!(config.variables.contains_key("approver"));
+        assert!(!config.commands["deploy"].variables.contains_key("approver"));
+        assert_eq!(config.options.exec_timeout.as_deref(), Some("5m"));
+        assert!(config.options.print_commands);
     }
 
-    // TODO: Command order is preserved
-
     #[test]
-    fn single_action_command_parses() {
-        let yaml = "commands:
-    demo:
-        action: ls";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn apply_environment_fails_for_unknown_environment() {
+        let mut config = parse_config(
+            &"commands:
+    deploy:
+        action: ./deploy.sh
+environments:
+    staging: {}"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
 
-        let demo_command = config.commands.get("demo").unwrap();
-        assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "ls".to_string()
-                    )),
-                })),
-            }
-        );
+        let result = config.apply_environment("prod");
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnknownEnvironment { available, .. }) if available == "staging"
+        ));
     }
 
     #[test]
-    fn alias_command_parses() {
-        let yaml = "commands:
-    deps:
-        alias: docker compose -f docker-compose.deps.yml";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn parse_config_rejects_unknown_fields_in_strict_mode() {
+        let yaml = |strict: bool| {
+            format!(
+                "options:
+    strict: {}
+commands:
+    build:
+        decription: Build the project
+        action: cargo build",
+                strict
+            )
+        };
 
-        let demo_command = config.commands.get("deps").unwrap();
-        assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::Alias(AliasActionConfig {
-                    alias: "docker compose -f docker-compose.deps.yml".to_string()
-                })),
-            }
-        );
+        let lenient = parse_config(&yaml(false), Platform::Linux, None);
+        let strict = parse_config(&yaml(true), Platform::Linux, None);
+
+        assert!(lenient.is_ok());
+        assert!(matches!(
+            strict,
+            Err(ConfigError::UnknownFields { fields })
+                if fields == "  <stdin>:5: unknown field \"decription\" in commands.build, did you mean \"description\"?"
+        ));
     }
 
     #[test]
-    fn single_action_command_with_optional_fields_parses() {
-        let yaml = "commands:
-    demo:
-        description: Says hello.
-        action: ls";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn apply_default_only_overrides_variable_for_command() {
+        let mut config = parse_config(
+            &"variables:
+    region:
+        arg:
+            long: region
+        value: us-west-2
+commands:
+    deploy:
+        variables:
+            replicas:
+                prompt:
+                    message: Replicas?
+                arg:
+                    long: replicas
+        action: ./deploy.sh
+    status:
+        action: ./status.sh"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
 
-        let demo_command = config.commands.get("demo").unwrap();
+        config.apply_default("deploy.region", "us-east-1").unwrap();
+        config.apply_default("deploy.replicas", "3").unwrap();
+
+        let deploy = &config.commands["deploy"];
+        let value = |variable: &VariableConfig| match variable {
+            VariableConfig::Literal(literal) => (literal.value.clone(), literal.argument.is_some()),
+            variable => panic!("unexpected variable: {variable:?}"),
+        };
         assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                platform: None,
-                description: Some("Says hello.".to_string()),
-                hidden: false,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "ls".to_string()
-                    )),
-                })),
-            }
+            value(&deploy.variables["region"]),
+            ("us-east-1".to_string(), true)
+        );
+        assert_eq!(
+            value(&deploy.variables["replicas"]),
+            ("3".to_string(), true)
+        );
+        assert_eq!(
+            value(&config.variables["region"]),
+            ("us-west-2".to_string(), true)
         );
+        assert!(config.commands["status"].variables.is_empty());
+        assert!(matches!(
+            config.apply_default("status.replicas", "3"),
+            Err(ConfigError::UnknownDefault { .. })
+        ));
     }
 
     #[test]
-    fn action_with_subcommands_parses() {
-        let yaml = "commands:
-    demo:
-        commands:
-            gday:
-                action: ls
-        action: cat example.txt";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
-
-        let demo_command = config.commands.get("demo").unwrap();
-        let gday_command = demo_command.commands.get("gday").unwrap();
+    fn selected_environment_only_checks_arguments_before_the_command() {
+        let config = parse_config(
+            &"commands:
+    deploy:
+        action: ./deploy.sh"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let get_env = |name: &str| (name == "PLZ_ENV").then(|| "staging".to_string());
 
         assert_eq!(
-            gday_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "ls".to_string()
-                    )),
-                })),
-            }
+            config.selected_environment(&args(&["plz", "--env", "production", "deploy"]), get_env),
+            Some("production".to_string())
         );
-
-        let mut map = CommandConfigMap::new();
-        map.insert("gday".to_string(), gday_command.clone());
-
         assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: map,
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "cat example.txt".to_string()
-                    )),
-                })),
-            }
+            config.selected_environment(&args(&["plz", "--env=production", "deploy"]), get_env),
+            Some("production".to_string())
+        );
+        assert_eq!(
+            config.selected_environment(&args(&["plz", "deploy", "--env", "production"]), get_env),
+            Some("staging".to_string())
+        );
+        assert_eq!(
+            config.selected_environment(&args(&["plz", "deploy"]), |_| None),
+            None
         );
     }
 
     #[test]
-    fn command_with_subcommands_only_parses() {
-        let yaml = "commands:
-    demo:
-        commands:
-            gday:
-                action: ls";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn apply_overrides_fails_for_invalid_boolean() {
+        let mut options = Options::default();
 
-        let demo_command = config.commands.get("demo").unwrap();
-        let gday_command = demo_command.commands.get("gday").unwrap();
+        let result = options.apply_overrides(&[], |name| match name {
+            "PLZ_ACTIVATE_ENV" => Some("maybe".to_string()),
+            _ => None,
+        });
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidOption { field, .. }) if field == "activate_env"
+        ));
+    }
+
+    #[test]
+    fn apply_overrides_parses_variable_precedence() {
+        let mut options = Options::default();
 
+        let result = options.apply_overrides(&[], |name| match name {
+            "PLZ_VARIABLE_PRECEDENCE" => Some("env, cli,defaults".to_string()),
+            _ => None,
+        });
+
+        assert!(result.is_ok());
         assert_eq!(
-            gday_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "ls".to_string()
-                    )),
-                })),
-            }
+            options.variable_precedence,
+            vec![
+                VariableSource::Env,
+                VariableSource::Cli,
+                VariableSource::Defaults
+            ]
         );
 
-        let mut map = CommandConfigMap::new();
-        map.insert("gday".to_string(), gday_command.clone());
+        let result = options.apply_overrides(&[], |name| match name {
+            "PLZ_VARIABLE_PRECEDENCE" => Some("cli,config".to_string()),
+            _ => None,
+        });
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidOption { field, .. }) if field == "variable_precedence"
+        ));
+    }
+
+    #[test]
+    fn environment_variable_name_is_derived_when_auto_env_is_enabled() {
+        let options = Options {
+            auto_env: true,
+            auto_env_prefix: Some("APP_".to_string()),
+            ..Options::default()
+        };
+        let literal = VariableConfig::ShorthandLiteral("value".to_string());
+        let named = VariableConfig::Literal(LiteralVariableConfig {
+            value: "value".to_string(),
+            argument: None,
+            environment_variable_name: Some("CUSTOM".to_string()),
+            no_arg: false,
+            sensitive: false,
+        });
 
         assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: map,
-                action: None,
-            }
+            literal.environment_variable_name("deployTarget", &options),
+            "APP_DEPLOY_TARGET"
+        );
+        assert_eq!(
+            literal.environment_variable_name("deploy-target", &options),
+            "APP_DEPLOY_TARGET"
+        );
+        assert_eq!(
+            literal.environment_variable_name("api_v2Url", &options),
+            "APP_API_V2_URL"
+        );
+        assert_eq!(
+            named.environment_variable_name("custom", &options),
+            "CUSTOM"
+        );
+        assert_eq!(
+            literal.environment_variable_name("deployTarget", &Options::default()),
+            "deployTarget"
         );
     }
 
-    // TODO: Command with no subcommands or action - Fail
+    #[test]
+    fn duplicate_command_names_fail() {
+        let yaml = "commands:
+    build:
+        action: ./build.sh
+    build-alt:
+        name: build
+        action: ./build-alt.sh";
+
+        let result = parse_config(&yaml.to_string(), Platform::Linux, None);
+
+        match result {
+            Err(ConfigError::DuplicateCommandName {
+                name,
+                first,
+                second,
+            }) => {
+                assert_eq!(name, "build");
+                assert_eq!(first, "the command \"build\"");
+                assert_eq!(second, "the command \"build-alt\"");
+            }
+            _ => panic!("expected duplicate command name error"),
+        }
+    }
 
     #[test]
-    fn command_with_multiple_actions_parses() {
+    fn duplicate_subcommand_names_fail() {
         let yaml = "commands:
-    demo:
-        actions:
-            - cat example.txt
-            - ls";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    deploy:
+        commands:
+            staging:
+                action: ./deploy.sh staging
+            stg:
+                name: staging
+                action: ./deploy.sh staging";
 
-        let demo_command = config.commands.get("demo").unwrap();
-        assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::MultiStep(MultiActionConfig {
-                    actions: vec![
-                        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                            "cat example.txt".to_string()
-                        )),
-                        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                            "ls".to_string()
-                        )),
-                    ],
-                })),
-            }
-        );
+        let result = parse_config(&yaml.to_string(), Platform::Linux, None);
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::DuplicateCommandName { second, .. }) if second == "the command \"deploy stg\""
+        ));
     }
 
     #[test]
-    fn commands_with_specific_platforms_parse() {
+    fn duplicate_command_names_on_other_platforms_are_allowed() {
         let yaml = "commands:
-    demo_nix:
+    build-win:
+        name: build
+        platform: Windows
+        action: ./build.ps1
+    build-nix:
+        name: build
         platforms:
-            - Linux
             - MacOS
-        action: cat example.txt
-    demo_win:
-        platform: Windows
-        action: Get-Content example.txt";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+            - Linux
+        action: ./build.sh";
 
-        let demo_command_nix = config.commands.get("demo_nix").unwrap();
-        let demo_command_win = config.commands.get("demo_win").unwrap();
-        assert_eq!(
-            demo_command_nix,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: Some(Many(ManyPlatforms {
-                    platforms: vec![Platform::Linux, Platform::MacOS]
-                })),
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "cat example.txt".to_string()
-                    ))
-                })),
-            }
-        );
+        let result = parse_config(&yaml.to_string(), Platform::Linux, None);
+
+        assert!(result.is_ok());
+    }
 
+    #[test]
+    fn variable_sets_are_added_to_commands() {
+        // Arrange
+        let yaml = "variable_sets:
+    aws:
+        AWS_REGION: eu-west-1
+        AWS_PROFILE: dev
+    docker:
+        REGISTRY: ghcr.io
+commands:
+    deploy:
+        use_variables: [aws, docker]
+        variables:
+            AWS_PROFILE: prod
+        commands:
+            api:
+                use_vars: [docker]
+                action: ./deploy.sh api
+    push:
+        use_variables: [kubernetes]
+        action: docker push";
+
+        // Act
+        let result = parse_config(&yaml.replace("[kubernetes]", "[]"), Platform::Linux, None);
+        let unknown = parse_config(&yaml.to_string(), Platform::Linux, None);
+
+        // Assert
+        let config = result.unwrap();
+        let deploy = &config.commands["deploy"];
         assert_eq!(
-            demo_command_win,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: Some(One(OnePlatform {
-                    platform: Platform::Windows
-                })),
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "Get-Content example.txt".to_string()
-                    ))
-                })),
-            }
+            deploy.variables.keys().collect::<Vec<_>>(),
+            vec!["AWS_REGION", "REGISTRY", "AWS_PROFILE"]
         );
+        assert!(matches!(
+            &deploy.variables["AWS_PROFILE"],
+            VariableConfig::ShorthandLiteral(value) if value == "prod"
+        ));
+        assert!(deploy.commands["api"].variables.contains_key("REGISTRY"));
+        assert!(matches!(
+            unknown,
+            Err(ConfigError::UnknownVariableSet { command, name })
+                if command == "push" && name == "kubernetes"
+        ));
     }
 
     #[test]
-    fn commands_with_name_parse() {
-        let yaml = "commands:
-    demo:
-        name: demonstration
-        action: cat example.txt";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn import_with_same_name_as_command_fails() {
+        let yaml = "imports:
+    - alias: build
+      source: ./build.yaml
+commands:
+    build:
+        action: ./build.sh";
 
-        let demo_command = config.commands.get("demo").unwrap();
-        assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: Some("demonstration".to_string()),
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::SingleStep(SingleActionConfig {
-                    action: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
-                        "cat example.txt".to_string()
-                    ))
-                })),
-            }
+        let result = parse_config(&yaml.to_string(), Platform::Linux, None);
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::DuplicateCommandName { name, .. }) if name == "build"
+        ));
+    }
+
+    fn create_temp_file(content: &str) -> NamedTempFile {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(content.as_bytes()).unwrap();
+        return temp_file;
+    }
+
+    #[test]
+    fn config_file_names_can_be_overridden() {
+        assert_eq!(config_file_names(None), CONFIG_FILE_NAMES.to_vec());
+        assert_eq!(
+            config_file_names(Some("".to_string())),
+            CONFIG_FILE_NAMES.to_vec()
+        );
+        assert_eq!(
+            config_file_names(Some("tasks.yaml, .plz.yml".to_string())),
+            vec!["tasks.yaml", ".plz.yml"]
         );
     }
 
     #[test]
-    fn shell_action_parses() {
-        let yaml = "commands:
-    demo:
-        actions:
-            - bash: echo \"Hello, World!\"
-            - bash: pwd
-              workdir: /";
-        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+    fn config_path_args_only_checks_arguments_before_the_command() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
 
-        let demo_command = config.commands.get("demo").unwrap();
         assert_eq!(
-            demo_command,
-            &CommandConfig {
-                name: None,
-                description: None,
-                hidden: false,
-                platform: None,
-                variables: Default::default(),
-                commands: Default::default(),
-                action: Some(ActionConfig::MultiStep(MultiActionConfig {
-                    actions: vec![
-                        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
-                            BashCommandConfig {
-                                working_directory: None,
-                                command: "echo \"Hello, World!\"".to_string(),
-                            }
-                        )),
-                        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
-                            BashCommandConfig {
-                                working_directory: Some("/".to_string()),
-                                command: "pwd".to_string(),
-                            }
-                        )),
-                    ]
-                })),
-            }
+            config_path_args(&args(&["plz", "--config", "tools/plz.yaml", "build"])),
+            vec!["tools/plz.yaml"]
+        );
+        assert_eq!(
+            config_path_args(&args(&[
+                "plz",
+                "--timings",
+                "--config=base.yaml",
+                "-f",
+                "local.yaml",
+                "build"
+            ])),
+            vec!["base.yaml", "local.yaml"]
         );
+        assert!(config_path_args(&args(&["plz", "build", "--config", "release.toml"])).is_empty());
     }
 
     #[test]
-    fn import() {
-        let yaml3 = "variables:
-    age: Forty Two
+    fn merge_override_config_prefers_later_config() {
+        let base_text = "variables:
+    region: us-east-1
+    profile: default
 commands:
-    demo:
-        action: echo \"You are $age years old.\"";
-        let yaml3_file = create_temp_file(yaml3);
-        let yaml3_dir = yaml3_file
-            .path()
-            .parent()
-            .unwrap()
-            .to_str()
-            .unwrap()
+    build:
+        action: cargo build
+    lint:
+        action: cargo clippy
+options:
+    print_commands: true
+    exec_timeout: 1m"
             .to_string();
-
-        let yaml2 = format!(
-            "imports:
-    - alias: level-3
-      source: {}
-      hidden: true
-variables:
-    last_name: Smith
+        let override_text = "variables:
+    profile: local
 commands:
-    demo:
-        action: echo \"Your last name is $last_name!\"",
-            yaml3_file.path().to_str().unwrap()
-        );
-        let yaml2_file = create_temp_file(yaml2.as_str());
-        let yaml2_dir = yaml2_file
-            .path()
-            .parent()
-            .unwrap()
-            .to_str()
-            .unwrap()
+    build:
+        action: cargo build --release
+options:
+    exec_timeout: 5s"
             .to_string();
+        let base = parse_config(&base_text, Platform::Linux, None).unwrap();
+        let override_config = parse_config(&override_text, Platform::Linux, None).unwrap();
 
-        let yaml1 = format!(
-            "imports:
-    - alias: level-2
-      source: {}
-      platform: Linux
-variables:
-    first_name: Alice
-commands:
-    demo:
-        action: echo \"Your first name is $first_name!\"",
-            yaml2_file.path().to_str().unwrap()
-        );
+        let config = merge_override_config(base, override_config, &override_text).unwrap();
 
-        let config = parse_config(&yaml1.to_string(), Platform::Linux, None).unwrap();
+        assert!(serde_yaml::to_string(&config.variables["profile"])
+            .unwrap()
+            .contains("local"));
+        assert!(config.variables.contains_key("region"));
+        assert!(serde_yaml::to_string(&config.commands["build"])
+            .unwrap()
+            .contains("--release"));
+        assert!(config.commands.contains_key("lint"));
+        assert!(config.options.print_commands);
+        assert_eq!(config.options.exec_timeout.as_deref(), Some("5s"));
+    }
 
-        let root_demo_command = config.commands.get("demo").unwrap();
+    #[test]
+    fn find_config_file_respects_precedence() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".plz.yml"), "commands: {}").unwrap();
+        fs::write(dir.path().join("plzfile.yml"), "commands: {}").unwrap();
+
+        let names = config_file_names(None);
         assert_eq!(
-            root_demo_command.action,
-            Some(ActionConfig::SingleStep(SingleActionConfig {
-                action: ExecutionConfigVariant::RawCommand(Shorthand(
-                    "echo \"Your first name is $first_name!\"".to_string()
-                ))
-            }))
+            find_config_file(dir.path(), &names),
+            Some(dir.path().join("plzfile.yml"))
         );
+
+        let names = config_file_names(Some(".plz.yml,plzfile.yml".to_string()));
         assert_eq!(
-            config.variables.get("first_name").unwrap(),
-            &VariableConfig::ShorthandLiteral("Alice".to_string())
+            find_config_file(dir.path(), &names),
+            Some(dir.path().join(".plz.yml"))
         );
 
-        let second_level_command = config.commands.get("level-2").unwrap();
+        let names = config_file_names(Some("missing.yaml".to_string()));
+        assert_eq!(find_config_file(dir.path(), &names), None);
+    }
+
+    #[test]
+    fn split_dependency_separates_directory_from_command() {
+        assert_eq!(split_dependency("build"), (None, "build"));
+        assert_eq!(split_dependency("db:migrate"), (None, "db:migrate"));
         assert_eq!(
-            second_level_command.commands.get("demo").unwrap().action,
-            Some(ActionConfig::SingleStep(SingleActionConfig {
-                action: ExecutionConfigVariant::RawCommand(
-                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
-                        command: "echo \"Your last name is $last_name!\"".to_string(),
-                        working_directory: Some(yaml2_dir),
-                    })
-                )
-            }))
+            split_dependency("../shared-lib:build"),
+            (Some("../shared-lib"), "build")
         );
         assert_eq!(
-            second_level_command.platform,
-            Some(One(OnePlatform { platform: Linux }))
+            split_dependency("../api:db:migrate"),
+            (Some("../api"), "db:migrate")
         );
         assert_eq!(
-            second_level_command.variables.get("last_name").unwrap(),
-            &VariableConfig::ShorthandLiteral("Smith".to_string())
+            split_dependency("C:\\repo\\api:deploy staging"),
+            (Some("C:\\repo\\api"), "deploy staging")
         );
+    }
 
-        let third_level_command = second_level_command.commands.get("level-3").unwrap();
-        assert_eq!(
-            third_level_command.commands.get("demo").unwrap().action,
-            Some(ActionConfig::SingleStep(SingleActionConfig {
-                action: ExecutionConfigVariant::RawCommand(
-                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
-                        command: "echo \"You are $age years old.\"".to_string(),
-                        working_directory: Some(yaml3_dir),
-                    })
-                )
-            }))
-        );
-        assert_eq!(third_level_command.hidden, true);
+    #[test]
+    fn decode_inline_config_accepts_text_and_base64() {
+        let yaml = "commands:\n  hi:\n    action: echo hi\n";
+        let json = r#"{"commands": {"hi": {"action": "echo hi"}}}"#;
+        let encoded = BASE64_STANDARD.encode(yaml);
+
+        assert_eq!(decode_inline_config(yaml).unwrap(), yaml);
+        assert_eq!(decode_inline_config(json).unwrap(), json);
+        assert_eq!(decode_inline_config(&encoded).unwrap(), yaml);
+        assert!(matches!(
+            decode_inline_config("not base64!"),
+            Err(ConfigError::InvalidInlineConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn find_config_file_prefers_yaml_over_json() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("plz.json"), "{}").unwrap();
+        fs::write(dir.path().join(".plz.yml"), "commands: {}").unwrap();
+
+        let names = config_file_names(None);
+        let path = find_config_file(dir.path(), &names).unwrap();
+        assert_eq!(path, dir.path().join(".plz.yml"));
+        assert!(ensure_unambiguous(&path, &names).is_ok());
+
+        fs::write(dir.path().join(".plz.json"), "{}").unwrap();
+        assert!(matches!(
+            ensure_unambiguous(&path, &names),
+            Err(ConfigError::AmbiguousConfigFile { .. })
+        ));
+    }
+
+    #[test]
+    fn find_config_files_includes_parents_up_to_repository_root() {
+        let dir = TempDir::new().unwrap();
+        let repository = dir.path().join("repo");
+        let package = repository.join("packages").join("app");
+        fs::create_dir_all(&package).unwrap();
+        fs::create_dir(repository.join(".git")).unwrap();
+        fs::write(dir.path().join("plz.yaml"), "commands: {}").unwrap();
+        fs::write(repository.join("plz.yaml"), "commands: {}").unwrap();
+        fs::write(package.join("plz.yaml"), "commands: {}").unwrap();
+
+        let names = config_file_names(None);
+        let paths = find_config_files(&package, &names).unwrap();
         assert_eq!(
-            third_level_command.variables.get("age").unwrap(),
-            &VariableConfig::ShorthandLiteral("Forty Two".to_string())
+            paths,
+            vec![package.join("plz.yaml"), repository.join("plz.yaml")]
         );
+
+        fs::remove_dir(repository.join(".git")).unwrap();
+        let paths = find_config_files(&package, &names).unwrap();
+        assert_eq!(paths, vec![package.join("plz.yaml")]);
     }
 
     #[test]
-    fn import_for_other_platform_is_ignored() {
-        let yaml2 = "commands:
-    demo:
-        action: echo \"Your last name is $last_name!\""
-            .to_string();
-        let yaml2_file = create_temp_file(yaml2.as_str());
-
-        let yaml1 = format!(
-            "imports:
-    - alias: other
-      source: {}
-      platform: Windows
+    fn merge_parent_config_prefers_closest_config() {
+        let parent = parse_config(
+            &"description: Workspace
 variables:
-    first_name: Alice
+    region: us-east-1
+    profile: default
 commands:
-    demo:
-        action: echo \"Your first name is $first_name!\"",
-            yaml2_file.path().to_str().unwrap()
-        );
+    build:
+        action: cargo build --workspace
+    lint:
+        action: cargo clippy"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
+        let config = parse_config(
+            &"variables:
+    profile: app
+commands:
+    build:
+        action: cargo build -p app"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
 
-        let config = parse_config(&yaml1.to_string(), Platform::Linux, None).unwrap();
+        let config = merge_parent_config(config, parent);
 
-        let root_demo_command = config.commands.get("demo").unwrap();
-        assert_eq!(
-            root_demo_command.action,
-            Some(ActionConfig::SingleStep(SingleActionConfig {
-                action: ExecutionConfigVariant::RawCommand(Shorthand(
-                    "echo \"Your first name is $first_name!\"".to_string()
-                ))
-            }))
-        );
+        assert_eq!(config.description.as_deref(), Some("Workspace"));
         assert_eq!(
-            config.variables.get("first_name").unwrap(),
-            &VariableConfig::ShorthandLiteral("Alice".to_string())
+            config.variables.keys().collect::<Vec<_>>(),
+            vec!["region", "profile"]
         );
-
-        let second_level_command = config.commands.get("other");
-        assert_eq!(second_level_command, None);
+        assert!(serde_yaml::to_string(&config.variables["profile"])
+            .unwrap()
+            .contains("app"));
+        assert!(serde_yaml::to_string(&config.commands["build"])
+            .unwrap()
+            .contains("cargo build -p app"));
+        assert!(config.commands.contains_key("lint"));
     }
 
-    fn create_temp_file(content: &str) -> NamedTempFile {
-        let mut temp_file = NamedTempFile::new().unwrap();
-        temp_file.write_all(content.as_bytes()).unwrap();
-        return temp_file;
+    #[test]
+    fn read_config_file_converts_json() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("plz.json");
+        fs::write(
+            &path,
+            "{\n\t\"commands\": {\n\t\t\"build\": {\"action\": \"cargo build\"}\n\t}\n}",
+        )
+        .unwrap();
+
+        let text = read_config_file(&path).unwrap();
+        let config = parse_config(&text, Platform::Linux, None).unwrap();
+
+        assert!(config.commands.contains_key("build"));
     }
 
     // --- Import path and working directory resolution tests ---
@@ -1727,6 +6419,42 @@ commands:
         fs::write(path, content).unwrap();
     }
 
+    #[test]
+    fn circular_import_fails_with_chain() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+
+        let parent_path = dir.path().join("a.yaml");
+        write_file(
+            &parent_path,
+            "imports:
+  - alias: b
+    source: ./nested/b.yaml
+commands: {}",
+        );
+        write_file(
+            &dir.path().join("nested/b.yaml"),
+            "imports:
+  - alias: a
+    source: ../a.yaml
+commands: {}",
+        );
+
+        let mut error = parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new())
+            .err()
+            .unwrap();
+        while let ConfigError::ImportFailed { source, .. } = error {
+            error = *source;
+        }
+
+        match error {
+            ConfigError::CircularImport { chain } => {
+                assert_eq!(chain, "a.yaml -> nested/b.yaml -> a.yaml")
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
     #[test]
     fn relative_import_source_resolves_from_config_file_location() {
         let dir = create_temp_dir();
@@ -1747,7 +6475,8 @@ commands:
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         assert!(config.commands.contains_key("child"));
     }
@@ -1773,7 +6502,8 @@ commands: {}",
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         let demo = config.commands["child"].commands["demo"].clone();
         assert_eq!(
@@ -1811,7 +6541,8 @@ commands: {}",
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         let demo = config.commands["child"].commands["demo"].clone();
         assert_eq!(
@@ -1850,7 +6581,8 @@ commands: {}",
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         let demo = config.commands["child"].commands["demo"].clone();
         assert_eq!(
@@ -1866,6 +6598,66 @@ commands: {}",
         );
     }
 
+    #[test]
+    fn imported_command_templated_workdir_resolves_against_config_location() {
+        let dir = create_temp_dir();
+        let expected_workdir = dir
+            .path()
+            .join("services/$service")
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        write_file(
+            &dir.path().join("child.yaml"),
+            "commands:
+  relative:
+    action:
+      command: ./run.sh
+      workdir: ./services/$service
+  templated:
+    action:
+      command: ./run.sh
+      workdir: $root/scripts",
+        );
+
+        let parent_path = dir.path().join("parent.yaml");
+        write_file(
+            &parent_path,
+            "imports:
+  - alias: child
+    source: ./child.yaml
+commands: {}",
+        );
+
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
+
+        let child = &config.commands["child"];
+        assert_eq!(
+            child.commands["relative"].action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                        command: "./run.sh".to_string(),
+                        working_directory: Some(expected_workdir),
+                    })
+                )
+            }))
+        );
+        assert_eq!(
+            child.commands["templated"].action,
+            Some(ActionConfig::SingleStep(SingleActionConfig {
+                action: ExecutionConfigVariant::RawCommand(
+                    RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                        command: "./run.sh".to_string(),
+                        working_directory: Some("$root/scripts".to_string()),
+                    })
+                )
+            }))
+        );
+    }
+
     #[test]
     fn imported_command_absolute_workdir_is_unchanged() {
         let dir = create_temp_dir();
@@ -1895,7 +6687,8 @@ commands: {}",
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         let demo = config.commands["child"].commands["demo"].clone();
         assert_eq!(
@@ -1942,7 +6735,8 @@ commands: {}",
 commands: {}",
         );
 
-        let config = parse_config_from(&parent_path, Platform::Linux).unwrap();
+        let config =
+            parse_config_from(&parent_path, Platform::Linux, &[], &mut Vec::new()).unwrap();
 
         let demo = config.commands["child"].commands["grandchild"].commands["demo"].clone();
         assert_eq!(
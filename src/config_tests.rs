@@ -0,0 +1,202 @@
+use colored::Colorize;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// The name of the file the built-in `test-config` command reads test cases from by default.
+pub const DEFAULT_SPEC_FILE_NAME: &str = "plz.test.yaml";
+
+/// Test cases for a config file, each of which checks the command an invocation resolves to.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestSpec {
+    pub tests: Vec<TestCase>,
+}
+
+/// An invocation of plz, and the command it's expected to resolve to.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    /// An optional name for the test case. Defaults to the arguments.
+    pub name: Option<String>,
+
+    /// The arguments to invoke plz with (e.g. `deploy --region eu-west-1`).
+    pub args: TestArgs,
+
+    /// Answers for any prompts, in the order the prompts are shown.
+    #[serde(default)]
+    pub answers: Vec<String>,
+
+    /// The command the invocation is expected to resolve to. Each action of a multi-step command
+    /// is on its own line.
+    pub expect: String,
+}
+
+/// The arguments of a [`TestCase`], either as a single string or a list.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum TestArgs {
+    /// Arguments separated by whitespace.
+    Line(String),
+
+    /// Arguments that may contain whitespace.
+    List(Vec<String>),
+}
+
+impl TestCase {
+    pub fn args(&self) -> Vec<String> {
+        match &self.args {
+            TestArgs::Line(line) => line.split_whitespace().map(str::to_string).collect(),
+            TestArgs::List(args) => args.clone(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.args().join(" "))
+    }
+}
+
+impl TestSpec {
+    /// Loads the [`TestSpec`] from the provided path.
+    pub fn load(path: &Path) -> Result<TestSpec, ConfigTestError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigTestError::ReadFailed {
+            path: path.to_string_lossy().to_string(),
+            source,
+        })?;
+        serde_yaml::from_str(&text).map_err(ConfigTestError::ParseFailed)
+    }
+}
+
+/// The result of a [`TestCase`].
+pub enum TestOutcome {
+    Passed,
+
+    /// The invocation resolved to a different command than expected.
+    Mismatch {
+        actual: String,
+    },
+
+    /// The invocation couldn't be resolved (e.g. because a command doesn't exist).
+    Error(String),
+}
+
+/// Checks the resolved command of each [`TestCase`] and prints the results, returning an error if
+/// any of them failed.
+/// `resolve` returns the command text the provided arguments and answers resolve to.
+pub fn run(
+    spec: &TestSpec,
+    resolve: impl Fn(&TestCase) -> Result<String, String>,
+) -> Result<(), ConfigTestError> {
+    let mut failed = 0;
+    for case in &spec.tests {
+        let outcome = match resolve(case) {
+            Ok(actual) if actual.trim_end() == case.expect.trim_end() => TestOutcome::Passed,
+            Ok(actual) => TestOutcome::Mismatch { actual },
+            Err(message) => TestOutcome::Error(message),
+        };
+
+        match outcome {
+            TestOutcome::Passed => println!("{} {}", "ok  ".green(), case.name()),
+            TestOutcome::Mismatch { actual } => {
+                failed += 1;
+                println!("{} {}", "FAIL".red().bold(), case.name());
+                print!("{}", diff(case.expect.trim_end(), actual.trim_end()));
+            }
+            TestOutcome::Error(message) => {
+                failed += 1;
+                println!("{} {}", "FAIL".red().bold(), case.name());
+                println!("    {}", message);
+            }
+        }
+    }
+
+    println!();
+    println!("{} passed, {} failed", spec.tests.len() - failed, failed);
+
+    if failed > 0 {
+        return Err(ConfigTestError::Failed {
+            failed,
+            total: spec.tests.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns a line-by-line diff of the expected and actual text, where removed (expected) lines
+/// are prefixed with `-` and added (actual) lines are prefixed with `+`.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // The length of the longest common subsequence of the remaining lines
+    let mut lengths = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = match expected[i] == actual[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            output.push_str(&format!("      {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len()
+            && (j == actual.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            output.push_str(&format!("    {}\n", format!("- {}", expected[i]).red()));
+            i += 1;
+        } else {
+            output.push_str(&format!("    {}\n", format!("+ {}", actual[j]).green()));
+            j += 1;
+        }
+    }
+
+    output
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigTestError {
+    #[error("failed to read {path}")]
+    ReadFailed {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to parse the test cases")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("{failed} of {total} test cases failed")]
+    Failed { failed: usize, total: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        // Arrange
+        colored::control::set_override(false);
+        let expected = "docker compose up -d\ncargo test\ndocker compose down";
+        let actual = "docker compose up -d\ncargo test --release\ndocker compose down";
+
+        // Act
+        let output = diff(expected, actual);
+
+        // Assert
+        assert_eq!(
+            output,
+            "      docker compose up -d\n    - cargo test\n    + cargo test --release\n      docker compose down\n"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::state::{config_key, user_state_dir};
+use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const DEFAULTS_FILE_NAME: &str = "defaults";
+
+/// Default argument values saved by the user with `plz config set`.
+/// These are stored in the user's state directory rather than the config file, so each user can
+/// have their own defaults without changing the config file.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct DefaultsStore {
+    /// The saved defaults for each config file, keyed by the config file's path.
+    /// Each default is keyed by the command path and variable name (e.g. `deploy.region`).
+    #[serde(default)]
+    pub defaults: HashMap<String, LinkedHashMap<String, String>>,
+}
+
+impl DefaultsStore {
+    /// Loads the [`DefaultsStore`] from the provided path.
+    /// If nothing has been saved yet, an empty [`DefaultsStore`] is returned.
+    pub fn load(path: &Path) -> Result<DefaultsStore, DefaultsError> {
+        if !path.exists() {
+            return Ok(DefaultsStore::default());
+        }
+
+        let text = fs::read_to_string(path).map_err(DefaultsError::ReadFailed)?;
+        serde_yaml::from_str(&text).map_err(DefaultsError::ParseFailed)
+    }
+
+    /// Saves the [`DefaultsStore`] to the provided path, creating its directory if necessary.
+    pub fn save(&self, path: &Path) -> Result<(), DefaultsError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(DefaultsError::WriteFailed)?;
+        }
+
+        let text = serde_yaml::to_string(self).map_err(DefaultsError::SerializeFailed)?;
+        fs::write(path, text).map_err(DefaultsError::WriteFailed)
+    }
+
+    /// Returns the defaults saved for the config file at `config_path`, in the order they were
+    /// saved.
+    pub fn get(&self, config_path: &Path) -> Vec<(&String, &String)> {
+        self.defaults
+            .get(&config_key(config_path))
+            .map(|defaults| defaults.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Saves a default for the config file at `config_path`, replacing any existing value.
+    pub fn set(&mut self, config_path: &Path, key: &str, value: &str) {
+        let defaults = self.defaults.entry(config_key(config_path)).or_default();
+
+        // Replaced in place, since inserting an existing key would move it to the end
+        match defaults.get_mut(key) {
+            Some(existing) => *existing = value.to_string(),
+            None => {
+                defaults.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    /// Removes a default for the config file at `config_path`, returning `false` if it wasn't set.
+    pub fn unset(&mut self, config_path: &Path, key: &str) -> bool {
+        let config_key = config_key(config_path);
+        let Some(defaults) = self.defaults.get_mut(&config_key) else {
+            return false;
+        };
+
+        let removed = defaults.remove(key).is_some();
+        if defaults.is_empty() {
+            self.defaults.remove(&config_key);
+        }
+
+        removed
+    }
+}
+
+/// Returns the path of the [`DefaultsStore`] within the user's state directory, or `None` if the
+/// user's home directory can't be determined.
+pub fn store_path(get_env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    Some(user_state_dir(get_env)?.join(DEFAULTS_FILE_NAME))
+}
+
+#[derive(Error, Debug)]
+pub enum DefaultsError {
+    #[error("failed to read saved defaults")]
+    ReadFailed(#[source] io::Error),
+
+    #[error("failed to write saved defaults")]
+    WriteFailed(#[source] io::Error),
+
+    #[error("failed to parse saved defaults")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("failed to serialize saved defaults")]
+    SerializeFailed(#[source] serde_yaml::Error),
+
+    #[error("couldn't determine the home directory to store defaults in")]
+    NoHomeDirectory,
+
+    #[error("defaults can only be saved for config files")]
+    NoConfigFile,
+
+    #[error("no default is saved for \"{key}\"")]
+    NotSet { key: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn defaults_store_is_scoped_to_config_file() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let store_path = dir.path().join("state").join(DEFAULTS_FILE_NAME);
+        let config_path = dir.path().join("plz.yaml");
+        let other_config_path = dir.path().join("other.yaml");
+
+        let mut store = DefaultsStore::load(&store_path).unwrap();
+        store.set(&config_path, "deploy.region", "us-east-1");
+        store.set(&config_path, "deploy.replicas", "3");
+        store.set(&config_path, "deploy.region", "eu-west-1");
+        store.set(&other_config_path, "build.profile", "release");
+
+        // Act
+        store.save(&store_path).unwrap();
+        let mut loaded_store = DefaultsStore::load(&store_path).unwrap();
+        let removed = loaded_store.unset(&other_config_path, "build.profile");
+
+        // Assert
+        assert_eq!(
+            loaded_store.get(&config_path),
+            vec![
+                (&"deploy.region".to_string(), &"eu-west-1".to_string()),
+                (&"deploy.replicas".to_string(), &"3".to_string())
+            ]
+        );
+        assert!(removed);
+        assert!(loaded_store.get(&other_config_path).is_empty());
+        assert!(!loaded_store.unset(&other_config_path, "build.profile"));
+    }
+}
@@ -0,0 +1,174 @@
+use colored::Colorize;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// A line of the diff between two texts.
+#[derive(Debug, PartialEq)]
+pub enum DiffLine<'a> {
+    Unchanged(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl DiffLine<'_> {
+    fn is_change(&self) -> bool {
+        !matches!(self, DiffLine::Unchanged(_))
+    }
+}
+
+/// Returns the lines of the diff between the provided texts, keeping the longest common
+/// subsequence of lines unchanged. Removed lines are placed before the lines which replace them.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = match old[i] == new[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(DiffLine::Unchanged(old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            lines.push(DiffLine::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j]));
+            j += 1;
+        }
+    }
+
+    lines.extend(old[i..].iter().map(|line| DiffLine::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| DiffLine::Added(line)));
+    lines
+}
+
+/// Formats the changes between the old and new contents of the file at `path` as a colored
+/// unified diff, or returns an empty string if they're the same.
+pub fn format(old: &str, new: &str, path: &str) -> String {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].is_change())
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group the changes into hunks, merging hunks whose context would overlap
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changes {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut text = format!(
+        "{}\n{}\n",
+        format!("--- {path}").bold(),
+        format!("+++ {path}").bold()
+    );
+    for (start, end) in hunks {
+        // Line numbers start at 1, and refer to the line before the hunk when it has no lines
+        let old_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_start = 1 + lines[..start]
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+        let hunk = &lines[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Removed(_)))
+            .count();
+
+        let header = format!(
+            "@@ -{},{} +{},{} @@",
+            old_start - usize::from(old_count == 0),
+            old_count,
+            new_start - usize::from(new_count == 0),
+            new_count
+        );
+        text.push_str(&format!("{}\n", header.cyan()));
+
+        for line in hunk {
+            let line = match line {
+                DiffLine::Unchanged(line) => format!(" {line}").normal(),
+                DiffLine::Removed(line) => format!("-{line}").red(),
+                DiffLine::Added(line) => format!("+{line}").green(),
+            };
+            text.push_str(&format!("{line}\n"));
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines_keeps_common_lines() {
+        // Arrange
+        let old = "commands:\n  cmds:\n    build: make\nversion: 1\n";
+        let new = "version: 2\ncommands:\n  commands:\n    build: make\n";
+
+        // Act
+        let lines = diff_lines(old, new);
+
+        // Assert
+        assert_eq!(
+            lines,
+            vec![
+                DiffLine::Added("version: 2"),
+                DiffLine::Unchanged("commands:"),
+                DiffLine::Removed("  cmds:"),
+                DiffLine::Added("  commands:"),
+                DiffLine::Unchanged("    build: make"),
+                DiffLine::Removed("version: 1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_shows_changes_with_context() {
+        // Arrange
+        let old = (1..=20).map(|n| format!("line {n}\n")).collect::<String>();
+        let new = old.replace("line 2\n", "line two\n") + "line 21\n";
+
+        // Act
+        let unchanged = format(&old, &old, "plz.yaml");
+        let diff = format(&old, &new, "plz.yaml");
+
+        // Assert
+        assert_eq!(unchanged, "");
+        assert!(diff.contains("--- plz.yaml"));
+        assert!(diff.contains("@@ -1,5 +1,5 @@"));
+        assert!(diff.contains("-line 2"));
+        assert!(diff.contains("+line two"));
+        assert!(diff.contains("@@ -18,3 +18,4 @@"));
+        assert!(diff.contains("+line 21"));
+        assert!(!diff.contains("line 10"));
+    }
+}
@@ -0,0 +1,324 @@
+use crate::config::{Options, SaveEnvConfig, VariableConfigMap};
+use crate::variables::{substitute_variables, VariableMap};
+use std::fs;
+use std::io;
+use thiserror::Error;
+
+/// Writes the variables listed in the provided [`SaveEnvConfig`] to a dotenv file.
+/// Variables are written using their environment variable names, in the order they were listed.
+pub fn save(
+    save_env_config: &SaveEnvConfig,
+    variable_configs: &VariableConfigMap,
+    variables: &VariableMap,
+    options: &Options,
+) -> Result<(), DotenvError> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for key in &save_env_config.variables {
+        let name = match variable_configs.get(key) {
+            Some(variable_config) => variable_config.environment_variable_name(key, options),
+            None => key.clone(),
+        };
+
+        let value = variables
+            .get(&name)
+            .ok_or(DotenvError::UnknownVariable { key: key.clone() })?;
+
+        lines.push(format!("{}={}", name, quote(value)));
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+
+    let path = substitute_variables(&save_env_config.path, variables);
+    fs::write(&path, content).map_err(|err| DotenvError::WriteFailed { path, source: err })
+}
+
+/// Formats all of the provided variables as the contents of a dotenv file, sorted by name.
+pub fn to_string(variables: &VariableMap) -> String {
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .map(|name| format!("{}={}\n", name, quote(&variables[name])))
+        .collect()
+}
+
+/// Loads the variables from the provided dotenv files, in order, so variables in later files take
+/// precedence. Files that don't exist are skipped.
+pub fn load(paths: &[String]) -> Result<VariableMap, DotenvError> {
+    let mut variables = VariableMap::new();
+
+    for path in paths {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(DotenvError::ReadFailed {
+                    path: path.clone(),
+                    source: err,
+                })
+            }
+        };
+
+        let parsed = parse(&text).map_err(|line| DotenvError::ParseFailed {
+            path: path.clone(),
+            line,
+        })?;
+        variables.extend(parsed);
+    }
+
+    Ok(variables)
+}
+
+/// Parses the contents of a dotenv file, returning the number of the first invalid line if it
+/// can't be parsed.
+/// Lines may start with `export`, and blank lines and comments (`#`) are ignored.
+/// Double-quoted values support escapes and may span multiple lines, single-quoted values are
+/// used as-is, and unquoted values end at a comment.
+fn parse(text: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut variables = Vec::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (name, value) = line.split_once('=').ok_or(index + 1)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(index + 1);
+        }
+
+        let value = value.trim_start();
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            // Keep reading lines until the closing quote
+            let mut quoted = quoted.to_string();
+            loop {
+                if let Some(value) = unescape(&quoted) {
+                    break value;
+                }
+                let (_, next) = lines.next().ok_or(index + 1)?;
+                quoted.push('\n');
+                quoted.push_str(next);
+            }
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            let end = quoted.find('\'').ok_or(index + 1)?;
+            quoted[..end].to_string()
+        } else {
+            let end = value.find(" #").unwrap_or(value.len());
+            value[..end].trim_end().to_string()
+        };
+
+        variables.push((name.to_string(), value));
+    }
+
+    Ok(variables)
+}
+
+/// Unescapes the contents of a double-quoted value, up to the closing quote.
+/// Returns `None` if the value doesn't have a closing quote.
+fn unescape(quoted: &str) -> Option<String> {
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                ch => value.push(ch),
+            },
+            ch => value.push(ch),
+        }
+    }
+
+    None
+}
+
+/// Quotes a value so it can be safely written to a dotenv file.
+/// Values that only contain "safe" characters are written as-is.
+fn quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || "_-./:@%+,".contains(ch));
+    if is_safe {
+        return value.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for ch in value.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[derive(Error, Debug)]
+pub enum DotenvError {
+    #[error("cannot save variable \"{key}\" as it has not been resolved")]
+    UnknownVariable { key: String },
+
+    #[error("failed to write {path}")]
+    WriteFailed { path: String, source: io::Error },
+
+    #[error("failed to read {path}")]
+    ReadFailed { path: String, source: io::Error },
+
+    #[error("failed to parse {path}: line {line} is invalid")]
+    ParseFailed { path: String, line: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LiteralVariableConfig, VariableConfig};
+    use tempfile::TempDir;
+
+    #[test]
+    fn save_writes_listed_variables() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".plz.env");
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "version".to_string(),
+            VariableConfig::ShorthandLiteral("1.2.3".to_string()),
+        );
+        variable_configs.insert(
+            "build_id".to_string(),
+            VariableConfig::Literal(LiteralVariableConfig {
+                argument: None,
+                environment_variable_name: Some("BUILD_ID".to_string()),
+                no_arg: false,
+                sensitive: false,
+                value: "Build $42".to_string(),
+            }),
+        );
+        variable_configs.insert(
+            "secret".to_string(),
+            VariableConfig::ShorthandLiteral("hunter2".to_string()),
+        );
+
+        let mut variables = VariableMap::new();
+        variables.insert("version".to_string(), "1.2.3".to_string());
+        variables.insert("BUILD_ID".to_string(), "Build $42".to_string());
+        variables.insert("secret".to_string(), "hunter2".to_string());
+
+        let save_env_config = SaveEnvConfig {
+            path: path.to_str().unwrap().to_string(),
+            variables: vec!["version".to_string(), "build_id".to_string()],
+        };
+
+        // Act
+        let result = save(
+            &save_env_config,
+            &variable_configs,
+            &variables,
+            &Options::default(),
+        );
+
+        // Assert
+        assert!(result.is_ok());
+
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(content, "version=1.2.3\nBUILD_ID=\"Build \\$42\"\n");
+    }
+
+    #[test]
+    fn save_fails_for_unknown_variable() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".plz.env");
+
+        let save_env_config = SaveEnvConfig {
+            path: path.to_str().unwrap().to_string(),
+            variables: vec!["version".to_string()],
+        };
+
+        // Act
+        let result = save(
+            &save_env_config,
+            &VariableConfigMap::new(),
+            &VariableMap::new(),
+            &Options::default(),
+        );
+
+        // Assert
+        assert!(matches!(result, Err(DotenvError::UnknownVariable { .. })));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn load_parses_dotenv_files() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let env_path = dir.path().join(".env");
+        let local_path = dir.path().join(".env.local");
+        fs::write(
+            &env_path,
+            r#"
+# Database settings
+export DB_HOST=localhost # the local database
+DB_NAME = "plz \"dev\""
+DB_PASSWORD='pa$$word # not a comment'
+CERT="line one
+line two\tend"
+EMPTY=
+"#,
+        )
+        .unwrap();
+        fs::write(&local_path, "DB_HOST=db.internal\n").unwrap();
+
+        let paths = [
+            env_path.to_str().unwrap().to_string(),
+            local_path.to_str().unwrap().to_string(),
+            dir.path()
+                .join(".env.missing")
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ];
+
+        // Act
+        let variables = load(&paths).unwrap();
+        let invalid = parse("VALID=1\nnot a variable\n");
+
+        // Assert
+        assert_eq!(variables["DB_HOST"], "db.internal");
+        assert_eq!(variables["DB_NAME"], "plz \"dev\"");
+        assert_eq!(variables["DB_PASSWORD"], "pa$$word # not a comment");
+        assert_eq!(variables["CERT"], "line one\nline two\tend");
+        assert_eq!(variables["EMPTY"], "");
+        assert_eq!(invalid, Err(2));
+    }
+
+    #[test]
+    fn to_string_formats_all_variables_sorted() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("VERSION".to_string(), "1.2.3".to_string());
+        variables.insert("BUILD_ID".to_string(), "Build $42".to_string());
+
+        // Act
+        let content = to_string(&variables);
+
+        // Assert
+        assert_eq!(content, "BUILD_ID=\"Build \\$42\"\nVERSION=1.2.3\n");
+    }
+}
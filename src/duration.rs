@@ -0,0 +1,103 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// Parses a human-readable duration such as `30s`, `5m`, `2h`, `1d`, or `1h30m`.
+/// A number without a unit is treated as seconds.
+pub fn parse(text: &str) -> Result<Duration, DurationError> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(DurationError::Invalid(text.to_string()));
+    }
+
+    let mut total_seconds: u64 = 0;
+    let mut number = String::new();
+
+    for ch in trimmed.chars() {
+        if ch.is_ascii_digit() {
+            number.push(ch);
+            continue;
+        }
+
+        if ch.is_whitespace() {
+            continue;
+        }
+
+        let multiplier = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            _ => return Err(DurationError::Invalid(text.to_string())),
+        };
+
+        let value: u64 = number
+            .parse()
+            .map_err(|_| DurationError::Invalid(text.to_string()))?;
+        total_seconds += value * multiplier;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        let value: u64 = number
+            .parse()
+            .map_err(|_| DurationError::Invalid(text.to_string()))?;
+        total_seconds += value;
+    }
+
+    Ok(Duration::from_secs(total_seconds))
+}
+
+/// Formats a [`Duration`] in a human-readable form such as `1h 5m 30s`.
+pub fn format(duration: &Duration) -> String {
+    let total_seconds = duration.as_secs();
+    let days = total_seconds / (60 * 60 * 24);
+    let hours = (total_seconds / (60 * 60)) % 24;
+    let minutes = (total_seconds / 60) % 60;
+    let seconds = total_seconds % 60;
+
+    let parts: Vec<String> = [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect();
+
+    if parts.is_empty() {
+        return "0s".to_string();
+    }
+
+    parts.join(" ")
+}
+
+#[derive(Error, Debug)]
+pub enum DurationError {
+    #[error("invalid duration \"{0}\", expected a duration such as 30s, 5m, 2h, or 1d")]
+    Invalid(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_parses_durations() {
+        assert_eq!(parse("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse("1d 2h").unwrap(), Duration::from_secs(93600));
+    }
+
+    #[test]
+    fn parse_fails_for_invalid_durations() {
+        assert!(parse("").is_err());
+        assert!(parse("m").is_err());
+        assert!(parse("5 minutes").is_err());
+    }
+
+    #[test]
+    fn format_formats_durations() {
+        assert_eq!(format(&Duration::from_secs(0)), "0s");
+        assert_eq!(format(&Duration::from_secs(252)), "4m 12s");
+        assert_eq!(format(&Duration::from_secs(93600)), "1d 2h");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::config::{
+    ExecutionConfigVariant, PythonEnvConfig, RawCommandConfig, RawCommandConfigVariant,
+};
+use crate::exec::{CommandExecutor, ExecutionError, ExitStatus};
+use crate::variables::VariableMap;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::string::FromUtf8Error;
+use thiserror::Error;
+
+/// Files that indicate the directory is managed by mise (or asdf, which mise is compatible with).
+const MISE_FILE_NAMES: [&str; 4] = [".mise.toml", "mise.toml", ".tool-versions", ".rtx.toml"];
+
+/// Files that indicate the directory is managed by direnv.
+const DIRENV_FILE_NAMES: [&str; 1] = [".envrc"];
+
+/// Detects environment managers (mise, asdf, direnv) in a directory and determines the
+/// environment variables they would apply.
+pub struct EnvironmentActivator {
+    pub command_executor: Box<dyn CommandExecutor>,
+}
+
+impl EnvironmentActivator {
+    /// Returns the environment variables exported by any environment managers configured in
+    /// `directory`. If no environment managers are detected, an empty [`VariableMap`] is returned.
+    pub fn activate(&self, directory: &Path) -> Result<VariableMap, ActivationError> {
+        let mut environment = VariableMap::new();
+
+        if has_any_file(directory, &MISE_FILE_NAMES) {
+            let mise_environment = self.export("mise", "mise env --json", directory)?;
+            environment.extend(mise_environment);
+        }
+
+        // direnv is applied last so that it can override anything set by the toolchain.
+        if has_any_file(directory, &DIRENV_FILE_NAMES) {
+            let direnv_environment = self.export("direnv", "direnv export json", directory)?;
+            environment.extend(direnv_environment);
+        }
+
+        Ok(environment)
+    }
+
+    /// Returns the environment variables required to activate the provided [`PythonEnvConfig`].
+    /// The environment's executables are prepended to the `PATH` found in `environment`, falling
+    /// back to the `PATH` of the current process.
+    pub fn activate_python_env(
+        &self,
+        python_env: &PythonEnvConfig,
+        directory: &Path,
+        environment: &VariableMap,
+    ) -> Result<VariableMap, ActivationError> {
+        let mut python_environment = VariableMap::new();
+
+        let prefix = match python_env {
+            PythonEnvConfig::Venv(venv_path) => {
+                let prefix = directory.join(venv_path);
+                python_environment.insert(
+                    "VIRTUAL_ENV".to_string(),
+                    prefix.to_string_lossy().to_string(),
+                );
+                prefix
+            }
+            PythonEnvConfig::Conda(conda_config) => {
+                let prefix = self.find_conda_env(&conda_config.conda, directory)?;
+                python_environment.insert(
+                    "CONDA_PREFIX".to_string(),
+                    prefix.to_string_lossy().to_string(),
+                );
+                python_environment
+                    .insert("CONDA_DEFAULT_ENV".to_string(), conda_config.conda.clone());
+                prefix
+            }
+        };
+
+        let current_path = environment
+            .get("PATH")
+            .cloned()
+            .or_else(|| env::var("PATH").ok())
+            .unwrap_or_default();
+        let path = prepend_path(&python_bin_dirs(&prefix), &current_path)?;
+        python_environment.insert("PATH".to_string(), path);
+
+        Ok(python_environment)
+    }
+
+    fn find_conda_env(&self, name: &str, directory: &Path) -> Result<PathBuf, ActivationError> {
+        let output = self.export_raw("conda", "conda env list --json", directory)?;
+        let env_list: CondaEnvList =
+            serde_yaml::from_str(&output).map_err(|err| ActivationError::Parse {
+                tool: "conda".to_string(),
+                source: err,
+            })?;
+
+        env_list
+            .envs
+            .iter()
+            .map(PathBuf::from)
+            .find(|prefix| {
+                prefix
+                    .file_name()
+                    .is_some_and(|file_name| file_name == name)
+            })
+            .ok_or(ActivationError::CondaEnvNotFound {
+                name: name.to_string(),
+            })
+    }
+
+    fn export(
+        &self,
+        tool: &str,
+        command: &str,
+        directory: &Path,
+    ) -> Result<VariableMap, ActivationError> {
+        let stdout = self.export_raw(tool, command, directory)?;
+        parse_environment(&stdout).map_err(|err| ActivationError::Parse {
+            tool: tool.to_string(),
+            source: err,
+        })
+    }
+
+    fn export_raw(
+        &self,
+        tool: &str,
+        command: &str,
+        directory: &Path,
+    ) -> Result<String, ActivationError> {
+        let execution = ExecutionConfigVariant::RawCommand(
+            RawCommandConfigVariant::RawCommandConfig(RawCommandConfig {
+                working_directory: Some(directory.to_string_lossy().to_string()),
+                command: command.to_string(),
+            }),
+        );
+
+        let output = self
+            .command_executor
+            .get_output(&execution, &VariableMap::new())
+            .map_err(|err| ActivationError::Execution {
+                tool: tool.to_string(),
+                source: err,
+            })?;
+
+        if output.status != ExitStatus::Success {
+            return Err(ActivationError::ExitStatus {
+                tool: tool.to_string(),
+                status: output.status,
+            });
+        }
+
+        String::from_utf8(output.stdout).map_err(|err| ActivationError::Utf8 {
+            tool: tool.to_string(),
+            source: err,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct CondaEnvList {
+    envs: Vec<String>,
+}
+
+/// Returns the directories containing the executables for a Python environment.
+fn python_bin_dirs(prefix: &Path) -> Vec<PathBuf> {
+    if cfg!(windows) {
+        vec![prefix.to_path_buf(), prefix.join("Scripts")]
+    } else {
+        vec![prefix.join("bin")]
+    }
+}
+
+/// Prepends the provided directories to a `PATH`-style string.
+fn prepend_path(dirs: &[PathBuf], current_path: &str) -> Result<String, ActivationError> {
+    let mut paths = dirs.to_vec();
+    paths.extend(env::split_paths(current_path));
+
+    let joined = env::join_paths(paths).map_err(|_| ActivationError::InvalidPath)?;
+    Ok(joined.to_string_lossy().to_string())
+}
+
+fn has_any_file(directory: &Path, file_names: &[&str]) -> bool {
+    file_names
+        .iter()
+        .any(|file_name| directory.join(file_name).exists())
+}
+
+/// Parses a JSON object of environment variables.
+/// `null` values (used by direnv to denote variables that should be unset) are ignored.
+fn parse_environment(text: &str) -> Result<VariableMap, serde_yaml::Error> {
+    // direnv prints nothing when there are no changes to apply
+    if text.trim().is_empty() {
+        return Ok(VariableMap::new());
+    }
+
+    // JSON is valid YAML, so there's no need to pull in a separate JSON parser.
+    let values: HashMap<String, Option<String>> = serde_yaml::from_str(text)?;
+    let environment = values
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (key, value)))
+        .collect();
+
+    Ok(environment)
+}
+
+#[derive(Error, Debug)]
+pub enum ActivationError {
+    #[error("failed to execute {tool}")]
+    Execution {
+        tool: String,
+        source: ExecutionError,
+    },
+
+    #[error("failed to activate environment using {tool}: {status}")]
+    ExitStatus { tool: String, status: ExitStatus },
+
+    #[error("failed to read environment from {tool}")]
+    Utf8 { tool: String, source: FromUtf8Error },
+
+    #[error("failed to parse environment from {tool}")]
+    Parse {
+        tool: String,
+        source: serde_yaml::Error,
+    },
+
+    #[error("could not find conda environment \"{name}\"")]
+    CondaEnvNotFound { name: String },
+
+    #[error("failed to construct PATH")]
+    InvalidPath,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CondaEnvConfig;
+    use crate::exec::{MockCommandExecutor, Output};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn activate_returns_empty_environment_when_nothing_detected() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor.expect_get_output().times(0);
+
+        let activator = EnvironmentActivator {
+            command_executor: Box::new(command_executor),
+        };
+
+        // Act
+        let environment = activator.activate(dir.path()).unwrap();
+
+        // Assert
+        assert!(environment.is_empty());
+    }
+
+    #[test]
+    fn activate_applies_mise_then_direnv() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".tool-versions"), "rust 1.78.0").unwrap();
+        fs::write(dir.path().join(".envrc"), "export NAME=Bob").unwrap();
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .times(2)
+            .returning(|execution, _| {
+                let stdout = match execution {
+                    ExecutionConfigVariant::RawCommand(
+                        RawCommandConfigVariant::RawCommandConfig(config),
+                    ) if config.command.starts_with("mise") => {
+                        "{\"PATH\": \"/mise/bin\", \"NAME\": \"Alice\"}"
+                    }
+                    _ => "{\"NAME\": \"Bob\", \"OLD\": null}",
+                };
+
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: stdout.as_bytes().to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let activator = EnvironmentActivator {
+            command_executor: Box::new(command_executor),
+        };
+
+        // Act
+        let environment = activator.activate(dir.path()).unwrap();
+
+        // Assert
+        assert_eq!(environment.get("PATH").unwrap(), "/mise/bin");
+        assert_eq!(environment.get("NAME").unwrap(), "Bob");
+        assert_eq!(environment.get("OLD"), None);
+    }
+
+    #[test]
+    fn activate_fails_on_non_zero_exit_code() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".envrc"), "export NAME=Bob").unwrap();
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor.expect_get_output().returning(|_, _| {
+            Ok(Output {
+                status: ExitStatus::Fail(1),
+                stdout: vec![],
+                stderr: "direnv: error .envrc is blocked".as_bytes().to_vec(),
+            })
+        });
+
+        let activator = EnvironmentActivator {
+            command_executor: Box::new(command_executor),
+        };
+
+        // Act
+        let result = activator.activate(dir.path());
+
+        // Assert
+        assert!(matches!(result, Err(ActivationError::ExitStatus { .. })));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn activate_python_env_activates_venv() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+        let activator = EnvironmentActivator {
+            command_executor: Box::new(command_executor),
+        };
+
+        let mut environment = VariableMap::new();
+        environment.insert("PATH".to_string(), "/usr/bin".to_string());
+
+        // Act
+        let python_environment = activator
+            .activate_python_env(
+                &PythonEnvConfig::Venv(".venv".to_string()),
+                Path::new("/project"),
+                &environment,
+            )
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            python_environment.get("VIRTUAL_ENV").unwrap(),
+            "/project/.venv"
+        );
+        assert_eq!(
+            python_environment.get("PATH").unwrap(),
+            "/project/.venv/bin:/usr/bin"
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn activate_python_env_activates_conda_env() {
+        // Arrange
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .once()
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: "{\"envs\": [\"/opt/conda\", \"/opt/conda/envs/science\"]}"
+                        .as_bytes()
+                        .to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let activator = EnvironmentActivator {
+            command_executor: Box::new(command_executor),
+        };
+
+        let mut environment = VariableMap::new();
+        environment.insert("PATH".to_string(), "/usr/bin".to_string());
+
+        // Act
+        let python_environment = activator
+            .activate_python_env(
+                &PythonEnvConfig::Conda(CondaEnvConfig {
+                    conda: "science".to_string(),
+                }),
+                Path::new("/project"),
+                &environment,
+            )
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            python_environment.get("CONDA_PREFIX").unwrap(),
+            "/opt/conda/envs/science"
+        );
+        assert_eq!(
+            python_environment.get("PATH").unwrap(),
+            "/opt/conda/envs/science/bin:/usr/bin"
+        );
+    }
+}
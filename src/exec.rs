@@ -1,19 +1,65 @@
-use colored::Colorize;
+use colored::{Color, Colorize};
 use mockall::automock;
 use std::fmt::Formatter;
-use std::process::Command;
-use std::{fmt, io};
+use std::fs::File;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fmt, fs, io, thread};
+use tempfile::NamedTempFile;
 use thiserror::Error;
 
+use crate::cli;
 use crate::config::{
-    ExecutionConfigVariant, Options, RawCommandConfigVariant, ShellCommandConfigVariant,
+    ExecutionConfigVariant, ExecutorConfig, Options, RawCommandConfigVariant, RestartPolicy,
+    ServicesOutput, ShellCommandConfigVariant,
 };
+use crate::duration;
+use crate::duration::DurationError;
 use crate::exec::ExitStatus::Unknown;
+use crate::executor::{create_executor, Executor};
+use crate::filters::{FilterError, OutputFilter};
+use crate::interrupt;
+use crate::mask::mask;
+use crate::process_group::ProcessGroup;
+use crate::services;
+use crate::state::seconds_since_epoch;
+use crate::template::Shell;
 use crate::variables;
 use crate::variables::VariableMap;
 
+/// The maximum number of bytes that will be captured from a single output stream.
+const MAX_OUTPUT_SIZE: usize = 16 * 1024 * 1024;
+
+/// How long to wait for a command whose output is being captured before letting the user know.
+const SLOW_OUTPUT_NOTICE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often to check whether plz has been interrupted while waiting for captured output.
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 pub type ExecutionResult = Result<ExitStatus, ExecutionError>;
 pub type ExecutionOutputResult = Result<Output, ExecutionError>;
+pub type SessionResult = Result<Option<(usize, ExitStatus)>, ExecutionError>;
+pub type ServicesResult = Result<Option<(String, ExitStatus)>, ExecutionError>;
+
+/// A long-running process executed alongside other services by
+/// [`CommandExecutor::execute_services`].
+#[derive(PartialEq, Debug, Clone)]
+pub struct Service {
+    pub name: String,
+    pub execution: ExecutionConfigVariant,
+
+    /// The variables to execute the service with, including its own environment variables.
+    pub variables: VariableMap,
+
+    pub restart: RestartPolicy,
+
+    /// Glob patterns matching the files which restart the service when they change.
+    pub watch: Vec<String>,
+}
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum ExitStatus {
@@ -23,7 +69,7 @@ pub enum ExitStatus {
 }
 
 impl ExitStatus {
-    fn from_std_exitstatus(exit_status: &std::process::ExitStatus) -> ExitStatus {
+    pub fn from_std_exitstatus(exit_status: &std::process::ExitStatus) -> ExitStatus {
         if exit_status.success() {
             ExitStatus::Success
         } else if let Some(code) = exit_status.code() {
@@ -32,6 +78,15 @@ impl ExitStatus {
             Unknown
         }
     }
+
+    /// Returns the numeric exit code, or `None` if the exit code is unknown.
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            ExitStatus::Success => Some(0),
+            ExitStatus::Fail(code) => Some(*code),
+            Unknown => None,
+        }
+    }
 }
 
 impl fmt::Display for ExitStatus {
@@ -51,21 +106,39 @@ pub struct Output {
     pub stderr: Vec<u8>,
 }
 
-impl Output {
-    fn from_std_output(output: &std::process::Output) -> Output {
-        Output {
-            status: ExitStatus::from_std_exitstatus(&output.status),
-            stdout: output.stdout.clone(),
-            stderr: output.stderr.clone(),
-        }
-    }
-}
-
 // TODO: Consider refactoring these to take stdio as args so we can test with stdin.
 
 /// Capable of executing an [`ExecutionConfigVariant`].
 #[automock]
 pub trait CommandExecutor {
+    /// Creates a [`CommandExecutor`] which executes commands using the [`Executor`] described by
+    /// the provided [`ExecutorConfig`] (e.g. within a container).
+    fn with_executor(&self, executor_config: &ExecutorConfig) -> Box<dyn CommandExecutor>;
+
+    /// Creates a [`CommandExecutor`] which waits for captured output for at most the provided
+    /// timeout (e.g. `30s`), instead of the `exec_timeout` option.
+    fn with_timeout(&self, timeout: &str) -> Box<dyn CommandExecutor>;
+
+    /// Creates a [`CommandExecutor`] which applies the provided [`OutputFilter`] to stdout when
+    /// executing a command with [`CommandExecutor::execute`] or
+    /// [`CommandExecutor::execute_and_capture`].
+    fn with_output_filter(&self, output_filter: OutputFilter) -> Box<dyn CommandExecutor>;
+
+    /// Creates a [`CommandExecutor`] which writes stdout and stderr to the provided files instead
+    /// of the current process' stdout and stderr when executing a command with
+    /// [`CommandExecutor::execute`], [`CommandExecutor::execute_and_capture`], or
+    /// [`CommandExecutor::execute_session`].
+    /// The files are created (or truncated), along with any missing parent directories.
+    fn with_output_files(
+        &self,
+        stdout_file: Option<PathBuf>,
+        stderr_file: Option<PathBuf>,
+    ) -> Result<Box<dyn CommandExecutor>, ExecutionError>;
+
+    /// Describes the step that's about to be executed, for executors which print what they
+    /// would have executed rather than executing it.
+    fn describe_step(&self, _description: &str) {}
+
     /// Executes the provided [`ExecutionConfigVariant`] with the provided [`VariableMap`]
     /// inheriting stdin, stdout, and stderr from the current process.
     fn execute(
@@ -74,6 +147,17 @@ pub trait CommandExecutor {
         variables: &VariableMap,
     ) -> ExecutionResult;
 
+    /// Executes another plz process with the provided arguments and [`VariableMap`], inheriting
+    /// stdin, stdout, and stderr from the current process.
+    /// The current executable is used, so it's the same plz regardless of the `PATH` or the binary
+    /// name, and it always executes on the current machine. The arguments are passed as is.
+    fn execute_plz(
+        &self,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> ExecutionResult;
+
     /// Executes the provided [`ExecutionConfigVariant`] with the provided [`VariableMap`]
     /// and returns the output from stdout and stderr.
     fn get_output(
@@ -81,27 +165,193 @@ pub trait CommandExecutor {
         execution_config: &ExecutionConfigVariant,
         variables: &VariableMap,
     ) -> ExecutionOutputResult;
+
+    /// Executes the provided [`ExecutionConfigVariant`] with the provided [`VariableMap`]
+    /// inheriting stdin and stderr from the current process.
+    /// Output written to stdout is forwarded to the current process' stdout, and returned once
+    /// the command has exited.
+    fn execute_and_capture(
+        &self,
+        execution_config: &ExecutionConfigVariant,
+        variables: &VariableMap,
+    ) -> ExecutionOutputResult;
+
+    /// Executes the provided [`ExecutionConfigVariant`]s in sequence within a single bash session
+    /// with the provided [`VariableMap`], inheriting stdin, stdout, and stderr from the current
+    /// process.
+    /// Execution stops at the first failure, returning the index and [`ExitStatus`] of the
+    /// failed execution.
+    fn execute_session(
+        &self,
+        execution_configs: &[ExecutionConfigVariant],
+        variables: &VariableMap,
+    ) -> SessionResult;
+
+    /// Executes the provided [`Service`]s at the same time, prefixing each line they write to
+    /// stdout or stderr with the name of the service, and restarting them according to their
+    /// [`RestartPolicy`] or when their watched files change.
+    /// The output of each service is printed as it's written, or all at once when the service
+    /// exits, depending on the provided [`ServicesOutput`].
+    /// Every service is stopped once one of them exits without being restarted, returning its
+    /// name and [`ExitStatus`], or once plz is interrupted, returning `None`.
+    fn execute_services(&self, services: &[Service], output: ServicesOutput) -> ServicesResult;
 }
 
 pub fn create_command_executor(options: &Options) -> Box<dyn CommandExecutor> {
+    if options.inspect {
+        return Box::new(InspectCommandExecutor {
+            quiet: false,
+            binary_name: cli::binary_name(options.binary_name.as_deref(), None),
+        });
+    }
+
     Box::new(CommandExecutorImpl {
         options: options.clone(),
+        executor_config: None,
+        executor: create_executor(None),
+        output_filter: None,
+        output_files: OutputFiles::default(),
     })
 }
 
+/// Creates a [`CommandExecutor`] which doesn't execute or print anything.
+/// Captured output is replaced with the command that would have produced it, as with the `inspect`
+/// option.
+pub fn create_dry_run_command_executor() -> Box<dyn CommandExecutor> {
+    Box::new(InspectCommandExecutor {
+        quiet: true,
+        binary_name: cli::DEFAULT_BINARY_NAME.to_string(),
+    })
+}
+
+/// The files a command's stdout and stderr are written to, instead of the current process' stdout
+/// and stderr.
+#[derive(Clone, Default)]
+struct OutputFiles {
+    stdout: Option<Arc<File>>,
+    stderr: Option<Arc<File>>,
+}
+
+impl OutputFiles {
+    /// Creates (or truncates) the files at the provided paths, along with any missing parent
+    /// directories. If both paths are the same, stdout and stderr are written to the same file.
+    fn create(stdout: Option<&Path>, stderr: Option<&Path>) -> Result<OutputFiles, ExecutionError> {
+        let stdout = stdout.map(create_output_file).transpose()?;
+        let stderr = match (stderr, &stdout) {
+            (Some(stderr_path), Some((stdout_path, stdout))) if stderr_path == stdout_path => {
+                Some((stdout_path.clone(), stdout.clone()))
+            }
+            (stderr, _) => stderr.map(create_output_file).transpose()?,
+        };
+
+        Ok(OutputFiles {
+            stdout: stdout.map(|(_, file)| file),
+            stderr: stderr.map(|(_, file)| file),
+        })
+    }
+
+    /// Redirects the stdout and stderr of the provided [`Command`] to the files.
+    fn redirect(&self, command: &mut Command) -> Result<(), ExecutionError> {
+        if let Some(stdout) = &self.stdout {
+            command.stdout(stdout.try_clone().map_err(ExecutionError::IO)?);
+        }
+        if let Some(stderr) = &self.stderr {
+            command.stderr(stderr.try_clone().map_err(ExecutionError::IO)?);
+        }
+
+        Ok(())
+    }
+}
+
+fn create_output_file(path: &Path) -> Result<(PathBuf, Arc<File>), ExecutionError> {
+    let open_failed = |err| ExecutionError::OutputFile {
+        path: path.to_path_buf(),
+        source: err,
+    };
+
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(open_failed)?;
+    }
+    let file = File::create(path).map_err(open_failed)?;
+
+    Ok((path.to_path_buf(), Arc::new(file)))
+}
+
 struct CommandExecutorImpl {
     options: Options,
+    executor_config: Option<ExecutorConfig>,
+    executor: Box<dyn Executor>,
+    output_filter: Option<Arc<OutputFilter>>,
+    output_files: OutputFiles,
 }
 
 impl CommandExecutor for CommandExecutorImpl {
+    fn with_executor(&self, executor_config: &ExecutorConfig) -> Box<dyn CommandExecutor> {
+        Box::new(CommandExecutorImpl {
+            options: self.options.clone(),
+            executor_config: Some(executor_config.clone()),
+            executor: create_executor(Some(executor_config)),
+            output_filter: self.output_filter.clone(),
+            output_files: self.output_files.clone(),
+        })
+    }
+
+    fn with_timeout(&self, timeout: &str) -> Box<dyn CommandExecutor> {
+        Box::new(CommandExecutorImpl {
+            options: Options {
+                exec_timeout: Some(timeout.to_string()),
+                ..self.options.clone()
+            },
+            executor_config: self.executor_config.clone(),
+            executor: create_executor(self.executor_config.as_ref()),
+            output_filter: self.output_filter.clone(),
+            output_files: self.output_files.clone(),
+        })
+    }
+
+    fn with_output_filter(&self, output_filter: OutputFilter) -> Box<dyn CommandExecutor> {
+        Box::new(CommandExecutorImpl {
+            options: self.options.clone(),
+            executor_config: self.executor_config.clone(),
+            executor: create_executor(self.executor_config.as_ref()),
+            output_filter: Some(Arc::new(output_filter)),
+            output_files: self.output_files.clone(),
+        })
+    }
+
+    fn with_output_files(
+        &self,
+        stdout_file: Option<PathBuf>,
+        stderr_file: Option<PathBuf>,
+    ) -> Result<Box<dyn CommandExecutor>, ExecutionError> {
+        Ok(Box::new(CommandExecutorImpl {
+            options: self.options.clone(),
+            executor_config: self.executor_config.clone(),
+            executor: create_executor(self.executor_config.as_ref()),
+            output_filter: self.output_filter.clone(),
+            output_files: OutputFiles::create(stdout_file.as_deref(), stderr_file.as_deref())?,
+        }))
+    }
+
     fn execute(
         &self,
         execution_config: &ExecutionConfigVariant,
         variables: &VariableMap,
     ) -> ExecutionResult {
-        let mut command = get_command_for(execution_config, variables);
+        // Filtered output has to pass through plz, so it's handled the same way as captured output
+        if self.output_filter.is_some() {
+            return self
+                .execute_and_capture(execution_config, variables)
+                .map(|output| output.status);
+        }
+
+        let mut command = get_command_for(self.executor.as_ref(), execution_config, variables)?;
 
         self.log(&command);
+        self.output_files.redirect(&mut command)?;
 
         let exit_status = command
             .spawn()
@@ -112,48 +362,662 @@ impl CommandExecutor for CommandExecutorImpl {
         Ok(ExitStatus::from_std_exitstatus(&exit_status))
     }
 
+    fn execute_plz(
+        &self,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> ExecutionResult {
+        let current_exe = std::env::current_exe().map_err(ExecutionError::IO)?;
+        let mut command = create_executor(None).command(
+            &current_exe.to_string_lossy(),
+            args,
+            working_directory,
+            variables,
+        );
+
+        self.log(&command);
+        self.output_files.redirect(&mut command)?;
+
+        let exit_status = command
+            .spawn()
+            .map_err(ExecutionError::IO)?
+            .wait()
+            .map_err(ExecutionError::IO)?;
+
+        Ok(ExitStatus::from_std_exitstatus(&exit_status))
+    }
+
     fn get_output(
         &self,
         execution_config: &ExecutionConfigVariant,
         variables: &VariableMap,
     ) -> ExecutionOutputResult {
-        let mut command = get_command_for(execution_config, variables);
+        let mut command = get_command_for(self.executor.as_ref(), execution_config, variables)?;
 
         self.log(&command);
 
-        let output = command
-            .output()
-            .map_err(|io_err| ExecutionError::IO(io_err))?;
+        let timeout = self
+            .options
+            .exec_timeout
+            .as_ref()
+            .map(|timeout| duration::parse(timeout))
+            .transpose()
+            .map_err(ExecutionError::InvalidTimeout)?;
+
+        // Captured commands are started in their own process group, so a timeout kills every
+        // process they've spawned. The group is given the terminal so they can still prompt for
+        // input on /dev/tty (e.g. for a passphrase).
+        let process_group = ProcessGroup::spawn_in_foreground(
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )
+        .map_err(ExecutionError::IO)?;
+
+        wait_for_output(process_group, &get_command_text(&command), timeout)
+    }
+
+    fn execute_and_capture(
+        &self,
+        execution_config: &ExecutionConfigVariant,
+        variables: &VariableMap,
+    ) -> ExecutionOutputResult {
+        let mut command = get_command_for(self.executor.as_ref(), execution_config, variables)?;
+
+        self.log(&command);
+        self.output_files.redirect(&mut command)?;
+
+        let mut child = command
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(ExecutionError::IO)?;
+
+        // Forward stdout as it's written so long-running commands don't appear to hang.
+        let mut writer: Box<dyn Write> = match &self.output_files.stdout {
+            Some(file) => Box::new(file.try_clone().map_err(ExecutionError::IO)?),
+            None => Box::new(io::stdout()),
+        };
+        let captured = match child.stdout.take() {
+            Some(child_stdout) => {
+                forward_output(child_stdout, self.output_filter.as_deref(), &mut writer)
+            }
+            None => Ok(Vec::new()),
+        };
+
+        let exit_status = child.wait().map_err(ExecutionError::IO)?;
+        let status = ExitStatus::from_std_exitstatus(&exit_status);
+
+        // The unfiltered output has already been forwarded if the filter failed, so the command
+        // failing is the more relevant error
+        let captured = match captured {
+            Err(ExecutionError::OutputFilter(_)) if status != ExitStatus::Success => Vec::new(),
+            captured => captured?,
+        };
+
+        Ok(Output {
+            status,
+            stdout: captured,
+            stderr: vec![],
+        })
+    }
+
+    fn execute_session(
+        &self,
+        execution_configs: &[ExecutionConfigVariant],
+        variables: &VariableMap,
+    ) -> SessionResult {
+        // The index of the current execution is written to this file before it's executed so we
+        // can report which one failed, even if it exits the session.
+        let status_file = NamedTempFile::new().map_err(ExecutionError::IO)?;
+        let status_file_path = Shell::Posix.quote(&status_file.path().to_string_lossy());
+
+        let mut script = String::new();
+        for (idx, execution_config) in execution_configs.iter().enumerate() {
+            let (command_text, working_directory) =
+                get_session_command_text(execution_config, variables)?;
+            let working_directory = substitute_working_directory(&working_directory, variables)?;
+
+            self.log_command_text("bash", &command_text, working_directory.as_deref());
+
+            script.push_str(&format!("echo {idx} > {status_file_path}\n"));
+
+            if let Some(wd) = working_directory {
+                script.push_str(&format!("cd {} || exit $?\n", Shell::Posix.quote(&wd)));
+            }
+
+            script.push_str(&format!(
+                "{command_text}\n__plz_status=$?\nif [ $__plz_status -ne 0 ]; then exit $__plz_status; fi\n"
+            ));
+        }
+
+        let mut command =
+            self.executor
+                .command("bash", &["-c".to_string(), script], None, variables);
+        self.output_files.redirect(&mut command)?;
+
+        let exit_status = command
+            .spawn()
+            .map_err(ExecutionError::IO)?
+            .wait()
+            .map_err(ExecutionError::IO)?;
+
+        let exit_status = ExitStatus::from_std_exitstatus(&exit_status);
+        if exit_status == ExitStatus::Success {
+            return Ok(None);
+        }
+
+        let index = fs::read_to_string(status_file.path())
+            .ok()
+            .and_then(|text| text.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+
+        Ok(Some((index, exit_status)))
+    }
+
+    fn execute_services(&self, services: &[Service], output: ServicesOutput) -> ServicesResult {
+        services::execute(services, output, |service| {
+            let command = get_command_for(
+                self.executor.as_ref(),
+                &service.execution,
+                &service.variables,
+            )?;
+            self.log(&command);
+            Ok(command)
+        })
+    }
+}
+
+/// Forwards the output read from `reader` to `writer` (e.g. stdout) as it's written, returning the
+/// forwarded output (up to [`MAX_OUTPUT_SIZE`] bytes).
+/// If an [`OutputFilter`] is provided, it's applied to each line as it's written, or to the entire
+/// output once it has been read if the filter needs to be buffered. If the filter fails, the
+/// unfiltered output is forwarded instead.
+fn forward_output(
+    mut reader: impl Read,
+    output_filter: Option<&OutputFilter>,
+    writer: &mut dyn Write,
+) -> Result<Vec<u8>, ExecutionError> {
+    let mut captured = Vec::new();
+    let mut forward = |bytes: &[u8]| {
+        writer
+            .write_all(bytes)
+            .and_then(|_| writer.flush())
+            .map_err(ExecutionError::IO)?;
+
+        // Output is still forwarded once the limit has been reached, it's just not captured
+        let capacity = MAX_OUTPUT_SIZE.saturating_sub(captured.len());
+        captured.extend_from_slice(&bytes[..bytes.len().min(capacity)]);
+        Ok::<(), ExecutionError>(())
+    };
+
+    match output_filter {
+        None => {
+            let mut buffer = [0; 4096];
+            loop {
+                let read = reader.read(&mut buffer).map_err(ExecutionError::IO)?;
+                if read == 0 {
+                    break;
+                }
+
+                forward(&buffer[..read])?;
+            }
+        }
+
+        Some(output_filter) if output_filter.is_buffered() => {
+            let mut output = Vec::new();
+            reader
+                .read_to_end(&mut output)
+                .map_err(ExecutionError::IO)?;
+
+            match output_filter.filter(&String::from_utf8_lossy(&output)) {
+                Ok(filtered) => forward(filtered.as_bytes())?,
+                Err(err) => {
+                    forward(&output)?;
+                    return Err(ExecutionError::OutputFilter(err));
+                }
+            }
+        }
+
+        Some(output_filter) => {
+            let mut reader = BufReader::new(reader);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                let read = reader
+                    .read_until(b'\n', &mut line)
+                    .map_err(ExecutionError::IO)?;
+                if read == 0 {
+                    break;
+                }
+
+                // Line endings are kept as they were written
+                let line = String::from_utf8_lossy(&line);
+                let content = line.trim_end_matches(['\r', '\n']);
+                if let Some(filtered) = output_filter.filter_line(content) {
+                    forward(format!("{}{}", filtered, &line[content.len()..]).as_bytes())?;
+                }
+            }
+        }
+    }
+
+    Ok(captured)
+}
+
+/// Reads the stdout and stderr of the child in the provided [`ProcessGroup`] as it's written, until
+/// it exits.
+/// The process group is killed if the child writes more than [`MAX_OUTPUT_SIZE`] bytes to either stream, or if it
+/// doesn't exit within the provided `timeout`.
+fn wait_for_output(
+    mut process_group: ProcessGroup,
+    command_text: &str,
+    timeout: Option<Duration>,
+) -> ExecutionOutputResult {
+    let child = process_group.child();
+    let (sender, receiver) = mpsc::channel();
+    let mut readers = 0;
+    if let Some(stdout) = child.stdout.take() {
+        spawn_output_reader(OutputStream::Stdout, stdout, sender.clone());
+        readers += 1;
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_output_reader(OutputStream::Stderr, stderr, sender.clone());
+        readers += 1;
+    }
+    drop(sender);
+
+    let started = Instant::now();
+    let mut notified = false;
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    while readers > 0 {
+        // Wake up periodically so we can stop if plz is interrupted, and let the user know we're
+        // still waiting
+        let mut wait = INTERRUPT_CHECK_INTERVAL;
+        if let Some(timeout) = timeout {
+            wait = wait.min(timeout.saturating_sub(started.elapsed()));
+        }
+
+        match receiver.recv_timeout(wait) {
+            Ok(OutputEvent::Finished(OutputStream::Stdout, output)) => {
+                stdout = output;
+                readers -= 1;
+            }
+            Ok(OutputEvent::Finished(OutputStream::Stderr, output)) => {
+                stderr = output;
+                readers -= 1;
+            }
+            Ok(OutputEvent::LimitExceeded) => {
+                process_group.kill();
+                return Err(ExecutionError::OutputLimitExceeded {
+                    command: command_text.to_string(),
+                    limit: MAX_OUTPUT_SIZE,
+                });
+            }
+            Ok(OutputEvent::Failed(io_err)) => {
+                process_group.kill();
+                return Err(ExecutionError::IO(io_err));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // Checking whether the child has exited hands the terminal back to plz, and
+                // handles interrupts that were only sent to the child's process group
+                process_group.try_wait().map_err(ExecutionError::IO)?;
+
+                // The process group has already been sent the interrupt, but background processes
+                // may ignore it and keep the output open
+                if interrupt::is_interrupted() {
+                    process_group.kill();
+                    return Err(ExecutionError::Interrupted {
+                        command: command_text.to_string(),
+                    });
+                }
 
-        Ok(Output::from_std_output(&output))
+                if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+                    process_group.kill();
+                    return Err(ExecutionError::Timeout {
+                        command: command_text.to_string(),
+                        timeout: duration::format(&timeout.unwrap_or_default()),
+                    });
+                }
+
+                if !notified
+                    && started.elapsed() >= SLOW_OUTPUT_NOTICE_INTERVAL
+                    && io::stderr().is_terminal()
+                {
+                    eprintln!("Still waiting for {}...", command_text.trim().yellow());
+                    notified = true;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let exit_status = process_group.wait().map_err(ExecutionError::IO)?;
+    if interrupt::is_interrupted() {
+        return Err(ExecutionError::Interrupted {
+            command: command_text.to_string(),
+        });
     }
+
+    Ok(Output {
+        status: ExitStatus::from_std_exitstatus(&exit_status),
+        stdout,
+        stderr,
+    })
+}
+
+#[derive(Clone, Copy)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+enum OutputEvent {
+    Finished(OutputStream, Vec<u8>),
+    LimitExceeded,
+    Failed(io::Error),
+}
+
+/// Reads the provided stream on a separate thread, sending an [`OutputEvent`] once it has been
+/// read to completion, or once it has exceeded [`MAX_OUTPUT_SIZE`].
+fn spawn_output_reader(
+    stream: OutputStream,
+    mut reader: impl Read + Send + 'static,
+    sender: Sender<OutputEvent>,
+) {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buffer = [0; 4096];
+        let event = loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break OutputEvent::Finished(stream, output),
+                Ok(read) if output.len() + read > MAX_OUTPUT_SIZE => {
+                    break OutputEvent::LimitExceeded
+                }
+                Ok(read) => output.extend_from_slice(&buffer[..read]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => break OutputEvent::Failed(err),
+            }
+        };
+
+        // The receiver may have already given up
+        let _ = sender.send(event);
+    });
+}
+
+/// A [`CommandExecutor`] used by the `inspect` option, which prints the commands it would execute
+/// instead of executing them.
+/// Captured output (e.g. for execution variables) is replaced with the command that would have
+/// produced it (e.g. `$(git rev-parse HEAD)`).
+#[derive(Clone)]
+struct InspectCommandExecutor {
+    /// Whether the commands should be skipped without printing them.
+    quiet: bool,
+
+    /// The name plz was invoked as, used when printing the plz processes that would be executed.
+    binary_name: String,
+}
+
+impl CommandExecutor for InspectCommandExecutor {
+    fn with_executor(&self, _: &ExecutorConfig) -> Box<dyn CommandExecutor> {
+        Box::new(self.clone())
+    }
+
+    fn with_timeout(&self, _: &str) -> Box<dyn CommandExecutor> {
+        Box::new(self.clone())
+    }
+
+    fn with_output_filter(&self, _: OutputFilter) -> Box<dyn CommandExecutor> {
+        Box::new(self.clone())
+    }
+
+    // Nothing is written when inspecting, so the files aren't created
+    fn with_output_files(
+        &self,
+        _: Option<PathBuf>,
+        _: Option<PathBuf>,
+    ) -> Result<Box<dyn CommandExecutor>, ExecutionError> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn describe_step(&self, description: &str) {
+        if !self.quiet {
+            println!("{} {}", "step:".yellow().bold(), description);
+        }
+    }
+
+    fn execute(
+        &self,
+        execution_config: &ExecutionConfigVariant,
+        variables: &VariableMap,
+    ) -> ExecutionResult {
+        if !self.quiet {
+            let command_text = inspect_command_text(execution_config, variables);
+            println!("{} {}", "would execute:".yellow().bold(), command_text);
+        }
+        Ok(ExitStatus::Success)
+    }
+
+    fn execute_plz(&self, args: &[String], _: Option<String>, _: &VariableMap) -> ExecutionResult {
+        if !self.quiet {
+            let command_text = mask(&format!("{} {}", self.binary_name, args.join(" ")));
+            println!("{} {}", "would execute:".yellow().bold(), command_text);
+        }
+        Ok(ExitStatus::Success)
+    }
+
+    fn get_output(
+        &self,
+        execution_config: &ExecutionConfigVariant,
+        variables: &VariableMap,
+    ) -> ExecutionOutputResult {
+        let command_text = inspect_command_text(execution_config, variables);
+        if !self.quiet {
+            eprintln!("{} {}", "would capture:".yellow().bold(), command_text);
+        }
+        Ok(Output {
+            status: ExitStatus::Success,
+            stdout: format!("$({})", command_text).into_bytes(),
+            stderr: vec![],
+        })
+    }
+
+    fn execute_and_capture(
+        &self,
+        execution_config: &ExecutionConfigVariant,
+        variables: &VariableMap,
+    ) -> ExecutionOutputResult {
+        self.execute(execution_config, variables)?;
+        Ok(Output {
+            status: ExitStatus::Success,
+            stdout: vec![],
+            stderr: vec![],
+        })
+    }
+
+    fn execute_session(
+        &self,
+        execution_configs: &[ExecutionConfigVariant],
+        variables: &VariableMap,
+    ) -> SessionResult {
+        for execution_config in execution_configs {
+            self.execute(execution_config, variables)?;
+        }
+
+        Ok(None)
+    }
+
+    fn execute_services(&self, services: &[Service], _: ServicesOutput) -> ServicesResult {
+        if !self.quiet {
+            for service in services {
+                let command_text = inspect_command_text(&service.execution, &service.variables);
+                println!(
+                    "{} {} {}",
+                    "would run:".yellow().bold(),
+                    format!("{}:", service.name).bold(),
+                    command_text
+                );
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Returns the command text of the provided [`ExecutionConfigVariant`] with any variables
+/// substituted and sensitive values masked, as printed by the [`InspectCommandExecutor`].
+fn inspect_command_text(
+    execution_config: &ExecutionConfigVariant,
+    variables: &VariableMap,
+) -> String {
+    mask(&variables::substitute_variables(
+        execution_config.command(),
+        variables,
+    ))
 }
 
 impl CommandExecutorImpl {
     fn log(&self, command: &Command) {
+        let program = command.get_program().to_string_lossy();
+        let args = command
+            .get_args()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let working_directory = command
+            .get_current_dir()
+            .map(|working_directory| working_directory.to_string_lossy());
+
+        self.log_command_text(&program, &args, working_directory.as_deref());
+    }
+
+    fn log_command_text(&self, program: &str, args: &str, working_directory: Option<&str>) {
         if self.options.print_commands {
-            let command_text = get_command_text(&command);
-            println!("Executing: {}", command_text.green())
+            println!(
+                "{}",
+                format_command_text(
+                    &self.options.print_commands_format,
+                    &mask(program),
+                    &mask(args),
+                    working_directory.map(mask).as_deref(),
+                    SystemTime::now()
+                )
+            )
         }
     }
 }
 
-fn get_command_for(execution_config: &ExecutionConfigVariant, variables: &VariableMap) -> Command {
-    match execution_config {
-        ExecutionConfigVariant::ShellCommand(shell_command_config) => match shell_command_config {
-            ShellCommandConfigVariant::Bash(bash_command_config) => {
-                let mut binding = Command::new("bash");
-                binding
-                    .arg("-c")
-                    .envs(variables)
-                    .arg(bash_command_config.clone().command);
-
-                if let Some(wd) = bash_command_config.clone().working_directory {
-                    binding.current_dir(wd);
-                }
-
-                binding
+/// Formats a command using the provided template.
+///
+/// The template can contain the following placeholders:
+/// - `{command}`: The full command text, including the program and its arguments.
+/// - `{program}`: The program (or shell) executing the command.
+/// - `{args}`: The arguments passed to the program.
+/// - `{workdir}`: The working directory of the command.
+/// - `{time}`: The current time (UTC) in the `HH:MM:SS` format.
+///
+/// Placeholders can be colored by adding the name of a color (e.g. `{command:green}`).
+/// Unknown placeholders are left as-is.
+fn format_command_text(
+    template: &str,
+    program: &str,
+    args: &str,
+    working_directory: Option<&str>,
+    time: SystemTime,
+) -> String {
+    let mut formatted = String::new();
+    let mut remaining = template;
+
+    while let Some(start) = remaining.find('{') {
+        formatted.push_str(&remaining[..start]);
+        remaining = &remaining[start..];
+
+        let Some(end) = remaining.find('}') else {
+            break;
+        };
+
+        let placeholder = &remaining[1..end];
+        let (name, color) = match placeholder.split_once(':') {
+            Some((name, color)) => (name, Some(color)),
+            None => (placeholder, None),
+        };
+
+        let value = match name {
+            "command" if args.is_empty() => program.to_string(),
+            "command" => format!("{} {}", program, args),
+            "program" => program.to_string(),
+            "args" => args.to_string(),
+            "workdir" => working_directory.unwrap_or(".").to_string(),
+            "time" => {
+                let seconds = seconds_since_epoch(time) % 86400;
+                format!(
+                    "{:02}:{:02}:{:02}",
+                    seconds / 3600,
+                    (seconds / 60) % 60,
+                    seconds % 60
+                )
+            }
+            _ => {
+                formatted.push_str(&remaining[..=end]);
+                remaining = &remaining[end + 1..];
+                continue;
             }
+        };
+
+        match color.and_then(|color| color.parse::<Color>().ok()) {
+            Some(color) => formatted.push_str(&value.color(color).to_string()),
+            None => formatted.push_str(&value),
+        }
+
+        remaining = &remaining[end + 1..];
+    }
+
+    formatted.push_str(remaining);
+    formatted
+}
+
+/// Returns the command text and working directory for an execution within a bash session.
+/// Only templates are rendered here, as bash will handle `$` variable substitution.
+fn get_session_command_text(
+    execution_config: &ExecutionConfigVariant,
+    variables: &VariableMap,
+) -> Result<(String, Option<String>), ExecutionError> {
+    let (command, working_directory) = match execution_config {
+        ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(bash_config)) => {
+            (&bash_config.command, bash_config.working_directory.clone())
+        }
+        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(command)) => {
+            (command, None)
+        }
+        ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::RawCommandConfig(
+            raw_config,
+        )) => (&raw_config.command, raw_config.working_directory.clone()),
+    };
+
+    Ok((
+        render_tags(command, variables, Shell::Posix)?,
+        working_directory,
+    ))
+}
+
+fn get_command_for(
+    executor: &dyn Executor,
+    execution_config: &ExecutionConfigVariant,
+    variables: &VariableMap,
+) -> Result<Command, ExecutionError> {
+    Ok(match execution_config {
+        ExecutionConfigVariant::ShellCommand(shell_command_config) => match shell_command_config {
+            ShellCommandConfigVariant::Bash(bash_command_config) => executor.command(
+                "bash",
+                // Bash substitutes `$` variables itself, so only templates are rendered
+                &[
+                    "-c".to_string(),
+                    render_tags(&bash_command_config.command, variables, Shell::Posix)?,
+                ],
+                substitute_working_directory(&bash_command_config.working_directory, variables)?,
+                variables,
+            ),
         },
 
         ExecutionConfigVariant::RawCommand(raw_command_config) => {
@@ -166,28 +1030,53 @@ fn get_command_for(execution_config: &ExecutionConfigVariant, variables: &Variab
             };
 
             // Substitute any variables in the command invocation
-            let command = variables::substitute_variables(&command_template, variables);
+            let command = variables::try_substitute_variables(&command_template, variables)
+                .map_err(|err| template_error(&command_template, err))?;
 
             const DELIMITER: &str = " ";
-            let mut cmd = match command.split_once(DELIMITER) {
+            let (program, args) = match command.split_once(DELIMITER) {
                 Some((program, args)) => {
-                    let argv = args.split(DELIMITER);
-                    let mut binding = Command::new(program);
-                    binding.args(argv).envs(variables);
-                    binding
+                    (program, args.split(DELIMITER).map(String::from).collect())
                 }
-                None => Command::new(command),
+                None => (command.as_str(), Vec::new()),
             };
 
-            if let Some(wd) = working_directory {
-                cmd.current_dir(wd);
-            }
-
-            return cmd;
+            let working_directory = substitute_working_directory(&working_directory, variables)?;
+            executor.command(program, &args, working_directory, variables)
         }
+    })
+}
+
+/// Renders the templates in the provided command, see [`variables::render_tags`].
+fn render_tags(
+    command: &str,
+    variables: &VariableMap,
+    shell: Shell,
+) -> Result<String, ExecutionError> {
+    variables::render_tags(command, variables, shell).map_err(|err| template_error(command, err))
+}
+
+fn template_error(template: &str, source: tera::Error) -> ExecutionError {
+    ExecutionError::Template {
+        template: template.trim().to_string(),
+        source: Box::new(source),
     }
 }
 
+/// Substitutes any variables in the provided working directory.
+fn substitute_working_directory(
+    working_directory: &Option<String>,
+    variables: &VariableMap,
+) -> Result<Option<String>, ExecutionError> {
+    working_directory
+        .as_ref()
+        .map(|working_directory| {
+            variables::try_substitute_variables(working_directory, variables)
+                .map_err(|err| template_error(working_directory, err))
+        })
+        .transpose()
+}
+
 fn get_command_text(command: &Command) -> String {
     let program_string = command.get_program().to_str().unwrap();
     let args_string = command
@@ -204,12 +1093,41 @@ fn get_command_text(command: &Command) -> String {
 pub enum ExecutionError {
     #[error(transparent)]
     IO(io::Error),
+
+    #[error("invalid timeout")]
+    InvalidTimeout(#[source] DurationError),
+
+    #[error("{command} did not exit within {timeout}")]
+    Timeout { command: String, timeout: String },
+
+    #[error("{command} was interrupted")]
+    Interrupted { command: String },
+
+    #[error("{command} wrote more than {limit} bytes of output")]
+    OutputLimitExceeded { command: String, limit: usize },
+
+    #[error("failed to filter the output")]
+    OutputFilter(#[source] FilterError),
+
+    #[error("failed to open {path} for the command's output")]
+    OutputFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to render the template {template}")]
+    Template {
+        template: String,
+        #[source]
+        source: Box<tera::Error>,
+    },
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{BashCommandConfig, RawCommandConfig};
+    use crate::config::{BashCommandConfig, OutputFilterConfig, RawCommandConfig};
     use std::collections::HashMap;
     use std::fs;
     use std::io::Write;
@@ -242,6 +1160,44 @@ mod tests {
         assert_eq!(file_content, format!("Hello, World!\n"));
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn inspect_command_executor_does_not_execute_commands() {
+        // Arrange
+        let temp_dir = TempDir::new().unwrap();
+        let temp_file_path = get_path(&temp_dir.path().join("executed"));
+
+        let mut variables = HashMap::new();
+        variables.insert("path".to_string(), temp_file_path.clone());
+
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "touch $path".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options {
+            inspect: true,
+            ..Options::default()
+        });
+
+        // Act
+        let status = command_executor
+            .execute(&bash_exec_config, &variables)
+            .unwrap();
+        let output = command_executor
+            .get_output(&bash_exec_config, &variables)
+            .unwrap();
+
+        // Assert
+        assert!(matches!(status, ExitStatus::Success));
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            format!("$(touch {temp_file_path})")
+        );
+        assert!(!Path::new(&temp_file_path).exists());
+    }
+
     #[test]
     #[cfg(not(windows))]
     fn bash_command_execute_evaluates_variables() {
@@ -372,6 +1328,25 @@ mod tests {
         assert_eq!(output_value, "Error message\n");
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_get_output_fails_when_template_is_invalid() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "echo build {% if verbose %}--verbose{% endfi %}".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.get_output(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        assert!(matches!(result, Err(ExecutionError::Template { .. })));
+    }
+
     #[test]
     #[cfg(not(windows))]
     fn bash_command_get_output_returns_exit_code() {
@@ -395,6 +1370,52 @@ mod tests {
         assert!(output.stderr.is_empty());
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_get_output_times_out() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "echo \"Hello\" && sleep 30".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options {
+            exec_timeout: Some("1s".to_string()),
+            ..Options::default()
+        });
+
+        // Act
+        let started = Instant::now();
+        let result = command_executor.get_output(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        assert!(matches!(result, Err(ExecutionError::Timeout { .. })));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_get_output_fails_when_output_exceeds_limit() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "yes".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.get_output(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        assert!(matches!(
+            result,
+            Err(ExecutionError::OutputLimitExceeded { .. })
+        ));
+    }
+
     #[test]
     #[cfg(not(windows))]
     fn bash_command_honours_workdir() {
@@ -420,6 +1441,204 @@ mod tests {
         assert!(output_value.ends_with("/src\n"));
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_substitutes_variables_in_workdir() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: Some("./$dir".to_string()),
+                command: "pwd".to_string(),
+            }),
+        );
+        let mut variables = HashMap::new();
+        variables.insert("dir".to_string(), "src".to_string());
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.get_output(&bash_exec_config, &variables);
+
+        // Assert
+        let output = result.unwrap();
+        assert_eq!(output.status, ExitStatus::Success);
+
+        let output_value = String::from_utf8(output.stdout).unwrap();
+        assert!(output_value.ends_with("/src\n"));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn session_preserves_state_between_executions() {
+        // Arrange
+        let temp_file = create_empty_temp_file();
+        let temp_file_path = get_path(temp_file.path());
+
+        let execution_configs = vec![
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "export NAME=Alice".to_string(),
+            )),
+            ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+                BashCommandConfig {
+                    working_directory: Some("./src".to_string()),
+                    command: "cd ..".to_string(),
+                },
+            )),
+            ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+                BashCommandConfig {
+                    working_directory: None,
+                    command: format!("echo \"$NAME $(basename $PWD)\" > {temp_file_path}"),
+                },
+            )),
+        ];
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.execute_session(&execution_configs, &HashMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap(), None);
+
+        let expected_dir_name = std::env::current_dir()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        let file_content = fs::read_to_string(temp_file_path).unwrap();
+        assert_eq!(file_content, format!("Alice {expected_dir_name}\n"));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn session_stops_at_first_failure() {
+        // Arrange
+        let execution_configs = vec![
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "true".to_string(),
+            )),
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "exit 42".to_string(),
+            )),
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "exit 1".to_string(),
+            )),
+        ];
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.execute_session(&execution_configs, &HashMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap(), Some((1, ExitStatus::Fail(42))));
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_execute_and_capture_returns_stdout() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "echo \"Hello, World!\"; exit 3".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.execute_and_capture(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        let output = result.unwrap();
+        assert_eq!(output.status, ExitStatus::Fail(3));
+
+        let output_value = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(output_value, "Hello, World!\n");
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_execute_and_capture_filters_stdout() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "printf 'DEBUG a\\nversion 1.2.3\\r\\nDEBUG b'".to_string(),
+            }),
+        );
+        let output_filter = OutputFilter::new(&[
+            OutputFilterConfig::Exclude {
+                exclude: "^DEBUG".to_string(),
+            },
+            OutputFilterConfig::Replace {
+                replace: r"version (\d+)\..*".to_string(),
+                with: "major $1".to_string(),
+            },
+        ])
+        .unwrap();
+        let command_executor =
+            create_command_executor(&Options::default()).with_output_filter(output_filter);
+
+        // Act
+        let result = command_executor.execute_and_capture(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        let output = result.unwrap();
+        assert_eq!(output.status, ExitStatus::Success);
+
+        let output_value = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(output_value, "major 1\r\n");
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_execute_writes_output_to_files() {
+        // Arrange
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("logs").join("build.log");
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "echo out; echo err >&2".to_string(),
+            }),
+        );
+        let command_executor = create_command_executor(&Options::default())
+            .with_output_files(Some(log_path.clone()), Some(log_path.clone()))
+            .unwrap();
+
+        // Act
+        let result = command_executor.execute(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap(), ExitStatus::Success);
+
+        let log = fs::read_to_string(&log_path).unwrap();
+        assert_eq!(log, "out\nerr\n");
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn bash_command_execute_fails_when_output_filter_fails() {
+        // Arrange
+        let bash_exec_config = ExecutionConfigVariant::ShellCommand(
+            ShellCommandConfigVariant::Bash(BashCommandConfig {
+                working_directory: None,
+                command: "echo 'not json'".to_string(),
+            }),
+        );
+        let output_filter = OutputFilter::new(&[OutputFilterConfig::Jq {
+            jq: ".name".to_string(),
+        }])
+        .unwrap();
+        let command_executor =
+            create_command_executor(&Options::default()).with_output_filter(output_filter);
+
+        // Act
+        let result = command_executor.execute(&bash_exec_config, &HashMap::new());
+
+        // Assert
+        assert!(matches!(result, Err(ExecutionError::OutputFilter(_))));
+    }
+
     #[test]
     fn raw_command_execute_executes_command() {
         // Arrange
@@ -491,6 +1710,25 @@ mod tests {
         assert!(matches!(exit_status, ExitStatus::Fail(101)));
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    fn raw_command_get_output_does_not_quote_values() {
+        // Arrange
+        // Raw commands aren't run by a shell, so quotes would be passed to the command as they are
+        let variables = HashMap::from([("message".to_string(), "it's".to_string())]);
+        let exec_config = ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+            "printf [%s] {{ message | quote }}".to_string(),
+        ));
+        let command_executor = create_command_executor(&Options::default());
+
+        // Act
+        let result = command_executor.get_output(&exec_config, &variables);
+
+        // Assert
+        let output = result.unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "[it's]");
+    }
+
     #[test]
     fn raw_command_get_output_substitutes_variables_in_invocation() {
         // Arrange
@@ -639,6 +1877,37 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn format_command_text_substitutes_placeholders() {
+        // Arrange
+        let template = "[{time}] {workdir} $ {program} -> {args} ({command}) {unknown}";
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(86400 + 3723);
+
+        // Act
+        let text = format_command_text(template, "bash", "-c ls", Some("./src"), time);
+
+        // Assert
+        assert_eq!(
+            text,
+            "[01:02:03] ./src $ bash -> -c ls (bash -c ls) {unknown}"
+        );
+    }
+
+    #[test]
+    fn format_command_text_colors_placeholders() {
+        // Act
+        let text = format_command_text(
+            "Executing: {command:green}",
+            "ls",
+            "",
+            None,
+            SystemTime::now(),
+        );
+
+        // Assert
+        assert_eq!(text, format!("Executing: {}", "ls".green()));
+    }
+
     fn create_temp_dir() -> TempDir {
         let temp_dir = TempDir::new().unwrap();
         return temp_dir;
@@ -0,0 +1,318 @@
+use crate::config::ExecutorConfig;
+use crate::template::Shell;
+use crate::variables::VariableMap;
+use mockall::automock;
+use std::process::Command;
+
+/// Capable of creating processes in a particular environment, such as the local machine, a
+/// container, or a remote machine.
+#[automock]
+pub trait Executor {
+    /// Creates a [`Command`] which executes `program` with `args` in the provided working
+    /// directory, with the provided [`VariableMap`] set as environment variables.
+    fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> Command;
+}
+
+/// Creates the [`Executor`] for the provided [`ExecutorConfig`], or a [`LocalExecutor`] if no
+/// [`ExecutorConfig`] is provided.
+pub fn create_executor(executor_config: Option<&ExecutorConfig>) -> Box<dyn Executor> {
+    match executor_config {
+        None => Box::new(LocalExecutor {}),
+        Some(ExecutorConfig::Docker(docker)) => Box::new(DockerExecutor {
+            container: docker.docker.clone(),
+        }),
+        Some(ExecutorConfig::Ssh(ssh)) => Box::new(SshExecutor {
+            destination: ssh.ssh.clone(),
+        }),
+        Some(ExecutorConfig::Wsl(wsl)) => Box::new(WslExecutor {
+            distribution: wsl.wsl.clone(),
+        }),
+    }
+}
+
+/// Executes processes on the local machine.
+pub struct LocalExecutor;
+
+impl Executor for LocalExecutor {
+    fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> Command {
+        let mut command = Command::new(program);
+        command.args(args).envs(variables);
+
+        if let Some(wd) = working_directory {
+            command.current_dir(wd);
+        }
+
+        command
+    }
+}
+
+/// Executes processes within a running docker container using `docker exec`.
+pub struct DockerExecutor {
+    container: String,
+}
+
+impl Executor for DockerExecutor {
+    fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> Command {
+        let mut command = Command::new("docker");
+        command.arg("exec").arg("--interactive");
+
+        if let Some(wd) = working_directory {
+            command.arg("--workdir").arg(wd);
+        }
+
+        // Only pass the names of the variables so their values aren't visible in the process list.
+        // Docker reads the values from its own environment.
+        for name in sorted_names(variables) {
+            command.arg("--env").arg(name);
+        }
+
+        command
+            .arg(&self.container)
+            .arg(program)
+            .args(args)
+            .envs(variables);
+
+        command
+    }
+}
+
+/// Executes processes on a remote machine using `ssh`.
+pub struct SshExecutor {
+    destination: String,
+}
+
+impl Executor for SshExecutor {
+    fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> Command {
+        // SSH executes commands using the remote user's shell, so the whole command needs to be
+        // built as a single shell command.
+        let mut remote_command = String::new();
+        if let Some(wd) = working_directory {
+            remote_command.push_str(&format!("cd {} && ", Shell::Posix.quote(&wd)));
+        }
+
+        remote_command.push_str(&Shell::Posix.quote(program));
+        for arg in args {
+            remote_command.push_str(&format!(" {}", Shell::Posix.quote(arg)));
+        }
+
+        // Only pass the names of the variables so their values aren't visible in the process list
+        // on either machine. SSH sends the values from its own environment, as long as the remote
+        // server accepts them.
+        let mut command = Command::new("ssh");
+        for name in sorted_names(variables) {
+            command.arg("-o").arg(format!("SendEnv={}", name));
+        }
+
+        command
+            .arg(&self.destination)
+            .arg("--")
+            .arg(remote_command)
+            .envs(variables);
+
+        command
+    }
+}
+
+/// Executes processes within a Windows Subsystem for Linux distribution using `wsl`.
+pub struct WslExecutor {
+    distribution: String,
+}
+
+impl Executor for WslExecutor {
+    fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        working_directory: Option<String>,
+        variables: &VariableMap,
+    ) -> Command {
+        let mut command = Command::new("wsl");
+        command.arg("--distribution").arg(&self.distribution);
+
+        if let Some(wd) = working_directory {
+            command.arg("--cd").arg(wd);
+        }
+
+        command
+            .arg("--exec")
+            .arg(program)
+            .args(args)
+            .envs(variables);
+
+        // WSL only shares the environment variables listed in WSLENV with the distribution
+        if !variables.is_empty() {
+            let mut shared_names: Vec<String> = sorted_names(variables)
+                .into_iter()
+                .map(|name| format!("{}/u", name))
+                .collect();
+            if let Ok(existing) = std::env::var("WSLENV") {
+                shared_names.push(existing);
+            }
+
+            command.env("WSLENV", shared_names.join(":"));
+        }
+
+        command
+    }
+}
+
+fn sorted_names(variables: &VariableMap) -> Vec<&String> {
+    let mut names: Vec<&String> = variables.keys().collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn docker_executor_creates_docker_exec_command() {
+        // Arrange
+        let executor = DockerExecutor {
+            container: "app".to_string(),
+        };
+        let mut variables = VariableMap::new();
+        variables.insert("TARGET".to_string(), "release".to_string());
+
+        // Act
+        let command = executor.command(
+            "cargo",
+            &["build".to_string()],
+            Some("/src".to_string()),
+            &variables,
+        );
+
+        // Assert
+        assert_eq!(command.get_program(), "docker");
+        assert_eq!(
+            get_args(&command),
+            vec![
+                "exec",
+                "--interactive",
+                "--workdir",
+                "/src",
+                "--env",
+                "TARGET",
+                "app",
+                "cargo",
+                "build"
+            ]
+        );
+    }
+
+    #[test]
+    fn ssh_executor_creates_quoted_remote_command() {
+        // Arrange
+        let executor = SshExecutor {
+            destination: "deploy@example.com".to_string(),
+        };
+        let mut variables = VariableMap::new();
+        variables.insert("NAME".to_string(), "it's me".to_string());
+
+        // Act
+        let command = executor.command(
+            "bash",
+            &["-c".to_string(), "echo $NAME".to_string()],
+            Some("/it's here".to_string()),
+            &variables,
+        );
+
+        // Assert
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(
+            get_args(&command),
+            vec![
+                "-o",
+                "SendEnv=NAME",
+                "deploy@example.com",
+                "--",
+                "cd '/it'\\''s here' && 'bash' '-c' 'echo $NAME'"
+            ]
+        );
+    }
+
+    #[test]
+    fn ssh_executor_sends_variables_through_the_environment() {
+        // Arrange
+        let executor = SshExecutor {
+            destination: "deploy@example.com".to_string(),
+        };
+        let mut variables = VariableMap::new();
+        variables.insert("TOKEN".to_string(), "hunter2".to_string());
+        variables.insert("REGION".to_string(), "ap-southeast-2".to_string());
+
+        // Act
+        let command = executor.command("./deploy.sh", &[], None, &variables);
+
+        // Assert
+        assert!(get_args(&command)
+            .iter()
+            .all(|arg| !arg.contains("hunter2") && !arg.contains("ap-southeast-2")));
+        assert_eq!(
+            get_args(&command)[..4],
+            ["-o", "SendEnv=REGION", "-o", "SendEnv=TOKEN"]
+        );
+        assert!(command
+            .get_envs()
+            .any(|(name, value)| name == "TOKEN" && value == Some("hunter2".as_ref())));
+    }
+
+    #[test]
+    fn wsl_executor_shares_variables() {
+        // Arrange
+        let executor = WslExecutor {
+            distribution: "Ubuntu".to_string(),
+        };
+        let mut variables = VariableMap::new();
+        variables.insert("NAME".to_string(), "Alice".to_string());
+
+        // Act
+        let command = executor.command("ls", &[], None, &variables);
+
+        // Assert
+        assert_eq!(command.get_program(), "wsl");
+        assert_eq!(
+            get_args(&command),
+            vec!["--distribution", "Ubuntu", "--exec", "ls"]
+        );
+
+        let wslenv = command
+            .get_envs()
+            .find(|(name, _)| *name == "WSLENV")
+            .and_then(|(_, value)| value)
+            .unwrap();
+        assert!(wslenv.to_string_lossy().starts_with("NAME/u"));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::config::OutputFilterConfig;
+use regex::Regex;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("invalid regex {pattern}")]
+    InvalidRegex {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    #[error("invalid path {path}, expected something like .items[0].name or $.items[0].name")]
+    InvalidPath { path: String },
+
+    #[error("nothing in the output matches the path {path}")]
+    NoMatch { path: String },
+
+    #[error("nothing matches the regex {pattern}")]
+    NoRegexMatch { pattern: String },
+
+    #[error("failed to parse the output as JSON for path {path}")]
+    NotJson {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// A series of filters applied to the output of a step, in order.
+pub struct OutputFilter {
+    filters: Vec<Filter>,
+}
+
+enum Filter {
+    /// Only keeps lines matching the regex.
+    Grep(Regex),
+
+    /// Removes lines matching the regex.
+    Exclude(Regex),
+
+    /// Replaces all matches of the regex within each line.
+    Replace(Regex, String),
+
+    /// Parses the output as JSON and extracts the values at the path.
+    Jq(Vec<PathSegment>),
+}
+
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    Iterate,
+}
+
+impl OutputFilter {
+    pub fn new(configs: &[OutputFilterConfig]) -> Result<OutputFilter, FilterError> {
+        let filters = configs
+            .iter()
+            .map(|config| match config {
+                OutputFilterConfig::Grep { grep } => compile(grep).map(Filter::Grep),
+                OutputFilterConfig::Exclude { exclude } => compile(exclude).map(Filter::Exclude),
+                OutputFilterConfig::Replace { replace, with } => {
+                    compile(replace).map(|regex| Filter::Replace(regex, with.clone()))
+                }
+                OutputFilterConfig::Jq { jq } => parse_path(jq).map(Filter::Jq),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(OutputFilter { filters })
+    }
+
+    /// Whether the output needs to be read in its entirety before it can be filtered (e.g. to
+    /// parse it as JSON). Otherwise, the output can be filtered line-by-line as it's written.
+    pub fn is_buffered(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|filter| matches!(filter, Filter::Jq(_)))
+    }
+
+    /// Filters a single line of output (without its line ending), returning `None` if the line
+    /// should be removed.
+    /// Only used when the output isn't buffered.
+    pub fn filter_line(&self, line: &str) -> Option<String> {
+        self.filters
+            .iter()
+            .try_fold(line.to_string(), |line, filter| filter.filter_line(line))
+    }
+
+    /// Filters the entire output at once.
+    pub fn filter(&self, text: &str) -> Result<String, FilterError> {
+        let mut text = text.to_string();
+        for filter in &self.filters {
+            text = match filter {
+                Filter::Jq(path) => {
+                    let value: Value =
+                        serde_json::from_str(&text).map_err(|source| FilterError::NotJson {
+                            path: path_text(path),
+                            source,
+                        })?;
+
+                    select(&value, path)
+                        .iter()
+                        .map(|value| format!("{}\n", value_text(value)))
+                        .collect()
+                }
+                filter => text
+                    .lines()
+                    .filter_map(|line| filter.filter_line(line.to_string()))
+                    .map(|line| format!("{}\n", line))
+                    .collect(),
+            };
+        }
+
+        Ok(text)
+    }
+}
+
+impl Filter {
+    /// Filters a single line of output, returning `None` if the line should be removed.
+    /// `jq` filters are skipped, since they can only be applied to the entire output.
+    fn filter_line(&self, line: String) -> Option<String> {
+        match self {
+            Filter::Grep(regex) => regex.is_match(&line).then_some(line),
+            Filter::Exclude(regex) => (!regex.is_match(&line)).then_some(line),
+            Filter::Replace(regex, replacement) => {
+                Some(regex.replace_all(&line, replacement.as_str()).to_string())
+            }
+            Filter::Jq(_) => Some(line),
+        }
+    }
+}
+
+/// Parses the provided text as JSON and returns the values at the provided path, one per line.
+/// Fails if nothing matches the path.
+pub fn extract_json(text: &str, path: &str) -> Result<String, FilterError> {
+    let segments = parse_path(path)?;
+    let value: Value = serde_json::from_str(text).map_err(|source| FilterError::NotJson {
+        path: path.to_string(),
+        source,
+    })?;
+
+    let values = select(&value, &segments);
+    if values.is_empty() {
+        return Err(FilterError::NoMatch {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(values
+        .iter()
+        .map(|value| value_text(value))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Returns the first capture group of the first match of the provided regex within the text, or
+/// the entire match if the regex doesn't have any capture groups.
+/// Fails if nothing matches the regex.
+pub fn extract_regex(text: &str, pattern: &str) -> Result<String, FilterError> {
+    let regex = compile(pattern)?;
+    let captures = regex
+        .captures(text)
+        .ok_or_else(|| FilterError::NoRegexMatch {
+            pattern: pattern.to_string(),
+        })?;
+
+    let value = captures.get(1).or_else(|| captures.get(0)).unwrap();
+    Ok(value.as_str().to_string())
+}
+
+fn compile(pattern: &str) -> Result<Regex, FilterError> {
+    Regex::new(pattern).map_err(|source| FilterError::InvalidRegex {
+        pattern: pattern.to_string(),
+        source,
+    })
+}
+
+/// Parses a jq-style path (e.g. `.items[0].name` or `.items[].name`), or a JSONPath (e.g.
+/// `$.items[0].name`, `$.items[*].name`, or `$['my key']`).
+fn parse_path(path: &str) -> Result<Vec<PathSegment>, FilterError> {
+    let invalid = || FilterError::InvalidPath {
+        path: path.to_string(),
+    };
+
+    let mut segments = Vec::new();
+    let path = path.trim();
+    let mut rest = match path.strip_prefix('$') {
+        Some(rest) => rest.strip_prefix('.').unwrap_or(rest),
+        None => path.strip_prefix('.').ok_or_else(invalid)?,
+    };
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let (index, remaining) = index.split_once(']').ok_or_else(invalid)?;
+            let index = index.trim();
+            let quoted_key = ['\'', '"'].into_iter().find_map(|quote| {
+                index
+                    .strip_prefix(quote)
+                    .and_then(|index| index.strip_suffix(quote))
+            });
+            segments.push(match (index, quoted_key) {
+                (_, Some(key)) => PathSegment::Key(key.to_string()),
+                ("" | "*", _) => PathSegment::Iterate,
+                (index, _) => PathSegment::Index(index.parse().map_err(|_| invalid())?),
+            });
+            rest = remaining.strip_prefix('.').unwrap_or(remaining);
+            continue;
+        }
+
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(invalid());
+        }
+
+        segments.push(PathSegment::Key(rest[..end].to_string()));
+        rest = &rest[end..];
+        rest = rest.strip_prefix('.').unwrap_or(rest);
+    }
+
+    Ok(segments)
+}
+
+fn path_text(path: &[PathSegment]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => text.push_str(&format!(".{}", key)),
+            PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+            PathSegment::Iterate => text.push_str("[]"),
+        }
+    }
+
+    if text.is_empty() {
+        ".".to_string()
+    } else {
+        text
+    }
+}
+
+/// Returns the values at the provided path. Missing values are skipped, like `jq`'s `?` operator.
+fn select<'a>(value: &'a Value, path: &[PathSegment]) -> Vec<&'a Value> {
+    let Some((segment, path)) = path.split_first() else {
+        return vec![value];
+    };
+
+    match segment {
+        PathSegment::Key(key) => value
+            .get(key)
+            .map(|value| select(value, path))
+            .unwrap_or_default(),
+        PathSegment::Index(index) => value
+            .get(index)
+            .map(|value| select(value, path))
+            .unwrap_or_default(),
+        PathSegment::Iterate => match value {
+            Value::Array(values) => values
+                .iter()
+                .flat_map(|value| select(value, path))
+                .collect(),
+            Value::Object(values) => values
+                .values()
+                .flat_map(|value| select(value, path))
+                .collect(),
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// Strings are written without quotes (like `jq -r`), anything else is written as JSON.
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => serde_json::to_string_pretty(value).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_line_applies_filters_in_order() {
+        // Arrange
+        let filter = OutputFilter::new(&[
+            OutputFilterConfig::Exclude {
+                exclude: "^DEBUG".to_string(),
+            },
+            OutputFilterConfig::Replace {
+                replace: r"\x1b\[[0-9;]*m".to_string(),
+                with: String::new(),
+            },
+            OutputFilterConfig::Grep {
+                grep: "^(WARN|ERROR)".to_string(),
+            },
+        ])
+        .unwrap();
+
+        // Act
+        let lines: Vec<Option<String>> = [
+            "DEBUG connecting",
+            "\x1b[33mWARN\x1b[0m slow response",
+            "INFO done",
+        ]
+        .iter()
+        .map(|line| filter.filter_line(line))
+        .collect();
+
+        // Assert
+        assert!(!filter.is_buffered());
+        assert_eq!(
+            lines,
+            vec![None, Some("WARN slow response".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn filter_extracts_json_paths() {
+        // Arrange
+        let filter = OutputFilter::new(&[
+            OutputFilterConfig::Jq {
+                jq: ".items[].metadata.name".to_string(),
+            },
+            OutputFilterConfig::Replace {
+                replace: "^api-(.+)$".to_string(),
+                with: "$1".to_string(),
+            },
+        ])
+        .unwrap();
+        let output = r#"{"items": [{"metadata": {"name": "api-users"}}, {"metadata": {"name": "api-orders"}}]}"#;
+
+        // Act
+        let filtered = filter.filter(output).unwrap();
+
+        // Assert
+        assert!(filter.is_buffered());
+        assert_eq!(filtered, "users\norders\n");
+    }
+
+    #[test]
+    fn extract_json_supports_json_paths() {
+        // Arrange
+        let output = r#"{"items": [{"id": 7, "my name": "api"}, {"id": 8, "my name": "web"}]}"#;
+
+        // Act
+        let first = extract_json(output, "$.items[0].id").unwrap();
+        let names = extract_json(output, "$.items[*]['my name']").unwrap();
+        let jq = extract_json(output, ".items[1].id").unwrap();
+        let missing = extract_json(output, "$.items[2].id");
+
+        // Assert
+        assert_eq!(first, "7");
+        assert_eq!(names, "api\nweb");
+        assert_eq!(jq, "8");
+        assert!(matches!(missing, Err(FilterError::NoMatch { .. })));
+    }
+
+    #[test]
+    fn extract_regex_returns_first_capture_group() {
+        // Arrange
+        let output = "Docker version 24.0.7, build afdd53b\nDocker version 25.0.1";
+
+        // Act
+        let version = extract_regex(output, r"version (\d+\.\d+)").unwrap();
+        let build = extract_regex(output, r"build \w+").unwrap();
+        let missing = extract_regex(output, r"podman (\d+)");
+        let invalid = extract_regex(output, r"version (\d+");
+
+        // Assert
+        assert_eq!(version, "24.0");
+        assert_eq!(build, "build afdd53b");
+        assert!(matches!(missing, Err(FilterError::NoRegexMatch { .. })));
+        assert!(matches!(invalid, Err(FilterError::InvalidRegex { .. })));
+    }
+
+    #[test]
+    fn new_fails_for_invalid_paths() {
+        for path in ["items", ".items[x]", ".items..name", ".items[0", "$..name"] {
+            let result = OutputFilter::new(&[OutputFilterConfig::Jq {
+                jq: path.to_string(),
+            }]);
+            assert!(
+                matches!(result, Err(FilterError::InvalidPath { .. })),
+                "{path}"
+            );
+        }
+    }
+}
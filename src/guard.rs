@@ -0,0 +1,193 @@
+use crate::config::GuardConfig;
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Weekday};
+use thiserror::Error;
+
+/// Checks that the provided [`GuardConfig`] allows the command to be executed at `now` (in local
+/// time), returning an error explaining why if it doesn't.
+pub fn check(
+    guard: &GuardConfig,
+    now: NaiveDateTime,
+    get_env: impl Fn(&str) -> Option<String>,
+) -> Result<(), GuardError> {
+    let reason = match find_violation(guard, now, get_env)? {
+        Some(reason) => reason,
+        None => return Ok(()),
+    };
+
+    let reason = match &guard.message {
+        Some(message) => format!("{} ({})", message, reason),
+        None => reason,
+    };
+    Err(GuardError::Refused { reason })
+}
+
+fn find_violation(
+    guard: &GuardConfig,
+    now: NaiveDateTime,
+    get_env: impl Fn(&str) -> Option<String>,
+) -> Result<Option<String>, GuardError> {
+    for window in guard.not_on.iter().flat_map(|not_on| not_on.as_slice()) {
+        if window_contains(window, now)? {
+            return Ok(Some(format!(
+                "the command can't be executed during \"{}\"",
+                window
+            )));
+        }
+    }
+
+    for requirement in guard
+        .only_if_env
+        .iter()
+        .flat_map(|only_if_env| only_if_env.as_slice())
+    {
+        let violation = match requirement.split_once('=') {
+            Some((name, expected)) => (get_env(name).as_deref() != Some(expected)).then(|| {
+                format!(
+                    "the command can only be executed when {} is \"{}\"",
+                    name, expected
+                )
+            }),
+            None => get_env(requirement)
+                .filter(|value| !value.is_empty())
+                .is_none()
+                .then(|| {
+                    format!(
+                        "the command can only be executed when {} is set",
+                        requirement
+                    )
+                }),
+        };
+
+        if violation.is_some() {
+            return Ok(violation);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns `true` if `now` is within the provided time window.
+///
+/// Time windows are made of an optional day (e.g. `friday`, `weekdays`, or `weekends`), followed
+/// by an optional time of day:
+/// - `after-16:00` matches from 16:00 until midnight.
+/// - `before-09:00` matches from midnight until 09:00.
+/// - `22:00-06:00` matches from 22:00 until 06:00, wrapping past midnight if the end is earlier.
+fn window_contains(window: &str, now: NaiveDateTime) -> Result<bool, GuardError> {
+    let invalid = || GuardError::InvalidWindow {
+        window: window.to_string(),
+    };
+
+    let lowercase = window.trim().to_lowercase();
+    let (days, times) = match lowercase.split_once('-') {
+        Some((day, times)) if parse_days(day).is_some() => (parse_days(day), Some(times)),
+        _ => match parse_days(&lowercase) {
+            Some(days) => (Some(days), None),
+            None => (None, Some(lowercase.as_str())),
+        },
+    };
+
+    if let Some(days) = days {
+        if !days.contains(&now.weekday()) {
+            return Ok(false);
+        }
+    }
+
+    let time = now.time();
+    let contains = match times {
+        None => true,
+        Some(times) => {
+            if let Some(start) = times.strip_prefix("after-") {
+                time >= parse_time(start).ok_or_else(invalid)?
+            } else if let Some(end) = times.strip_prefix("before-") {
+                time < parse_time(end).ok_or_else(invalid)?
+            } else {
+                let (start, end) = times.split_once('-').ok_or_else(invalid)?;
+                let start = parse_time(start).ok_or_else(invalid)?;
+                let end = parse_time(end).ok_or_else(invalid)?;
+                match start <= end {
+                    true => start <= time && time < end,
+                    false => start <= time || time < end,
+                }
+            }
+        }
+    };
+
+    Ok(contains)
+}
+
+fn parse_days(text: &str) -> Option<Vec<Weekday>> {
+    use Weekday::*;
+    let days = match text {
+        "weekdays" => vec![Mon, Tue, Wed, Thu, Fri],
+        "weekends" | "weekend" => vec![Sat, Sun],
+        day => vec![day.parse::<Weekday>().ok()?],
+    };
+
+    Some(days)
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(text, "%H:%M").ok()
+}
+
+#[derive(Error, Debug)]
+pub enum GuardError {
+    #[error("{reason}, pass --ignore-guard to execute it anyway")]
+    Refused { reason: String },
+
+    #[error("\"{window}\" isn't a valid guard time, expected something like \"friday-after-16:00\", \"weekends\", or \"22:00-06:00\"")]
+    InvalidWindow { window: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OneOrManyStrings;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn check_refuses_commands_outside_of_guard() {
+        // Arrange
+        let guard = GuardConfig {
+            not_on: Some(OneOrManyStrings::Many(vec![
+                "friday-after-16:00".to_string(),
+                "weekends".to_string(),
+                "22:00-06:00".to_string(),
+            ])),
+            only_if_env: Some(OneOrManyStrings::One("DEPLOY_ENV=production".to_string())),
+            message: Some("No deploys after hours".to_string()),
+        };
+        // 2024-06-14 was a Friday
+        let at = |day: u32, hour: u32| {
+            NaiveDate::from_ymd_opt(2024, 6, day)
+                .unwrap()
+                .and_hms_opt(hour, 30, 0)
+                .unwrap()
+        };
+        let production = |name: &str| (name == "DEPLOY_ENV").then(|| "production".to_string());
+
+        // Act
+        let friday_morning = check(&guard, at(14, 10), production);
+        let friday_evening = check(&guard, at(14, 17), production);
+        let saturday = check(&guard, at(15, 10), production);
+        let monday_night = check(&guard, at(17, 2), production);
+        let staging = check(&guard, at(14, 10), |_| Some("staging".to_string()));
+        let invalid = window_contains("friday-after-4pm", at(14, 10));
+
+        // Assert
+        assert!(friday_morning.is_ok());
+        assert!(matches!(
+            friday_evening,
+            Err(GuardError::Refused { reason })
+                if reason == "No deploys after hours (the command can't be executed during \"friday-after-16:00\")"
+        ));
+        assert!(saturday.is_err());
+        assert!(monday_night.is_err());
+        assert!(matches!(
+            staging,
+            Err(GuardError::Refused { reason }) if reason.ends_with("when DEPLOY_ENV is \"production\")")
+        ));
+        assert!(matches!(invalid, Err(GuardError::InvalidWindow { .. })));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::config::{Config, Platform};
+use crate::platform::is_current_platform;
+use clap_complete::Shell;
+use thiserror::Error;
+
+/// The shells the built-in `hook` command can generate a hook for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+    Pwsh,
+}
+
+impl HookShell {
+    pub const NAMES: [&'static str; 4] = ["bash", "zsh", "fish", "pwsh"];
+
+    pub fn from_name(name: &str) -> Option<HookShell> {
+        match name {
+            "bash" => Some(HookShell::Bash),
+            "zsh" => Some(HookShell::Zsh),
+            "fish" => Some(HookShell::Fish),
+            "pwsh" | "powershell" => Some(HookShell::Pwsh),
+            _ => None,
+        }
+    }
+
+    /// Returns the [`HookShell`] for the provided [`Shell`], or `None` if hooks aren't supported
+    /// for it.
+    pub fn from_shell(shell: Shell) -> Option<HookShell> {
+        match shell {
+            Shell::Bash => Some(HookShell::Bash),
+            Shell::Zsh => Some(HookShell::Zsh),
+            Shell::Fish => Some(HookShell::Fish),
+            Shell::PowerShell => Some(HookShell::Pwsh),
+            _ => None,
+        }
+    }
+}
+
+// Each hook asks plz for the commands whenever the directory changes, then defines a function for
+// each one that doesn't conflict with an existing command, alias, or function.
+// The functions defined for the previous directory are removed first.
+// Each function stores the exit code of the command in PLZ_LAST_STATUS, for prompt frameworks.
+
+const BASH_HOOK: &str = r#"_plz_hook() {
+  [[ "$PWD" == "$_plz_hook_dir" ]] && return
+  _plz_hook_dir="$PWD"
+  local name
+  for name in "${_plz_hook_functions[@]}"; do
+    unset -f "$name"
+  done
+  _plz_hook_functions=()
+  while IFS= read -r name; do
+    if ! type "$name" >/dev/null 2>&1; then
+      eval "$name() { command {{bin_name}} $name \"\$@\"; PLZ_LAST_STATUS=\$?; return \$PLZ_LAST_STATUS; }"
+      _plz_hook_functions+=("$name")
+    fi
+  done < <(command {{bin_name}} hook --commands 2>/dev/null)
+}
+_plz_hook_functions=()
+if [[ ";${PROMPT_COMMAND:-};" != *";_plz_hook;"* ]]; then
+  PROMPT_COMMAND="_plz_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
+_plz_hook
+"#;
+
+const ZSH_HOOK: &str = r#"_plz_hook() {
+  local name
+  for name in "${_plz_hook_functions[@]}"; do
+    unfunction "$name" 2>/dev/null
+  done
+  _plz_hook_functions=()
+  for name in ${(f)"$(command {{bin_name}} hook --commands 2>/dev/null)"}; do
+    if ! whence "$name" >/dev/null; then
+      eval "$name() { command {{bin_name}} $name \"\$@\"; PLZ_LAST_STATUS=\$?; return \$PLZ_LAST_STATUS; }"
+      _plz_hook_functions+=("$name")
+    fi
+  done
+}
+typeset -ga _plz_hook_functions
+autoload -Uz add-zsh-hook
+add-zsh-hook chpwd _plz_hook
+_plz_hook
+"#;
+
+const FISH_HOOK: &str = r#"function __plz_hook --on-variable PWD
+    for name in $__plz_hook_functions
+        functions --erase $name
+    end
+    set -g __plz_hook_functions
+    for name in (command {{bin_name}} hook --commands 2>/dev/null)
+        if not type -q $name
+            function $name --inherit-variable name
+                command {{bin_name}} $name $argv
+                set -g PLZ_LAST_STATUS $status
+                return $PLZ_LAST_STATUS
+            end
+            set -a __plz_hook_functions $name
+        end
+    end
+end
+__plz_hook
+"#;
+
+const PWSH_HOOK: &str = r#"$global:PlzHookFunctions = @()
+$global:PlzHookDirectory = $null
+function global:Invoke-PlzHook {
+    if ($PWD.Path -eq $global:PlzHookDirectory) { return }
+    $global:PlzHookDirectory = $PWD.Path
+    foreach ($name in $global:PlzHookFunctions) {
+        Remove-Item -Path "Function:\$name" -ErrorAction SilentlyContinue
+    }
+    $global:PlzHookFunctions = @()
+    foreach ($name in (& {{bin_name}} hook --commands 2>$null)) {
+        if (-not (Get-Command $name -ErrorAction SilentlyContinue)) {
+            Set-Item -Path "Function:\global:$name" -Value ([ScriptBlock]::Create("& {{bin_name}} $name @args; `$global:PLZ_LAST_STATUS = `$LASTEXITCODE"))
+            $global:PlzHookFunctions += $name
+        }
+    }
+}
+$global:PlzHookPrompt = $function:prompt
+function global:prompt { Invoke-PlzHook; & $global:PlzHookPrompt }
+"#;
+
+/// Generates the script which defines a function for each of the current directory's commands
+/// when evaluated by the provided [`HookShell`].
+/// `bin_name` is the name plz is invoked as, which the functions call.
+pub fn generate(shell: HookShell, bin_name: &str) -> String {
+    let hook = match shell {
+        HookShell::Bash => BASH_HOOK,
+        HookShell::Zsh => ZSH_HOOK,
+        HookShell::Fish => FISH_HOOK,
+        HookShell::Pwsh => PWSH_HOOK,
+    };
+    hook.replace("{{bin_name}}", bin_name)
+}
+
+/// Returns the names of the top-level commands the hook should define functions for.
+/// Hidden and unavailable commands are skipped, as are names which aren't safe to use as a
+/// function name, since the hook defines the functions for any directory the user changes to.
+pub fn command_names(config: &Config, platform: &Platform) -> Vec<String> {
+    let mut names: Vec<String> = config
+        .commands
+        .iter()
+        .filter(|(_, command_config)| match &command_config.platform {
+            Some(platforms) => is_current_platform(platform.clone(), platforms),
+            None => true,
+        })
+        .filter(|(_, command_config)| !command_config.hidden && command_config.is_available())
+        .map(|(key, command_config)| command_config.name.as_ref().unwrap_or(key).clone())
+        .filter(|name| is_safe_name(name))
+        .collect();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn is_safe_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphanumeric() || first == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[derive(Error, Debug)]
+pub enum HookError {
+    #[error("couldn't detect the current shell, try specifying one explicitly")]
+    UnknownShell,
+
+    #[error("the hook isn't supported for {shell}, try one of: bash, zsh, fish, pwsh")]
+    UnsupportedShell { shell: Shell },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn command_names_skips_hidden_and_unsafe_commands() {
+        // Arrange
+        let yaml = r#"
+commands:
+  build:
+    action: cargo build
+  deploy-prod:
+    action: ./deploy.sh
+  secret:
+    hidden: true
+    action: echo secret
+  "rm -rf;x":
+    action: echo unsafe
+  windows-only:
+    platform: Windows
+    action: echo windows
+"#;
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let names = command_names(&config, &Platform::Linux);
+
+        // Assert
+        assert_eq!(names, vec!["build", "deploy-prod"]);
+    }
+}
@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// The name of the file remote imports are locked in, stored next to the config file.
+pub const LOCK_FILE_NAME: &str = "plz.lock";
+
+const LOCK_FILE_HEADER: &str =
+    "# Generated by plz, run `plz update` to update the locked imports.\n";
+
+/// The versions of the remote and git imports used by a config file, so everyone using the config
+/// file executes the same commands.
+#[derive(Serialize, Deserialize, Default, PartialEq, Debug, Clone)]
+pub struct ImportLock {
+    /// The locked imports, keyed by their URL (or their repository, ref, and path for git
+    /// imports).
+    #[serde(default)]
+    pub imports: BTreeMap<String, LockedImport>,
+}
+
+/// The version of a remote or git import.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct LockedImport {
+    /// The commit a git import's ref resolved to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+
+    /// A SHA-256 hash of the imported config file.
+    pub sha256: String,
+}
+
+impl ImportLock {
+    /// Loads the [`ImportLock`] from the provided path.
+    /// If the lock file doesn't exist yet, an empty [`ImportLock`] is returned.
+    pub fn load(path: &Path) -> Result<ImportLock, ImportLockError> {
+        if !path.exists() {
+            return Ok(ImportLock::default());
+        }
+
+        let text = fs::read_to_string(path).map_err(ImportLockError::ReadFailed)?;
+        serde_yaml::from_str(&text).map_err(ImportLockError::ParseFailed)
+    }
+
+    /// Saves the [`ImportLock`] to the provided path.
+    pub fn save(&self, path: &Path) -> Result<(), ImportLockError> {
+        let text = serde_yaml::to_string(self).map_err(ImportLockError::SerializeFailed)?;
+        fs::write(path, format!("{}{}", LOCK_FILE_HEADER, text))
+            .map_err(ImportLockError::WriteFailed)
+    }
+
+    /// Returns a copy of this lock with the provided imports added (or replaced).
+    /// If `prune` is set, imports that aren't in `resolved` are removed.
+    pub fn merge(&self, resolved: &ImportLock, prune: bool) -> ImportLock {
+        let mut imports = if prune {
+            BTreeMap::new()
+        } else {
+            self.imports.clone()
+        };
+        imports.extend(resolved.imports.clone());
+
+        ImportLock { imports }
+    }
+}
+
+/// The lock file being used while the config is loaded.
+struct ActiveLock {
+    path: PathBuf,
+
+    /// The lock file as it was when it was activated.
+    locked: ImportLock,
+
+    /// The imports that have been resolved since the lock file was activated.
+    resolved: ImportLock,
+
+    /// Whether the locked versions should be ignored, so the latest versions are resolved.
+    updating: bool,
+}
+
+static ACTIVE_LOCK: Mutex<Option<ActiveLock>> = Mutex::new(None);
+
+/// Uses the lock file at the provided path for any remote or git imports resolved from now on.
+/// If `updating` is set, the locked versions are ignored so the latest versions are resolved.
+pub fn activate(path: PathBuf, updating: bool) -> Result<(), ImportLockError> {
+    let locked = ImportLock::load(&path)?;
+    *ACTIVE_LOCK.lock().unwrap() = Some(ActiveLock {
+        path,
+        locked,
+        resolved: ImportLock::default(),
+        updating,
+    });
+
+    Ok(())
+}
+
+/// Returns `true` if the lock file is being updated.
+pub fn is_updating() -> bool {
+    ACTIVE_LOCK
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|active| active.updating)
+}
+
+/// Returns the locked version of the provided import, or `None` if it isn't locked (or the lock
+/// file is being updated).
+pub fn locked(key: &str) -> Option<LockedImport> {
+    let active_lock = ACTIVE_LOCK.lock().unwrap();
+    let active = active_lock.as_ref().filter(|active| !active.updating)?;
+    active.locked.imports.get(key).cloned()
+}
+
+/// Records the version an import resolved to, so it can be written to the lock file.
+pub fn record(key: &str, import: LockedImport) {
+    if let Some(active) = ACTIVE_LOCK.lock().unwrap().as_mut() {
+        active.resolved.imports.insert(key.to_string(), import);
+    }
+}
+
+/// Writes the resolved imports to the lock file if they've changed, returning `true` if the lock
+/// file was written.
+/// Locked imports that weren't resolved (e.g. because they weren't needed for the command being
+/// executed) are kept unless `prune` is set.
+pub fn save(prune: bool) -> Result<bool, ImportLockError> {
+    let active_lock = ACTIVE_LOCK.lock().unwrap();
+    let Some(active) = active_lock.as_ref() else {
+        return Ok(false);
+    };
+
+    let lock = active.locked.merge(&active.resolved, prune);
+    if lock == active.locked {
+        return Ok(false);
+    }
+
+    lock.save(&active.path)?;
+    Ok(true)
+}
+
+/// Returns a SHA-256 hash of the provided content, as used in [`LockedImport::sha256`].
+pub fn hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum ImportLockError {
+    #[error("failed to read {LOCK_FILE_NAME}")]
+    ReadFailed(#[source] io::Error),
+
+    #[error("failed to write {LOCK_FILE_NAME}")]
+    WriteFailed(#[source] io::Error),
+
+    #[error("failed to parse {LOCK_FILE_NAME}")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("failed to serialize {LOCK_FILE_NAME}")]
+    SerializeFailed(#[source] serde_yaml::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn locked_import(sha256: &str) -> LockedImport {
+        LockedImport {
+            commit: None,
+            sha256: sha256.to_string(),
+        }
+    }
+
+    #[test]
+    fn merge_keeps_unresolved_imports_unless_pruned() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOCK_FILE_NAME);
+        let mut lock = ImportLock::default();
+        lock.imports
+            .insert("https://example.com/a.yaml".to_string(), locked_import("a"));
+        lock.imports
+            .insert("https://example.com/b.yaml".to_string(), locked_import("b"));
+        lock.save(&path).unwrap();
+
+        let mut resolved = ImportLock::default();
+        resolved.imports.insert(
+            "https://example.com/b.yaml".to_string(),
+            locked_import("b2"),
+        );
+
+        // Act
+        let loaded = ImportLock::load(&path).unwrap();
+        let merged = loaded.merge(&resolved, false);
+        let pruned = loaded.merge(&resolved, true);
+
+        // Assert
+        assert_eq!(loaded, lock);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with(LOCK_FILE_HEADER));
+        assert_eq!(
+            merged.imports.values().collect::<Vec<_>>(),
+            vec![&locked_import("a"), &locked_import("b2")]
+        );
+        assert_eq!(
+            pruned.imports.values().collect::<Vec<_>>(),
+            vec![&locked_import("b2")]
+        );
+    }
+}
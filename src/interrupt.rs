@@ -0,0 +1,177 @@
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// The exit code used when plz is interrupted (128 + SIGINT).
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether plz should keep running when it's interrupted, rather than exiting immediately.
+static WAIT_FOR_CHILDREN: AtomicBool = AtomicBool::new(false);
+
+/// Whether the handler has been registered. It can only be registered once per process.
+static REGISTERED: Mutex<bool> = Mutex::new(false);
+
+/// The IDs of the process groups which don't receive interrupt signals from the terminal, and need
+/// them forwarded instead.
+#[cfg(unix)]
+static PROCESS_GROUPS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Installs a handler for interrupt signals (Ctrl+C).
+///
+/// Child processes still receive the signal from the terminal, so once the handler is installed
+/// plz will wait for them to exit rather than exiting immediately, giving it a chance to clean up.
+pub fn install_handler() -> Result<(), ctrlc::Error> {
+    WAIT_FOR_CHILDREN.store(true, Ordering::SeqCst);
+    register()
+}
+
+/// Forwards interrupt signals to the process groups registered with [`track_process_group`].
+/// Unless [`install_handler`] has been called, plz still exits once they've been forwarded.
+#[cfg(unix)]
+pub fn forward_to_process_groups() -> Result<(), ctrlc::Error> {
+    register()
+}
+
+/// Registers a process group which should receive interrupt signals.
+#[cfg(unix)]
+pub fn track_process_group(id: u32) {
+    PROCESS_GROUPS.lock().unwrap().push(id);
+}
+
+/// Stops forwarding interrupt signals to a process group registered with [`track_process_group`].
+#[cfg(unix)]
+pub fn untrack_process_group(id: u32) {
+    PROCESS_GROUPS
+        .lock()
+        .unwrap()
+        .retain(|tracked| *tracked != id);
+}
+
+/// Handles an interrupt as if plz had received the signal, for interrupts which were only sent to
+/// a process group that had the terminal (see [`crate::process_group::ProcessGroup::spawn_in_foreground`]).
+#[cfg(unix)]
+pub fn raise() {
+    handle();
+}
+
+fn register() -> Result<(), ctrlc::Error> {
+    let mut registered = REGISTERED.lock().unwrap();
+    if !*registered {
+        ctrlc::set_handler(handle)?;
+        *registered = true;
+    }
+
+    Ok(())
+}
+
+fn handle() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    let wait = WAIT_FOR_CHILDREN.load(Ordering::SeqCst);
+
+    #[cfg(unix)]
+    for id in PROCESS_GROUPS.lock().unwrap().iter() {
+        crate::process_group::interrupt(*id);
+
+        // Nothing will be left to wait for them, so make sure they don't outlive plz
+        if !wait {
+            crate::process_group::terminate(*id);
+        }
+    }
+
+    if !wait {
+        process::exit(INTERRUPTED_EXIT_CODE);
+    }
+}
+
+/// Returns `true` if an interrupt signal has been received since the handler was installed.
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Clears any previously received interrupt signal.
+pub fn clear() {
+    INTERRUPTED.store(false, Ordering::SeqCst)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process::{Command, Output};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// The name of the variable used to choose how [`interrupt_self`] handles interrupts.
+    const INTERRUPT_TEST_VARIABLE: &str = "PLZ_TEST_INTERRUPT";
+
+    /// Interrupts its own process, and prints whether it kept running afterwards.
+    /// Interrupts affect the whole process, so this is run in a separate process by the tests
+    /// below.
+    #[test]
+    #[ignore]
+    fn interrupt_self() {
+        let Ok(mode) = env::var(INTERRUPT_TEST_VARIABLE) else {
+            return;
+        };
+
+        if mode == "install" {
+            install_handler().unwrap();
+        } else {
+            forward_to_process_groups().unwrap();
+        }
+        assert!(!is_interrupted());
+
+        // SAFETY: kill has no memory safety requirements
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGINT);
+        }
+
+        // The signal is handled on another thread
+        let started = Instant::now();
+        while !is_interrupted() && started.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(is_interrupted());
+
+        clear();
+        assert!(!is_interrupted());
+        println!("still running");
+    }
+
+    fn run_interrupt_self(mode: &str) -> Output {
+        Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "interrupt::tests::interrupt_self",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(INTERRUPT_TEST_VARIABLE, mode)
+            .output()
+            .unwrap()
+    }
+
+    #[test]
+    fn install_handler_records_interrupts_until_cleared() {
+        // Act
+        let output = run_interrupt_self("install");
+
+        // Assert
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("still running"));
+    }
+
+    #[test]
+    fn interrupts_exit_without_handler() {
+        // Act
+        let output = run_interrupt_self("forward");
+
+        // Assert
+        assert_eq!(output.status.code(), Some(INTERRUPTED_EXIT_CODE));
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("still running"));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::{
+    BashCommandConfig, ExecutionConfigVariant, RawCommandConfigVariant, ShellCommandConfigVariant,
+};
+use crate::template::Shell;
+
+/// Reads a generic credential from the Windows Credential Manager.
+/// The credential's target name is the service, and its user name must match the account. If
+/// there isn't one, a credential with the `account@service` target name is used instead.
+const WINDOWS_READ_CREDENTIAL: &str = r#"Add-Type -TypeDefinition 'using System; using System.Runtime.InteropServices; public static class PlzKeyring { [StructLayout(LayoutKind.Sequential, CharSet = CharSet.Unicode)] struct Credential { public int Flags; public int Type; public string TargetName; public string Comment; public long LastWritten; public int CredentialBlobSize; public IntPtr CredentialBlob; public int Persist; public int AttributeCount; public IntPtr Attributes; public string TargetAlias; public string UserName; } [DllImport("advapi32.dll", CharSet = CharSet.Unicode, SetLastError = true)] static extern bool CredRead(string target, int type, int flags, out IntPtr credential); [DllImport("advapi32.dll")] static extern void CredFree(IntPtr credential); public static string Read(string target, string user) { IntPtr pointer; if (!CredRead(target, 1, 0, out pointer)) { return null; } try { Credential credential = (Credential)Marshal.PtrToStructure(pointer, typeof(Credential)); if (user != null && credential.UserName != user) { return null; } return Marshal.PtrToStringUni(credential.CredentialBlob, credential.CredentialBlobSize / 2); } finally { CredFree(pointer); } } }'; $plzSecret = [PlzKeyring]::Read({service}, {account}); if ($plzSecret -eq $null) { $plzSecret = [PlzKeyring]::Read({target}, $null) }; if ($plzSecret -eq $null) { exit 1 }; $plzSecret"#;
+
+/// Returns the command which prints the secret stored in the platform's credential store for the
+/// provided service and account, and fails if there isn't one.
+/// `os` is the name of the operating system, as it appears in [`std::env::consts::OS`].
+///
+/// - macOS uses the login Keychain (`security`).
+/// - Windows uses the Credential Manager (PowerShell).
+/// - Other platforms use the Secret Service (`secret-tool`), with the `service` and `username`
+///   attributes.
+pub fn lookup_command(os: &str, service: &str, account: &str) -> ExecutionConfigVariant {
+    let command = match os {
+        "macos" => format!(
+            "security find-generic-password -s {} -a {} -w",
+            Shell::Posix.quote(service),
+            Shell::Posix.quote(account)
+        ),
+        "windows" => {
+            // Raw commands are split on spaces, and PowerShell joins the arguments back together
+            let script = WINDOWS_READ_CREDENTIAL
+                .replace("{service}", &Shell::PowerShell.quote(service))
+                .replace("{account}", &Shell::PowerShell.quote(account))
+                .replace(
+                    "{target}",
+                    &Shell::PowerShell.quote(&format!("{}@{}", account, service)),
+                );
+            return ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                format!("powershell -NoProfile -NonInteractive -Command {}", script),
+            ));
+        }
+        _ => format!(
+            "secret-tool lookup service {} username {}",
+            Shell::Posix.quote(service),
+            Shell::Posix.quote(account)
+        ),
+    };
+
+    ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(BashCommandConfig {
+        working_directory: None,
+        command,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_command_uses_platform_credential_store() {
+        // Act
+        let macos = lookup_command("macos", "github", "it's me");
+        let linux = lookup_command("linux", "github", "me");
+        let windows = lookup_command("windows", "github", "it's me");
+
+        // Assert
+        assert_eq!(
+            macos.command(),
+            "security find-generic-password -s 'github' -a 'it'\\''s me' -w"
+        );
+        assert_eq!(
+            linux.command(),
+            "secret-tool lookup service 'github' username 'me'"
+        );
+        assert!(matches!(
+            windows,
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(_))
+        ));
+        assert!(windows
+            .command()
+            .starts_with("powershell -NoProfile -NonInteractive -Command Add-Type"));
+        assert!(windows
+            .command()
+            .contains("[PlzKeyring]::Read('github', 'it''s me')"));
+        assert!(windows
+            .command()
+            .contains("[PlzKeyring]::Read('it''s me@github', $null)"));
+    }
+}
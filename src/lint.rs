@@ -0,0 +1,324 @@
+use crate::config::{
+    CommandConfig, CommandConfigMap, Config, OneOrManyPlatforms, Options, Platform, Severity,
+    VariableConfigMap,
+};
+use crate::platform::is_current_platform;
+use colored::Colorize;
+use std::fmt;
+use std::fmt::Formatter;
+use thiserror::Error;
+
+/// Commands that destroy data without asking, flagged by the `dangerous_commands` rule.
+const DANGEROUS_COMMANDS: &[&str] = &["rm -rf", "rm -fr", "git clean -fd", "git reset --hard"];
+
+/// The platforms a platform-specific command is expected to have counterparts for.
+const COMMON_PLATFORMS: [Platform; 3] = [Platform::Linux, Platform::MacOS, Platform::Windows];
+
+#[derive(Error, Debug)]
+#[error("found {count} lint errors")]
+pub struct LintError {
+    pub count: usize,
+}
+
+/// A problem found in the config by one of the lint rules.
+#[derive(PartialEq, Debug)]
+pub struct Finding {
+    /// The name of the rule that found the problem (e.g. `unused_variables`).
+    pub rule: &'static str,
+
+    pub severity: Severity,
+
+    /// The path to the offending part of the config (e.g. `commands.build`).
+    pub path: String,
+
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.path, self.message, self.rule)
+    }
+}
+
+/// Checks the provided [`Config`] against the lint rules enabled in its options.
+pub fn lint(config: &Config) -> Vec<Finding> {
+    let mut linter = Linter {
+        options: &config.options,
+        findings: Vec::new(),
+    };
+
+    // Root variables can be referenced anywhere in the config
+    let root_text = serde_yaml::to_string(&config.commands).unwrap_or_default();
+    linter.check_unused_variables(&config.variables, "", &root_text, &[]);
+    linter.check_commands(&config.commands, "");
+
+    linter.findings
+}
+
+/// Prints the provided findings to stderr, returning an error if any of them have the
+/// [`Severity::Error`] severity.
+pub fn report(findings: &[Finding]) -> Result<(), LintError> {
+    for finding in findings {
+        match finding.severity {
+            Severity::Error => eprintln!("{} {}", "error:".red().bold(), finding),
+            _ => eprintln!("{} {}", "warning:".yellow().bold(), finding),
+        }
+    }
+
+    let count = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    if count > 0 {
+        return Err(LintError { count });
+    }
+
+    Ok(())
+}
+
+struct Linter<'a> {
+    options: &'a Options,
+    findings: Vec<Finding>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: &'static str, severity: Severity, path: &str, message: String) {
+        if severity == Severity::Off {
+            return;
+        }
+
+        self.findings.push(Finding {
+            rule,
+            severity,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn check_commands(&mut self, commands: &CommandConfigMap, path_prefix: &str) {
+        self.check_platform_counterparts(commands, path_prefix);
+
+        // Sort the keys so findings are reported in the same order every time
+        let mut keys: Vec<&String> = commands.keys().collect();
+        keys.sort();
+
+        for key in keys {
+            let command_config = &commands[key];
+            let path = format!("{}commands.{}", path_prefix, key);
+
+            if command_config.description.is_none() && !command_config.hidden {
+                self.report(
+                    "missing_descriptions",
+                    self.options.lint.missing_descriptions,
+                    &path,
+                    "command has no description".to_string(),
+                );
+            }
+
+            let action_text = serde_yaml::to_string(&command_config.action).unwrap_or_default();
+            for dangerous_command in DANGEROUS_COMMANDS {
+                if action_text.contains(dangerous_command) {
+                    self.report(
+                        "dangerous_commands",
+                        self.options.lint.dangerous_commands,
+                        &path,
+                        format!("action runs \"{}\"", dangerous_command),
+                    );
+                }
+            }
+
+            // Command variables can be referenced anywhere within the command
+            let command_text = serde_yaml::to_string(&CommandConfig {
+                variables: VariableConfigMap::new(),
+                ..command_config.clone()
+            })
+            .unwrap_or_default();
+            let saved_keys = command_config
+                .save_env
+                .as_ref()
+                .map(|save_env| save_env.variables.as_slice())
+                .unwrap_or_default();
+            self.check_unused_variables(
+                &command_config.variables,
+                &format!("{}.", path),
+                &command_text,
+                saved_keys,
+            );
+
+            self.check_commands(&command_config.commands, &format!("{}.", path));
+        }
+    }
+
+    /// Reports any of the provided variables which aren't referenced in `text`, by the other
+    /// variables, or in `saved_keys`.
+    fn check_unused_variables(
+        &mut self,
+        variables: &VariableConfigMap,
+        path_prefix: &str,
+        text: &str,
+        saved_keys: &[String],
+    ) {
+        for (key, variable_config) in variables.iter() {
+            let name = variable_config.environment_variable_name(key, self.options);
+
+            let other_variables: VariableConfigMap = variables
+                .iter()
+                .filter(|(other_key, _)| *other_key != key)
+                .map(|(other_key, other_config)| (other_key.clone(), other_config.clone()))
+                .collect();
+            let variables_text = serde_yaml::to_string(&other_variables).unwrap_or_default();
+
+            let is_used = references(text, &name)
+                || references(&variables_text, &name)
+                || saved_keys.contains(key);
+            if !is_used {
+                self.report(
+                    "unused_variables",
+                    self.options.lint.unused_variables,
+                    &format!("{}variables.{}", path_prefix, key),
+                    format!("variable is never referenced as ${}", name),
+                );
+            }
+        }
+    }
+
+    /// Reports any platform-specific commands which don't have a counterpart (a command with the
+    /// same name) for each of the [`COMMON_PLATFORMS`].
+    fn check_platform_counterparts(&mut self, commands: &CommandConfigMap, path_prefix: &str) {
+        let mut names: Vec<&String> = commands
+            .iter()
+            .map(|(key, command_config)| command_config.name.as_ref().unwrap_or(key))
+            .collect();
+        names.sort();
+        names.dedup();
+
+        for name in names {
+            let mut keys: Vec<&String> = commands
+                .iter()
+                .filter(|(key, command_config)| command_config.name.as_ref().unwrap_or(key) == name)
+                .map(|(key, _)| key)
+                .collect();
+            keys.sort();
+
+            let platforms: Vec<&OneOrManyPlatforms> = keys
+                .iter()
+                .filter_map(|key| commands[*key].platform.as_ref())
+                .collect();
+
+            // Commands available on every platform don't need counterparts
+            if platforms.len() < keys.len() {
+                continue;
+            }
+
+            let missing: Vec<String> = COMMON_PLATFORMS
+                .iter()
+                .filter(|platform| {
+                    !platforms
+                        .iter()
+                        .any(|platforms| is_current_platform((*platform).clone(), platforms))
+                })
+                .map(|platform| format!("{:?}", platform))
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+
+            self.report(
+                "missing_platform_counterparts",
+                self.options.lint.missing_platform_counterparts,
+                &format!("{}commands.{}", path_prefix, keys[0]),
+                format!(
+                    "command \"{}\" isn't available on {}",
+                    name,
+                    missing.join(", ")
+                ),
+            );
+        }
+    }
+}
+
+/// Returns `true` if `text` references the variable with the provided name (e.g. `$name`).
+fn references(text: &str, name: &str) -> bool {
+    let reference = format!("${}", name);
+    text.match_indices(&reference).any(|(index, _)| {
+        !text[index + reference.len()..]
+            .chars()
+            .next()
+            .is_some_and(|next| next.is_alphanumeric() || next == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn lint_reports_findings() {
+        // Arrange
+        let yaml = "variables:
+    region: us-east-1
+    unused: value
+commands:
+    clean:
+        description: Remove build artifacts
+        variables:
+            dir: ./target
+        action: rm -rf $dir
+    build-win:
+        name: build
+        description: Build the project
+        platform: Windows
+        action: ./build.ps1
+    test:
+        action: cargo test --region $region";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let findings: Vec<String> = lint(&config)
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect();
+
+        // Assert
+        assert_eq!(
+            findings,
+            vec![
+                "variables.unused: variable is never referenced as $unused (unused_variables)",
+                "commands.build-win: command \"build\" isn't available on Linux, MacOS (missing_platform_counterparts)",
+                "commands.clean: action runs \"rm -rf\" (dangerous_commands)",
+                "commands.test: command has no description (missing_descriptions)",
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_skips_rules_that_are_off() {
+        // Arrange
+        let yaml = "options:
+    lint:
+        missing_descriptions: off
+        unused_variables: error
+variables:
+    unused: value
+commands:
+    test:
+        action: cargo test";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let findings = lint(&config);
+
+        // Assert
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "unused_variables");
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn references_requires_whole_name() {
+        assert!(references("echo $name!", "name"));
+        assert!(!references("echo $names", "name"));
+        assert!(!references("echo name", "name"));
+    }
+}
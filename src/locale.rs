@@ -0,0 +1,144 @@
+use linked_hash_map::LinkedHashMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::env;
+
+/// The name of the environment variable used to choose the language of descriptions and prompts,
+/// overriding the system locale.
+pub const LANGUAGE_VARIABLE_NAME: &str = "PLZ_LANG";
+
+/// The environment variables the system locale is read from, in order of precedence.
+const LOCALE_VARIABLE_NAMES: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+/// The language used when a text doesn't have a translation for the current language.
+const FALLBACK_LANGUAGE: &str = "en";
+
+/// Text that can either be written in a single language, or translated into several languages
+/// keyed by locale (e.g. `en`, `ja`, or `pt-BR`).
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum LocalizedText {
+    Text(String),
+    Localized(#[schemars(with = "HashMap<String, String>")] LinkedHashMap<String, String>),
+}
+
+impl LocalizedText {
+    /// Returns the text for the provided language.
+    /// If there isn't a translation for the language, the English translation is used, followed
+    /// by the first translation.
+    pub fn localize(self, language: Option<&str>) -> String {
+        let translations = match self {
+            LocalizedText::Text(text) => return text,
+            LocalizedText::Localized(translations) => translations,
+        };
+
+        let find = |locale: &str| {
+            translations
+                .iter()
+                .find(|(key, _)| normalize(key) == locale)
+                .map(|(_, text)| text.clone())
+        };
+
+        let language = language.map(normalize);
+        let primary_language = language
+            .as_deref()
+            .and_then(|language| language.split('-').next());
+        language
+            .as_deref()
+            .and_then(find)
+            .or_else(|| primary_language.and_then(find))
+            .or_else(|| find(FALLBACK_LANGUAGE))
+            .or_else(|| translations.values().next().cloned())
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the language descriptions and prompts should be shown in (e.g. `ja` or `pt_BR`), from
+/// `PLZ_LANG` or the system locale.
+pub fn current_language(get_env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    [LANGUAGE_VARIABLE_NAME]
+        .into_iter()
+        .chain(LOCALE_VARIABLE_NAMES)
+        .filter_map(get_env)
+        // Strip the encoding and modifier (e.g. `ja_JP.UTF-8` or `de_DE@euro`)
+        .map(|locale| {
+            locale
+                .split(['.', '@'])
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .find(|locale| !locale.is_empty() && locale != "C" && locale != "POSIX")
+}
+
+/// Normalizes a locale so `ja_JP`, `ja-jp`, and `ja-JP` are treated the same.
+fn normalize(locale: &str) -> String {
+    locale.replace('_', "-").to_lowercase()
+}
+
+/// Deserializes a [`LocalizedText`], selecting the text for the current language.
+pub fn deserialize_localized<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = LocalizedText::deserialize(deserializer)?;
+    Ok(text.localize(current_language(|name| env::var(name).ok()).as_deref()))
+}
+
+/// Deserializes an optional [`LocalizedText`], selecting the text for the current language.
+pub fn deserialize_optional_localized<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = Option::<LocalizedText>::deserialize(deserializer)?;
+    let language = current_language(|name| env::var(name).ok());
+    Ok(text.map(|text| text.localize(language.as_deref())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translations() -> LocalizedText {
+        let mut translations = LinkedHashMap::new();
+        translations.insert("de".to_string(), "Das Projekt bauen".to_string());
+        translations.insert("en".to_string(), "Build the project".to_string());
+        translations.insert("ja".to_string(), "プロジェクトをビルドする".to_string());
+        translations.insert("pt_BR".to_string(), "Compilar o projeto".to_string());
+        LocalizedText::Localized(translations)
+    }
+
+    #[test]
+    fn localize_falls_back_to_language_then_english() {
+        assert_eq!(
+            translations().localize(Some("ja-JP")),
+            "プロジェクトをビルドする"
+        );
+        assert_eq!(translations().localize(Some("pt-br")), "Compilar o projeto");
+        assert_eq!(translations().localize(Some("fr")), "Build the project");
+        assert_eq!(translations().localize(None), "Build the project");
+        assert_eq!(
+            LocalizedText::Text("Build".to_string()).localize(Some("ja")),
+            "Build"
+        );
+    }
+
+    #[test]
+    fn current_language_prefers_plz_lang_over_system_locale() {
+        let get_env = |plz_lang: Option<&'static str>| {
+            move |name: &str| match name {
+                LANGUAGE_VARIABLE_NAME => plz_lang.map(str::to_string),
+                "LANG" => Some("ja_JP.UTF-8".to_string()),
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            current_language(get_env(Some("de"))),
+            Some("de".to_string())
+        );
+        assert_eq!(current_language(get_env(None)), Some("ja_JP".to_string()));
+        assert_eq!(current_language(|_| Some("C".to_string())), None);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::duration;
+use crate::state::seconds_since_epoch;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, process, thread};
+use thiserror::Error;
+
+/// The name of the directory (within the state directory) where lock files are stored.
+const LOCKS_DIR_NAME: &str = "locks";
+
+/// An exclusive lock on a command, preventing it from being executed by multiple processes at
+/// the same time. The lock is released when this is dropped, or when the process exits.
+pub struct CommandLock {
+    _file: File,
+}
+
+/// The frames of the spinner displayed while waiting for a lock.
+const SPINNER_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+
+/// How often to retry acquiring a lock while waiting for it.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Attempts to acquire the lock for the provided command.
+/// Lock files are stored in the provided state directory, so locks are scoped to the config file.
+///
+/// If `wait` is provided and the lock is held by another process, this will wait up to `wait` for
+/// the lock to be released before giving up.
+pub fn acquire(
+    state_dir: &Path,
+    command: &str,
+    wait: Option<&Duration>,
+) -> Result<CommandLock, LockError> {
+    let locks_dir = locks_dir(state_dir);
+    fs::create_dir_all(&locks_dir).map_err(LockError::IO)?;
+
+    let lock_file_path = locks_dir.join(format!("{}.lock", lock_file_name(command)));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_file_path)
+        .map_err(LockError::IO)?;
+
+    let started = Instant::now();
+    let show_spinner = io::stderr().is_terminal();
+    let mut frame = 0;
+
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(fs::TryLockError::WouldBlock) => {}
+            Err(fs::TryLockError::Error(err)) => return Err(LockError::IO(err)),
+        }
+
+        let holder = read_holder(&mut file).unwrap_or("another process".to_string());
+
+        let should_wait = wait.is_some_and(|wait| started.elapsed() < *wait);
+        if !should_wait {
+            if show_spinner && frame > 0 {
+                eprintln!();
+            }
+
+            return Err(LockError::Held {
+                command: command.to_string(),
+                holder,
+            });
+        }
+
+        if show_spinner {
+            eprint!(
+                "\r{} Waiting for \"{}\" (held by {})",
+                SPINNER_FRAMES[frame % SPINNER_FRAMES.len()],
+                command,
+                holder
+            );
+            frame += 1;
+        }
+
+        thread::sleep(RETRY_INTERVAL);
+    }
+
+    // Clear the spinner
+    if show_spinner && frame > 0 {
+        eprint!("\r\x1b[2K");
+    }
+
+    // Record who holds the lock so other processes can report it
+    let now = seconds_since_epoch(SystemTime::now());
+    file.set_len(0)
+        .and_then(|_| file.write_all(format!("{}\n{}\n", process::id(), now).as_bytes()))
+        .map_err(LockError::IO)?;
+
+    Ok(CommandLock { _file: file })
+}
+
+/// Returns the directory lock files are stored in within the provided state directory.
+pub fn locks_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join(LOCKS_DIR_NAME)
+}
+
+/// Removes the lock files in the provided locks directory that aren't held by another process.
+pub fn remove_released(locks_dir: &Path) -> Result<(), io::Error> {
+    for entry in fs::read_dir(locks_dir)? {
+        let path = entry?.path();
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        if file.try_lock().is_err() {
+            continue;
+        }
+
+        // The file is closed first, since open files can't be removed on Windows
+        drop(file);
+        fs::remove_file(&path)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the PID and acquisition time written by the process holding the lock.
+fn read_holder(file: &mut File) -> Option<String> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+
+    let mut lines = content.lines();
+    let pid = lines.next()?;
+    let acquired: u64 = lines.next()?.parse().ok()?;
+
+    let now = seconds_since_epoch(SystemTime::now());
+    let held_for = Duration::from_secs(now.saturating_sub(acquired));
+
+    Some(format!(
+        "PID {} since {} ago",
+        pid,
+        duration::format(&held_for)
+    ))
+}
+
+/// Converts a command path (e.g. `db migrate`) into a name that's safe to use as a file name.
+/// Characters other than ASCII letters, digits, `-`, and `_` are percent-encoded (e.g.
+/// `db%20migrate`), so different commands never share a lock file.
+fn lock_file_name(command: &str) -> String {
+    let mut name = String::new();
+    for byte in command.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    name
+}
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("\"{command}\" is already running (held by {holder})")]
+    Held { command: String, holder: String },
+
+    #[error("failed to acquire lock")]
+    IO(#[source] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn acquire_fails_while_lock_is_held() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let lock = acquire(dir.path(), "db migrate", None).unwrap();
+
+        // Act
+        let result = acquire(dir.path(), "db migrate", None);
+        let other_command_result = acquire(dir.path(), "db seed", None);
+
+        // Assert
+        match result {
+            Err(LockError::Held { holder, .. }) => {
+                assert!(holder.starts_with(&format!("PID {} since", process::id())))
+            }
+            _ => panic!("expected lock to be held"),
+        }
+        assert!(other_command_result.is_ok());
+
+        drop(lock);
+        assert!(acquire(dir.path(), "db migrate", None).is_ok());
+    }
+
+    #[test]
+    fn acquire_keeps_similar_commands_separate() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let _lock = acquire(dir.path(), "db migrate", None).unwrap();
+
+        // Act
+        let dashed = acquire(dir.path(), "db-migrate", None);
+        let namespaced = acquire(dir.path(), "db:migrate", None);
+
+        // Assert
+        assert!(dashed.is_ok());
+        assert!(namespaced.is_ok());
+        assert_eq!(lock_file_name("db migrate"), "db%20migrate");
+        assert_eq!(lock_file_name("db-migrate"), "db-migrate");
+        assert_eq!(lock_file_name("db:migrate"), "db%3Amigrate");
+        assert_eq!(lock_file_name("déployer"), "d%C3%A9ployer");
+    }
+
+    #[test]
+    fn acquire_waits_for_lock() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let state_dir = dir.path().to_path_buf();
+        let lock = acquire(&state_dir, "db migrate", None).unwrap();
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            drop(lock);
+        });
+
+        // Act
+        let result = acquire(dir.path(), "db migrate", Some(&Duration::from_secs(10)));
+
+        // Assert
+        assert!(result.is_ok());
+        releaser.join().unwrap();
+    }
+
+    #[test]
+    fn acquire_gives_up_after_waiting() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let _lock = acquire(dir.path(), "db migrate", None).unwrap();
+
+        // Act
+        let result = acquire(dir.path(), "db migrate", Some(&Duration::from_millis(200)));
+
+        // Assert
+        assert!(matches!(result, Err(LockError::Held { .. })));
+    }
+}
@@ -1,44 +1,121 @@
-use crate::actions::ActionExecutor;
+use crate::actions::{ActionError, ActionExecutor, ForwardedArgs};
 use crate::args::ClapArgumentResolver;
-use crate::config::ConfigError;
-use crate::exec::create_command_executor;
+use crate::audit::{AuditLog, Receipt};
+use crate::completions::{CompletionsError, InstallChange};
+use crate::config::{
+    CommandConfig, Config, ConfigError, ConfigFormat, PythonEnvConfig, VariableConfigMap,
+};
+use crate::config_tests::{TestCase, TestSpec};
+use crate::defaults::{DefaultsError, DefaultsStore};
+use crate::environment::EnvironmentActivator;
+use crate::exec::{create_command_executor, create_dry_run_command_executor};
+use crate::hook::{HookError, HookShell};
+use crate::migrate::MigrateError;
 use crate::platform::current_platform_provider;
-use crate::prompt::TerminalPromptExecutor;
-use crate::variables::{RealVariableResolver, VariableResolver};
+use crate::prompt::{
+    create_prompt_backend, create_scripted_prompt_backend, InquirePromptBackend, PromptExecutor,
+    ScriptedPromptBackend, TerminalPromptExecutor,
+};
+use crate::state::{FailedRun, State, StateError};
+use crate::step_headers::StepSymbols;
+use crate::storage::StorageKind;
+use crate::trust::{TrustError, TrustStore};
+use crate::variables::{substitute_variables, RealVariableResolver, VariableMap, VariableResolver};
 use anyhow::Result;
-use std::env;
+use chrono::Local;
+use clap::ArgMatches;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::{env, fs, process};
 use thiserror::Error;
 
 mod actions;
 mod args;
+mod audit;
+mod cache;
 mod cli;
+mod clipboard;
+mod command_templates;
+mod completions;
+mod conditions;
 mod config;
+mod config_tests;
+mod defaults;
+mod diff;
+mod dotenv;
+mod duration;
+mod environment;
 mod exec;
+mod executor;
+mod filters;
+mod guard;
+mod hook;
+mod import_lock;
+mod interrupt;
+mod keyring;
+mod lint;
+mod locale;
+mod lock;
+mod mask;
+mod migrate;
 mod platform;
+mod process_group;
 mod prompt;
+mod remote;
+mod schema;
+mod secrets;
+mod services;
+mod snippets;
+mod state;
+mod step_headers;
+mod storage;
+mod template;
+mod terminal_marks;
+mod timings;
+mod tree;
+mod trust;
+mod unknown_fields;
 mod variables;
+mod worktree;
 
 // Ideas:
-// - Preconditions: Specify a list of applications that must be installed, or a custom script that must succeed before running a command
-// - Deferred actions: Always executes at the end, even if one of the actions fails.
-// - Cached variable results: Allow the results of an execution variable to be cached on disk for future use.
-// - Remote commands: Execute commands on a remote machine (Like a mini Ansible)
-// - Container actions: Run an action inside a docker container
-// - Include other config files with a remote link
-// - YAML schema.
+// - Deferred actions: Always executes at the end, even if one of the actions fails (`on_failure` only runs on failure).
 
-fn main() -> Result<()> {
-    let config_result = config::load();
+fn main() {
+    timings::enable_from_args();
+
+    let result = run();
+
+    timings::report();
+
+    // Errors can embed command lines, which can contain sensitive values
+    if let Err(err) = result {
+        eprintln!("Error: {}", mask::mask(&format!("{:?}", err)));
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let config_result = timings::measure("config loading", config::load);
 
     // Offer to create the config file if one doesn't exist
     if let Err(config_err) = config_result {
         return match config_err {
             ConfigError::FileNotFound => {
-                let should_init = inquire::Confirm::new(
+                // The hook is evaluated in every directory, including ones without a config file
+                if let Some(request) = cli::parse_hook_command(&env::args().collect::<Vec<_>>()) {
+                    let binary_name = cli::binary_name(None, env::args_os().next().as_deref());
+                    return print_hook(request, None, &binary_name);
+                }
+
+                let should_init = create_prompt_backend().confirm(
                     "Couldn't find a config file in this directory. Do you want to create one?",
-                )
-                .with_default(true)
-                .prompt()?;
+                    true,
+                )?;
 
                 if !should_init {
                     return Err(config_err.into());
@@ -48,26 +125,354 @@ fn main() -> Result<()> {
                 println!("created {file_name}");
                 return Ok(());
             }
-            _ => Err(config_err.into()),
+            _ => {
+                // Older config files may fail to load, so they can still be migrated
+                let args: Vec<String> = iter::once("plz".to_string())
+                    .chain(config::command_args())
+                    .collect();
+                if let Some(request) = cli::parse_migrate_command(&args) {
+                    if let Some(config_file_path) = config::config_file_path()? {
+                        return migrate_config_file(&config_file_path, request);
+                    }
+                }
+
+                Err(config_err.into())
+            }
         };
     }
 
     let found_config = config_result?;
-    let config = found_config.config;
+    let mut config = found_config.config;
+
+    let args: Vec<String> = env::args().collect();
+
+    // Applied before the option overrides so the command-line and environment variables still win
+    if let Some(environment) = config.selected_environment(&args, |name| env::var(name).ok()) {
+        config.apply_environment(&environment)?;
+    }
+
+    config
+        .options
+        .apply_overrides(&args, |name| env::var(name).ok())?;
+
+    // Resolved up front so a renamed or symlinked binary is referred to by its own name
+    let binary_name = cli::binary_name(
+        config.options.binary_name.as_deref(),
+        env::args_os().next().as_deref(),
+    );
+    config.options.binary_name = Some(binary_name.clone());
+
+    // Applied before the arguments are created so the saved defaults are used as the argument
+    // defaults, and still lose to arguments that are provided
+    if let config::Source::File(config_file_path) = &found_config.source {
+        apply_saved_defaults(&mut config, config_file_path)?;
+    }
+
+    let config_file_name = match &found_config.source {
+        config::Source::File(config_file_path) => config_file_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        config::Source::Stdin => "<stdin>".to_string(),
+        config::Source::Inline => format!("<{}>", config::INLINE_CONFIG_VARIABLE_NAME),
+    };
 
     // Change the current working directory to the directory that the config file came from.
-    if let config::Source::File(config_file_path) = found_config.source {
+    let invocation_dir = env::current_dir()?;
+
+    // Nested plz processes (e.g. for delegate actions) need to use the same config files and
+    // environment, and the config file paths are relative to where plz was invoked
+    let forwarded_args = ForwardedArgs {
+        config_paths: config::config_path_args(&args)
+            .iter()
+            .map(|config_path| invocation_dir.join(config_path))
+            .collect(),
+        environment: config.selected_environment(&args, |_| None),
+    };
+    if let config::Source::File(config_file_path) = &found_config.source {
         if let Some(parent_directory) = config_file_path.parent() {
             env::set_current_dir(parent_directory)?;
         }
     }
 
+    // The failed command is executed by another plz process, so the config file, environment, and
+    // arguments are resolved the same way they were the first time
+    if state::retry_requested(&args) {
+        return retry_failed(&binary_name);
+    }
+
     let platform_provider = current_platform_provider();
 
-    let root_command = cli::create_root_command(&config, &platform_provider);
+    let root_command = timings::measure("clap tree construction", || {
+        cli::create_root_command(&config, &platform_provider)
+    });
 
     // This will exit on any match failures
-    let arg_matches = root_command.clone().get_matches();
+    let arg_matches = timings::measure("argument parsing", || {
+        root_command.clone().try_get_matches()
+    })
+    .unwrap_or_else(|err| {
+        timings::report();
+        err.exit()
+    });
+
+    // The built-in tree command prints the available commands
+    if let Some(show_variables) = cli::find_tree_command(&arg_matches) {
+        let platform = platform_provider.get_platform();
+        print!(
+            "{}",
+            tree::render(&config, &config_file_name, &platform, show_variables)
+        );
+        return Ok(());
+    }
+
+    // The built-in lint command checks the config for common problems
+    if cli::find_lint_command(&arg_matches, &config.commands) {
+        lint::report(&lint::lint(&config))?;
+        return Ok(());
+    }
+
+    // The built-in schema command prints a JSON Schema for the config file
+    if cli::find_schema_command(&arg_matches, &config.commands) {
+        println!("{}", schema::generate());
+        return Ok(());
+    }
+
+    // The built-in completions command prints or installs shell completions
+    if let Some(request) = cli::find_completions_command(&arg_matches) {
+        let shell = request
+            .shell
+            .or_else(|| completions::detect_shell(|name| env::var(name).ok()))
+            .ok_or(CompletionsError::UnknownShell)?;
+
+        if !request.install {
+            print!(
+                "{}",
+                completions::generate(shell, &mut root_command.clone())
+            );
+            return Ok(());
+        }
+
+        match completions::install(shell, &binary_name, |name| env::var(name).ok())? {
+            InstallChange::Wrote(path) => println!("wrote {}", path.display()),
+            InstallChange::Appended(path) => println!("updated {}", path.display()),
+            InstallChange::Unchanged(path) => {
+                println!("completions are already installed in {}", path.display());
+                return Ok(());
+            }
+        }
+        println!("restart your shell to load completions for {binary_name}");
+        return Ok(());
+    }
+
+    // The built-in cache command manages the data plz stores on disk
+    if let Some(request) = cli::find_cache_command(&arg_matches, &config.commands) {
+        let state_dir = env::current_dir()?.join(state::STATE_DIR_NAME);
+        let kinds = match &request {
+            cli::CacheRequest::Clean(kinds) => kinds.clone(),
+            _ => StorageKind::ALL.to_vec(),
+        };
+
+        for kind in kinds {
+            let Some(path) = kind.path(&state_dir) else {
+                continue;
+            };
+
+            match request {
+                cli::CacheRequest::Usage => println!(
+                    "{:<9} {:>10}  {}",
+                    kind.name(),
+                    storage::format_size(storage::disk_usage(&path)),
+                    path.display()
+                ),
+                cli::CacheRequest::Paths => println!("{:<9} {}", kind.name(), path.display()),
+                cli::CacheRequest::Clean(_) => {
+                    let freed = storage::clean(kind, &path)?;
+                    println!("{:<9} removed {}", kind.name(), storage::format_size(freed));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    // The built-in update command locks the latest versions of remote imports, which were
+    // resolved while loading the config
+    if cli::find_update_command(&arg_matches, &config.commands) {
+        if import_lock::save(true)? {
+            println!("updated {}", import_lock::LOCK_FILE_NAME);
+        } else {
+            println!("{} is up to date", import_lock::LOCK_FILE_NAME);
+        }
+        return Ok(());
+    }
+
+    // The built-in hook command prints the shell hook, or the commands it defines functions for
+    if let Some(request) = cli::find_hook_command(&arg_matches, &config.commands) {
+        let platform = platform_provider.get_platform();
+        return print_hook(
+            request,
+            Some(hook::command_names(&config, &platform)),
+            &binary_name,
+        );
+    }
+
+    // The built-in test-config command checks the commands invocations resolve to against a spec.
+    // Nothing is executed, so the config doesn't need to be trusted.
+    if let Some(spec_path) = cli::find_test_config_command(&arg_matches, &config.commands) {
+        // Relative to where plz was invoked from, since the working directory is now the config
+        // file's directory
+        let spec_path = match spec_path {
+            Some(spec_path) => invocation_dir.join(spec_path),
+            None => env::current_dir()?.join(config_tests::DEFAULT_SPEC_FILE_NAME),
+        };
+        let spec = TestSpec::load(&spec_path)?;
+        config_tests::run(&spec, |case| {
+            // Only the first line is used, since argument parsing errors include the usage
+            resolve_test_case(&config, &root_command, case).map_err(|err| {
+                let message = format!("{:#}", err);
+                message.lines().next().unwrap_or_default().to_string()
+            })
+        })?;
+        return Ok(());
+    }
+
+    // The built-in migrate command updates the config file to the current version of the format
+    if let Some(request) = cli::find_migrate_command(&arg_matches, &config.commands) {
+        let config::Source::File(config_file_path) = &found_config.source else {
+            return Err(MigrateError::Stdin.into());
+        };
+
+        return migrate_config_file(config_file_path, request);
+    }
+
+    // The built-in config command manages the defaults saved by the user
+    if let Some(request) = cli::find_config_command(&arg_matches, &config.commands) {
+        let config::Source::File(config_file_path) = &found_config.source else {
+            return Err(DefaultsError::NoConfigFile.into());
+        };
+
+        let store_path = defaults::store_path(|name| env::var(name).ok())
+            .ok_or(DefaultsError::NoHomeDirectory)?;
+        let mut store = DefaultsStore::load(&store_path)?;
+        match request {
+            cli::ConfigRequest::Set { key, value } => {
+                // Make sure the default would actually be used before saving it
+                config.clone().apply_default(&key, &value)?;
+                store.set(config_file_path, &key, &value);
+                store.save(&store_path)?;
+            }
+            cli::ConfigRequest::Unset { key } => {
+                if !store.unset(config_file_path, &key) {
+                    return Err(DefaultsError::NotSet { key }.into());
+                }
+                store.save(&store_path)?;
+            }
+            cli::ConfigRequest::List => {
+                for (key, value) in store.get(config_file_path) {
+                    println!("{key}: {value}");
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Config files need to be trusted before anything is executed, since execution variables and
+    // handlers run without the user asking for them
+    if let config::Source::File(config_file_path) = &found_config.source {
+        if !config.options.inspect && !arg_matches.get_flag(cli::TRUST_ARG_NAME) {
+            ensure_trusted(
+                &config,
+                config_file_path,
+                &found_config.text,
+                &found_config.imports,
+            )?;
+        }
+    }
+
+    // The built-in env command prints the environment of another command instead of executing it
+    if let Some((target, format)) = cli::find_env_command(&arg_matches) {
+        let target_arg_matches = root_command
+            .clone()
+            .try_get_matches_from(iter::once(root_command.get_name().to_string()).chain(target))
+            .unwrap_or_else(|err| err.exit());
+
+        let (target_command, available_variable_configs, sucbommand_arg_matches) =
+            cli::find_subcommand(
+                &target_arg_matches,
+                &root_command,
+                &config.commands,
+                &config.variables,
+            )
+            .ok_or(CommandError::CommandNotFound)?;
+
+        let env_file_variables = load_env_files(&config, &target_arg_matches)?;
+        let variables = resolve_variables(
+            &config,
+            &required_variable_configs(&config, target_command, available_variable_configs),
+            Arc::new(sucbommand_arg_matches.clone()),
+            env_file_variables,
+        )?;
+        let environment = activate_environment(&config, target_command, variables)?;
+
+        match format {
+            cli::EnvFormat::Dotenv => print!("{}", dotenv::to_string(&environment)),
+            cli::EnvFormat::Json => {
+                let sorted: BTreeMap<_, _> = environment.iter().collect();
+                println!("{}", serde_json::to_string_pretty(&sorted)?);
+            }
+        }
+
+        return Ok(());
+    }
+
+    // The built-in copy command copies the command text of another command instead of executing it
+    if let Some(target) = cli::find_copy_command(&arg_matches) {
+        let target_arg_matches = root_command
+            .clone()
+            .try_get_matches_from(iter::once(root_command.get_name().to_string()).chain(target))
+            .unwrap_or_else(|err| err.exit());
+
+        let (target_command, available_variable_configs, sucbommand_arg_matches) =
+            cli::find_subcommand(
+                &target_arg_matches,
+                &root_command,
+                &config.commands,
+                &config.variables,
+            )
+            .ok_or(CommandError::CommandNotFound)?;
+        let command_action = target_command
+            .action
+            .as_ref()
+            .ok_or(CommandError::NoAction)?;
+
+        let env_file_variables = load_env_files(&config, &target_arg_matches)?;
+        let sucbommand_arg_matches = Arc::new(sucbommand_arg_matches.clone());
+        let variables = resolve_variables(
+            &config,
+            &required_variable_configs(&config, target_command, available_variable_configs),
+            sucbommand_arg_matches.clone(),
+            env_file_variables,
+        )?;
+
+        let action_executor = ActionExecutor {
+            command_executor: create_command_executor(&config.options),
+            arg_resolver: Box::new(ClapArgumentResolver::from_arg_matches(
+                sucbommand_arg_matches,
+            )),
+            step_symbols: None,
+            binary_name: binary_name.clone(),
+            forwarded_args: forwarded_args.clone(),
+        };
+        let command_text = action_executor.command_text(command_action, &variables);
+
+        // Printed first so the command text is still available if the clipboard isn't
+        println!("{}", mask::mask(&command_text));
+        clipboard::copy(&command_text)?;
+        eprintln!("copied to the clipboard");
+        return Ok(());
+    }
 
     // Otherwise, look for a configured command
     let find_result = cli::find_subcommand(
@@ -79,28 +484,230 @@ fn main() -> Result<()> {
 
     if let Some((target_command, available_variable_configs, sucbommand_arg_matches)) = find_result
     {
-        if let Some(command_action) = target_command.action {
+        if let Some(command_action) = &target_command.action {
+            let command_path = cli::command_path(&arg_matches).join(" ");
+            let state_dir = env::current_dir()?.join(state::STATE_DIR_NAME);
+
+            // Held until the process exits.
+            // Acquired before anything else, so a second process fails (or waits) before it
+            // resolves variables or prompts for anything.
+            let lock_wait = target_command
+                .lock_wait
+                .as_ref()
+                .map(|lock_wait| duration::parse(lock_wait))
+                .transpose()?;
+            let _lock = if (target_command.lock || lock_wait.is_some()) && !config.options.inspect {
+                Some(lock::acquire(
+                    &state_dir,
+                    &command_path,
+                    lock_wait.as_ref(),
+                )?)
+            } else {
+                None
+            };
+
+            // Refuse to execute the command if it was executed too recently
+            let cooldown = target_command
+                .cooldown
+                .as_ref()
+                .map(|cooldown| duration::parse(cooldown))
+                .transpose()?;
+            if let Some(cooldown) = &cooldown {
+                if !sucbommand_arg_matches.get_flag(cli::FORCE_ARG_NAME) {
+                    State::load(&state_dir)?.check_cooldown(
+                        &command_path,
+                        cooldown,
+                        SystemTime::now(),
+                    )?;
+                }
+            }
+
+            // Refuse to execute the command if the working tree has uncommitted changes.
+            // Checked before any variables are resolved, so nobody answers prompts for nothing.
+            if target_command.require_clean_git
+                && !config.options.inspect
+                && !sucbommand_arg_matches.get_flag(cli::ALLOW_DIRTY_ARG_NAME)
+            {
+                worktree::check_clean(&env::current_dir()?)?;
+            }
+
+            // Refuse to execute the command if its guard doesn't allow it right now
+            if let Some(guard_config) = &target_command.guard {
+                if !config.options.inspect
+                    && !sucbommand_arg_matches.get_flag(cli::IGNORE_GUARD_ARG_NAME)
+                {
+                    guard::check(guard_config, Local::now().naive_local(), |name| {
+                        env::var(name).ok()
+                    })?;
+                }
+            }
+
             // Set up the dependencies
-            let arg_resolver = ClapArgumentResolver::from_arg_matches(&sucbommand_arg_matches);
-            let variable_resolver = RealVariableResolver {
-                command_executor: create_command_executor(&config.options),
-                prompt_executor: Box::new(TerminalPromptExecutor::new(create_command_executor(
+            // Shared between the variable resolver and the action executor
+            let sucbommand_arg_matches = Arc::new(sucbommand_arg_matches.clone());
+
+            let available_variable_configs =
+                required_variable_configs(&config, target_command, available_variable_configs);
+            let env_file_variables = load_env_files(&config, &arg_matches)?;
+            let variables = resolve_variables(
+                &config,
+                &available_variable_configs,
+                sucbommand_arg_matches.clone(),
+                env_file_variables,
+            )?;
+
+            // Nothing is written or recorded when inspecting, the action only prints what it would
+            // have executed
+            if config.options.inspect {
+                let action_executor = ActionExecutor {
+                    command_executor: create_command_executor(&config.options),
+                    arg_resolver: Box::new(ClapArgumentResolver::from_arg_matches(
+                        sucbommand_arg_matches,
+                    )),
+                    step_symbols: None,
+                    binary_name: binary_name.clone(),
+                    forwarded_args,
+                };
+                action_executor.execute_dependencies(&target_command.depends_on, &variables)?;
+                action_executor.execute(command_action, &variables)?;
+                return Ok(());
+            }
+
+            if let Some(save_env_config) = &target_command.save_env {
+                dotenv::save(
+                    save_env_config,
+                    &available_variable_configs,
+                    &variables,
                     &config.options,
-                ))),
-                argument_resolver: Box::new(arg_resolver),
-                options: config.options.clone(),
+                )?;
+            }
+
+            let variables = activate_environment(&config, target_command, variables)?;
+
+            // Receipts are written once the command finishes, but the log is opened beforehand so
+            // a command is never executed without a receipt
+            let mut audit_log = config
+                .options
+                .audit_log
+                .as_ref()
+                .map(|path| AuditLog::open(Path::new(path)))
+                .transpose()?;
+            let mut record_receipt = |exit_code: Option<i32>| -> Result<()> {
+                if let Some(audit_log) = &mut audit_log {
+                    let args: Vec<String> = env::args().skip(1).collect();
+                    audit_log.record(&Receipt::new(command_path.clone(), &args, exit_code))?;
+                }
+
+                Ok(())
             };
 
-            let variables = variable_resolver.resolve_variables(&available_variable_configs)?;
+            // The command's output can be written to files instead of the terminal
+            let command_executor = create_command_executor(&config.options);
+            let command_executor =
+                if target_command.stdout_file.is_none() && target_command.stderr_file.is_none() {
+                    command_executor
+                } else {
+                    actions::redirect_output(
+                        command_executor.as_ref(),
+                        target_command.stdout_file.as_deref(),
+                        target_command.stderr_file.as_deref(),
+                        &variables,
+                    )?
+                };
 
             let action_executor = ActionExecutor {
-                command_executor: create_command_executor(&config.options),
+                command_executor,
                 arg_resolver: Box::new(ClapArgumentResolver::from_arg_matches(
-                    &sucbommand_arg_matches,
+                    sucbommand_arg_matches,
                 )),
+                step_symbols: StepSymbols::from_options(&config.options),
+                binary_name: binary_name.clone(),
+                forwarded_args,
+            };
+
+            if target_command.on_interrupt.is_some() {
+                interrupt::install_handler()?;
+            }
+
+            if config.options.terminal_marks {
+                terminal_marks::command_started();
+            }
+
+            let result = timings::measure("execution", || {
+                action_executor
+                    .execute_dependencies(&target_command.depends_on, &variables)
+                    .and_then(|_| action_executor.execute(command_action, &variables))
+            });
+
+            let exit_code = match &result {
+                Ok(_) if interrupt::is_interrupted() => Some(interrupt::INTERRUPTED_EXIT_CODE),
+                Ok(_) => Some(0),
+                Err(ActionError::Interrupted { .. }) => Some(interrupt::INTERRUPTED_EXIT_CODE),
+                Err(action_err) => action_err.exit_code(),
             };
+            if config.options.terminal_marks {
+                // Errors without an exit code (e.g. a command that couldn't be started) still fail
+                terminal_marks::command_finished(exit_code.unwrap_or(1));
+            }
+            record_receipt(exit_code)?;
+
+            if let Some(exit_code) = action_executor
+                .execute_on_interrupt(target_command.on_interrupt.as_ref(), &variables)?
+            {
+                process::exit(exit_code);
+            }
+
+            if let Err(action_err) = result {
+                if let Some(on_failure) = &target_command.on_failure {
+                    action_executor.execute_on_failure(
+                        on_failure,
+                        command_action,
+                        &action_err,
+                        &variables,
+                    )?;
+                }
+
+                if let Some(on_failure_message) = &target_command.on_failure_message {
+                    let failure_variables =
+                        actions::failure_variables(command_action, &action_err, &variables);
+                    eprintln!(
+                        "{}",
+                        mask::mask(&substitute_variables(
+                            on_failure_message,
+                            &failure_variables
+                        ))
+                    );
+                }
+
+                // Failing to record the failure shouldn't hide the action's error
+                if let Ok(mut state) = State::load(&state_dir) {
+                    state.record_failure(FailedRun {
+                        command: command_path.clone(),
+                        dir: invocation_dir,
+                        args: args.iter().skip(1).cloned().collect(),
+                    });
+                    let _ = state.save(&state_dir);
+                }
+
+                return Err(action_err.into());
+            }
+
+            if let Some(on_success_message) = &target_command.on_success_message {
+                println!(
+                    "{}",
+                    mask::mask(&substitute_variables(on_success_message, &variables))
+                );
+            }
+
+            let mut state = State::load(&state_dir)?;
+            let retried = state.clear_failure(&command_path);
+            if cooldown.is_some() {
+                state.record_execution(&command_path, SystemTime::now());
+            }
+            if retried || cooldown.is_some() {
+                state.save(&state_dir)?;
+            }
 
-            action_executor.execute(&command_action, &variables)?;
             return Ok(());
         }
     }
@@ -108,8 +715,358 @@ fn main() -> Result<()> {
     Err(CommandError::CommandNotFound.into())
 }
 
+/// Executes the last command that failed again, exiting with its exit code.
+fn retry_failed(binary_name: &str) -> Result<()> {
+    let state_dir = env::current_dir()?.join(state::STATE_DIR_NAME);
+    let failed_run = State::load(&state_dir)?
+        .failed_run
+        .ok_or(StateError::NoFailedRun)?;
+
+    eprintln!(
+        "{}",
+        format!("Retrying: {} {}", binary_name, failed_run.args.join(" ")).dimmed()
+    );
+    let status = process::Command::new(env::current_exe()?)
+        .args(&failed_run.args)
+        .current_dir(&failed_run.dir)
+        .status()?;
+
+    process::exit(status.code().unwrap_or(1));
+}
+
+/// Prints the shell hook for the requested shell, or the names of the commands the hook should
+/// define functions for if they were requested.
+/// `command_names` is `None` if there isn't a config file in the current directory.
+fn print_hook(
+    request: cli::HookRequest,
+    command_names: Option<Vec<String>>,
+    binary_name: &str,
+) -> Result<()> {
+    if request.list_commands {
+        for name in command_names.unwrap_or_default() {
+            println!("{name}");
+        }
+        return Ok(());
+    }
+
+    let shell = match request.shell {
+        Some(shell) => shell,
+        None => {
+            let shell = completions::detect_shell(|name| env::var(name).ok())
+                .ok_or(HookError::UnknownShell)?;
+            HookShell::from_shell(shell).ok_or(HookError::UnsupportedShell { shell })?
+        }
+    };
+
+    print!("{}", hook::generate(shell, binary_name));
+    Ok(())
+}
+
+/// Overrides the default values of variables with the defaults the user has saved for the
+/// provided config file.
+/// Defaults that no longer match a variable (e.g. because the config file changed) are skipped.
+fn apply_saved_defaults(config: &mut Config, config_file_path: &Path) -> Result<()> {
+    let Some(store_path) = defaults::store_path(|name| env::var(name).ok()) else {
+        return Ok(());
+    };
+
+    let store = DefaultsStore::load(&store_path)?;
+    for (key, value) in store.get(config_file_path) {
+        if let Err(err) = config.apply_default(key, value) {
+            eprintln!(
+                "{} {}, ignoring saved default",
+                "warning:".yellow().bold(),
+                err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks the user whether they trust the provided config file if it executes anything implicitly,
+/// and it hasn't been trusted before (or has changed since it was trusted).
+fn ensure_trusted(
+    config: &Config,
+    config_file_path: &Path,
+    config_text: &str,
+    imports: &[PathBuf],
+) -> Result<()> {
+    let executions = trust::implicit_executions(config);
+    if executions.is_empty() {
+        return Ok(());
+    }
+
+    let store_path =
+        trust::store_path(|name| env::var(name).ok()).ok_or(TrustError::NoHomeDirectory)?;
+    let mut store = TrustStore::load(&store_path)?;
+    if store.is_trusted(config_file_path, config_text, imports) {
+        return Ok(());
+    }
+
+    let path = config_file_path.display().to_string();
+    eprintln!("{path} executes the following without being asked to:");
+    for execution in &executions {
+        eprintln!("  {execution}");
+    }
+
+    if !create_prompt_backend().confirm("Do you trust this config?", false)? {
+        return Err(TrustError::NotTrusted { path }.into());
+    }
+
+    store.trust(config_file_path, config_text, imports);
+    store.save(&store_path)?;
+    Ok(())
+}
+
+/// Migrates the config file at the provided path to the current version of the config format.
+/// The changes are shown before they're written, and the user is asked to confirm them unless
+/// `--yes` was passed.
+fn migrate_config_file(path: &Path, request: cli::MigrateRequest) -> Result<()> {
+    let text = fs::read_to_string(path)?;
+    let Some(migrated) = migrate::migrate(&text, &ConfigFormat::from_path(path))? else {
+        println!("{} is up to date", path.display());
+        return Ok(());
+    };
+
+    // Config files without a version are treated as the first version, so they're only checked
+    // for migrations that need to be applied
+    if request.check {
+        if migrated.applied.is_empty() {
+            println!("{} is up to date", path.display());
+            return Ok(());
+        }
+
+        print!(
+            "{}",
+            diff::format(&text, &migrated.text, &path.display().to_string())
+        );
+        return Err(MigrateError::Outdated {
+            version: migrated.from,
+        }
+        .into());
+    }
+
+    print!(
+        "{}",
+        diff::format(&text, &migrated.text, &path.display().to_string())
+    );
+    if !request.yes
+        && !create_prompt_backend()
+            .confirm(&format!("Write the changes to {}?", path.display()), true)?
+    {
+        println!("{} was left unchanged", path.display());
+        return Ok(());
+    }
+
+    fs::write(path, &migrated.text)?;
+    if migrated.applied.is_empty() {
+        println!(
+            "set the version of {} to {}",
+            path.display(),
+            migrate::CURRENT_VERSION
+        );
+        return Ok(());
+    }
+
+    println!(
+        "migrated {} from version {} to {}",
+        path.display(),
+        migrated.from,
+        migrate::CURRENT_VERSION
+    );
+    for description in migrated.applied {
+        println!("  {}", description);
+    }
+
+    Ok(())
+}
+
+/// Returns the command text the provided [`TestCase`] resolves to, without executing anything.
+/// Execution variables resolve to the command that would have produced them (e.g.
+/// `$(git rev-parse HEAD)`), and prompts are answered with the test case's answers.
+fn resolve_test_case(
+    config: &Config,
+    root_command: &clap::Command,
+    case: &TestCase,
+) -> Result<String> {
+    let arg_matches = root_command
+        .clone()
+        .try_get_matches_from(iter::once(root_command.get_name().to_string()).chain(case.args()))?;
+
+    let (target_command, available_variable_configs, sucbommand_arg_matches) =
+        cli::find_subcommand(
+            &arg_matches,
+            root_command,
+            &config.commands,
+            &config.variables,
+        )
+        .ok_or(CommandError::CommandNotFound)?;
+    let command_action = target_command
+        .action
+        .as_ref()
+        .ok_or(CommandError::NoAction)?;
+
+    // Nothing should be printed while the variables are resolved
+    let mut options = config.options.clone();
+    options.print_variables = false;
+    options.trace_vars = false;
+
+    let sucbommand_arg_matches = Arc::new(sucbommand_arg_matches.clone());
+    let variable_resolver = RealVariableResolver {
+        command_executor: create_dry_run_command_executor(),
+        prompt_executor: Box::new(TerminalPromptExecutor::new(
+            create_dry_run_command_executor(),
+            Box::new(ScriptedPromptBackend::new(case.answers.clone())),
+        )),
+        answer_executor: None,
+        argument_resolver: Box::new(ClapArgumentResolver::from_arg_matches(
+            sucbommand_arg_matches.clone(),
+        )),
+        options,
+        state_dir: None,
+    };
+    let mut variables = load_env_files(config, &arg_matches)?;
+    variables.extend(variable_resolver.resolve_variables(&available_variable_configs)?);
+
+    let action_executor = ActionExecutor {
+        command_executor: create_dry_run_command_executor(),
+        arg_resolver: Box::new(ClapArgumentResolver::from_arg_matches(
+            sucbommand_arg_matches,
+        )),
+        step_symbols: None,
+        binary_name: cli::binary_name(config.options.binary_name.as_deref(), None),
+        forwarded_args: ForwardedArgs::default(),
+    };
+    Ok(action_executor.command_text(command_action, &variables))
+}
+
+/// Loads the dotenv files listed by the config file and by each of the commands matching the
+/// provided `arg_matches`.
+/// The loaded variables are also added to plz's environment, so variables can read them (e.g. with
+/// `from_env`). Variables that are already set in the environment take precedence.
+fn load_env_files(config: &Config, arg_matches: &ArgMatches) -> Result<VariableMap> {
+    let mut env_files = config.env_files.clone();
+    for command_config in cli::find_command_path(arg_matches, &config.commands) {
+        env_files.extend(command_config.env_files.iter().cloned());
+    }
+
+    let mut variables = dotenv::load(&env_files)?;
+    variables.retain(|name, _| env::var_os(name).is_none());
+    for (name, value) in &variables {
+        env::set_var(name, value);
+    }
+
+    Ok(variables)
+}
+
+/// Returns the variables that need to be resolved to execute the provided command.
+/// When the `lazy_vars` option is enabled, execution variables the command doesn't reference are
+/// left out, so their commands aren't executed.
+fn required_variable_configs(
+    config: &Config,
+    command_config: &CommandConfig,
+    variable_configs: VariableConfigMap,
+) -> VariableConfigMap {
+    match config.options.lazy_vars {
+        true => variables::referenced_variables(&variable_configs, command_config, &config.options),
+        false => variable_configs,
+    }
+}
+
+/// Resolves the provided variables.
+/// The built-in variables and the variables loaded from dotenv files are included, but the
+/// resolved variables take precedence.
+fn resolve_variables(
+    config: &Config,
+    variable_configs: &VariableConfigMap,
+    arg_matches: Arc<ArgMatches>,
+    env_file_variables: VariableMap,
+) -> Result<VariableMap> {
+    // Built-in variables are also added to plz's environment, so execution variables can read them
+    let builtin_variables = variables::builtin_variables();
+    for (name, value) in &builtin_variables {
+        env::set_var(name, value);
+    }
+
+    let arg_resolver = ClapArgumentResolver::from_arg_matches(arg_matches);
+    let variable_resolver = RealVariableResolver {
+        command_executor: create_command_executor(&config.options),
+        prompt_executor: Box::new(TerminalPromptExecutor::new(
+            create_command_executor(&config.options),
+            Box::new(InquirePromptBackend {}),
+        )),
+        answer_executor: create_scripted_prompt_backend().map(|prompt_backend| {
+            Box::new(TerminalPromptExecutor::new(
+                create_command_executor(&config.options),
+                prompt_backend,
+            )) as Box<dyn PromptExecutor>
+        }),
+        argument_resolver: Box::new(arg_resolver),
+        options: config.options.clone(),
+        state_dir: Some(env::current_dir()?.join(state::STATE_DIR_NAME)),
+    };
+
+    let variables = timings::measure("variable resolution", || {
+        variable_resolver.resolve_variables(variable_configs)
+    })?;
+
+    let mut all_variables = builtin_variables;
+    all_variables.extend(env_file_variables);
+    all_variables.extend(variables);
+    Ok(all_variables)
+}
+
+/// Applies the environment exported by any environment managers (mise, direnv, etc.) and the
+/// command's Python environment to the provided variables.
+/// Variables defined in the config take precedence over the activated environment.
+fn activate_environment(
+    config: &Config,
+    command_config: &CommandConfig,
+    variables: VariableMap,
+) -> Result<VariableMap> {
+    // Environment managers execute code (e.g. a .envrc file), so they're skipped when inspecting
+    if config.options.inspect
+        || (!config.options.activate_env && command_config.python_env.is_none())
+    {
+        return Ok(variables);
+    }
+
+    let environment_activator = EnvironmentActivator {
+        command_executor: create_command_executor(&config.options),
+    };
+    let current_dir = env::current_dir()?;
+
+    let mut environment = VariableMap::new();
+    if config.options.activate_env {
+        let activated_environment = timings::measure("environment activation", || {
+            environment_activator.activate(&current_dir)
+        })?;
+        environment.extend(activated_environment);
+    }
+
+    if let Some(python_env) = &command_config.python_env {
+        // The path to a venv may reference variables
+        let python_env = match python_env {
+            PythonEnvConfig::Venv(venv_path) => {
+                PythonEnvConfig::Venv(substitute_variables(venv_path, &variables))
+            }
+            python_env => python_env.clone(),
+        };
+        let python_environment =
+            environment_activator.activate_python_env(&python_env, &current_dir, &environment)?;
+        environment.extend(python_environment);
+    }
+
+    environment.extend(variables);
+    Ok(environment)
+}
+
 #[derive(Error, Debug, Clone)]
 enum CommandError {
     #[error("could not find a suitable command")]
     CommandNotFound,
+
+    #[error("the command doesn't have an action")]
+    NoAction,
 }
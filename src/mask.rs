@@ -0,0 +1,51 @@
+use std::sync::Mutex;
+
+/// The text sensitive values are replaced with when they're printed.
+pub const MASK: &str = "***";
+
+/// The values of the sensitive variables resolved by this process.
+static SENSITIVE_VALUES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Registers a sensitive value, so it's masked by [`mask`] from now on.
+pub fn register(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+
+    let mut sensitive_values = SENSITIVE_VALUES.lock().unwrap();
+    if !sensitive_values.iter().any(|existing| existing == value) {
+        sensitive_values.push(value.to_string());
+
+        // Longer values are masked first, so a value containing another one is masked entirely
+        sensitive_values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+    }
+}
+
+/// Replaces every registered sensitive value within the provided text with [`MASK`].
+pub fn mask(text: &str) -> String {
+    SENSITIVE_VALUES
+        .lock()
+        .unwrap()
+        .iter()
+        .fold(text.to_string(), |text, value| text.replace(value, MASK))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_replaces_registered_values() {
+        // Arrange
+        register("mask-test-token");
+        register("mask-test-token-with-suffix");
+        register("");
+
+        // Act
+        let masked =
+            mask("curl -H 'Authorization: mask-test-token' -d mask-test-token-with-suffix");
+
+        // Assert
+        assert_eq!(masked, "curl -H 'Authorization: ***' -d ***");
+    }
+}
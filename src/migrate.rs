@@ -0,0 +1,257 @@
+use crate::config::ConfigFormat;
+use serde_yaml::{Mapping, Value};
+use thiserror::Error;
+
+/// The version of the config format this version of plz uses.
+pub const CURRENT_VERSION: u64 = 1;
+
+/// The name of the field containing the version of the config format a config file uses.
+/// Config files without a version are treated as version 1.
+const VERSION_FIELD: &str = "version";
+
+/// A change to the config format, which rewrites config files from the previous version.
+struct Migration {
+    /// The version the migration rewrites config files to.
+    version: u64,
+
+    /// A description of the change, printed when a config file is migrated.
+    description: &'static str,
+
+    /// Rewrites the config (as a mapping) from the previous version.
+    apply: fn(&mut Mapping),
+}
+
+/// The changes made to the config format, in order.
+/// When a field is renamed or moved, add a migration here and increment [`CURRENT_VERSION`], so
+/// older config files keep working and can be updated with `plz migrate`.
+const MIGRATIONS: &[Migration] = &[];
+
+/// The result of migrating a config file.
+pub struct Migrated {
+    /// The version the config file was migrated from.
+    pub from: u64,
+
+    /// The descriptions of the migrations that were applied.
+    pub applied: Vec<&'static str>,
+
+    /// The migrated config file.
+    pub text: String,
+}
+
+/// Returns the version of the config format the provided config uses.
+pub fn version(config: &Value) -> Result<u64, MigrateError> {
+    match config.get(VERSION_FIELD) {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version > 0)
+            .ok_or_else(|| MigrateError::InvalidVersion {
+                version: format!("{:?}", version),
+            }),
+    }
+}
+
+/// Migrates the provided config to the current version in memory, returning the descriptions of
+/// the migrations that were applied.
+/// Config files using a newer version than this version of plz supports can't be migrated.
+pub fn upgrade(config: &mut Value) -> Result<Vec<&'static str>, MigrateError> {
+    upgrade_with(config, MIGRATIONS, CURRENT_VERSION)
+}
+
+fn upgrade_with(
+    config: &mut Value,
+    migrations: &[Migration],
+    current_version: u64,
+) -> Result<Vec<&'static str>, MigrateError> {
+    let version = version(config)?;
+    if version > current_version {
+        return Err(MigrateError::UnsupportedVersion {
+            version,
+            supported: current_version,
+        });
+    }
+
+    let Some(mapping) = config.as_mapping_mut() else {
+        return Ok(Vec::new());
+    };
+
+    let mut applied = Vec::new();
+    for migration in migrations
+        .iter()
+        .filter(|migration| migration.version > version)
+    {
+        (migration.apply)(mapping);
+        applied.push(migration.description);
+    }
+
+    if !applied.is_empty() {
+        mapping.insert(VERSION_FIELD.into(), current_version.into());
+    }
+
+    Ok(applied)
+}
+
+/// Migrates the provided config file text to the current version, and sets its version.
+/// Returns `None` if the config file is already up to date.
+/// The text is only rewritten if a migration was applied, so comments and formatting are kept
+/// when only the version is set.
+pub fn migrate(text: &str, format: &ConfigFormat) -> Result<Option<Migrated>, MigrateError> {
+    let mut config: Value = match format {
+        ConfigFormat::Yaml => serde_yaml::from_str(text).map_err(MigrateError::ParseFailed)?,
+        ConfigFormat::Json => serde_json::from_str(text).map_err(MigrateError::ParseJsonFailed)?,
+    };
+
+    let has_version = config.get(VERSION_FIELD).is_some();
+    let from = version(&config)?;
+    let applied = upgrade(&mut config)?;
+    if has_version && applied.is_empty() {
+        return Ok(None);
+    }
+
+    let text = match format {
+        ConfigFormat::Yaml if applied.is_empty() => insert_version(text),
+        ConfigFormat::Yaml => {
+            serde_yaml::to_string(&with_version_first(config)).map_err(MigrateError::ParseFailed)?
+        }
+        ConfigFormat::Json => {
+            let text = serde_json::to_string_pretty(&with_version_first(config))
+                .map_err(MigrateError::ParseJsonFailed)?;
+            format!("{}\n", text)
+        }
+    };
+
+    Ok(Some(Migrated {
+        from,
+        applied,
+        text,
+    }))
+}
+
+/// Adds the current version to the top of the provided YAML text, after any leading comments
+/// (e.g. a `yaml-language-server` comment).
+fn insert_version(text: &str) -> String {
+    let header_length: usize = text
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with('#') || line.starts_with("---"))
+        .map(str::len)
+        .sum();
+
+    let (header, rest) = text.split_at(header_length);
+    let separator = match header.is_empty() || header.ends_with('\n') {
+        true => "",
+        false => "\n",
+    };
+    format!(
+        "{}{}{}: {}\n\n{}",
+        header,
+        separator,
+        VERSION_FIELD,
+        CURRENT_VERSION,
+        rest.trim_start_matches('\n')
+    )
+}
+
+fn with_version_first(config: Value) -> Value {
+    let Value::Mapping(mapping) = config else {
+        return config;
+    };
+
+    let mut sorted = Mapping::new();
+    sorted.insert(VERSION_FIELD.into(), CURRENT_VERSION.into());
+    for (key, value) in mapping {
+        if key.as_str() != Some(VERSION_FIELD) {
+            sorted.insert(key, value);
+        }
+    }
+
+    Value::Mapping(sorted)
+}
+
+#[derive(Error, Debug)]
+pub enum MigrateError {
+    #[error("the config file uses version {version} of the config format, but this version of plz only supports up to version {supported}, try updating plz")]
+    UnsupportedVersion { version: u64, supported: u64 },
+
+    #[error("the config file's version must be a positive number, found {version}")]
+    InvalidVersion { version: String },
+
+    #[error("the config file uses version {version} of the config format, run `plz migrate` to update it")]
+    Outdated { version: u64 },
+
+    #[error("config files read from stdin or PLZ_CONFIG_INLINE can't be migrated")]
+    Stdin,
+
+    #[error("failed to parse config file")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("failed to parse config file")]
+    ParseJsonFailed(#[source] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_cmds(config: &mut Mapping) {
+        if let Some(commands) = config.remove("cmds") {
+            config.insert("commands".into(), commands);
+        }
+    }
+
+    #[test]
+    fn upgrade_applies_newer_migrations() {
+        // Arrange
+        let migrations = [Migration {
+            version: 2,
+            description: "renamed cmds to commands",
+            apply: rename_cmds,
+        }];
+        let mut config: Value = serde_yaml::from_str("cmds:\n  build: cargo build\n").unwrap();
+        let mut newer: Value = serde_yaml::from_str("version: 3\ncommands: {}\n").unwrap();
+
+        // Act
+        let applied = upgrade_with(&mut config, &migrations, 2).unwrap();
+        let unsupported = upgrade_with(&mut newer, &migrations, 2);
+
+        // Assert
+        assert_eq!(applied, vec!["renamed cmds to commands"]);
+        assert_eq!(
+            config,
+            serde_yaml::from_str::<Value>("commands:\n  build: cargo build\nversion: 2\n").unwrap()
+        );
+        assert!(matches!(
+            unsupported,
+            Err(MigrateError::UnsupportedVersion {
+                version: 3,
+                supported: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn migrate_sets_version_after_leading_comments() {
+        // Arrange
+        let text = "# yaml-language-server: $schema=./plz.schema.json\n\n# Build\ncommands:\n  build: cargo build # fast\n";
+
+        // Act
+        let migrated = migrate(text, &ConfigFormat::Yaml).unwrap().unwrap();
+        let up_to_date = migrate(&migrated.text, &ConfigFormat::Yaml).unwrap();
+
+        // Assert
+        assert_eq!(
+            migrated.text,
+            "# yaml-language-server: $schema=./plz.schema.json\nversion: 1\n\n# Build\ncommands:\n  build: cargo build # fast\n"
+        );
+        assert!(migrated.applied.is_empty());
+        assert!(up_to_date.is_none());
+    }
+
+    #[test]
+    fn migrations_lead_to_current_version() {
+        // Each migration should migrate to the version after the previous one
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as u64 + 2);
+        }
+        assert_eq!(MIGRATIONS.len() as u64 + 1, CURRENT_VERSION);
+    }
+}
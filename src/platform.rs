@@ -11,8 +11,11 @@ pub fn is_current_platform(
     platform_or_platforms: &OneOrManyPlatforms,
 ) -> bool {
     match platform_or_platforms {
-        OneOrManyPlatforms::One(platform) => platform.platform == current_platform,
-        OneOrManyPlatforms::Many(platforms) => platforms.platforms.contains(&current_platform),
+        OneOrManyPlatforms::One(platform) => platform.platform.includes(&current_platform),
+        OneOrManyPlatforms::Many(platforms) => platforms
+            .platforms
+            .iter()
+            .any(|platform| platform.includes(&current_platform)),
     }
 }
 
@@ -24,11 +27,72 @@ pub trait PlatformProvider {
 struct RealPlatformProvider;
 impl PlatformProvider for RealPlatformProvider {
     fn get_platform(&self) -> Platform {
-        match env::consts::OS {
-            "linux" => Platform::Linux,
-            "macos" => Platform::MacOS,
-            "windows" => Platform::Windows,
-            platform => panic!("unknown platform: {}", platform),
-        }
+        platform_from(env::consts::OS, env::consts::ARCH)
+    }
+}
+
+/// Determines the [`Platform`] from the provided OS and architecture names, as they appear in
+/// [`env::consts::OS`] and [`env::consts::ARCH`].
+fn platform_from(os: &str, arch: &str) -> Platform {
+    match (os, arch) {
+        ("linux", _) => Platform::Linux,
+        ("macos", _) => Platform::MacOS,
+        ("windows", "aarch64") => Platform::WindowsArm64,
+        ("windows", _) => Platform::Windows,
+        ("freebsd", _) => Platform::FreeBSD,
+        ("openbsd", _) => Platform::OpenBSD,
+        _ => Platform::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ManyPlatforms, OnePlatform};
+
+    #[test]
+    fn platform_from_detects_platform() {
+        assert_eq!(platform_from("linux", "x86_64"), Platform::Linux);
+        assert_eq!(platform_from("macos", "aarch64"), Platform::MacOS);
+        assert_eq!(platform_from("windows", "x86_64"), Platform::Windows);
+        assert_eq!(platform_from("windows", "aarch64"), Platform::WindowsArm64);
+        assert_eq!(platform_from("freebsd", "x86_64"), Platform::FreeBSD);
+        assert_eq!(platform_from("openbsd", "x86_64"), Platform::OpenBSD);
+        assert_eq!(platform_from("haiku", "x86_64"), Platform::Unknown);
+    }
+
+    #[test]
+    fn windows_includes_windows_arm64() {
+        // Arrange
+        let windows = OneOrManyPlatforms::One(OnePlatform {
+            platform: Platform::Windows,
+        });
+        let windows_arm64 = OneOrManyPlatforms::One(OnePlatform {
+            platform: Platform::WindowsArm64,
+        });
+
+        // Act / Assert
+        assert!(is_current_platform(Platform::Windows, &windows));
+        assert!(is_current_platform(Platform::WindowsArm64, &windows));
+        assert!(is_current_platform(Platform::WindowsArm64, &windows_arm64));
+        assert!(!is_current_platform(Platform::Windows, &windows_arm64));
+    }
+
+    #[test]
+    fn unknown_platform_is_never_included() {
+        // Arrange
+        let platforms = OneOrManyPlatforms::Many(ManyPlatforms {
+            platforms: vec![
+                Platform::Linux,
+                Platform::MacOS,
+                Platform::Windows,
+                Platform::FreeBSD,
+                Platform::OpenBSD,
+            ],
+        });
+
+        // Act / Assert
+        assert!(is_current_platform(Platform::FreeBSD, &platforms));
+        assert!(!is_current_platform(Platform::Unknown, &platforms));
     }
 }
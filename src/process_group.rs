@@ -0,0 +1,474 @@
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::io::IsTerminal;
+#[cfg(unix)]
+use std::mem;
+#[cfg(unix)]
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(unix)]
+use std::time::Instant;
+
+/// How often to check whether a process group being stopped has exited.
+#[cfg(unix)]
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A child process spawned along with every process it spawns (e.g. `npm` spawning `node` spawning
+/// `esbuild`), so they can all be killed together.
+///
+/// On Unix, the child is started in a new process group with `setpgid`. Interrupts (Ctrl+C) are
+/// forwarded to the group, since the terminal only sends them to the foreground group, unless the
+/// group has been given the terminal with [`ProcessGroup::spawn_in_foreground`].
+/// On Windows, the child is assigned to a Job Object, which kills any remaining processes when
+/// it's dropped. Interrupts are already sent to every process attached to the console.
+pub struct ProcessGroup {
+    child: Child,
+
+    /// The terminal the group has been given, which is handed back to plz once the child exits.
+    #[cfg(unix)]
+    terminal: Option<OwnedFd>,
+
+    #[cfg(windows)]
+    job: windows::Job,
+}
+
+impl ProcessGroup {
+    /// Spawns the provided command in a new process group in the background, so it can't read from
+    /// the terminal.
+    pub fn spawn(command: &mut Command) -> io::Result<ProcessGroup> {
+        Self::spawn_with_terminal(command, false)
+    }
+
+    /// Spawns the provided command in a new process group, and gives the group the terminal while
+    /// plz has it, so the command can still prompt for input (e.g. for a passphrase) on
+    /// `/dev/tty`.
+    pub fn spawn_in_foreground(command: &mut Command) -> io::Result<ProcessGroup> {
+        Self::spawn_with_terminal(command, true)
+    }
+
+    fn spawn_with_terminal(command: &mut Command, foreground: bool) -> io::Result<ProcessGroup> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            crate::interrupt::forward_to_process_groups().map_err(io::Error::other)?;
+
+            // The child's stdin may not be the terminal, so it's given a copy of plz's
+            let terminal = match foreground && is_foreground() {
+                true => Some(io::stdin().as_fd().try_clone_to_owned()?),
+                false => None,
+            };
+            let terminal_fd = terminal.as_ref().map(|terminal| terminal.as_raw_fd());
+
+            // SAFETY: setpgid, getpid, tcsetpgrp, and pthread_sigmask are async-signal-safe, so
+            // they can be called between fork and exec
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::setpgid(0, 0) == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                    if let Some(terminal_fd) = terminal_fd {
+                        set_foreground(terminal_fd, libc::getpid())?;
+                    }
+                    Ok(())
+                });
+            }
+
+            let child = command.spawn()?;
+            crate::interrupt::track_process_group(child.id());
+            Ok(ProcessGroup { child, terminal })
+        }
+
+        #[cfg(windows)]
+        {
+            let _ = foreground;
+            let job = windows::Job::create()?;
+            let mut child = command.spawn()?;
+
+            // Processes spawned before the child is assigned to the job won't be killed with it,
+            // but the window is too small to matter for the commands plz executes.
+            if let Err(err) = job.assign(&child) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+
+            Ok(ProcessGroup { child, job })
+        }
+    }
+
+    pub fn child(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    /// Waits for the child to exit, see [`ProcessGroup::try_wait`].
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        let status = self.child.wait()?;
+        self.exited(&status);
+        Ok(status)
+    }
+
+    /// Returns the child's exit status if it has exited.
+    /// Once it has, plz is given back the terminal. If the child had the terminal and was
+    /// interrupted (Ctrl+C), plz handles the interrupt as if it had received it too.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        let status = self.child.try_wait()?;
+        if let Some(status) = &status {
+            self.exited(status);
+        }
+        Ok(status)
+    }
+
+    fn exited(&mut self, status: &ExitStatus) {
+        #[cfg(unix)]
+        if self.release_terminal() && status.signal() == Some(libc::SIGINT) {
+            crate::interrupt::raise();
+        }
+
+        #[cfg(windows)]
+        let _ = status;
+    }
+
+    /// Gives the terminal back to plz if the group has it, returning `true` if it did.
+    #[cfg(unix)]
+    fn release_terminal(&mut self) -> bool {
+        let Some(terminal) = self.terminal.take() else {
+            return false;
+        };
+
+        // SAFETY: getpgrp has no memory safety requirements
+        let _ = set_foreground(terminal.as_raw_fd(), unsafe { libc::getpgrp() });
+        true
+    }
+
+    /// Asks the child and every process it has spawned to exit (SIGTERM), without waiting for
+    /// them. Does nothing on Windows, where processes can't be asked to exit.
+    pub fn terminate(&self) {
+        #[cfg(unix)]
+        terminate(self.child.id());
+    }
+
+    /// Asks the child and every process it has spawned to exit, killing them if the child hasn't
+    /// exited within the provided grace period.
+    /// Processes on Windows can't be asked to exit, so they're killed immediately.
+    pub fn stop(&mut self, grace_period: Duration) -> io::Result<ExitStatus> {
+        #[cfg(unix)]
+        {
+            self.terminate();
+
+            let started = Instant::now();
+            while started.elapsed() < grace_period {
+                if let Some(status) = self.child.try_wait()? {
+                    // Make sure nothing it started is left behind
+                    signal(self.child.id(), libc::SIGKILL);
+                    self.release_terminal();
+                    return Ok(status);
+                }
+                std::thread::sleep(STOP_POLL_INTERVAL);
+            }
+        }
+
+        #[cfg(windows)]
+        let _ = grace_period;
+
+        self.kill();
+        self.child.wait()
+    }
+
+    /// Kills the child and every process it has spawned, then waits for the child to exit.
+    pub fn kill(&mut self) {
+        // The child's process group ID is the same as its process ID, since it's the session
+        // leader. The group is killed rather than the child so grandchildren aren't orphaned.
+        #[cfg(unix)]
+        signal(self.child.id(), libc::SIGKILL);
+
+        #[cfg(windows)]
+        self.job.terminate();
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+
+        #[cfg(unix)]
+        self.release_terminal();
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            self.release_terminal();
+            crate::interrupt::untrack_process_group(self.child.id());
+        }
+    }
+}
+
+/// Returns `true` if plz's stdin is a terminal, and plz's process group is in the foreground.
+#[cfg(unix)]
+fn is_foreground() -> bool {
+    let stdin = io::stdin();
+    // SAFETY: tcgetpgrp and getpgrp have no memory safety requirements
+    stdin.is_terminal() && unsafe { libc::tcgetpgrp(stdin.as_raw_fd()) == libc::getpgrp() }
+}
+
+/// Moves the provided process group to the foreground of the terminal.
+/// Background processes are stopped (SIGTTOU) when they change the foreground group, so the
+/// signal is blocked while it's changed.
+#[cfg(unix)]
+fn set_foreground(terminal_fd: libc::c_int, process_group_id: libc::pid_t) -> io::Result<()> {
+    // SAFETY: The signal sets are initialised before they're used
+    unsafe {
+        let mut blocked = mem::zeroed::<libc::sigset_t>();
+        let mut previous = mem::zeroed::<libc::sigset_t>();
+        libc::sigemptyset(&mut blocked);
+        libc::sigaddset(&mut blocked, libc::SIGTTOU);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &blocked, &mut previous);
+
+        let result = libc::tcsetpgrp(terminal_fd, process_group_id);
+        libc::pthread_sigmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+
+        match result {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sends an interrupt signal (SIGINT) to the provided process group, as the terminal would.
+#[cfg(unix)]
+pub fn interrupt(process_group_id: u32) {
+    signal(process_group_id, libc::SIGINT);
+}
+
+/// Asks every process in the provided process group to exit (SIGTERM), including background
+/// processes which ignore interrupt signals.
+#[cfg(unix)]
+pub fn terminate(process_group_id: u32) {
+    signal(process_group_id, libc::SIGTERM);
+}
+
+#[cfg(unix)]
+fn signal(process_group_id: u32, signal: libc::c_int) {
+    // SAFETY: killpg has no memory safety requirements
+    unsafe {
+        libc::killpg(process_group_id as libc::pid_t, signal);
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::{io, mem, ptr};
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    /// A Job Object which kills the processes assigned to it once every handle to it is closed,
+    /// including when plz exits unexpectedly.
+    pub struct Job(HANDLE);
+
+    impl Job {
+        pub fn create() -> io::Result<Job> {
+            // SAFETY: The job handle is checked before it's used, and closed when it's dropped
+            unsafe {
+                let handle = CreateJobObjectW(ptr::null(), ptr::null());
+                if handle.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let job = Job(handle);
+                let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+                limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                let result = SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &limits as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *const _,
+                    mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                );
+                if result == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(job)
+            }
+        }
+
+        pub fn assign(&self, child: &Child) -> io::Result<()> {
+            // SAFETY: The child's handle is valid until it's dropped
+            match unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle() as HANDLE) } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+
+        pub fn terminate(&self) {
+            // SAFETY: The job handle is valid until it's dropped
+            unsafe {
+                TerminateJobObject(self.0, 1);
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            // SAFETY: The job handle is only closed once
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::ffi::CStr;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Read, Write};
+    use std::os::fd::FromRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Set when the test binary is run by [`spawn_in_foreground_can_read_from_terminal`], so
+    /// [`read_from_terminal`] knows it has a terminal.
+    const TERMINAL_TEST_VARIABLE: &str = "PLZ_TEST_PROCESS_GROUP_TERMINAL";
+
+    /// Opens a new pseudo-terminal, returning the master and slave sides.
+    fn open_terminal() -> (File, File) {
+        // SAFETY: The master is only used once it has been opened successfully, and ptsname is
+        // only called from this test
+        unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+            assert!(master >= 0);
+            assert_eq!(libc::grantpt(master), 0);
+            assert_eq!(libc::unlockpt(master), 0);
+            let slave_path = CStr::from_ptr(libc::ptsname(master))
+                .to_string_lossy()
+                .to_string();
+
+            let slave = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_NOCTTY)
+                .open(slave_path)
+                .unwrap();
+            (File::from_raw_fd(master), slave)
+        }
+    }
+
+    /// Reads a line from `/dev/tty` with a command spawned by [`ProcessGroup::spawn_in_foreground`],
+    /// printing what it read.
+    /// Only runs from [`spawn_in_foreground_can_read_from_terminal`], which gives it a terminal.
+    #[test]
+    #[ignore]
+    fn read_from_terminal() {
+        if env::var_os(TERMINAL_TEST_VARIABLE).is_none() {
+            return;
+        }
+
+        let mut command = Command::new("bash");
+        command
+            .args(["-c", "read -r line < /dev/tty && echo \"read $line\""])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+        let mut process_group = ProcessGroup::spawn_in_foreground(&mut command).unwrap();
+
+        let mut output = String::new();
+        let mut stdout = process_group.child().stdout.take().unwrap();
+        stdout.read_to_string(&mut output).unwrap();
+        process_group.wait().unwrap();
+
+        println!("captured: {}", output.trim());
+    }
+
+    #[test]
+    fn spawn_in_foreground_can_read_from_terminal() {
+        // Arrange
+        // The test binary is run again with the terminal as its controlling terminal, since plz
+        // (and this test) may not have one
+        let (mut master, slave) = open_terminal();
+        let mut command = Command::new(env::current_exe().unwrap());
+        command
+            .args([
+                "--exact",
+                "process_group::tests::read_from_terminal",
+                "--ignored",
+                "--nocapture",
+                "--test-threads=1",
+            ])
+            .env(TERMINAL_TEST_VARIABLE, "1")
+            .stdin(slave.try_clone().unwrap())
+            .stdout(slave.try_clone().unwrap())
+            .stderr(slave);
+
+        // SAFETY: setsid and ioctl are async-signal-safe
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        // Act
+        let mut child = command.spawn().unwrap();
+        drop(command);
+        master.write_all(b"hello\n").unwrap();
+
+        // Reading fails once every process using the terminal has exited
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let mut buffer = [0; 1024];
+            while let Ok(length @ 1..) = master.read(&mut buffer) {
+                output.extend_from_slice(&buffer[..length]);
+            }
+            let _ = sender.send(String::from_utf8_lossy(&output).to_string());
+        });
+        let output = receiver.recv_timeout(Duration::from_secs(30));
+        let _ = child.kill();
+        let _ = child.wait();
+
+        // Assert
+        assert!(output.unwrap().contains("captured: read hello"));
+    }
+
+    #[test]
+    fn kill_kills_grandchildren() {
+        // Arrange
+        // The grandchild inherits the pipe, so it's only closed once the grandchild has exited too
+        let mut command = Command::new("bash");
+        command
+            .args(["-c", "sleep 30 & wait"])
+            .stdout(Stdio::piped());
+        let mut process_group = ProcessGroup::spawn(&mut command).unwrap();
+        let mut stdout = process_group.child().stdout.take().unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = stdout.read_to_end(&mut Vec::new());
+            let _ = sender.send(());
+        });
+
+        // Act
+        process_group.kill();
+
+        // Assert
+        let closed = receiver.recv_timeout(Duration::from_secs(10));
+        assert!(closed.is_ok());
+    }
+}
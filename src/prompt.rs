@@ -2,12 +2,18 @@ use crate::config::{
     PromptConfig, PromptOptionsVariant, SelectOptionsConfig, SelectPromptOptions, TextPromptOptions,
 };
 use crate::exec::{CommandExecutor, ExecutionError};
-use inquire::{InquireError, Password, PasswordDisplayMode, Select, Text};
+use crate::variables::{substitute_variables, VariableMap};
+use inquire::{Confirm, InquireError, Password, PasswordDisplayMode, Select, Text};
 use mockall::automock;
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::env;
 use std::string::FromUtf8Error;
+use std::sync::Mutex;
 use thiserror::Error;
 
+/// The name of the environment variable containing answers for the [`ScriptedPromptBackend`].
+const PROMPT_ANSWERS_VARIABLE_NAME: &str = "PLZ_PROMPT_ANSWERS";
+
 #[derive(Error, Debug)]
 pub enum PromptError {
     #[error("prompt failed")]
@@ -18,34 +24,224 @@ pub enum PromptError {
 
     #[error("failed to parse prompt options")]
     ParseError(#[source] FromUtf8Error),
+
+    #[error("no answer was provided for \"{message}\"")]
+    NoAnswer { message: String },
+
+    #[error("\"{answer}\" is not a valid answer for \"{message}\"")]
+    InvalidAnswer { message: String, answer: String },
 }
 
 #[automock]
 pub trait PromptExecutor {
     /// Prompts the user using the provided [`PromptConfig`], returning the user's response.
-    fn execute(&self, prompt_config: &PromptConfig) -> Result<String, PromptError>;
+    /// The provided variables are substituted into the message, options, and default answer of
+    /// the prompt, and are available to the command used to determine its options.
+    fn execute(
+        &self,
+        prompt_config: &PromptConfig,
+        variables: &VariableMap,
+    ) -> Result<String, PromptError>;
+}
+
+/// Capable of displaying individual prompts to the user.
+#[automock]
+pub trait PromptBackend {
+    /// Prompts the user for a line of text, using the default if they don't provide one.
+    fn text(&self, message: &str, default: Option<String>) -> Result<String, PromptError>;
+
+    /// Prompts the user for a line of text without displaying it.
+    fn password(&self, message: &str) -> Result<String, PromptError>;
+
+    /// Prompts the user to select one of the provided options, starting with the default if it's
+    /// one of them.
+    fn select(
+        &self,
+        message: &str,
+        options: Vec<String>,
+        default: Option<String>,
+    ) -> Result<String, PromptError>;
+
+    /// Prompts the user for a yes or no answer.
+    fn confirm(&self, message: &str, default: bool) -> Result<bool, PromptError>;
+}
+
+/// Creates the [`PromptBackend`] to use for the current process.
+/// If the `PLZ_PROMPT_ANSWERS` environment variable is set, prompts will be answered using the
+/// answers it contains (one per line) rather than prompting the user.
+pub fn create_prompt_backend() -> Box<dyn PromptBackend> {
+    create_scripted_prompt_backend().unwrap_or(Box::new(InquirePromptBackend {}))
+}
+
+/// Creates a [`ScriptedPromptBackend`] using the answers in the `PLZ_PROMPT_ANSWERS` environment
+/// variable, or `None` if it isn't set.
+pub fn create_scripted_prompt_backend() -> Option<Box<dyn PromptBackend>> {
+    let answers = env::var(PROMPT_ANSWERS_VARIABLE_NAME).ok()?;
+    Some(Box::new(ScriptedPromptBackend::new(
+        answers.lines().map(String::from).collect(),
+    )))
+}
+
+/// A [`PromptBackend`] that prompts the user in the terminal using inquire.
+pub struct InquirePromptBackend;
+
+impl PromptBackend for InquirePromptBackend {
+    fn text(&self, message: &str, default: Option<String>) -> Result<String, PromptError> {
+        let mut text = Text::new(message);
+        if let Some(default) = &default {
+            text = text.with_default(default);
+        }
+
+        text.prompt().map_err(PromptError::InquireError)
+    }
+
+    fn password(&self, message: &str) -> Result<String, PromptError> {
+        Password::new(message)
+            .with_display_mode(PasswordDisplayMode::Masked)
+            .without_confirmation()
+            .prompt()
+            .map_err(PromptError::InquireError)
+    }
+
+    fn select(
+        &self,
+        message: &str,
+        options: Vec<String>,
+        default: Option<String>,
+    ) -> Result<String, PromptError> {
+        let starting_cursor = default
+            .and_then(|default| options.iter().position(|option| *option == default))
+            .unwrap_or(0);
+
+        Select::new(message, options)
+            .with_starting_cursor(starting_cursor)
+            .prompt()
+            .map_err(PromptError::InquireError)
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<bool, PromptError> {
+        Confirm::new(message)
+            .with_default(default)
+            .prompt()
+            .map_err(PromptError::InquireError)
+    }
+}
+
+/// A [`PromptBackend`] that answers prompts using a pre-defined list of answers, in order.
+/// Useful for scripting and testing prompt flows without a terminal.
+pub struct ScriptedPromptBackend {
+    answers: Mutex<VecDeque<String>>,
+}
+
+impl ScriptedPromptBackend {
+    pub fn new(answers: Vec<String>) -> ScriptedPromptBackend {
+        ScriptedPromptBackend {
+            answers: Mutex::new(answers.into()),
+        }
+    }
+
+    fn next_answer(&self, message: &str) -> Result<String, PromptError> {
+        self.answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(PromptError::NoAnswer {
+                message: message.to_string(),
+            })
+    }
+}
+
+impl PromptBackend for ScriptedPromptBackend {
+    fn text(&self, message: &str, default: Option<String>) -> Result<String, PromptError> {
+        let answer = self.next_answer(message)?;
+        match default {
+            Some(default) if answer.is_empty() => Ok(default),
+            _ => Ok(answer),
+        }
+    }
+
+    fn password(&self, message: &str) -> Result<String, PromptError> {
+        self.next_answer(message)
+    }
+
+    fn select(
+        &self,
+        message: &str,
+        options: Vec<String>,
+        default: Option<String>,
+    ) -> Result<String, PromptError> {
+        let mut answer = self.next_answer(message)?;
+        if let Some(default) = default.filter(|_| answer.is_empty()) {
+            answer = default;
+        }
+
+        if !options.contains(&answer) {
+            return Err(PromptError::InvalidAnswer {
+                message: message.to_string(),
+                answer,
+            });
+        }
+
+        Ok(answer)
+    }
+
+    fn confirm(&self, message: &str, default: bool) -> Result<bool, PromptError> {
+        let answer = self.next_answer(message)?;
+        match answer.to_lowercase().as_str() {
+            "" => Ok(default),
+            "y" | "yes" | "true" => Ok(true),
+            "n" | "no" | "false" => Ok(false),
+            _ => Err(PromptError::InvalidAnswer {
+                message: message.to_string(),
+                answer,
+            }),
+        }
+    }
 }
 
 pub struct TerminalPromptExecutor {
     command_executor: Box<dyn CommandExecutor>,
+    prompt_backend: Box<dyn PromptBackend>,
 }
 
 impl TerminalPromptExecutor {
-    pub fn new(command_executor: Box<dyn CommandExecutor>) -> TerminalPromptExecutor {
-        return TerminalPromptExecutor { command_executor };
+    pub fn new(
+        command_executor: Box<dyn CommandExecutor>,
+        prompt_backend: Box<dyn PromptBackend>,
+    ) -> TerminalPromptExecutor {
+        return TerminalPromptExecutor {
+            command_executor,
+            prompt_backend,
+        };
     }
 }
 
 impl PromptExecutor for TerminalPromptExecutor {
-    fn execute(&self, prompt_config: &PromptConfig) -> Result<String, PromptError> {
+    fn execute(
+        &self,
+        prompt_config: &PromptConfig,
+        variables: &VariableMap,
+    ) -> Result<String, PromptError> {
+        let message = substitute_variables(&prompt_config.message, variables);
+        let default = prompt_config
+            .default
+            .as_ref()
+            .map(|default| substitute_variables(default, variables));
+
         match prompt_config.clone().options {
-            PromptOptionsVariant::Text(text_prompt_options) => {
-                execute_text_prompt(prompt_config.message.as_str(), &text_prompt_options)
-            }
+            PromptOptionsVariant::Text(text_prompt_options) => execute_text_prompt(
+                message.as_str(),
+                default,
+                &text_prompt_options,
+                self.prompt_backend.as_ref(),
+            ),
             PromptOptionsVariant::Select(select_prompt_config) => execute_select_prompt(
-                prompt_config.message.as_str(),
+                message.as_str(),
+                default,
                 &select_prompt_config,
                 &self.command_executor,
+                self.prompt_backend.as_ref(),
+                variables,
             ),
         }
     }
@@ -53,45 +249,42 @@ impl PromptExecutor for TerminalPromptExecutor {
 
 fn execute_text_prompt(
     message: &str,
+    default: Option<String>,
     text_prompt_options: &TextPromptOptions,
+    prompt_backend: &dyn PromptBackend,
 ) -> Result<String, PromptError> {
-    let result = if text_prompt_options.sensitive {
-        Password::new(message)
-            .with_display_mode(PasswordDisplayMode::Masked)
-            .without_confirmation()
-            .prompt()
+    if text_prompt_options.sensitive {
+        prompt_backend.password(message)
     } else {
-        Text::new(message).prompt()
-    };
-
-    match result {
-        Ok(value) => Ok(value),
-        Err(err) => Err(PromptError::InquireError(err)),
+        prompt_backend.text(message, default)
     }
 }
 
 fn execute_select_prompt(
     message: &str,
+    default: Option<String>,
     select_prompt_options: &SelectPromptOptions,
     command_executor: &Box<dyn CommandExecutor>,
+    prompt_backend: &dyn PromptBackend,
+    variables: &VariableMap,
 ) -> Result<String, PromptError> {
-    let options = get_options(&select_prompt_options.options, command_executor)?;
-    let result = Select::new(message, options).prompt();
-    match result {
-        Ok(value) => Ok(value),
-        Err(err) => Err(PromptError::InquireError(err)),
-    }
+    let options = get_options(&select_prompt_options.options, command_executor, variables)?;
+    prompt_backend.select(message, options, default)
 }
 
 fn get_options(
     select_options_config: &SelectOptionsConfig,
     command_executor: &Box<dyn CommandExecutor>,
+    variables: &VariableMap,
 ) -> Result<Vec<String>, PromptError> {
     match select_options_config {
-        SelectOptionsConfig::Literal(options) => Ok(options.clone()),
+        SelectOptionsConfig::Literal(options) => Ok(options
+            .iter()
+            .map(|option| substitute_variables(option, variables))
+            .collect()),
         SelectOptionsConfig::Execution(execution_config) => {
             let output = command_executor
-                .get_output(&execution_config.execution, &HashMap::new())
+                .get_output(&execution_config.execution, variables)
                 .map_err(|err| PromptError::ExecutionError(err))?;
             let stdout =
                 String::from_utf8(output.stdout).map_err(|err| PromptError::ParseError(err))?;
@@ -101,4 +294,86 @@ fn get_options(
     }
 }
 
-// This is hard to write tests for. Fow now, let's assume the Inquire crate has sufficient tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::MockCommandExecutor;
+    use mockall::predicate::eq;
+
+    #[test]
+    fn execute_uses_password_prompt_for_sensitive_text() {
+        // Arrange
+        let mut prompt_backend = MockPromptBackend::new();
+        prompt_backend
+            .expect_password()
+            .with(eq("Password?"))
+            .once()
+            .returning(|_| Ok("hunter2".to_string()));
+
+        let prompt_executor = TerminalPromptExecutor::new(
+            Box::new(MockCommandExecutor::new()),
+            Box::new(prompt_backend),
+        );
+
+        let prompt_config = PromptConfig {
+            message: "Password?".to_string(),
+            default: None,
+            options: PromptOptionsVariant::Text(TextPromptOptions {
+                multi_line: false,
+                sensitive: true,
+            }),
+        };
+
+        // Act
+        let result = prompt_executor.execute(&prompt_config, &VariableMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn execute_selects_from_literal_options() {
+        // Arrange
+        let prompt_executor = TerminalPromptExecutor::new(
+            Box::new(MockCommandExecutor::new()),
+            Box::new(ScriptedPromptBackend::new(vec!["staging".to_string()])),
+        );
+
+        let prompt_config = PromptConfig {
+            message: "Environment?".to_string(),
+            default: None,
+            options: PromptOptionsVariant::Select(SelectPromptOptions {
+                options: SelectOptionsConfig::Literal(vec![
+                    "staging".to_string(),
+                    "production".to_string(),
+                ]),
+            }),
+        };
+
+        // Act
+        let result = prompt_executor.execute(&prompt_config, &VariableMap::new());
+
+        // Assert
+        assert_eq!(result.unwrap(), "staging");
+    }
+
+    #[test]
+    fn scripted_backend_rejects_invalid_answers() {
+        // Arrange
+        let prompt_backend = ScriptedPromptBackend::new(vec!["dev".to_string(), "y".to_string()]);
+
+        // Act
+        let select_result =
+            prompt_backend.select("Environment?", vec!["staging".to_string()], None);
+        let confirm_result = prompt_backend.confirm("Continue?", false);
+        let missing_result = prompt_backend.text("Name?", None);
+
+        // Assert
+        assert!(matches!(
+            select_result,
+            Err(PromptError::InvalidAnswer { .. })
+        ));
+        assert!(confirm_result.unwrap());
+        assert!(matches!(missing_result, Err(PromptError::NoAnswer { .. })));
+    }
+}
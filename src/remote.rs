@@ -0,0 +1,644 @@
+use crate::config::{self, GitImport};
+use crate::import_lock::{self, LockedImport, LOCK_FILE_NAME};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// The name of the command-line argument used to force remote imports to be downloaded again.
+pub const REFRESH_IMPORTS_ARG: &str = "--refresh-imports";
+
+/// How long a remote config is used for before checking for changes, when the server doesn't
+/// provide a `max-age`.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("couldn't determine the cache directory")]
+    NoCacheDirectory,
+
+    #[error("failed to download {url}")]
+    RequestFailed {
+        url: String,
+        #[source]
+        source: Box<ureq::Error>,
+    },
+
+    #[error("failed to run git")]
+    GitUnavailable(#[source] io::Error),
+
+    #[error("failed to fetch {repo}: {message}")]
+    GitFailed { repo: String, message: String },
+
+    #[error("the git import {field} \"{value}\" can't start with \"-\"")]
+    InvalidGitArgument { field: &'static str, value: String },
+
+    #[error("{import} has changed since it was locked in {LOCK_FILE_NAME}, run `plz update` to use the new version")]
+    LockMismatch { import: String },
+
+    #[error("failed to read the cached copy of {url}")]
+    CacheFailed {
+        url: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Information about a downloaded config, stored next to it in the cache.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct CacheMetadata {
+    url: String,
+
+    /// The `ETag` the server returned, used to check whether the config has changed.
+    /// For git imports, this is the hash of the commit that was checked out.
+    etag: Option<String>,
+
+    /// When the config was last downloaded or checked, in seconds since the Unix epoch.
+    fetched_at: u64,
+
+    /// How long the config can be used for before checking for changes, in seconds.
+    max_age: u64,
+}
+
+impl CacheMetadata {
+    /// Returns `true` if the cached copy can still be used at the provided time.
+    fn is_fresh(&self, now: u64) -> bool {
+        now < self.fetched_at.saturating_add(self.max_age)
+    }
+}
+
+/// The response to a request for a remote config.
+pub enum Response {
+    Modified {
+        body: String,
+        etag: Option<String>,
+        max_age: Option<u64>,
+    },
+    NotModified {
+        max_age: Option<u64>,
+    },
+}
+
+/// Returns `true` if the provided import source is a URL rather than a path.
+pub fn is_remote(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// Returns `true` if the refresh imports argument was provided on the command-line.
+/// This needs to happen before the config is loaded, so it can't wait for clap to parse the
+/// arguments.
+pub fn refresh_from_args() -> bool {
+    refresh_requested(&env::args().collect::<Vec<_>>())
+}
+
+/// Returns `true` if the refresh imports argument was provided before the command.
+fn refresh_requested(args: &[String]) -> bool {
+    config::leading_args(args)
+        .iter()
+        .any(|arg| arg == REFRESH_IMPORTS_ARG)
+}
+
+/// Downloads the config at the provided URL, returning the path of the cached copy.
+/// Cached copies are reused until they're older than the `max-age` provided by the server, after
+/// which they're only downloaded again if their `ETag` has changed.
+///
+/// If the URL is locked in the lock file, the downloaded config must match the locked version.
+pub fn load(url: &str, refresh: bool) -> Result<PathBuf, RemoteError> {
+    let cache_dir = cache_dir().ok_or(RemoteError::NoCacheDirectory)?;
+    let locked = import_lock::locked(url);
+    let refresh = refresh || import_lock::is_updating();
+
+    let mut path = load_with(url, &cache_dir, refresh, SystemTime::now(), fetch)?;
+    let mut sha256 = hash_file(url, &path)?;
+    if let Some(locked) = locked {
+        // The cached copy may be a different version, so download it again before giving up
+        if sha256 != locked.sha256 && !refresh {
+            path = load_with(url, &cache_dir, true, SystemTime::now(), fetch)?;
+            sha256 = hash_file(url, &path)?;
+        }
+
+        if sha256 != locked.sha256 {
+            return Err(RemoteError::LockMismatch {
+                import: url.to_string(),
+            });
+        }
+    }
+
+    import_lock::record(
+        url,
+        LockedImport {
+            commit: None,
+            sha256,
+        },
+    );
+    Ok(path)
+}
+
+fn load_with(
+    url: &str,
+    cache_dir: &Path,
+    refresh: bool,
+    now: SystemTime,
+    fetch: impl Fn(&str, Option<&str>) -> Result<Response, RemoteError>,
+) -> Result<PathBuf, RemoteError> {
+    let map_err = |source| RemoteError::CacheFailed {
+        url: url.to_string(),
+        source,
+    };
+
+    let (config_path, metadata_path) = cache_paths(cache_dir, url);
+    let now = unix_seconds(now);
+
+    let metadata = read_metadata(&metadata_path).filter(|_| config_path.is_file());
+    if metadata
+        .as_ref()
+        .is_some_and(|metadata| !refresh && metadata.is_fresh(now))
+    {
+        return Ok(config_path);
+    }
+
+    let etag = metadata
+        .as_ref()
+        .filter(|_| !refresh)
+        .and_then(|metadata| metadata.etag.clone());
+    let (etag, max_age) = match fetch(url, etag.as_deref()) {
+        Ok(Response::Modified {
+            body,
+            etag,
+            max_age,
+        }) => {
+            fs::create_dir_all(cache_dir).map_err(map_err)?;
+            fs::write(&config_path, body).map_err(map_err)?;
+            (etag, max_age)
+        }
+        Ok(Response::NotModified { max_age }) => (etag, max_age),
+
+        // Fall back to the cached copy so plz still works offline
+        Err(err) if metadata.is_some() && !refresh => {
+            eprintln!(
+                "{} {}, using the cached copy instead",
+                "warning:".yellow().bold(),
+                err
+            );
+            return Ok(config_path);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let metadata = CacheMetadata {
+        url: url.to_string(),
+        etag,
+        fetched_at: now,
+        max_age: max_age.unwrap_or(DEFAULT_MAX_AGE.as_secs()),
+    };
+    write_metadata(&metadata_path, &metadata).map_err(map_err)?;
+
+    Ok(config_path)
+}
+
+/// Fetches the provided git repository, returning the path of the config file within it.
+/// Checkouts are reused until they're older than an hour, or indefinitely if the ref is a commit
+/// hash, after which they're fetched again.
+///
+/// If the import is locked in the lock file, the locked commit is checked out instead of the ref.
+pub fn load_git(git: &GitImport, refresh: bool) -> Result<PathBuf, RemoteError> {
+    let cache_dir = cache_dir().ok_or(RemoteError::NoCacheDirectory)?;
+    let key = git_lock_key(git);
+    let locked = import_lock::locked(&key);
+    let refresh = refresh || import_lock::is_updating();
+
+    let mut git = git.clone();
+    if let Some(commit) = locked.as_ref().and_then(|locked| locked.commit.clone()) {
+        git.reference = Some(commit);
+    }
+
+    let (path, commit) = load_git_with(&git, &cache_dir.join("git"), refresh, SystemTime::now())?;
+    let sha256 = hash_file(&key, &path)?;
+    if locked.is_some_and(|locked| locked.sha256 != sha256) {
+        return Err(RemoteError::LockMismatch { import: key });
+    }
+
+    import_lock::record(&key, LockedImport { commit, sha256 });
+    Ok(path)
+}
+
+/// Returns the key a git import is locked under in the lock file.
+/// The path is included, since multiple files can be imported from the same repository.
+fn git_lock_key(git: &GitImport) -> String {
+    format!(
+        "{}@{}#{}",
+        git.repo,
+        git.reference.as_deref().unwrap_or("HEAD"),
+        git.path
+    )
+}
+
+/// Returns a hash of the downloaded config at the provided path, for comparing with the lock
+/// file.
+fn hash_file(import: &str, path: &Path) -> Result<String, RemoteError> {
+    let content = fs::read(path).map_err(|err| RemoteError::CacheFailed {
+        url: import.to_string(),
+        source: err,
+    })?;
+
+    Ok(import_lock::hash(&content))
+}
+
+/// Fetches the provided git repository, returning the path of the config file within it, and the
+/// commit that was checked out.
+fn load_git_with(
+    git: &GitImport,
+    cache_dir: &Path,
+    refresh: bool,
+    now: SystemTime,
+) -> Result<(PathBuf, Option<String>), RemoteError> {
+    let reference = git.reference.as_deref().unwrap_or("HEAD");
+    let key = format!("{}@{}", git.repo, reference);
+    let map_err = |source| RemoteError::CacheFailed {
+        url: key.clone(),
+        source,
+    };
+
+    let name = hash_name(&key);
+    let checkout_dir = cache_dir.join(&name);
+    let metadata_path = cache_dir.join(format!("{}.meta.json", name));
+    let config_path = checkout_dir.join(&git.path);
+    let now = unix_seconds(now);
+
+    let metadata = read_metadata(&metadata_path).filter(|_| checkout_dir.join(".git").is_dir());
+    if metadata
+        .as_ref()
+        .is_some_and(|metadata| !refresh && metadata.is_fresh(now))
+    {
+        return Ok((config_path, metadata.and_then(|metadata| metadata.etag)));
+    }
+
+    let commit = match fetch_git(&git.repo, reference, &checkout_dir) {
+        Ok(commit) => commit,
+
+        // Fall back to the last checkout so plz still works offline
+        Err(err) if metadata.is_some() && !refresh => {
+            eprintln!(
+                "{} {}, using the last fetched copy instead",
+                "warning:".yellow().bold(),
+                err
+            );
+            return Ok((config_path, metadata.and_then(|metadata| metadata.etag)));
+        }
+        Err(err) => return Err(err),
+    };
+
+    // Commits can't change, so there's no need to fetch them again
+    let max_age = if is_commit_hash(reference) {
+        u64::MAX
+    } else {
+        DEFAULT_MAX_AGE.as_secs()
+    };
+    let metadata = CacheMetadata {
+        url: key.clone(),
+        etag: Some(commit.clone()),
+        fetched_at: now,
+        max_age,
+    };
+    write_metadata(&metadata_path, &metadata).map_err(map_err)?;
+
+    Ok((config_path, Some(commit)))
+}
+
+/// Fetches the provided ref into a shallow checkout in `checkout_dir`, returning the hash of the
+/// commit that was checked out.
+fn fetch_git(repo: &str, reference: &str, checkout_dir: &Path) -> Result<String, RemoteError> {
+    // Values starting with "-" would be read as options by git (e.g. `--upload-pack`, which runs
+    // an arbitrary command)
+    for (field, value) in [("repo", repo), ("ref", reference)] {
+        if value.starts_with('-') {
+            return Err(RemoteError::InvalidGitArgument {
+                field,
+                value: value.to_string(),
+            });
+        }
+    }
+
+    if !checkout_dir.join(".git").is_dir() {
+        fs::create_dir_all(checkout_dir).map_err(|source| RemoteError::CacheFailed {
+            url: repo.to_string(),
+            source,
+        })?;
+        run_git(repo, checkout_dir, &["init", "--quiet"])?;
+    }
+
+    run_git(
+        repo,
+        checkout_dir,
+        &["fetch", "--quiet", "--depth", "1", "--", repo, reference],
+    )?;
+    run_git(
+        repo,
+        checkout_dir,
+        &["checkout", "--quiet", "--force", "--detach", "FETCH_HEAD"],
+    )?;
+    let commit = run_git(repo, checkout_dir, &["rev-parse", "HEAD"])?;
+
+    Ok(commit.trim().to_string())
+}
+
+fn run_git(repo: &str, dir: &Path, args: &[&str]) -> Result<String, RemoteError> {
+    let output = process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(RemoteError::GitUnavailable)?;
+
+    if !output.status.success() {
+        return Err(RemoteError::GitFailed {
+            repo: repo.to_string(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn is_commit_hash(reference: &str) -> bool {
+    reference.len() == 40 && reference.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+fn fetch(url: &str, etag: Option<&str>) -> Result<Response, RemoteError> {
+    let map_err = |err| RemoteError::RequestFailed {
+        url: url.to_string(),
+        source: Box::new(err),
+    };
+
+    let mut request = ureq::get(url).timeout(REQUEST_TIMEOUT);
+    if let Some(etag) = etag {
+        request = request.set("If-None-Match", etag);
+    }
+
+    let response = request.call().map_err(map_err)?;
+    let max_age = response.header("Cache-Control").and_then(parse_max_age);
+    if response.status() == 304 {
+        return Ok(Response::NotModified { max_age });
+    }
+
+    let etag = response.header("ETag").map(String::from);
+    let body = response
+        .into_string()
+        .map_err(|err| map_err(ureq::Error::from(err)))?;
+
+    Ok(Response::Modified {
+        body,
+        etag,
+        max_age,
+    })
+}
+
+/// Returns the `max-age` directive from a `Cache-Control` header, in seconds.
+/// `no-cache` and `no-store` are treated as a `max-age` of 0.
+fn parse_max_age(cache_control: &str) -> Option<u64> {
+    cache_control
+        .split(',')
+        .map(str::trim)
+        .find_map(|directive| match directive {
+            "no-cache" | "no-store" => Some(0),
+            _ => directive
+                .strip_prefix("max-age=")
+                .and_then(|max_age| max_age.parse().ok()),
+        })
+}
+
+/// Returns the paths of the cached config and its metadata for the provided URL.
+/// JSON configs keep their extension so they're parsed in the right format.
+fn cache_paths(cache_dir: &Path, url: &str) -> (PathBuf, PathBuf) {
+    let name = hash_name(url);
+    let extension = if url.to_lowercase().ends_with(".json") {
+        "json"
+    } else {
+        "yaml"
+    };
+
+    (
+        cache_dir.join(format!("{}.{}", name, extension)),
+        cache_dir.join(format!("{}.meta.json", name)),
+    )
+}
+
+/// Returns a file name for the cached copy of the provided URL, or repository and ref.
+/// The name needs to be the same across versions of plz, so a stable hash is used.
+fn hash_name(url: &str) -> String {
+    import_lock::hash(url.as_bytes())
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+fn read_metadata(path: &Path) -> Option<CacheMetadata> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+}
+
+fn write_metadata(path: &Path, metadata: &CacheMetadata) -> Result<(), io::Error> {
+    let text = serde_json::to_string(metadata).unwrap_or_default();
+    fs::write(path, text)
+}
+
+/// Returns the directory remote imports are cached in.
+/// Remote imports are shared between projects, so they're cached in the user's cache directory
+/// rather than the `.plz` directory.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(base.join("plz").join("imports"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    const URL: &str = "https://example.com/plz.yaml";
+
+    #[test]
+    fn load_reuses_cached_copy_until_max_age() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let requests = Cell::new(0);
+        let fetch = |_: &str, _: Option<&str>| {
+            requests.set(requests.get() + 1);
+            Ok(Response::Modified {
+                body: "commands: {}".to_string(),
+                etag: None,
+                max_age: Some(60),
+            })
+        };
+        let now = SystemTime::now();
+
+        // Act
+        let path = load_with(URL, dir.path(), false, now, fetch).unwrap();
+        load_with(URL, dir.path(), false, now + Duration::from_secs(30), fetch).unwrap();
+        load_with(URL, dir.path(), true, now + Duration::from_secs(30), fetch).unwrap();
+
+        // Assert
+        assert_eq!(requests.get(), 2);
+        assert_eq!(fs::read_to_string(path).unwrap(), "commands: {}");
+    }
+
+    #[test]
+    fn load_revalidates_expired_copy_with_etag() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let now = SystemTime::now();
+        load_with(URL, dir.path(), false, now, |_, _| {
+            Ok(Response::Modified {
+                body: "commands: {}".to_string(),
+                etag: Some("\"v1\"".to_string()),
+                max_age: Some(0),
+            })
+        })
+        .unwrap();
+
+        // Act
+        let path = load_with(URL, dir.path(), false, now, |_, etag| {
+            assert_eq!(etag, Some("\"v1\""));
+            Ok(Response::NotModified { max_age: None })
+        })
+        .unwrap();
+
+        // Assert
+        assert_eq!(fs::read_to_string(path).unwrap(), "commands: {}");
+    }
+
+    #[test]
+    fn load_git_falls_back_to_last_checkout() {
+        // Arrange
+        let repo = TempDir::new().unwrap();
+        fs::write(repo.path().join("plz.yaml"), "commands: {}").unwrap();
+        for args in [
+            vec!["init", "--quiet"],
+            vec!["add", "plz.yaml"],
+            vec![
+                "-c",
+                "user.name=plz",
+                "-c",
+                "user.email=plz@example.com",
+                "commit",
+                "--quiet",
+                "--message",
+                "Add config",
+            ],
+        ] {
+            run_git("", repo.path(), &args).unwrap();
+        }
+
+        let cache_dir = TempDir::new().unwrap();
+        let git = GitImport {
+            repo: repo.path().to_string_lossy().to_string(),
+            reference: None,
+            path: "plz.yaml".to_string(),
+        };
+        let now = SystemTime::now();
+
+        // Act
+        let (path, commit) = load_git_with(&git, cache_dir.path(), false, now).unwrap();
+        drop(repo);
+        let (offline_path, offline_commit) = load_git_with(
+            &git,
+            cache_dir.path(),
+            false,
+            now + DEFAULT_MAX_AGE + Duration::from_secs(1),
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(path, offline_path);
+        assert!(commit.is_some());
+        assert_eq!(commit, offline_commit);
+        assert_eq!(fs::read_to_string(path).unwrap(), "commands: {}");
+    }
+
+    #[test]
+    fn load_git_rejects_arguments_starting_with_a_dash() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let marker = dir.path().join("pwned");
+        let git = |repo: String, reference: Option<&str>| GitImport {
+            repo,
+            reference: reference.map(str::to_string),
+            path: "plz.yaml".to_string(),
+        };
+        let upload_pack = git(
+            format!(
+                "--upload-pack=touch {}; git-upload-pack",
+                marker.to_string_lossy()
+            ),
+            None,
+        );
+        let option_ref = git(
+            "https://example.com/repo.git".to_string(),
+            Some("--upload-pack=touch"),
+        );
+
+        // Act
+        let repo_result = load_git_with(&upload_pack, dir.path(), false, SystemTime::now());
+        let ref_result = load_git_with(&option_ref, dir.path(), false, SystemTime::now());
+
+        // Assert
+        assert!(matches!(
+            repo_result,
+            Err(RemoteError::InvalidGitArgument { field: "repo", .. })
+        ));
+        assert!(matches!(
+            ref_result,
+            Err(RemoteError::InvalidGitArgument { field: "ref", .. })
+        ));
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn refresh_requested_only_checks_arguments_before_the_command() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert!(refresh_requested(&args(&[
+            "plz",
+            "--env",
+            "prod",
+            "--refresh-imports",
+            "deploy"
+        ])));
+        assert!(!refresh_requested(&args(&[
+            "plz",
+            "deploy",
+            "--refresh-imports"
+        ])));
+    }
+
+    #[test]
+    fn hash_name_is_stable() {
+        assert_eq!(
+            hash_name(URL),
+            "76653e5497fda1ef97d3253f1e9cd14721a2b6420b7cd794bce869c48aa7a375"
+        );
+    }
+
+    #[test]
+    fn parse_max_age_reads_cache_control() {
+        assert_eq!(parse_max_age("public, max-age=300"), Some(300));
+        assert_eq!(parse_max_age("no-cache"), Some(0));
+        assert_eq!(parse_max_age("public"), None);
+    }
+}
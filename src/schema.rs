@@ -0,0 +1,112 @@
+use crate::config::Config;
+use schemars::{schema_for, Schema};
+use serde_json::{json, Value};
+
+/// Generates a JSON Schema for the config file format.
+/// The schema is derived from the [`Config`] type, so it covers every form a field can take (e.g.
+/// the shorthand and full forms of a variable).
+pub fn generate() -> String {
+    let schema = schema_for!(Config);
+    serde_json::to_string_pretty(&schema).expect("schemas can always be serialized")
+}
+
+/// The aliases of the config's fields, which schemars ignores.
+/// Each pair contains the name of a field, followed by its alias (e.g. `desc` for `description`).
+const FIELD_ALIASES: [(&str, &str); 10] = [
+    ("description", "desc"),
+    ("variables", "vars"),
+    ("commands", "cmds"),
+    ("options", "opts"),
+    ("argument", "arg"),
+    ("environment_variable", "env"),
+    ("execute", "exec"),
+    ("workdir", "wd"),
+    ("command", "cmd"),
+    ("bash", "sh"),
+];
+
+/// Adds the aliases of any of the fields in [`FIELD_ALIASES`] to the provided schema.
+/// Only used on types whose fields all accept the alias listed in [`FIELD_ALIASES`].
+pub fn add_aliases(schema: &mut Schema) {
+    for (field, alias) in FIELD_ALIASES {
+        let Some(property) = schema
+            .get("properties")
+            .and_then(|properties| properties.get(field))
+            .cloned()
+        else {
+            continue;
+        };
+
+        if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert(alias.to_string(), property);
+        }
+
+        // Required fields can be provided using either name
+        let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        let Some(index) = required.iter().position(|name| name == field) else {
+            continue;
+        };
+        required.remove(index);
+        if required.is_empty() {
+            schema.remove("required");
+        }
+
+        let either = json!({ "anyOf": [{ "required": [field] }, { "required": [alias] }] });
+        match schema.get_mut("allOf").and_then(Value::as_array_mut) {
+            Some(all_of) => all_of.push(either),
+            None => {
+                schema.insert("allOf".to_string(), json!([either]));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_covers_all_variants() {
+        // Act
+        let schema: Value = serde_json::from_str(&generate()).unwrap();
+
+        // Assert
+        let definitions = &schema["$defs"];
+        assert_eq!(
+            definitions["ActionConfig"]["anyOf"]
+                .as_array()
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(
+            definitions["VariableConfig"]["anyOf"]
+                .as_array()
+                .unwrap()
+                .len(),
+            8
+        );
+        assert_eq!(
+            definitions["StepConfigVariant"]["anyOf"][0]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn generate_includes_aliases() {
+        // Act
+        let schema: Value = serde_json::from_str(&generate()).unwrap();
+
+        // Assert
+        assert_eq!(
+            schema["properties"]["cmds"],
+            schema["properties"]["commands"]
+        );
+        assert_eq!(
+            schema["allOf"],
+            json!([{ "anyOf": [{ "required": ["commands"] }, { "required": ["cmds"] }] }])
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use crate::config::{
+    BashCommandConfig, ExecutionConfigVariant, RawCommandConfigVariant, SecretBackendKind,
+    SecretConfig, ShellCommandConfigVariant,
+};
+use crate::exec::{CommandExecutor, ExecutionError, ExitStatus};
+use crate::template::Shell;
+use crate::variables::{substitute_variables, VariableMap};
+use std::collections::BTreeMap;
+use std::env;
+use std::string::FromUtf8Error;
+use std::sync::Mutex;
+use thiserror::Error;
+
+/// The secrets that have already been read, by the command used to read them.
+/// Secrets are only read once per process, no matter how many variables reference them.
+static CACHE: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// A backend secret variables can be read from.
+/// To add a backend, add a [`SecretBackendKind`] and return its implementation from [`backend`].
+trait SecretBackend {
+    /// The name of the backend, as shown in errors.
+    fn name(&self) -> &'static str;
+
+    /// Returns the command which prints the provided secret, and fails if it doesn't exist.
+    /// Values from the config are quoted for the provided shell, so they're passed on as is.
+    fn lookup_command(&self, secret: &SecretConfig, shell: Shell) -> Result<String, SecretError>;
+}
+
+/// Reads secrets from HashiCorp Vault's key/value secrets engine with the `vault` CLI, which uses
+/// the `VAULT_ADDR` and `VAULT_TOKEN` environment variables (or the token helper).
+struct Vault;
+
+impl SecretBackend for Vault {
+    fn name(&self) -> &'static str {
+        "Vault"
+    }
+
+    fn lookup_command(&self, secret: &SecretConfig, shell: Shell) -> Result<String, SecretError> {
+        if secret.region.is_some() {
+            return Err(SecretError::UnsupportedField {
+                backend: self.name(),
+                field: "region",
+            });
+        }
+
+        let field = secret.field.as_deref().unwrap_or("value");
+        Ok(format!(
+            "vault kv get -field={} {}",
+            shell.quote(field),
+            shell.quote(&secret.path)
+        ))
+    }
+}
+
+/// Reads parameters from AWS Systems Manager Parameter Store with the `aws` CLI, decrypting
+/// secure strings.
+struct Ssm;
+
+impl SecretBackend for Ssm {
+    fn name(&self) -> &'static str {
+        "SSM"
+    }
+
+    fn lookup_command(&self, secret: &SecretConfig, shell: Shell) -> Result<String, SecretError> {
+        if secret.field.is_some() {
+            return Err(SecretError::UnsupportedField {
+                backend: self.name(),
+                field: "field",
+            });
+        }
+
+        let mut command = format!(
+            "aws ssm get-parameter --name {} --with-decryption --query Parameter.Value --output text",
+            shell.quote(&secret.path)
+        );
+        if let Some(region) = &secret.region {
+            command.push_str(&format!(" --region {}", shell.quote(region)));
+        }
+
+        Ok(command)
+    }
+}
+
+fn backend(kind: SecretBackendKind) -> &'static dyn SecretBackend {
+    match kind {
+        SecretBackendKind::Vault => &Vault,
+        SecretBackendKind::Ssm => &Ssm,
+    }
+}
+
+/// Returns the shell the lookup command is executed with on the provided operating system, as it
+/// appears in [`std::env::consts::OS`].
+fn lookup_shell(os: &str) -> Shell {
+    match os {
+        "windows" => Shell::PowerShell,
+        _ => Shell::Posix,
+    }
+}
+
+/// Returns the [`ExecutionConfigVariant`] which executes the provided lookup command with the
+/// provided shell.
+fn lookup_execution(command: String, shell: Shell) -> ExecutionConfigVariant {
+    match shell {
+        // Raw commands are split on spaces, and PowerShell joins the arguments back together
+        Shell::PowerShell => {
+            ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(format!(
+                "powershell -NoProfile -NonInteractive -Command {}",
+                command
+            )))
+        }
+        _ => ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
+            BashCommandConfig {
+                working_directory: None,
+                command,
+            },
+        )),
+    }
+}
+
+/// Reads the provided secret from its backend, or returns the cached value if it has already been
+/// read by this process.
+/// Variables are substituted into the path of the secret.
+pub fn read(
+    secret: &SecretConfig,
+    command_executor: &dyn CommandExecutor,
+    variables: &VariableMap,
+) -> Result<String, SecretError> {
+    let backend = backend(secret.backend);
+    let path = substitute_variables(&secret.path, variables);
+    let shell = lookup_shell(env::consts::OS);
+    let command = backend.lookup_command(
+        &SecretConfig {
+            path: path.clone(),
+            ..secret.clone()
+        },
+        shell,
+    )?;
+
+    if let Some(value) = CACHE.lock().unwrap().get(&command) {
+        return Ok(value.clone());
+    }
+
+    let execution = lookup_execution(command.clone(), shell);
+    let output = command_executor
+        .get_output(&execution, variables)
+        .map_err(|err| SecretError::Execution {
+            backend: backend.name(),
+            source: err,
+        })?;
+
+    if let ExitStatus::Fail(_) = output.status {
+        return Err(SecretError::NotFound {
+            backend: backend.name(),
+            path,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    let value = String::from_utf8(output.stdout)
+        .map_err(SecretError::Parse)?
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+
+    CACHE.lock().unwrap().insert(command, value.clone());
+    Ok(value)
+}
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("failed to read the secret from {backend}")]
+    Execution {
+        backend: &'static str,
+        #[source]
+        source: ExecutionError,
+    },
+
+    #[error("couldn't read the secret \"{path}\" from {backend}: {message}")]
+    NotFound {
+        backend: &'static str,
+        path: String,
+        message: String,
+    },
+
+    #[error("{backend} secrets don't support the {field} field")]
+    UnsupportedField {
+        backend: &'static str,
+        field: &'static str,
+    },
+
+    #[error("failed to parse the secret")]
+    Parse(#[source] FromUtf8Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exec::{MockCommandExecutor, Output};
+
+    #[test]
+    fn read_fetches_secrets_once() {
+        // Arrange
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .withf(|execution, _| {
+                execution.command()
+                    == "aws ssm get-parameter --name '/plz-test/read-once' --with-decryption --query Parameter.Value --output text --region 'eu-west-1'"
+            })
+            .times(1)
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: b"hunter2\n".to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let mut variables = VariableMap::new();
+        variables.insert("env".to_string(), "read-once".to_string());
+
+        let secret = SecretConfig {
+            backend: SecretBackendKind::Ssm,
+            path: "/plz-test/$env".to_string(),
+            field: None,
+            region: Some("eu-west-1".to_string()),
+        };
+
+        // Act
+        let first = read(&secret, &command_executor, &variables).unwrap();
+        let second = read(&secret, &command_executor, &variables).unwrap();
+
+        // Assert
+        assert_eq!(first, "hunter2");
+        assert_eq!(second, "hunter2");
+    }
+
+    #[test]
+    fn vault_lookup_command_reads_field() {
+        // Arrange
+        let secret = SecretConfig {
+            backend: SecretBackendKind::Vault,
+            path: "secret/db".to_string(),
+            field: Some("password".to_string()),
+            region: None,
+        };
+
+        // Act
+        let command = Vault.lookup_command(&secret, Shell::Posix).unwrap();
+        let unsupported = Ssm.lookup_command(&secret, Shell::Posix);
+
+        // Assert
+        assert_eq!(command, "vault kv get -field='password' 'secret/db'");
+        assert!(matches!(
+            unsupported,
+            Err(SecretError::UnsupportedField { field: "field", .. })
+        ));
+    }
+
+    #[test]
+    fn lookup_command_quotes_values() {
+        // Arrange
+        let secret = SecretConfig {
+            backend: SecretBackendKind::Ssm,
+            path: "/team's app/db; rm -rf ~".to_string(),
+            field: None,
+            region: Some("eu-west-1".to_string()),
+        };
+
+        // Act
+        let posix = Ssm.lookup_command(&secret, lookup_shell("linux")).unwrap();
+        let windows = lookup_execution(
+            Ssm.lookup_command(&secret, lookup_shell("windows"))
+                .unwrap(),
+            lookup_shell("windows"),
+        );
+
+        // Assert
+        assert_eq!(
+            posix,
+            "aws ssm get-parameter --name '/team'\\''s app/db; rm -rf ~' --with-decryption --query Parameter.Value --output text --region 'eu-west-1'"
+        );
+        assert_eq!(
+            windows.command(),
+            "powershell -NoProfile -NonInteractive -Command aws ssm get-parameter --name '/team''s app/db; rm -rf ~' --with-decryption --query Parameter.Value --output text --region 'eu-west-1'"
+        );
+    }
+}
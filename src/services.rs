@@ -0,0 +1,454 @@
+use crate::config::{RestartPolicy, ServicesOutput};
+use crate::exec::{ExecutionError, ExitStatus, Service, ServicesResult};
+use crate::interrupt;
+use crate::process_group::ProcessGroup;
+use colored::{Color, Colorize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use std::{fs, io, thread};
+
+/// How often to check whether any of the services have exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often to check whether any of the watched files have changed.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait before restarting a service that has exited, so a service that fails as soon
+/// as it starts doesn't keep the CPU busy.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// How long to wait for a service to exit once it has been asked to, before it's killed.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// The colors used for the name of each service, in order.
+const COLORS: &[Color] = &[
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::BrightRed,
+];
+
+/// The modification times of the files matching a service's watch patterns.
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+/// A line written by a service, and whether it was written to stderr.
+type Line = (String, bool);
+
+/// The output of a service which is printed once it exits, see [`ServicesOutput::Grouped`].
+struct GroupedOutput {
+    lines: Arc<Mutex<Vec<Line>>>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl GroupedOutput {
+    /// Waits for the service's output to be closed, returning the lines it wrote.
+    fn finish(self) -> Vec<Line> {
+        for reader in self.readers {
+            let _ = reader.join();
+        }
+
+        let mut lines = self.lines.lock().unwrap_or_else(|err| err.into_inner());
+        std::mem::take(&mut *lines)
+    }
+
+    /// Prints the lines the service wrote, with the provided prefix.
+    fn print(self, prefix: &str) {
+        for (line, to_stderr) in self.finish() {
+            print_line(prefix, &line, to_stderr);
+        }
+    }
+}
+
+enum State {
+    Running(ProcessGroup, Option<GroupedOutput>),
+
+    /// The service has exited, and will be restarted at the provided time.
+    Restarting(Instant),
+}
+
+struct RunningService<'a> {
+    service: &'a Service,
+    prefix: String,
+    state: State,
+    snapshot: Snapshot,
+}
+
+/// Executes the provided [`Service`]s, see [`crate::exec::CommandExecutor::execute_services`].
+/// Each service is started in its own process group, so stopping a service also stops any
+/// processes it has started.
+pub fn execute(
+    services: &[Service],
+    output: ServicesOutput,
+    create_command: impl Fn(&Service) -> Result<Command, ExecutionError>,
+) -> ServicesResult {
+    // Interrupts are forwarded to the services, so plz needs to stay running until they've exited
+    interrupt::install_handler().map_err(|err| ExecutionError::IO(io::Error::other(err)))?;
+
+    let width = services
+        .iter()
+        .map(|service| service.name.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    let mut running: Vec<RunningService> = Vec::new();
+    for (index, service) in services.iter().enumerate() {
+        let prefix = format!("{:<width$} |", service.name)
+            .color(COLORS[index % COLORS.len()])
+            .to_string();
+        match start(service, &create_command, &prefix, output) {
+            Ok((process_group, grouped_output)) => running.push(RunningService {
+                service,
+                prefix,
+                state: State::Running(process_group, grouped_output),
+                snapshot: snapshot(service),
+            }),
+            Err(err) => {
+                stop_all(&mut running);
+                return Err(err);
+            }
+        }
+    }
+
+    let mut last_watched = Instant::now();
+    loop {
+        if interrupt::is_interrupted() {
+            stop_all(&mut running);
+            return Ok(None);
+        }
+
+        let check_watched = last_watched.elapsed() >= WATCH_INTERVAL;
+        if check_watched {
+            last_watched = Instant::now();
+        }
+
+        for index in 0..running.len() {
+            let current = &mut running[index];
+            let service = current.service;
+            match &mut current.state {
+                State::Running(process_group, grouped_output) => {
+                    let exit_status = match process_group.child().try_wait() {
+                        Ok(exit_status) => exit_status,
+                        Err(err) => {
+                            stop_all(&mut running);
+                            return Err(ExecutionError::IO(err));
+                        }
+                    };
+
+                    if let Some(exit_status) = exit_status {
+                        // Don't leave anything the service started running
+                        process_group.kill();
+                        if let Some(grouped_output) = grouped_output.take() {
+                            grouped_output.print(&current.prefix);
+                        }
+
+                        let status = ExitStatus::from_std_exitstatus(&exit_status);
+                        notice(&current.prefix, &status.to_string());
+
+                        let restart = match service.restart {
+                            RestartPolicy::Never => false,
+                            RestartPolicy::OnFailure => status != ExitStatus::Success,
+                            RestartPolicy::Always => true,
+                        };
+                        if !restart {
+                            stop_all(&mut running);
+                            return Ok(Some((service.name.clone(), status)));
+                        }
+
+                        current.state = State::Restarting(Instant::now() + RESTART_DELAY);
+                        continue;
+                    }
+
+                    if check_watched && !service.watch.is_empty() {
+                        let snapshot = snapshot(service);
+                        if snapshot != current.snapshot {
+                            notice(&current.prefix, "files changed");
+                            let _ = process_group.stop(STOP_GRACE_PERIOD);
+                            if let Some(grouped_output) = grouped_output.take() {
+                                grouped_output.print(&current.prefix);
+                            }
+                            current.state = State::Restarting(Instant::now());
+                        }
+                    }
+                }
+                State::Restarting(restart_at) => {
+                    if Instant::now() < *restart_at {
+                        continue;
+                    }
+
+                    notice(&current.prefix, "restarting");
+                    match start(service, &create_command, &current.prefix, output) {
+                        Ok((process_group, grouped_output)) => {
+                            current.state = State::Running(process_group, grouped_output);
+                            current.snapshot = snapshot(service);
+                        }
+                        Err(err) => {
+                            stop_all(&mut running);
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Starts the provided service, forwarding its output with the provided prefix.
+/// When the output is grouped, it's collected instead, and returned along with the process group
+/// so it can be printed once the service exits.
+fn start(
+    service: &Service,
+    create_command: &impl Fn(&Service) -> Result<Command, ExecutionError>,
+    prefix: &str,
+    output: ServicesOutput,
+) -> Result<(ProcessGroup, Option<GroupedOutput>), ExecutionError> {
+    let mut command = create_command(service)?;
+    let mut process_group = ProcessGroup::spawn(
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )
+    .map_err(ExecutionError::IO)?;
+
+    let lines = match output {
+        ServicesOutput::Interleaved => None,
+        ServicesOutput::Grouped => Some(Arc::new(Mutex::new(Vec::new()))),
+    };
+
+    let child = process_group.child();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(forward_lines(
+            stdout,
+            prefix.to_string(),
+            false,
+            lines.clone(),
+        ));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(forward_lines(
+            stderr,
+            prefix.to_string(),
+            true,
+            lines.clone(),
+        ));
+    }
+
+    let grouped_output = lines.map(|lines| GroupedOutput { lines, readers });
+    Ok((process_group, grouped_output))
+}
+
+/// Forwards each line read from `reader` to stdout (or stderr) with the provided prefix, on a
+/// separate thread, until the stream is closed.
+/// If `lines` is provided, the lines are added to it instead of being printed.
+fn forward_lines(
+    reader: impl Read + Send + 'static,
+    prefix: String,
+    to_stderr: bool,
+    lines: Option<Arc<Mutex<Vec<Line>>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).split(b'\n') {
+            let Ok(line) = line else {
+                break;
+            };
+
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches('\r');
+            match &lines {
+                Some(lines) => lines
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push((line.to_string(), to_stderr)),
+                None => print_line(&prefix, line, to_stderr),
+            }
+        }
+    })
+}
+
+/// Prints a line written by a service to stdout (or stderr) with the provided prefix.
+fn print_line(prefix: &str, line: &str, to_stderr: bool) {
+    match to_stderr {
+        true => eprintln!("{} {}", prefix, line),
+        false => println!("{} {}", prefix, line),
+    }
+}
+
+/// Prints a message about a service (e.g. that it has exited).
+fn notice(prefix: &str, message: &str) {
+    eprintln!("{} {}", prefix, message.bold());
+}
+
+/// Asks every running service to exit, waiting for them (and killing them if they take too long).
+/// Services are stopped at the same time, so the grace period applies to all of them.
+/// Any grouped output is printed once the services have stopped.
+fn stop_all(running: &mut [RunningService]) {
+    let mut stopping: Vec<(&str, &mut ProcessGroup, Option<GroupedOutput>)> = running
+        .iter_mut()
+        .filter_map(|current| match &mut current.state {
+            State::Running(process_group, grouped_output) => Some((
+                current.prefix.as_str(),
+                process_group,
+                grouped_output.take(),
+            )),
+            State::Restarting(_) => None,
+        })
+        .collect();
+
+    // Ask each of them to exit first, so slow services don't delay the others
+    for (_, process_group, _) in stopping.iter_mut() {
+        process_group.terminate();
+    }
+    for (prefix, process_group, grouped_output) in stopping {
+        let _ = process_group.stop(STOP_GRACE_PERIOD);
+        if let Some(grouped_output) = grouped_output {
+            grouped_output.print(prefix);
+        }
+    }
+}
+
+/// Returns the modification times of the files matching the service's watch patterns.
+/// Relative patterns are matched from the service's working directory.
+fn snapshot(service: &Service) -> Snapshot {
+    let working_directory = service
+        .execution
+        .working_directory()
+        .map(|working_directory| {
+            crate::variables::substitute_variables(working_directory, &service.variables)
+        });
+
+    let mut snapshot = Snapshot::new();
+    for pattern in &service.watch {
+        let pattern = match &working_directory {
+            Some(working_directory) => Path::new(working_directory).join(pattern),
+            None => PathBuf::from(pattern),
+        };
+
+        let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+            continue;
+        };
+        for path in paths.flatten() {
+            if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+
+    snapshot
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::config::{ExecutionConfigVariant, RawCommandConfigVariant};
+    use crate::variables::VariableMap;
+    use tempfile::TempDir;
+
+    fn service(name: &str, restart: RestartPolicy) -> Service {
+        Service {
+            name: name.to_string(),
+            execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                String::new(),
+            )),
+            variables: VariableMap::new(),
+            restart,
+            watch: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn execute_stops_services_when_one_exits() {
+        // Arrange
+        let services = vec![
+            service("server", RestartPolicy::Never),
+            service("worker", RestartPolicy::Never),
+        ];
+
+        // Act
+        let started = Instant::now();
+        let result = execute(&services, ServicesOutput::Interleaved, |service| {
+            let script = match service.name.as_str() {
+                "server" => "sleep 30",
+                _ => "exit 3",
+            };
+            let mut command = Command::new("bash");
+            command.args(["-c", script]);
+            Ok(command)
+        });
+
+        // Assert
+        assert_eq!(
+            result.unwrap(),
+            Some(("worker".to_string(), ExitStatus::Fail(3)))
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn start_collects_grouped_output() {
+        // Arrange
+        let service = service("worker", RestartPolicy::Never);
+        let create_command = |_: &Service| {
+            let mut command = Command::new("bash");
+            command.args(["-c", "echo one; echo two >&2; echo three"]);
+            Ok(command)
+        };
+
+        // Act
+        let (mut process_group, grouped_output) = start(
+            &service,
+            &create_command,
+            "worker |",
+            ServicesOutput::Grouped,
+        )
+        .unwrap();
+        process_group.child().wait().unwrap();
+        let lines = grouped_output.unwrap().finish();
+
+        // Assert
+        let stdout: Vec<&str> = lines
+            .iter()
+            .filter(|(_, to_stderr)| !to_stderr)
+            .map(|(line, _)| line.as_str())
+            .collect();
+        assert_eq!(stdout, ["one", "three"]);
+        assert!(lines.contains(&("two".to_string(), true)));
+    }
+
+    #[test]
+    fn execute_restarts_services_on_failure() {
+        // Arrange
+        let temp_dir = TempDir::new().unwrap();
+        let counter = temp_dir.path().join("counter");
+        let services = vec![service("flaky", RestartPolicy::OnFailure)];
+
+        // Act
+        // Fails the first time it's started, then succeeds
+        let result = execute(&services, ServicesOutput::Interleaved, |_| {
+            let mut command = Command::new("bash");
+            command.args([
+                "-c",
+                &format!(
+                    "if [ -f {0} ]; then exit 0; else touch {0}; exit 1; fi",
+                    counter.display()
+                ),
+            ]);
+            Ok(command)
+        });
+
+        // Assert
+        assert_eq!(
+            result.unwrap(),
+            Some(("flaky".to_string(), ExitStatus::Success))
+        );
+    }
+}
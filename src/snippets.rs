@@ -0,0 +1,256 @@
+use linked_hash_map::LinkedHashMap;
+use serde_yaml::Value;
+use thiserror::Error;
+
+/// The name of the function used to reference a snippet (e.g. `{{ snippet("docker_login") }}`).
+const SNIPPET_FUNCTION: &str = "snippet";
+
+/// Replaces any references to the snippets defined in the `snippets` section of the provided
+/// config with their text, and removes the `snippets` section.
+/// Snippets are expanded before the config is parsed, so the rest of plz only ever sees the
+/// expanded commands.
+/// Returns `true` if the config was changed.
+pub fn expand(config: &mut Value) -> Result<bool, SnippetError> {
+    let Some(mapping) = config.as_mapping_mut() else {
+        return Ok(false);
+    };
+
+    let snippets = match mapping.remove("snippets") {
+        Some(snippets) => parse_snippets(snippets)?,
+        None => LinkedHashMap::new(),
+    };
+
+    let mut expanded = false;
+    for key in ["commands", "cmds"] {
+        if let Some(commands) = mapping.get_mut(key) {
+            expanded |= expand_value(commands, key, &snippets)?;
+        }
+    }
+
+    Ok(expanded || !snippets.is_empty())
+}
+
+fn parse_snippets(value: Value) -> Result<LinkedHashMap<String, String>, SnippetError> {
+    let mut snippets = LinkedHashMap::new();
+    let Value::Mapping(mapping) = value else {
+        return Ok(snippets);
+    };
+
+    for (name, snippet) in mapping {
+        let name = match name {
+            Value::String(name) => name,
+            name => serde_yaml::to_string(&name)
+                .map(|name| name.trim_end().to_string())
+                .unwrap_or_default(),
+        };
+        let Value::String(text) = snippet else {
+            return Err(SnippetError::NotText { snippet: name });
+        };
+
+        // Block scalars end with a newline, which would split the line the snippet is used in
+        let text = text.strip_suffix('\n').unwrap_or(&text).to_string();
+        snippets.insert(name, text);
+    }
+
+    // Snippets can use other snippets, so expand them before they're used
+    let names: Vec<String> = snippets.keys().cloned().collect();
+    for name in names {
+        let location = format!("the snippet \"{}\"", name);
+        let text = expand_text(
+            &snippets[&name],
+            &location,
+            &snippets,
+            &mut vec![name.clone()],
+        )?;
+        snippets.insert(name, text);
+    }
+
+    Ok(snippets)
+}
+
+/// Replaces the snippet references in every string within the provided value.
+/// `path` is the path to the value within the config, which is used in errors.
+fn expand_value(
+    value: &mut Value,
+    path: &str,
+    snippets: &LinkedHashMap<String, String>,
+) -> Result<bool, SnippetError> {
+    Ok(match value {
+        Value::String(text) => {
+            let expanded = expand_text(text, path, snippets, &mut Vec::new())?;
+            let changed = expanded != *text;
+            *text = expanded;
+            changed
+        }
+        Value::Sequence(sequence) => {
+            let mut changed = false;
+            for (index, value) in sequence.iter_mut().enumerate() {
+                changed |= expand_value(value, &format!("{}[{}]", path, index), snippets)?;
+            }
+            changed
+        }
+        Value::Mapping(mapping) => {
+            let mut changed = false;
+            for (key, value) in mapping.iter_mut() {
+                let path = match key {
+                    Value::String(key) => format!("{}.{}", path, key),
+                    _ => path.to_string(),
+                };
+                changed |= expand_value(value, &path, snippets)?;
+            }
+            changed
+        }
+        _ => false,
+    })
+}
+
+/// Replaces the snippet references in the provided text with the snippets' text.
+/// `location` describes where the text is from, which is used in errors.
+/// `stack` contains the snippets currently being expanded, so snippets can't use themselves.
+/// Any other `{{ ... }}` tags are left as is.
+fn expand_text(
+    text: &str,
+    location: &str,
+    snippets: &LinkedHashMap<String, String>,
+    stack: &mut Vec<String>,
+) -> Result<String, SnippetError> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}").map(|end| end + 2) else {
+            break;
+        };
+
+        let tag = &rest[start..start + length];
+        result.push_str(&rest[..start]);
+        rest = &rest[start + length..];
+
+        let Some(name) = snippet_name(tag) else {
+            result.push_str(tag);
+            continue;
+        };
+
+        if stack.iter().any(|current| current == name) {
+            return Err(SnippetError::Recursive {
+                snippet: name.to_string(),
+            });
+        }
+
+        let snippet = snippets
+            .get(name)
+            .ok_or_else(|| SnippetError::UnknownSnippet {
+                location: location.to_string(),
+                snippet: name.to_string(),
+            })?;
+
+        stack.push(name.to_string());
+        let location = format!("the snippet \"{}\"", name);
+        result.push_str(&expand_text(snippet, &location, snippets, stack)?);
+        stack.pop();
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Returns the name of the snippet if the provided tag references one (e.g.
+/// `{{ snippet("docker_login") }}`).
+fn snippet_name(tag: &str) -> Option<&str> {
+    let inner = tag.strip_prefix("{{")?.strip_suffix("}}")?;
+    let inner = inner.trim_matches(|c: char| c == '-' || c.is_whitespace());
+    let argument = inner
+        .strip_prefix(SNIPPET_FUNCTION)?
+        .trim_start()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .trim();
+
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| argument.strip_prefix(*quote)?.strip_suffix(*quote))
+}
+
+#[derive(Error, Debug)]
+pub enum SnippetError {
+    #[error("{location} uses the snippet \"{snippet}\", which doesn't exist")]
+    UnknownSnippet { location: String, snippet: String },
+
+    #[error("the snippet \"{snippet}\" isn't text")]
+    NotText { snippet: String },
+
+    #[error("the snippet \"{snippet}\" uses itself")]
+    Recursive { snippet: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, ActionConfig, Platform};
+
+    #[test]
+    fn expand_replaces_snippet_references() {
+        // Arrange
+        let yaml = r#"
+snippets:
+  docker_login: echo $TOKEN | docker login -u ci --password-stdin
+  push: |
+    {{ snippet("docker_login") }} && docker push {{ image }}
+
+commands:
+  push:
+    variables:
+      image: api
+    actions:
+      - bash: '{{ snippet("push") }}'
+      - docker ps --format '{{ .Names }}'
+"#;
+
+        // Act
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Assert
+        let Some(ActionConfig::MultiStep(action)) = &config.commands["push"].action else {
+            panic!("expected a multi-step action");
+        };
+        assert_eq!(
+            action.actions[0].execution.command(),
+            "echo $TOKEN | docker login -u ci --password-stdin && docker push {{ image }}"
+        );
+        assert_eq!(
+            action.actions[1].execution.command(),
+            "docker ps --format '{{ .Names }}'"
+        );
+    }
+
+    #[test]
+    fn expand_rejects_unknown_and_recursive_snippets() {
+        // Arrange
+        let config = |snippets: &str| {
+            serde_yaml::from_str::<Value>(&format!(
+                r#"
+snippets: {}
+commands:
+  build:
+    action: "{{{{ snippet('setup') }}}} && make"
+"#,
+                snippets
+            ))
+            .unwrap()
+        };
+
+        // Act
+        let unknown = expand(&mut config("{}"));
+        let recursive = expand(&mut config("{ setup: \"{{ snippet('setup') }}\" }"));
+
+        // Assert
+        assert!(matches!(
+            unknown,
+            Err(SnippetError::UnknownSnippet { location, snippet })
+                if location == "commands.build.action" && snippet == "setup"
+        ));
+        assert!(matches!(
+            recursive,
+            Err(SnippetError::Recursive { snippet }) if snippet == "setup"
+        ));
+    }
+}
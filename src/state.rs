@@ -0,0 +1,275 @@
+use crate::config;
+use crate::duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// The name of the directory (relative to the config file) where plz stores its state.
+pub const STATE_DIR_NAME: &str = ".plz";
+
+const STATE_FILE_NAME: &str = "state";
+
+/// The argument used to execute the last command that failed again.
+pub const RETRY_FAILED_ARG: &str = "--retry-failed";
+
+/// Returns `true` if the retry failed argument was provided before the command, so commands can
+/// still accept an argument with the same name.
+pub fn retry_requested(args: &[String]) -> bool {
+    config::leading_args(args)
+        .iter()
+        .any(|arg| arg == RETRY_FAILED_ARG)
+}
+
+/// Information that plz persists between executions.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct State {
+    /// The time each command was last executed successfully, in seconds since the Unix epoch.
+    /// Commands are identified by their full path (e.g. `deploy store`).
+    #[serde(default)]
+    pub last_executed: HashMap<String, u64>,
+
+    /// The last command that failed, so it can be executed again with `--retry-failed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_run: Option<FailedRun>,
+}
+
+/// A command that failed, and how it was invoked.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct FailedRun {
+    /// The full path of the command (e.g. `deploy store`).
+    pub command: String,
+
+    /// The directory plz was invoked from.
+    pub dir: PathBuf,
+
+    /// The arguments plz was invoked with, excluding the binary name.
+    pub args: Vec<String>,
+}
+
+impl State {
+    /// Loads the [`State`] from the provided state directory.
+    /// If no state has been saved yet, an empty [`State`] is returned.
+    pub fn load(state_dir: &Path) -> Result<State, StateError> {
+        let state_file_path = state_file_path(state_dir);
+        if !state_file_path.exists() {
+            return Ok(State::default());
+        }
+
+        let text = fs::read_to_string(&state_file_path).map_err(StateError::ReadFailed)?;
+        serde_yaml::from_str(&text).map_err(StateError::ParseFailed)
+    }
+
+    /// Saves the [`State`] to the provided state directory, creating it if necessary.
+    pub fn save(&self, state_dir: &Path) -> Result<(), StateError> {
+        fs::create_dir_all(state_dir).map_err(StateError::WriteFailed)?;
+
+        let text = serde_yaml::to_string(self).map_err(StateError::SerializeFailed)?;
+        fs::write(state_file_path(state_dir), text).map_err(StateError::WriteFailed)
+    }
+
+    /// Records that the provided command was executed at the provided time.
+    pub fn record_execution(&mut self, command: &str, time: SystemTime) {
+        self.last_executed
+            .insert(command.to_string(), seconds_since_epoch(time));
+    }
+
+    /// Records the provided command as the last command that failed, replacing any previous one.
+    pub fn record_failure(&mut self, failed_run: FailedRun) {
+        self.failed_run = Some(failed_run);
+    }
+
+    /// Forgets the last command that failed if it was the provided command, since it has now
+    /// succeeded. Returns `true` if it was forgotten.
+    pub fn clear_failure(&mut self, command: &str) -> bool {
+        if self
+            .failed_run
+            .as_ref()
+            .is_some_and(|failed_run| failed_run.command == command)
+        {
+            self.failed_run = None;
+            return true;
+        }
+
+        false
+    }
+
+    /// Returns an error if the provided command was last executed less than `cooldown` ago.
+    pub fn check_cooldown(
+        &self,
+        command: &str,
+        cooldown: &Duration,
+        now: SystemTime,
+    ) -> Result<(), StateError> {
+        let Some(last_executed) = self.last_executed.get(command) else {
+            return Ok(());
+        };
+
+        let elapsed = seconds_since_epoch(now).saturating_sub(*last_executed);
+        if elapsed >= cooldown.as_secs() {
+            return Ok(());
+        }
+
+        let remaining = Duration::from_secs(cooldown.as_secs() - elapsed);
+        Err(StateError::CooldownActive {
+            command: command.to_string(),
+            remaining: duration::format(&remaining),
+        })
+    }
+}
+
+/// Returns the directory where per-user state is stored (e.g. `~/.local/state/plz`), or `None` if
+/// the user's home directory can't be determined.
+/// Unlike the [`STATE_DIR_NAME`] directory, this is shared between projects, and can't be changed
+/// by a config file.
+pub fn user_state_dir(get_env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let state_home = get_env("XDG_STATE_HOME")
+        .or_else(|| get_env("LOCALAPPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| {
+            get_env("HOME")
+                .or_else(|| get_env("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+
+    Some(state_home.join("plz"))
+}
+
+/// Returns the key used to identify a config file within per-user state.
+/// Config files are identified by their absolute path, so per-user state doesn't carry over when a
+/// config file is moved.
+pub fn config_key(config_path: &Path) -> String {
+    fs::canonicalize(config_path)
+        .unwrap_or(config_path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Returns the path of the state file within the provided state directory.
+pub fn state_file_path(state_dir: &Path) -> PathBuf {
+    state_dir.join(STATE_FILE_NAME)
+}
+
+/// Returns the number of whole seconds between the Unix epoch and the provided time.
+pub fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("failed to read state")]
+    ReadFailed(#[source] io::Error),
+
+    #[error("failed to write state")]
+    WriteFailed(#[source] io::Error),
+
+    #[error("failed to parse state")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("failed to serialize state")]
+    SerializeFailed(#[source] serde_yaml::Error),
+
+    #[error("\"{command}\" was executed recently, try again in {remaining} (or use --force)")]
+    CooldownActive { command: String, remaining: String },
+
+    #[error("no command has failed, so there's nothing to retry")]
+    NoFailedRun,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn retry_requested_only_checks_arguments_before_the_command() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert!(retry_requested(&args(&[
+            "plz",
+            "--trust",
+            "--retry-failed"
+        ])));
+        assert!(!retry_requested(&args(&[
+            "plz",
+            "run",
+            "hi",
+            "--retry-failed"
+        ])));
+    }
+
+    #[test]
+    fn state_round_trips() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let state_dir = dir.path().join(STATE_DIR_NAME);
+
+        let mut state = State::load(&state_dir).unwrap();
+        state.record_execution("deploy store", UNIX_EPOCH + Duration::from_secs(1000));
+
+        // Act
+        state.save(&state_dir).unwrap();
+        let loaded_state = State::load(&state_dir).unwrap();
+
+        // Assert
+        assert_eq!(loaded_state.last_executed.get("deploy store"), Some(&1000));
+    }
+
+    #[test]
+    fn clear_failure_only_clears_same_command() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let state_dir = dir.path().join(STATE_DIR_NAME);
+        let failed_run = FailedRun {
+            command: "test integration".to_string(),
+            dir: dir.path().to_path_buf(),
+            args: vec!["test".to_string(), "integration".to_string()],
+        };
+
+        let mut state = State::default();
+        state.record_failure(failed_run.clone());
+        state.save(&state_dir).unwrap();
+
+        // Act
+        let mut loaded_state = State::load(&state_dir).unwrap();
+        let cleared_other = loaded_state.clear_failure("build");
+        let loaded_failed_run = loaded_state.failed_run.clone();
+        let cleared_same = loaded_state.clear_failure("test integration");
+
+        // Assert
+        assert!(!cleared_other);
+        assert_eq!(loaded_failed_run, Some(failed_run));
+        assert!(cleared_same);
+        assert_eq!(loaded_state.failed_run, None);
+    }
+
+    #[test]
+    fn check_cooldown_refuses_within_window() {
+        // Arrange
+        let mut state = State::default();
+        state.record_execution("purge", UNIX_EPOCH + Duration::from_secs(1000));
+
+        let cooldown = Duration::from_secs(300);
+
+        // Act
+        let within_window =
+            state.check_cooldown("purge", &cooldown, UNIX_EPOCH + Duration::from_secs(1048));
+        let after_window =
+            state.check_cooldown("purge", &cooldown, UNIX_EPOCH + Duration::from_secs(1300));
+        let other_command =
+            state.check_cooldown("deploy", &cooldown, UNIX_EPOCH + Duration::from_secs(1048));
+
+        // Assert
+        match within_window {
+            Err(StateError::CooldownActive { remaining, .. }) => assert_eq!(remaining, "4m 12s"),
+            _ => panic!("expected cooldown to be active"),
+        }
+        assert!(after_window.is_ok());
+        assert!(other_command.is_ok());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::config::Options;
+use crate::exec::ExitStatus;
+use colored::Colorize;
+use std::env;
+use std::time::Duration;
+
+/// The set of symbols used in step headers.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StepSymbols {
+    Unicode,
+
+    /// Used for terminals which can't display Unicode symbols.
+    Ascii,
+}
+
+impl StepSymbols {
+    /// Returns the [`StepSymbols`] to use for step headers, or `None` if step headers are disabled.
+    pub fn from_options(options: &Options) -> Option<StepSymbols> {
+        if !options.step_headers {
+            return None;
+        }
+
+        let is_dumb_terminal = env::var("TERM").is_ok_and(|term| term == "dumb");
+        if options.ascii_symbols || is_dumb_terminal {
+            Some(StepSymbols::Ascii)
+        } else {
+            Some(StepSymbols::Unicode)
+        }
+    }
+}
+
+/// Something that happened to a step, which a header is printed for.
+#[derive(PartialEq, Debug)]
+pub enum StepEvent<'a> {
+    Started,
+    Succeeded(Duration),
+    Failed(Option<&'a ExitStatus>),
+    Skipped,
+}
+
+/// Prints the header for the provided [`StepEvent`] to stderr.
+pub fn print(symbols: StepSymbols, label: &str, event: &StepEvent) {
+    let header = format(symbols, label, event);
+    let header = match event {
+        StepEvent::Started => header.bold(),
+        StepEvent::Succeeded(_) => header.green(),
+        StepEvent::Failed(_) => header.red(),
+        StepEvent::Skipped => header.dimmed(),
+    };
+
+    eprintln!("{}", header);
+}
+
+/// Formats the header for the provided [`StepEvent`] (e.g. `✔ build (2.3s)`).
+fn format(symbols: StepSymbols, label: &str, event: &StepEvent) -> String {
+    let symbol = match (symbols, event) {
+        (StepSymbols::Unicode, StepEvent::Started) => "▶",
+        (StepSymbols::Unicode, StepEvent::Succeeded(_)) => "✔",
+        (StepSymbols::Unicode, StepEvent::Failed(_)) => "✖",
+        (StepSymbols::Unicode, StepEvent::Skipped) => "○",
+        (StepSymbols::Ascii, StepEvent::Started) => ">",
+        (StepSymbols::Ascii, StepEvent::Succeeded(_)) => "+",
+        (StepSymbols::Ascii, StepEvent::Failed(_)) => "x",
+        (StepSymbols::Ascii, StepEvent::Skipped) => "-",
+    };
+
+    match event {
+        StepEvent::Started => format!("{} {}", symbol, label),
+        StepEvent::Succeeded(duration) => {
+            format!("{} {} ({:.1}s)", symbol, label, duration.as_secs_f64())
+        }
+        StepEvent::Failed(status) => {
+            let code = status
+                .and_then(ExitStatus::code)
+                .map(|code| code.to_string())
+                .unwrap_or("?".to_string());
+            format!("{} {} (exit {})", symbol, label, code)
+        }
+        StepEvent::Skipped => format!("{} {} (skipped)", symbol, label),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_formats_unicode_headers() {
+        // Arrange
+        let status = ExitStatus::Fail(1);
+
+        // Act
+        let started = format(StepSymbols::Unicode, "build", &StepEvent::Started);
+        let succeeded = format(
+            StepSymbols::Unicode,
+            "build",
+            &StepEvent::Succeeded(Duration::from_millis(2300)),
+        );
+        let failed = format(
+            StepSymbols::Unicode,
+            "test",
+            &StepEvent::Failed(Some(&status)),
+        );
+        let skipped = format(StepSymbols::Unicode, "lint", &StepEvent::Skipped);
+
+        // Assert
+        assert_eq!(started, "▶ build");
+        assert_eq!(succeeded, "✔ build (2.3s)");
+        assert_eq!(failed, "✖ test (exit 1)");
+        assert_eq!(skipped, "○ lint (skipped)");
+    }
+
+    #[test]
+    fn format_formats_ascii_headers() {
+        // Act
+        let started = format(StepSymbols::Ascii, "build", &StepEvent::Started);
+        let failed = format(StepSymbols::Ascii, "test", &StepEvent::Failed(None));
+
+        // Assert
+        assert_eq!(started, "> build");
+        assert_eq!(failed, "x test (exit ?)");
+    }
+}
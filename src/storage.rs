@@ -0,0 +1,201 @@
+use crate::cache::{CACHE_DIR_NAME, VARIABLES_CACHE_FILE_NAME};
+use crate::{defaults, lock, remote, state, trust};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The kinds of data plz stores on disk between executions.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StorageKind {
+    /// The parsed config, cached so it doesn't need to be parsed again.
+    Config,
+
+    /// The output of execution variables with a `cache`, reused until it expires.
+    Variables,
+
+    /// When each command was last executed, used for cooldowns, and the last command that failed.
+    State,
+
+    /// The lock files used to prevent commands from being executed by multiple processes.
+    Locks,
+
+    /// Downloaded remote imports, shared between projects.
+    Imports,
+
+    /// The config files the user has agreed to trust, shared between projects.
+    Trusted,
+
+    /// The default argument values saved by the user with `plz config set`.
+    Defaults,
+}
+
+impl StorageKind {
+    pub const ALL: [StorageKind; 7] = [
+        StorageKind::Config,
+        StorageKind::Variables,
+        StorageKind::State,
+        StorageKind::Locks,
+        StorageKind::Imports,
+        StorageKind::Trusted,
+        StorageKind::Defaults,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StorageKind::Config => "config",
+            StorageKind::Variables => "variables",
+            StorageKind::State => "state",
+            StorageKind::Locks => "locks",
+            StorageKind::Imports => "imports",
+            StorageKind::Trusted => "trusted",
+            StorageKind::Defaults => "defaults",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StorageKind> {
+        StorageKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+
+    /// Returns the path this kind of data is stored at, or `None` if the user's home directory
+    /// can't be determined.
+    /// Project-specific data is stored within the provided state directory.
+    pub fn path(&self, state_dir: &Path) -> Option<PathBuf> {
+        match self {
+            StorageKind::Config => Some(state_dir.join(CACHE_DIR_NAME)),
+            StorageKind::Variables => Some(state_dir.join(VARIABLES_CACHE_FILE_NAME)),
+            StorageKind::State => Some(state::state_file_path(state_dir)),
+            StorageKind::Locks => Some(lock::locks_dir(state_dir)),
+            StorageKind::Imports => remote::cache_dir(),
+            StorageKind::Trusted => trust::store_path(|name| std::env::var(name).ok()),
+            StorageKind::Defaults => defaults::store_path(|name| std::env::var(name).ok()),
+        }
+    }
+}
+
+/// Returns the number of bytes used by the file or directory at the provided path, or `0` if it
+/// doesn't exist.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+/// Removes the data of the provided kind stored at the provided path, returning the number of
+/// bytes that were freed.
+/// Lock files held by a running command are kept, so the command is still protected.
+pub fn clean(kind: StorageKind, path: &Path) -> Result<u64, StorageError> {
+    let map_err = |source| StorageError::CleanFailed {
+        path: path.to_string_lossy().to_string(),
+        source,
+    };
+
+    let usage = disk_usage(path);
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    match kind {
+        StorageKind::Locks => lock::remove_released(path).map_err(map_err)?,
+        _ if path.is_dir() => fs::remove_dir_all(path).map_err(map_err)?,
+        _ => fs::remove_file(path).map_err(map_err)?,
+    }
+
+    Ok(usage.saturating_sub(disk_usage(path)))
+}
+
+/// Formats a number of bytes using the largest unit that keeps the value above 1 (e.g. `1.5 KiB`).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("failed to remove {path}")]
+    CleanFailed {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{State, STATE_DIR_NAME};
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    #[test]
+    fn clean_removes_data_and_keeps_held_locks() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let state_dir = dir.path().join(STATE_DIR_NAME);
+        let mut state = State::default();
+        state.record_execution("deploy", SystemTime::now());
+        state.save(&state_dir).unwrap();
+
+        let _held_lock = lock::acquire(&state_dir, "db migrate", None).unwrap();
+        drop(lock::acquire(&state_dir, "db seed", None).unwrap());
+
+        let state_path = StorageKind::State.path(&state_dir).unwrap();
+        let locks_path = StorageKind::Locks.path(&state_dir).unwrap();
+        let state_usage = disk_usage(&state_path);
+
+        // Act
+        let state_freed = clean(StorageKind::State, &state_path).unwrap();
+        clean(StorageKind::Locks, &locks_path).unwrap();
+        let config_freed = clean(
+            StorageKind::Config,
+            &StorageKind::Config.path(&state_dir).unwrap(),
+        )
+        .unwrap();
+
+        // Assert
+        assert!(state_usage > 0);
+        assert_eq!(state_freed, state_usage);
+        assert!(!state_path.exists());
+        assert_eq!(config_freed, 0);
+
+        let lock_files: Vec<String> = fs::read_dir(&locks_path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(lock_files, vec!["db%20migrate.lock"]);
+    }
+
+    #[test]
+    fn format_size_uses_largest_unit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
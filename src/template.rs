@@ -0,0 +1,373 @@
+use crate::variables::VariableMap;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tera::{Context, Tera};
+
+/// The prefix of the variables which can also be referenced through the `plz` object (e.g.
+/// `PLZ_EXIT_CODE` as `plz.exit_code`).
+const PLZ_PREFIX: &str = "PLZ_";
+
+/// The name of the list containing the arguments passed to an alias (e.g. `{{ args }}`).
+pub const ARGS_NAME: &str = "args";
+
+/// The keywords, operators, and tests which can appear within a Tera tag, and aren't variables.
+const TERA_KEYWORDS: &[&str] = &[
+    "if",
+    "elif",
+    "else",
+    "endif",
+    "for",
+    "in",
+    "endfor",
+    "set",
+    "set_global",
+    "raw",
+    "endraw",
+    "filter",
+    "endfilter",
+    "and",
+    "or",
+    "not",
+    "is",
+    "true",
+    "false",
+    "loop",
+    "break",
+    "continue",
+];
+
+/// The shell a rendered template will be run by, which determines how the `quote` filter escapes
+/// values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Shell {
+    /// bash, or any other POSIX shell.
+    Posix,
+    PowerShell,
+
+    /// No shell, for text which isn't run by a shell (e.g. raw commands, which are split on spaces
+    /// and executed directly).
+    None,
+}
+
+impl Shell {
+    /// Wraps the provided text in single quotes so the shell treats it as a literal.
+    /// Text is left as is when there's no shell, since the quotes would be passed on literally.
+    pub fn quote(&self, text: &str) -> String {
+        match self {
+            Shell::Posix => format!("'{}'", text.replace('\'', "'\\''")),
+            // PowerShell also treats typographic single quotes as quotes, and all of them are escaped
+            // by doubling them
+            Shell::PowerShell => {
+                let mut quoted = String::from("'");
+                for ch in text.chars() {
+                    if matches!(ch, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                        quoted.push(ch);
+                    }
+                    quoted.push(ch);
+                }
+                quoted.push('\'');
+                quoted
+            }
+            Shell::None => text.to_string(),
+        }
+    }
+}
+
+/// Renders the provided template with Tera, so it can use conditionals, loops, and filters (e.g.
+/// `{% if verbose %}--verbose{% endif %}` or `{{ name | upper }}`).
+/// Variables are available by name, and variables starting with `PLZ_` are also available through
+/// the `plz` object (e.g. `{{ plz.exit_code }}`). Variables set to `true` or `false` are treated as
+/// booleans, so they can be used as conditions.
+/// Values can be escaped for a shell with the `sh_quote` and `ps_quote` filters, or with the `quote`
+/// filter for the provided shell.
+/// Variables which aren't set are treated as `false` in conditions, but fail to render when
+/// they're output (e.g. `{{ missing }}`), unless a `default` filter is used.
+/// Fails if the template isn't valid, or outputs a variable which isn't set.
+pub fn render(
+    template: &str,
+    variables: &VariableMap,
+    shell: Shell,
+) -> Result<String, tera::Error> {
+    render_with_args(template, variables, None, shell)
+}
+
+/// Like [`render`], but also makes the provided arguments available as the `args` list, so they
+/// can be used with filters and loops (e.g. `{{ args | join(sep=",") }}`).
+pub fn render_with_args(
+    template: &str,
+    variables: &VariableMap,
+    args: Option<&[String]>,
+    shell: Shell,
+) -> Result<String, tera::Error> {
+    let mut context = Context::new();
+    let mut plz = Map::new();
+    for (name, value) in variables {
+        let value = match value.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(value.clone()),
+        };
+
+        if let Some(plz_name) = name.strip_prefix(PLZ_PREFIX) {
+            plz.insert(plz_name.to_lowercase(), value.clone());
+        }
+        context.insert(name, &value);
+    }
+    if let Some(args) = args {
+        context.insert(ARGS_NAME, args);
+    }
+    context.insert("plz", &plz);
+
+    let mut tera = Tera::default();
+    tera.register_filter("sh_quote", quote_filter(Shell::Posix));
+    tera.register_filter("ps_quote", quote_filter(Shell::PowerShell));
+    tera.register_filter("quote", quote_filter(shell));
+    tera.render_str(template, &context)
+}
+
+/// Returns a Tera filter which quotes values for the provided shell.
+fn quote_filter(
+    shell: Shell,
+) -> impl Fn(&Value, &HashMap<String, Value>) -> tera::Result<Value> + Send + Sync {
+    move |value, _| {
+        let text = match value {
+            Value::String(text) => text.clone(),
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            _ => {
+                return Err(tera::Error::msg(
+                    "only strings, numbers, and booleans can be quoted",
+                ))
+            }
+        };
+
+        Ok(Value::String(shell.quote(&text)))
+    }
+}
+
+/// Returns `true` if the provided text contains a Tera tag (e.g. `{{ name }}` or `{% if name %}`).
+pub fn has_tags(text: &str) -> bool {
+    text.contains("{{") || text.contains("{%")
+}
+
+/// Returns `true` if the provided template uses syntax only Tera supports, such as a `{% ... %}`
+/// tag or a filter (e.g. `{{ name | upper }}`), so failing to render it is a mistake rather than
+/// a sign it's meant for another tool (e.g. a Go template passed to `docker --format`).
+/// Go templates can also pipe values, but they start with a field reference (e.g. `{{ .Names }}`).
+pub fn requires_rendering(text: &str) -> bool {
+    if text.contains("{%") {
+        return true;
+    }
+
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+
+        let expression = rest[start + 2..start + 2 + length]
+            .trim_matches(|c: char| c == '-' || c.is_whitespace());
+        if expression.contains('|') && !expression.starts_with('.') {
+            return true;
+        }
+
+        rest = &rest[start + 2 + length + 2..];
+    }
+
+    false
+}
+
+/// Returns the names of the variables which might be referenced by the Tera tags in the provided
+/// template, including the `PLZ_<NAME>` variables referenced through the `plz` object.
+/// Names are found by looking for identifiers outside of strings, so unrelated names (e.g. filters)
+/// may also be returned.
+pub fn references(template: &str) -> Vec<String> {
+    let mut references = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end_tag = match rest[start + 1..].chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            _ => {
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        let Some(length) = rest[start + 2..].find(end_tag) else {
+            break;
+        };
+
+        let tag = &rest[start + 2..start + 2 + length];
+        let mut chars = tag.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if matches!(ch, '"' | '\'' | '`') {
+                // Skip over strings
+                for next_ch in chars.by_ref() {
+                    if next_ch == ch {
+                        break;
+                    }
+                }
+            } else if ch.is_numeric() {
+                // Skip over numbers, so their fractional parts aren't mistaken for identifiers
+                while chars
+                    .peek()
+                    .is_some_and(|next_ch| next_ch.is_alphanumeric() || *next_ch == '.')
+                {
+                    chars.next();
+                }
+            } else if ch.is_alphabetic() || ch == '_' {
+                // Read the whole path (e.g. `plz.exit_code`)
+                let mut path = vec![ch.to_string()];
+                while let Some(&next_ch) = chars.peek() {
+                    if next_ch.is_alphanumeric() || next_ch == '_' {
+                        path.last_mut().unwrap().push(next_ch);
+                    } else if next_ch == '.' {
+                        path.push(String::new());
+                    } else {
+                        break;
+                    }
+                    chars.next();
+                }
+
+                match path.as_slice() {
+                    [plz, name, ..] if plz == "plz" => {
+                        references.push(format!("{}{}", PLZ_PREFIX, name.to_uppercase()))
+                    }
+                    [name, ..] if !TERA_KEYWORDS.contains(&name.as_str()) => {
+                        references.push(name.clone())
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        rest = &rest[start + 2 + length + end_tag.len()..];
+    }
+
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_can_check_the_platform() {
+        // Arrange
+        let template = "{% if plz.platform is starting_with(\"Windows\") %}.\\build.ps1{% else %}./build.sh{% endif %} --release{% if plz.platform != \"Linux\" %} --universal{% endif %}";
+        let variables = |platform: &str| {
+            let mut variables = VariableMap::new();
+            variables.insert(
+                crate::variables::PLATFORM_VARIABLE_NAME.to_string(),
+                platform.to_string(),
+            );
+            variables
+        };
+
+        // Act
+        let linux = render(template, &variables("Linux"), Shell::Posix).unwrap();
+        let windows = render(template, &variables("WindowsArm64"), Shell::Posix).unwrap();
+
+        // Assert
+        assert_eq!(linux, "./build.sh --release");
+        assert_eq!(windows, ".\\build.ps1 --release --universal");
+    }
+
+    #[test]
+    fn render_supports_conditionals_and_filters() {
+        // Arrange
+        let mut variables = VariableMap::new();
+        variables.insert("name".to_string(), "api".to_string());
+        variables.insert("verbose".to_string(), "false".to_string());
+        variables.insert("PLZ_EXIT_CODE".to_string(), "2".to_string());
+        let template = "deploy {{ name | upper }}{% if verbose %} --verbose{% endif %}{% if plz.exit_code != \"0\" %} --retry{% endif %}";
+
+        // Act
+        let result = render(template, &variables, Shell::Posix).unwrap();
+        let verbose = render(
+            "{% if verbose %}--verbose{% endif %}",
+            &VariableMap::from([("verbose".to_string(), "true".to_string())]),
+            Shell::Posix,
+        )
+        .unwrap();
+        let undefined = render(
+            "{% if missing %}x{% endif %}",
+            &VariableMap::new(),
+            Shell::Posix,
+        );
+        let undefined_output = render("{{ missing }}", &VariableMap::new(), Shell::Posix);
+        let undefined_default = render(
+            "{{ missing | default(value=\"x\") }}",
+            &VariableMap::new(),
+            Shell::Posix,
+        );
+
+        // Assert
+        assert_eq!(result, "deploy API --retry");
+        assert_eq!(verbose, "--verbose");
+        assert_eq!(undefined.unwrap(), "");
+        assert!(undefined_output.is_err());
+        assert_eq!(undefined_default.unwrap(), "x");
+    }
+
+    #[test]
+    fn render_quotes_values_for_shell() {
+        // Arrange
+        let variables = VariableMap::from([("message".to_string(), "it's $HOME".to_string())]);
+        let template = "{{ message | sh_quote }} {{ message | ps_quote }} {{ message | quote }}";
+
+        // Act
+        let posix = render(template, &variables, Shell::Posix).unwrap();
+        let powershell = render(template, &variables, Shell::PowerShell).unwrap();
+        let none = render(template, &variables, Shell::None).unwrap();
+
+        // Assert
+        assert_eq!(posix, "'it'\\''s $HOME' 'it''s $HOME' 'it'\\''s $HOME'");
+        assert_eq!(powershell, "'it'\\''s $HOME' 'it''s $HOME' 'it''s $HOME'");
+        assert_eq!(none, "'it'\\''s $HOME' 'it''s $HOME' it's $HOME");
+        assert_eq!(
+            Shell::PowerShell.quote("it\u{2019}s"),
+            "'it\u{2019}\u{2019}s'"
+        );
+    }
+
+    #[test]
+    fn requires_rendering_detects_tera_syntax() {
+        // Act
+        let block = requires_rendering("build {% if verbose %}--verbose{% endfi %}");
+        let filter = requires_rendering("deploy {{ name | upper }}");
+        let reference = requires_rendering("deploy {{ name }}");
+        let go_template = requires_rendering("docker ps --format '{{ .Names | printf \"%s\" }}'");
+
+        // Assert
+        assert!(block);
+        assert!(filter);
+        assert!(!reference);
+        assert!(!go_template);
+    }
+
+    #[test]
+    fn references_finds_variables_in_tags() {
+        // Act
+        let references = references(
+            "{% if verbose and count > 1.5 %}{{ name | replace(from=\"a\", to=sep) }}{% endif %} {{ plz.exit_code }} {{ item.name }} {notatag}",
+        );
+
+        // Assert
+        assert_eq!(
+            references,
+            vec![
+                "verbose",
+                "count",
+                "name",
+                "replace",
+                "from",
+                "to",
+                "sep",
+                "PLZ_EXIT_CODE",
+                "item"
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use base64::prelude::*;
+use std::io::{self, IsTerminal, Write};
+
+/// The name of the iTerm2 user variable set to the exit code of the command when it finishes.
+pub const LAST_STATUS_VARIABLE_NAME: &str = "PLZ_LAST_STATUS";
+
+/// The OSC 133 sequence marking the start of a command's output.
+const COMMAND_STARTED_MARK: &str = "\x1b]133;C\x07";
+
+/// Marks the start of the command's output, so the terminal can tell it apart from plz's prompts.
+pub fn command_started() {
+    write(COMMAND_STARTED_MARK);
+}
+
+/// Marks the end of the command's output with the provided exit code, so the terminal (or a prompt
+/// framework) can show whether it succeeded.
+pub fn command_finished(exit_code: i32) {
+    write(&finished_marks(exit_code));
+}
+
+/// Returns the OSC 133 sequence marking the end of a command's output with the provided exit code,
+/// followed by the iTerm2 sequence setting the [`LAST_STATUS_VARIABLE_NAME`] user variable.
+/// User variables are base64-encoded.
+fn finished_marks(exit_code: i32) -> String {
+    format!(
+        "\x1b]133;D;{exit_code}\x07\x1b]1337;SetUserVar={}={}\x07",
+        LAST_STATUS_VARIABLE_NAME,
+        BASE64_STANDARD.encode(exit_code.to_string())
+    )
+}
+
+/// Writes the provided escape sequences to stderr, unless it has been redirected somewhere other
+/// than a terminal.
+fn write(marks: &str) {
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        return;
+    }
+
+    // Marks are only hints for the terminal, so failing to write them isn't an error
+    let _ = stderr.write_all(marks.as_bytes());
+    let _ = stderr.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_marks_include_exit_code() {
+        // Act
+        let success = finished_marks(0);
+        let failure = finished_marks(127);
+
+        // Assert
+        assert_eq!(
+            success,
+            "\x1b]133;D;0\x07\x1b]1337;SetUserVar=PLZ_LAST_STATUS=MA==\x07"
+        );
+        assert_eq!(
+            failure,
+            "\x1b]133;D;127\x07\x1b]1337;SetUserVar=PLZ_LAST_STATUS=MTI3\x07"
+        );
+    }
+}
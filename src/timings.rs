@@ -0,0 +1,209 @@
+use crate::config;
+use colored::Colorize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The name of the command-line argument used to enable timings.
+pub const TIMINGS_ARG: &str = "--timings";
+
+/// A stage of the invocation that has been measured.
+struct Timing {
+    name: &'static str,
+
+    /// How many other stages were being measured when this stage started.
+    depth: usize,
+
+    duration: Duration,
+}
+
+struct Timings {
+    enabled: bool,
+    started: Option<Instant>,
+    active: Vec<&'static str>,
+    entries: Vec<Timing>,
+}
+
+static TIMINGS: Mutex<Timings> = Mutex::new(Timings {
+    enabled: false,
+    started: None,
+    active: Vec::new(),
+    entries: Vec::new(),
+});
+
+impl Timings {
+    /// Enables timings if the timings argument was provided before the command.
+    fn enable_from_args(&mut self, args: &[String]) {
+        if config::leading_args(args)
+            .iter()
+            .any(|arg| arg == TIMINGS_ARG)
+        {
+            self.enabled = true;
+            self.started = Some(Instant::now());
+        }
+    }
+}
+
+/// Enables timings if the timings argument was provided on the command-line.
+/// This needs to happen before the config is loaded, so it can't wait for clap to parse the
+/// arguments.
+pub fn enable_from_args() {
+    let args: Vec<String> = std::env::args().collect();
+    TIMINGS.lock().unwrap().enable_from_args(&args);
+}
+
+/// Executes `f`, recording how long it took under the provided name if timings are enabled.
+/// If a stage with the same name is already being measured (e.g. nested imports), the nested
+/// stage is not recorded separately.
+pub fn measure<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    measure_with(&TIMINGS, name, f)
+}
+
+/// Like [`measure`], but records to the provided [`Timings`].
+fn measure_with<T>(timings: &Mutex<Timings>, name: &'static str, f: impl FnOnce() -> T) -> T {
+    let index = {
+        let mut timings = timings.lock().unwrap();
+        if !timings.enabled || timings.active.contains(&name) {
+            None
+        } else {
+            let depth = timings.active.len();
+            timings.active.push(name);
+            timings.entries.push(Timing {
+                name,
+                depth,
+                duration: Duration::ZERO,
+            });
+            Some(timings.entries.len() - 1)
+        }
+    };
+
+    let Some(index) = index else {
+        return f();
+    };
+
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+
+    let mut timings = timings.lock().unwrap();
+    timings.entries[index].duration = elapsed;
+    timings.active.retain(|active_name| *active_name != name);
+
+    result
+}
+
+/// Prints the recorded timings to stderr if timings are enabled.
+pub fn report() {
+    let timings = TIMINGS.lock().unwrap();
+    if !timings.enabled {
+        return;
+    }
+
+    eprintln!("{}", "Timings:".bold());
+    for timing in &timings.entries {
+        let indent = "  ".repeat(timing.depth + 1);
+        eprintln!(
+            "{}{}: {}",
+            indent,
+            timing.name,
+            format_duration(&timing.duration).green()
+        );
+    }
+
+    if let Some(started) = timings.started {
+        eprintln!("  total: {}", format_duration(&started.elapsed()).green());
+    }
+}
+
+fn format_duration(duration: &Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(enabled: bool) -> Mutex<Timings> {
+        Mutex::new(Timings {
+            enabled,
+            started: None,
+            active: Vec::new(),
+            entries: Vec::new(),
+        })
+    }
+
+    fn recorded(timings: &Mutex<Timings>) -> Vec<(&'static str, usize)> {
+        timings
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|timing| (timing.name, timing.depth))
+            .collect()
+    }
+
+    #[test]
+    fn enable_from_args_only_checks_arguments_before_the_command() {
+        // Arrange
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let mut before_command = timings(false).into_inner().unwrap();
+        let mut after_command = timings(false).into_inner().unwrap();
+
+        // Act
+        before_command.enable_from_args(&args(&["plz", "--env", "prod", "--timings", "deploy"]));
+        after_command.enable_from_args(&args(&["plz", "deploy", "--timings"]));
+
+        // Assert
+        assert!(before_command.enabled);
+        assert!(before_command.started.is_some());
+        assert!(!after_command.enabled);
+    }
+
+    #[test]
+    fn measure_records_nested_stages() {
+        // Arrange
+        let timings = timings(true);
+
+        // Act
+        let result = measure_with(&timings, "config loading", || {
+            measure_with(&timings, "imports", || {
+                measure_with(&timings, "imports", || {
+                    std::thread::sleep(Duration::from_millis(5));
+                })
+            });
+            measure_with(&timings, "parsing", || 42)
+        });
+        measure_with(&timings, "execution", || {});
+
+        // Assert
+        assert_eq!(result, 42);
+        assert_eq!(
+            recorded(&timings),
+            vec![
+                ("config loading", 0),
+                ("imports", 1),
+                ("parsing", 1),
+                ("execution", 0)
+            ]
+        );
+
+        let timings = timings.lock().unwrap();
+        assert!(timings.active.is_empty());
+        assert!(timings.entries[1].duration >= Duration::from_millis(5));
+        assert!(timings.entries[0].duration >= timings.entries[1].duration);
+    }
+
+    #[test]
+    fn measure_records_nothing_when_disabled() {
+        // Arrange
+        let timings = timings(false);
+
+        // Act
+        let result = measure_with(&timings, "config loading", || {
+            measure_with(&timings, "imports", || "loaded")
+        });
+
+        // Assert
+        assert_eq!(result, "loaded");
+        assert!(recorded(&timings).is_empty());
+    }
+}
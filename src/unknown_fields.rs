@@ -0,0 +1,703 @@
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::fmt::Formatter;
+
+// Serde can't report unknown fields for most of the config since it relies on untagged enums and
+// flattened fields, so the known fields for each part of the config are listed here instead.
+// These need to be kept in sync with the structs in the config module, including any aliases.
+
+const CONFIG_FIELDS: &[&str] = &[
+    "version",
+    "imports",
+    "description",
+    "desc",
+    "variables",
+    "vars",
+    "variable_sets",
+    "commands",
+    "cmds",
+    "options",
+    "opts",
+    "environments",
+    "env_files",
+];
+
+const IMPORT_FIELDS: &[&str] = &[
+    "alias",
+    "source",
+    "git",
+    "hidden",
+    "platform",
+    "platforms",
+    "when",
+    "optional",
+    "quiet",
+];
+
+const OPTIONS_FIELDS: &[&str] = &[
+    "print_commands",
+    "print_commands_format",
+    "print_variables",
+    "auto_args",
+    "activate_env",
+    "exec_timeout",
+    "step_headers",
+    "ascii_symbols",
+    "terminal_marks",
+    "variable_precedence",
+    "trace_vars",
+    "lazy_vars",
+    "auto_env",
+    "auto_env_prefix",
+    "audit_log",
+    "inspect",
+    "strict",
+    "binary_name",
+    "lint",
+];
+
+const COMMAND_FIELDS: &[&str] = &[
+    "name",
+    "description",
+    "desc",
+    "hidden",
+    "platform",
+    "platforms",
+    "python_env",
+    "save_env",
+    "on_failure",
+    "on_interrupt",
+    "on_success_message",
+    "on_failure_message",
+    "when",
+    "cooldown",
+    "require_clean_git",
+    "guard",
+    "env_files",
+    "depends_on",
+    "stdout_file",
+    "stderr_file",
+    "lock",
+    "lock_wait",
+    "auto_args",
+    "use_variables",
+    "use_vars",
+    "variables",
+    "vars",
+    "commands",
+    "cmds",
+    "action",
+    "actions",
+    "session",
+    "alias",
+    "delegate",
+    "services",
+    "output",
+];
+
+const ACTION_FIELDS: &[&str] = &[
+    "action", "actions", "session", "alias", "delegate", "services", "output",
+];
+
+const SERVICE_FIELDS: &[&str] = &[
+    "env", "restart", "watch", "bash", "sh", "command", "cmd", "workdir", "wd",
+];
+
+const SAVE_ENV_FIELDS: &[&str] = &["path", "variables", "vars"];
+
+const PYTHON_ENV_FIELDS: &[&str] = &["conda"];
+
+const EXECUTION_FIELDS: &[&str] = &["bash", "sh", "command", "cmd", "workdir", "wd"];
+
+const STEP_FIELDS: &[&str] = &[
+    "name",
+    "description",
+    "desc",
+    "if",
+    "continue_on_error",
+    "executor",
+    "env",
+    "filters",
+    "stdout_file",
+    "stderr_file",
+    "bash",
+    "sh",
+    "command",
+    "cmd",
+    "workdir",
+    "wd",
+];
+
+const EXECUTOR_FIELDS: &[&str] = &["docker", "ssh", "wsl"];
+
+const VARIABLE_FIELDS: &[&str] = &[
+    "argument",
+    "arg",
+    "environment_variable",
+    "env",
+    "value",
+    "execute",
+    "exec",
+    "prompt",
+    "no_arg",
+    "sensitive",
+    "timeout",
+    "on_error",
+    "parse",
+    "cache",
+    "default",
+    "from_env",
+    "required",
+    "keyring",
+    "secret",
+];
+
+const ARGUMENT_FIELDS: &[&str] = &["long", "short", "description", "desc", "position", "hidden"];
+
+const PROMPT_FIELDS: &[&str] = &[
+    "message",
+    "default",
+    "multi_line",
+    "sensitive",
+    "options",
+    "opts",
+];
+
+const SELECT_OPTIONS_FIELDS: &[&str] = &["execute", "exec"];
+
+const KEYRING_FIELDS: &[&str] = &["service", "account"];
+
+/// A field in the config that plz doesn't recognise.
+#[derive(PartialEq, Debug)]
+pub struct UnknownField {
+    /// The path of the mapping containing the field (e.g. `commands.build`).
+    pub path: String,
+
+    /// The name of the unknown field.
+    pub field: String,
+
+    /// The known field with the most similar name, if there is one.
+    pub suggestion: Option<&'static str>,
+}
+
+impl fmt::Display for UnknownField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "unknown field \"{}\"", self.field)?;
+        } else {
+            write!(f, "unknown field \"{}\" in {}", self.field, self.path)?;
+        }
+
+        if let Some(suggestion) = self.suggestion {
+            write!(f, ", did you mean \"{}\"?", suggestion)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds any fields in the provided config that plz doesn't recognise.
+pub fn find(config: &Value) -> Vec<UnknownField> {
+    let mut unknown_fields = Vec::new();
+    check_config(config, "", &mut unknown_fields);
+    unknown_fields
+}
+
+/// Returns the (1-based) line number of the provided unknown field in the config text it was found
+/// in, if it can be located.
+/// The text is scanned by indentation rather than parsed, so fields in flow mappings (e.g.
+/// `{ a: b }`) or commands created from a template can't be located.
+pub fn find_line(text: &str, unknown_field: &UnknownField) -> Option<usize> {
+    // The mappings and sequence items containing the current line, with their indentation
+    let mut parents: Vec<(usize, String, usize)> = Vec::new();
+    let mut block_scalar_indent = None;
+    for (index, line) in text.lines().enumerate() {
+        let content = line.trim_start();
+        let mut indent = line.len() - content.len();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+
+        // Skip the contents of multi-line strings
+        match block_scalar_indent {
+            Some(scalar_indent) if indent > scalar_indent => continue,
+            _ => block_scalar_indent = None,
+        }
+
+        let mut content = content;
+        while let Some(item) = content
+            .strip_prefix("- ")
+            .or((content == "-").then_some(""))
+        {
+            parents.retain(|(parent_indent, segment, _)| {
+                *parent_indent < indent || (*parent_indent == indent && !segment.starts_with('['))
+            });
+            let item_index = match parents.last_mut() {
+                Some((_, _, items)) => {
+                    *items += 1;
+                    *items - 1
+                }
+                None => 0,
+            };
+            parents.push((indent, format!("[{}]", item_index), 0));
+
+            indent += content.len() - item.trim_start().len();
+            content = item.trim_start();
+        }
+
+        let Some((key, value)) = split_key(content) else {
+            continue;
+        };
+
+        parents.retain(|(parent_indent, _, _)| *parent_indent < indent);
+        let path = parents.iter().fold(String::new(), |path, (_, segment, _)| {
+            match segment.starts_with('[') {
+                true => format!("{}{}", path, segment),
+                false => join(&path, segment),
+            }
+        });
+
+        if path == unknown_field.path && key == unknown_field.field {
+            return Some(index + 1);
+        }
+
+        if value.starts_with('|') || value.starts_with('>') {
+            block_scalar_indent = Some(indent);
+        }
+        parents.push((indent, canonical_key(&key).to_string(), 0));
+    }
+
+    None
+}
+
+/// Splits a `key: value` line into the (unquoted) key and the rest of the line.
+fn split_key(content: &str) -> Option<(String, &str)> {
+    let (key, value) = match content.strip_suffix(':') {
+        Some(key) if !key.contains(": ") => (key, ""),
+        _ => content.split_once(": ")?,
+    };
+
+    let key = key.trim();
+    let key = key
+        .strip_prefix('"')
+        .and_then(|key| key.strip_suffix('"'))
+        .or_else(|| {
+            key.strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+        })
+        .unwrap_or(key);
+    Some((key.to_string(), value.trim()))
+}
+
+/// Returns the name used for the provided key in the paths of unknown fields, which always use the
+/// full name of a field rather than its alias.
+fn canonical_key(key: &str) -> &str {
+    match key {
+        "opts" => "options",
+        "vars" => "variables",
+        "cmds" => "commands",
+        "arg" => "argument",
+        "exec" => "execute",
+        key => key,
+    }
+}
+
+fn check_config(value: &Value, path: &str, unknown_fields: &mut Vec<UnknownField>) {
+    let Some(mapping) = value.as_mapping() else {
+        return;
+    };
+
+    check_fields(mapping, CONFIG_FIELDS, path, unknown_fields);
+
+    for (key, value) in mapping {
+        match key.as_str() {
+            Some("imports") => {
+                for (index, import) in value.as_sequence().into_iter().flatten().enumerate() {
+                    if let Some(import) = import.as_mapping() {
+                        let path = join(path, &format!("imports[{}]", index));
+                        check_fields(import, IMPORT_FIELDS, &path, unknown_fields);
+                    }
+                }
+            }
+            Some("options" | "opts") => {
+                if let Some(options) = value.as_mapping() {
+                    let path = join(path, "options");
+                    check_fields(options, OPTIONS_FIELDS, &path, unknown_fields);
+                }
+            }
+            Some("variables" | "vars") => check_variables(value, path, unknown_fields),
+            Some("variable_sets") => {
+                for (name, variables) in value.as_mapping().into_iter().flatten() {
+                    let path = join(path, &format!("variable_sets.{}", key_text(name)));
+                    check_variables(variables, &path, unknown_fields);
+                }
+            }
+            Some("commands" | "cmds") => check_commands(value, path, unknown_fields),
+            Some("environments") => check_environments(value, path, unknown_fields),
+            _ => {}
+        }
+    }
+}
+
+fn check_environments(value: &Value, path: &str, unknown_fields: &mut Vec<UnknownField>) {
+    for (name, environment) in value.as_mapping().into_iter().flatten() {
+        let path = join(path, &format!("environments.{}", key_text(name)));
+        for (key, value) in environment.as_mapping().into_iter().flatten() {
+            match key.as_str() {
+                Some("options" | "opts") => {
+                    if let Some(options) = value.as_mapping() {
+                        let path = join(&path, "options");
+                        check_fields(options, OPTIONS_FIELDS, &path, unknown_fields);
+                    }
+                }
+                Some("variables" | "vars") => check_variables(value, &path, unknown_fields),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn check_commands(value: &Value, path: &str, unknown_fields: &mut Vec<UnknownField>) {
+    for (name, command) in value.as_mapping().into_iter().flatten() {
+        let Some(command) = command.as_mapping() else {
+            continue;
+        };
+
+        let path = join(path, &format!("commands.{}", key_text(name)));
+        check_fields(command, COMMAND_FIELDS, &path, unknown_fields);
+        check_action_fields(command, &path, unknown_fields);
+
+        for (key, value) in command {
+            match key.as_str() {
+                Some("variables" | "vars") => check_variables(value, &path, unknown_fields),
+                Some("commands" | "cmds") => check_commands(value, &path, unknown_fields),
+                Some(field @ ("on_failure" | "on_interrupt")) => {
+                    if let Some(handler) = value.as_mapping() {
+                        let path = join(&path, field);
+                        check_fields(handler, ACTION_FIELDS, &path, unknown_fields);
+                        check_action_fields(handler, &path, unknown_fields);
+                    }
+                }
+                Some("save_env") => {
+                    if let Some(save_env) = value.as_mapping() {
+                        let path = join(&path, "save_env");
+                        check_fields(save_env, SAVE_ENV_FIELDS, &path, unknown_fields);
+                    }
+                }
+                Some("python_env") => {
+                    if let Some(python_env) = value.as_mapping() {
+                        let path = join(&path, "python_env");
+                        check_fields(python_env, PYTHON_ENV_FIELDS, &path, unknown_fields);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Checks the `action` and `actions` fields of a command (or handler).
+fn check_action_fields(mapping: &Mapping, path: &str, unknown_fields: &mut Vec<UnknownField>) {
+    for (key, value) in mapping {
+        match key.as_str() {
+            Some("action") => {
+                if let Some(execution) = value.as_mapping() {
+                    let path = join(path, "action");
+                    check_fields(execution, EXECUTION_FIELDS, &path, unknown_fields);
+                }
+            }
+            Some("services") => {
+                for (name, service) in value.as_mapping().into_iter().flatten() {
+                    if let Some(service) = service.as_mapping() {
+                        let path = join(path, &format!("services.{}", key_text(name)));
+                        check_fields(service, SERVICE_FIELDS, &path, unknown_fields);
+                    }
+                }
+            }
+            Some("actions") => {
+                for (index, step) in value.as_sequence().into_iter().flatten().enumerate() {
+                    if let Some(step) = step.as_mapping() {
+                        let path = join(path, &format!("actions[{}]", index));
+                        check_fields(step, STEP_FIELDS, &path, unknown_fields);
+
+                        if let Some(executor) = step.get("executor").and_then(Value::as_mapping) {
+                            let path = join(&path, "executor");
+                            check_fields(executor, EXECUTOR_FIELDS, &path, unknown_fields);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_variables(value: &Value, path: &str, unknown_fields: &mut Vec<UnknownField>) {
+    for (name, variable) in value.as_mapping().into_iter().flatten() {
+        let Some(variable) = variable.as_mapping() else {
+            continue;
+        };
+
+        let path = join(path, &format!("variables.{}", key_text(name)));
+        check_fields(variable, VARIABLE_FIELDS, &path, unknown_fields);
+
+        for (key, value) in variable {
+            match key.as_str() {
+                Some("argument" | "arg") => {
+                    if let Some(argument) = value.as_mapping() {
+                        let path = join(&path, "argument");
+                        check_fields(argument, ARGUMENT_FIELDS, &path, unknown_fields);
+                    }
+                }
+                Some("execute" | "exec") => {
+                    if let Some(execution) = value.as_mapping() {
+                        let path = join(&path, "execute");
+                        check_fields(execution, EXECUTION_FIELDS, &path, unknown_fields);
+                    }
+                }
+                Some("keyring") => {
+                    if let Some(keyring) = value.as_mapping() {
+                        let path = join(&path, "keyring");
+                        check_fields(keyring, KEYRING_FIELDS, &path, unknown_fields);
+                    }
+                }
+                Some("prompt") => {
+                    if let Some(prompt) = value.as_mapping() {
+                        let path = join(&path, "prompt");
+                        check_fields(prompt, PROMPT_FIELDS, &path, unknown_fields);
+
+                        let options = prompt
+                            .get("options")
+                            .or(prompt.get("opts"))
+                            .and_then(Value::as_mapping);
+                        if let Some(options) = options {
+                            let path = join(&path, "options");
+                            check_fields(options, SELECT_OPTIONS_FIELDS, &path, unknown_fields);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Records any fields in the provided mapping that aren't in `known_fields`.
+fn check_fields(
+    mapping: &Mapping,
+    known_fields: &[&'static str],
+    path: &str,
+    unknown_fields: &mut Vec<UnknownField>,
+) {
+    for key in mapping.keys() {
+        let field = key_text(key);
+        if known_fields.contains(&field.as_str()) {
+            continue;
+        }
+
+        unknown_fields.push(UnknownField {
+            path: path.to_string(),
+            suggestion: suggest(&field, known_fields),
+            field,
+        });
+    }
+}
+
+/// Returns the known field with the most similar name to the provided field, as long as it's
+/// similar enough to be a likely typo.
+fn suggest(field: &str, known_fields: &[&'static str]) -> Option<&'static str> {
+    let max_distance = (field.chars().count() / 3).max(1);
+    known_fields
+        .iter()
+        .map(|known_field| (*known_field, edit_distance(field, known_field)))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(known_field, _)| known_field)
+}
+
+/// Calculates the number of single-character insertions, deletions, substitutions, or swaps of
+/// adjacent characters needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn key_text(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .map(|key| key.trim().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_suggests_similar_fields() {
+        // Arrange
+        let yaml = "
+descripton: My config
+commands:
+    build:
+        platfrom: Linux
+        action: cargo build
+        variables:
+            target:
+                value: debug
+                arg: target
+                enviroment_variable: TARGET
+    test:
+        actions:
+            - name: unit
+              command: cargo test
+              continue_on_eror: true
+        frobnicate: true
+options:
+    print_comands: true";
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+        // Act
+        let unknown_fields = find(&value);
+
+        // Assert
+        assert_eq!(
+            unknown_fields,
+            vec![
+                UnknownField {
+                    path: "".to_string(),
+                    field: "descripton".to_string(),
+                    suggestion: Some("description"),
+                },
+                UnknownField {
+                    path: "commands.build".to_string(),
+                    field: "platfrom".to_string(),
+                    suggestion: Some("platform"),
+                },
+                UnknownField {
+                    path: "commands.build.variables.target".to_string(),
+                    field: "enviroment_variable".to_string(),
+                    suggestion: Some("environment_variable"),
+                },
+                UnknownField {
+                    path: "commands.test".to_string(),
+                    field: "frobnicate".to_string(),
+                    suggestion: None,
+                },
+                UnknownField {
+                    path: "commands.test.actions[0]".to_string(),
+                    field: "continue_on_eror".to_string(),
+                    suggestion: Some("continue_on_error"),
+                },
+                UnknownField {
+                    path: "options".to_string(),
+                    field: "print_comands".to_string(),
+                    suggestion: Some("print_commands"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_accepts_aliases() {
+        // Arrange
+        let yaml = "
+desc: My config
+vars:
+    name:
+        exec:
+            sh: whoami
+            wd: ./
+cmds:
+    deps:
+        alias: docker compose
+    greet:
+        desc: Says hello
+        action: echo Hello, $name";
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+        // Act
+        let unknown_fields = find(&value);
+
+        // Assert
+        assert!(unknown_fields.is_empty());
+    }
+
+    #[test]
+    fn unknown_field_display_includes_suggestion() {
+        let unknown_field = UnknownField {
+            path: "commands.build".to_string(),
+            field: "platfrom".to_string(),
+            suggestion: Some("platform"),
+        };
+
+        assert_eq!(
+            unknown_field.to_string(),
+            "unknown field \"platfrom\" in commands.build, did you mean \"platform\"?"
+        );
+    }
+
+    #[test]
+    fn find_line_locates_unknown_fields() {
+        // Arrange
+        let yaml = "opts:
+  print_comands: true
+cmds:
+  build:
+    description: |
+      decription: not a field
+    decription: Build the project
+    actions:
+      - cargo build
+      - name: test
+        command: cargo test
+        continue_on_eror: true
+";
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+        // Act
+        let lines: Vec<Option<usize>> = find(&value)
+            .iter()
+            .map(|unknown_field| find_line(yaml, unknown_field))
+            .collect();
+
+        // Assert
+        assert_eq!(lines, vec![Some(2), Some(7), Some(12)]);
+    }
+}
@@ -1,16 +1,61 @@
 use crate::args::ArgumentResolver;
-use crate::config::{Options, PromptOptionsVariant, VariableConfig, VariableConfigMap};
+use crate::audit;
+use crate::cache;
+use crate::config::{
+    CommandConfig, CommandConfigMap, ExecutionErrorPolicy, ExecutionVariableConfig, KeyringConfig,
+    Options, ParseConfig, Platform, PromptOptionsVariant, SelectOptionsConfig, VariableConfig,
+    VariableConfigMap, VariableSource,
+};
+use crate::duration::{self, DurationError};
 use crate::exec::{CommandExecutor, ExecutionError, ExitStatus};
+use crate::filters::{self, FilterError};
+use crate::keyring;
+use crate::mask::{self, mask, MASK};
+use crate::platform::current_platform_provider;
 use crate::prompt::{PromptError, PromptExecutor};
+use crate::secrets::{self, SecretError};
+use crate::template;
+use crate::template::Shell;
 use colored::Colorize;
 use std::collections::HashMap;
 use std::env;
+use std::error::Error as _;
+use std::path::{Path, PathBuf};
 use std::string::FromUtf8Error;
+use std::time::{Duration, Instant, SystemTime};
 use thiserror::Error;
 
 /// A [`HashMap`] where the key is the variable name, and the value is that variables value.
 pub type VariableMap = HashMap<String, String>;
 
+/// The name of the built-in variable containing the name of the user executing plz.
+pub const USER_VARIABLE_NAME: &str = "PLZ_USER";
+
+/// The name of the built-in variable containing the hostname of the machine executing plz.
+pub const HOSTNAME_VARIABLE_NAME: &str = "PLZ_HOSTNAME";
+
+/// The name of the built-in variable containing the platform plz is executing on, as it's written
+/// in config files (e.g. `Windows`).
+pub const PLATFORM_VARIABLE_NAME: &str = "PLZ_PLATFORM";
+
+/// Returns the built-in variables available to every command, which can be referenced as
+/// `{{ plz.user }}`, `{{ plz.hostname }}`, and `{{ plz.platform }}`.
+/// Variables that can't be determined are left out.
+pub fn builtin_variables() -> VariableMap {
+    let platform = Some(current_platform_provider().get_platform())
+        .filter(|platform| *platform != Platform::Unknown)
+        .map(|platform| format!("{:?}", platform));
+
+    [
+        (USER_VARIABLE_NAME, audit::current_user()),
+        (HOSTNAME_VARIABLE_NAME, audit::current_host()),
+        (PLATFORM_VARIABLE_NAME, platform),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name.to_string(), value?)))
+    .collect()
+}
+
 pub trait VariableResolver {
     /// Resolves variables from the provided [`VariableConfigMap`] into a [`VariableMap`].
     fn resolve_variables(
@@ -22,8 +67,22 @@ pub trait VariableResolver {
 pub struct RealVariableResolver {
     pub command_executor: Box<dyn CommandExecutor>,
     pub prompt_executor: Box<dyn PromptExecutor>,
+
+    /// Answers prompts using scripted answers, if there are any.
+    pub answer_executor: Option<Box<dyn PromptExecutor>>,
     pub argument_resolver: Box<dyn ArgumentResolver>,
     pub options: Options,
+
+    /// The state directory execution variables with a `cache` store their output in, or `None` if
+    /// their output shouldn't be cached.
+    pub state_dir: Option<PathBuf>,
+}
+
+/// Where the output of an execution variable is cached, and for how long.
+struct VariableCache<'a> {
+    state_dir: &'a Path,
+    key: String,
+    ttl: Duration,
 }
 
 impl VariableResolver for RealVariableResolver {
@@ -32,89 +91,327 @@ impl VariableResolver for RealVariableResolver {
         variable_configs: &VariableConfigMap,
     ) -> Result<VariableMap, VariableResolutionError> {
         // The names of sensitive variables are added to a separate vec so that the logging stuff
-        // below knows to obfuscate them.
+        // below knows to obfuscate them. Their values are also registered so they're masked
+        // whenever plz prints them.
         let mut resolved_variables = VariableMap::new();
         let mut sensitive_variable_names: Vec<String> = vec![];
 
-        for (key, config) in variable_configs.iter() {
-            let name = config.environment_variable_name(key);
+        for (key, config) in resolution_order(variable_configs, &self.options)? {
+            let name = config.environment_variable_name(key, &self.options);
+            let started = Instant::now();
+            let mut resolved_source = None;
+
+            // Sources are checked in order of precedence, the first one to provide a value wins.
+            for source in self.options.variable_precedence.iter() {
+                let Some(value) =
+                    self.resolve_from_source(source, key, &name, config, &resolved_variables)?
+                else {
+                    continue;
+                };
+
+                if config.sensitive() {
+                    mask::register(&value);
+                    sensitive_variable_names.push(name.clone());
+                }
 
-            // Args from the command-line have the highest priority, check there first.
-            if let Some(arg_value) = self.argument_resolver.get(key) {
-                resolved_variables.insert(name.clone(), arg_value.clone());
-            } else {
-                match config {
-                    VariableConfig::ShorthandLiteral(value) => {
-                        let substituted_value = substitute_variables(value, &resolved_variables);
-                        resolved_variables.insert(name.clone(), substituted_value.clone());
-                    }
+                resolved_variables.insert(name.clone(), value);
+                resolved_source = Some(source);
 
-                    VariableConfig::Literal(literal_conf) => {
-                        let substituted_value =
-                            substitute_variables(literal_conf.value.as_str(), &resolved_variables);
-                        resolved_variables.insert(name.clone(), substituted_value);
-                    }
+                break;
+            }
 
-                    VariableConfig::Execution(execution_conf) => {
-                        // Exec variables need access to the variables defined above them.
-                        let output = self
-                            .command_executor
-                            .get_output(&execution_conf.execution, &resolved_variables)
-                            .map_err(|err| VariableResolutionError::Execution {
-                                key: key.clone(),
-                                source: err,
-                            })?;
-
-                        // TODO: Make this configurable.
-                        // If the command has a non-zero exit code, we probably shouldn't trust it's output.
-                        // Return an error instead.
-                        if let ExitStatus::Fail(_) = output.status {
-                            return Err(VariableResolutionError::ExitStatus {
-                                key: key.clone(),
-                                status: output.status.clone(),
-                            });
-                        }
-
-                        let value = String::from_utf8(output.stdout)
-                            .map_err(|err| VariableResolutionError::Parse {
-                                key: key.clone(),
-                                source: err,
-                            })?
-                            .trim_end()
-                            .to_string();
-
-                        resolved_variables.insert(name.clone(), value.clone());
+            if self.options.trace_vars {
+                let value = resolved_source.map(|_| {
+                    if config.sensitive() {
+                        MASK.to_string()
+                    } else {
+                        mask(&format!("{:?}", resolved_variables[&name]))
                     }
+                });
+                let trace = format_trace(
+                    key,
+                    &name,
+                    config,
+                    resolved_source,
+                    value,
+                    started.elapsed(),
+                );
+                eprintln!("{} {}", "trace:".dimmed(), trace);
+            }
+        }
+
+        self.log_variables(&resolved_variables, &sensitive_variable_names);
+
+        Ok(resolved_variables)
+    }
+}
+
+impl RealVariableResolver {
+    /// Executes the command for an execution variable, returning its output.
+    fn execute_variable(
+        &self,
+        key: &str,
+        execution_conf: &ExecutionVariableConfig,
+        resolved_variables: &VariableMap,
+    ) -> Result<String, VariableResolutionError> {
+        let cache = self.variable_cache(key, execution_conf, resolved_variables)?;
+        if let Some(cache) = &cache {
+            if let Some(output) =
+                cache::load_variable(cache.state_dir, &cache.key, SystemTime::now())
+            {
+                return parse_value(key, output, execution_conf.parse.as_ref());
+            }
+        }
+
+        let timeout_executor;
+        let command_executor = match &execution_conf.timeout {
+            Some(timeout) => {
+                timeout_executor = self.command_executor.with_timeout(timeout);
+                timeout_executor.as_ref()
+            }
+            None => self.command_executor.as_ref(),
+        };
+
+        // Exec variables need access to the variables defined above them.
+        let output = command_executor
+            .get_output(&execution_conf.execution, resolved_variables)
+            .map_err(|err| VariableResolutionError::Execution {
+                key: key.to_string(),
+                source: err,
+            })?;
+
+        // If the command has a non-zero exit code, we probably shouldn't trust it's output.
+        // Return an error instead, which the on_error policy decides what to do with.
+        if let ExitStatus::Fail(_) = output.status {
+            return Err(VariableResolutionError::ExitStatus {
+                key: key.to_string(),
+                status: output.status.clone(),
+            });
+        }
+
+        let value = String::from_utf8(output.stdout)
+            .map_err(|err| VariableResolutionError::Parse {
+                key: key.to_string(),
+                source: err,
+            })?
+            .trim_end()
+            .to_string();
+
+        if let Some(cache) = &cache {
+            // The cache only saves time, so failing to write it isn't an error
+            let _ = cache::save_variable(
+                cache.state_dir,
+                &cache.key,
+                &value,
+                cache.ttl,
+                SystemTime::now(),
+            );
+        }
+
+        parse_value(key, value, execution_conf.parse.as_ref())
+    }
+
+    /// Returns where the output of an execution variable is cached, or `None` if it isn't.
+    /// Outputs are cached for each command and working directory, after variables have been
+    /// substituted.
+    fn variable_cache(
+        &self,
+        key: &str,
+        execution_conf: &ExecutionVariableConfig,
+        resolved_variables: &VariableMap,
+    ) -> Result<Option<VariableCache<'_>>, VariableResolutionError> {
+        let (Some(cache_conf), Some(state_dir)) = (&execution_conf.cache, &self.state_dir) else {
+            return Ok(None);
+        };
+
+        let ttl = duration::parse(&cache_conf.ttl).map_err(|err| {
+            VariableResolutionError::InvalidCacheTtl {
+                key: key.to_string(),
+                source: err,
+            }
+        })?;
+
+        let execution = &execution_conf.execution;
+        let command = substitute_variables(execution.command(), resolved_variables);
+        let mut working_dir = env::current_dir().unwrap_or_default();
+        if let Some(dir) = execution.working_directory() {
+            working_dir.push(substitute_variables(dir, resolved_variables));
+        }
+
+        Ok(Some(VariableCache {
+            state_dir,
+            key: cache::variable_key(&command, &working_dir),
+            ttl,
+        }))
+    }
+
+    /// Reads the secret for a keyring variable from the platform's credential store.
+    fn read_keyring_secret(
+        &self,
+        key: &str,
+        keyring_conf: &KeyringConfig,
+        resolved_variables: &VariableMap,
+    ) -> Result<String, VariableResolutionError> {
+        let service = substitute_variables(&keyring_conf.service, resolved_variables);
+        let account = substitute_variables(&keyring_conf.account, resolved_variables);
+        let lookup_command = keyring::lookup_command(env::consts::OS, &service, &account);
+
+        let output = self
+            .command_executor
+            .get_output(&lookup_command, resolved_variables)
+            .map_err(|err| VariableResolutionError::Execution {
+                key: key.to_string(),
+                source: err,
+            })?;
+
+        if let ExitStatus::Fail(_) = output.status {
+            return Err(VariableResolutionError::KeyringSecretNotFound {
+                key: key.to_string(),
+                service,
+                account,
+            });
+        }
+
+        let value = String::from_utf8(output.stdout)
+            .map_err(|err| VariableResolutionError::Parse {
+                key: key.to_string(),
+                source: err,
+            })?
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+
+        Ok(value)
+    }
 
-                    VariableConfig::Prompt(prompt_config) => {
-                        let value = self
-                            .prompt_executor
-                            .execute(&prompt_config.prompt)
-                            .map_err(|err| VariableResolutionError::Prompt {
-                                key: key.clone(),
-                                source: err,
-                            })?;
+    /// Resolves the value of a variable from the provided [`VariableSource`], returning `None` if
+    /// the source doesn't have a value for it.
+    fn resolve_from_source(
+        &self,
+        source: &VariableSource,
+        key: &String,
+        name: &String,
+        config: &VariableConfig,
+        resolved_variables: &VariableMap,
+    ) -> Result<Option<String>, VariableResolutionError> {
+        match (source, config) {
+            // Arguments for variables with a value use it as their default, which should only be
+            // used if nothing with a higher precedence provides a value
+            (VariableSource::Cli, _) => Ok(self
+                .argument_resolver
+                .get(key)
+                .filter(|_| self.argument_resolver.was_provided(key))),
+
+            (VariableSource::Env, _) => Ok(env::var(name).ok()),
+
+            (VariableSource::Answers, VariableConfig::Prompt(prompt_config)) => {
+                let Some(answer_executor) = &self.answer_executor else {
+                    return Ok(None);
+                };
+
+                let value = answer_executor
+                    .execute(&prompt_config.prompt, resolved_variables)
+                    .map_err(|err| VariableResolutionError::Prompt {
+                        key: key.clone(),
+                        source: err,
+                    })?;
+
+                Ok(Some(value))
+            }
 
-                        resolved_variables.insert(name.clone(), value.clone());
+            (VariableSource::Defaults, VariableConfig::ShorthandLiteral(value)) => {
+                Ok(Some(substitute_variables(value, resolved_variables)))
+            }
 
-                        if is_variable_sensitive(config) {
-                            sensitive_variable_names.push(name.clone());
-                        }
+            (VariableSource::Defaults, VariableConfig::Literal(literal_conf)) => Ok(Some(
+                substitute_variables(literal_conf.value.as_str(), resolved_variables),
+            )),
+
+            (VariableSource::Defaults, VariableConfig::Execution(execution_conf)) => {
+                let err = match self.execute_variable(key, execution_conf, resolved_variables) {
+                    Ok(value) => return Ok(Some(value)),
+                    Err(err) => err,
+                };
+
+                let (value, description) = match execution_conf.on_error {
+                    ExecutionErrorPolicy::Fail => return Err(err),
+                    ExecutionErrorPolicy::Default => {
+                        let default = execution_conf
+                            .default
+                            .as_ref()
+                            .ok_or(VariableResolutionError::MissingDefault { key: key.clone() })?;
+                        (
+                            substitute_variables(default, resolved_variables),
+                            "its default value",
+                        )
                     }
+                    ExecutionErrorPolicy::Empty => (String::new(), "an empty value"),
+                };
+
+                let message = match err.source() {
+                    Some(source) => format!("{}: {}", err, source),
+                    None => err.to_string(),
+                };
+                eprintln!(
+                    "{} {}, using {} instead",
+                    "warning:".yellow().bold(),
+                    mask(&message),
+                    description
+                );
+
+                Ok(Some(value))
+            }
 
-                    // Arguments are checked above, nothing to do here.
-                    VariableConfig::Argument(_) => {}
+            (VariableSource::Defaults, VariableConfig::Env(env_conf)) => {
+                match (env::var(&env_conf.from_env).ok(), &env_conf.default) {
+                    (Some(value), _) => Ok(Some(parse_value(key, value, env_conf.parse.as_ref())?)),
+                    (None, Some(default)) => {
+                        Ok(Some(substitute_variables(default, resolved_variables)))
+                    }
+                    (None, None) if env_conf.required => {
+                        Err(VariableResolutionError::MissingEnvironmentVariable {
+                            key: key.clone(),
+                            name: env_conf.from_env.clone(),
+                        })
+                    }
+                    (None, None) => Ok(Some(String::new())),
                 }
             }
-        }
 
-        self.log_variables(&resolved_variables, &sensitive_variable_names);
+            (VariableSource::Defaults, VariableConfig::Keyring(keyring_conf)) => Ok(Some(
+                self.read_keyring_secret(key, &keyring_conf.keyring, resolved_variables)?,
+            )),
+
+            (VariableSource::Defaults, VariableConfig::Secret(secret_conf)) => {
+                let value = secrets::read(
+                    &secret_conf.secret,
+                    self.command_executor.as_ref(),
+                    resolved_variables,
+                )
+                .map_err(|err| VariableResolutionError::Secret {
+                    key: key.clone(),
+                    source: err,
+                })?;
+
+                Ok(Some(value))
+            }
 
-        Ok(resolved_variables)
+            (VariableSource::Prompt, VariableConfig::Prompt(prompt_config)) => {
+                let value = self
+                    .prompt_executor
+                    .execute(&prompt_config.prompt, resolved_variables)
+                    .map_err(|err| VariableResolutionError::Prompt {
+                        key: key.clone(),
+                        source: err,
+                    })?;
+
+                Ok(Some(value))
+            }
+
+            // Argument variables only have a value when provided on the command-line.
+            _ => Ok(None),
+        }
     }
-}
 
-impl RealVariableResolver {
     fn log_variables(&self, variables: &VariableMap, sensitive_variable_names: &Vec<String>) {
         if !self.options.print_variables {
             return;
@@ -124,9 +421,10 @@ impl RealVariableResolver {
             let is_sensitive = sensitive_variable_names.contains(name);
 
             let variable_to_print = if is_sensitive {
-                "********".to_string() // Hard coded value to obscure the length
+                MASK.to_string() // Hard coded value to obscure the length
             } else {
-                value.clone()
+                // Values can still contain sensitive values (e.g. a URL containing a token)
+                mask(value)
             };
 
             println!("{}={}", name, variable_to_print.green());
@@ -134,18 +432,283 @@ impl RealVariableResolver {
     }
 }
 
-fn is_variable_sensitive(variable_config: &VariableConfig) -> bool {
-    match variable_config {
-        VariableConfig::Prompt(prompt_variable) => match prompt_variable.clone().prompt.options {
-            PromptOptionsVariant::Select(_) => false,
-            PromptOptionsVariant::Text(text_prompt_options) => text_prompt_options.sensitive,
-        },
-        _ => false,
+/// Returns the provided variables in the order they should be resolved in.
+/// Variables are resolved in the order they're defined, except that variables referenced by
+/// another variable (e.g. by its value, command, or a prompt's options) are resolved before it.
+/// Variables referencing each other in a cycle can't be resolved, so an error describing the cycle
+/// is returned instead. Variables referencing themselves (e.g. `PATH: $PATH:./bin`) refer to the
+/// environment variable, rather than a cycle.
+fn resolution_order<'a>(
+    variable_configs: &'a VariableConfigMap,
+    options: &Options,
+) -> Result<Vec<(&'a String, &'a VariableConfig)>, VariableResolutionError> {
+    let names: Vec<String> = variable_configs
+        .iter()
+        .map(|(key, config)| config.environment_variable_name(key, options))
+        .collect();
+    let configs: Vec<(&String, &VariableConfig)> = variable_configs.iter().collect();
+
+    fn visit(
+        index: usize,
+        configs: &[(&String, &VariableConfig)],
+        names: &[String],
+        visiting: &mut Vec<usize>,
+        order: &mut Vec<usize>,
+    ) -> Result<(), VariableResolutionError> {
+        if order.contains(&index) {
+            return Ok(());
+        }
+
+        if let Some(position) = visiting.iter().position(|visited| *visited == index) {
+            let chain = visiting[position..]
+                .iter()
+                .chain([&index])
+                .map(|index| configs[*index].0.as_str())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(VariableResolutionError::CircularReference { chain });
+        }
+
+        visiting.push(index);
+        let references = variable_references(configs[index].1);
+        for (dependency, name) in names.iter().enumerate() {
+            if dependency != index && references.contains(name) {
+                visit(dependency, configs, names, visiting, order)?;
+            }
+        }
+        visiting.pop();
+
+        order.push(index);
+        Ok(())
+    }
+
+    let mut order = Vec::with_capacity(configs.len());
+    for index in 0..configs.len() {
+        visit(index, &configs, &names, &mut vec![], &mut order)?;
+    }
+
+    Ok(order.into_iter().map(|index| configs[index]).collect())
+}
+
+/// Returns the names of the variables referenced (e.g. `$cluster` or `{{ cluster }}`) by the
+/// provided variable.
+fn variable_references(config: &VariableConfig) -> Vec<String> {
+    let templates: Vec<&str> = match config {
+        VariableConfig::ShorthandLiteral(value) => vec![value],
+        VariableConfig::Literal(literal_conf) => vec![&literal_conf.value],
+        VariableConfig::Execution(execution_conf) => {
+            let mut templates = vec![execution_conf.execution.command()];
+            templates.extend(execution_conf.default.as_deref());
+            templates
+        }
+        VariableConfig::Prompt(prompt_conf) => {
+            let prompt = &prompt_conf.prompt;
+            let mut templates = vec![prompt.message.as_str()];
+            templates.extend(prompt.default.as_deref());
+            if let PromptOptionsVariant::Select(select_options) = &prompt.options {
+                match &select_options.options {
+                    SelectOptionsConfig::Execution(execution_conf) => {
+                        templates.push(execution_conf.execution.command())
+                    }
+                    SelectOptionsConfig::Literal(options) => {
+                        templates.extend(options.iter().map(String::as_str))
+                    }
+                }
+            }
+            templates
+        }
+        VariableConfig::Env(env_conf) => env_conf.default.as_deref().into_iter().collect(),
+        VariableConfig::Keyring(keyring_conf) => {
+            vec![&keyring_conf.keyring.service, &keyring_conf.keyring.account]
+        }
+        VariableConfig::Secret(secret_conf) => vec![&secret_conf.secret.path],
+        VariableConfig::Argument(_) => vec![],
+    };
+
+    templates.into_iter().flat_map(text_references).collect()
+}
+
+/// Returns the names of the variables referenced (e.g. `$cluster` or `{{ cluster }}`) by the
+/// provided text.
+fn text_references(text: &str) -> Vec<String> {
+    let mut references = template::references(text);
+
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            chars.next();
+        } else if ch == '$' {
+            let mut name = String::new();
+            while let Some(&next_ch) = chars.peek() {
+                if next_ch.is_alphanumeric() || next_ch == '_' {
+                    name.push(next_ch);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            references.push(name);
+        }
+    }
+
+    references
+}
+
+/// Returns the provided variables without the execution variables that aren't referenced by the
+/// provided command (e.g. by its action, `env`, or messages), or by another variable that will be
+/// resolved. Variables saved by `save_env` are always included.
+pub fn referenced_variables(
+    variable_configs: &VariableConfigMap,
+    command_config: &CommandConfig,
+    options: &Options,
+) -> VariableConfigMap {
+    // Subcommands and the command's own variables aren't executed, so they're left out
+    let command_config = CommandConfig {
+        variables: VariableConfigMap::new(),
+        commands: CommandConfigMap::new(),
+        ..command_config.clone()
+    };
+    let command_text = serde_json::to_string(&command_config).unwrap_or_default();
+    let mut references = text_references(&command_text);
+
+    let saved_keys = command_config
+        .save_env
+        .as_ref()
+        .map(|save_env| save_env.variables.as_slice())
+        .unwrap_or_default();
+
+    // Including a variable can cause the variables it references to be included, so keep going
+    // until nothing else is included
+    let mut included = vec![false; variable_configs.len()];
+    loop {
+        let mut changed = false;
+        for (index, (key, config)) in variable_configs.iter().enumerate() {
+            let is_referenced = !matches!(config, VariableConfig::Execution(_))
+                || saved_keys.contains(key)
+                || references.contains(&config.environment_variable_name(key, options));
+            if included[index] || !is_referenced {
+                continue;
+            }
+
+            included[index] = true;
+            references.extend(variable_references(config));
+            changed = true;
+        }
+
+        if !changed {
+            break;
+        }
     }
+
+    variable_configs
+        .iter()
+        .zip(included)
+        .filter(|(_, included)| *included)
+        .map(|((key, config), _)| (key.clone(), config.clone()))
+        .collect()
+}
+
+/// Extracts the value of a variable from the provided text with its [`ParseConfig`], if it has one.
+fn parse_value(
+    key: &str,
+    text: String,
+    parse: Option<&ParseConfig>,
+) -> Result<String, VariableResolutionError> {
+    let value = match parse {
+        Some(ParseConfig::Json { json }) => filters::extract_json(&text, json),
+        Some(ParseConfig::Regex { regex }) => filters::extract_regex(&text, regex),
+        None => return Ok(text),
+    };
+
+    value.map_err(|err| VariableResolutionError::ParseOutput {
+        key: key.to_string(),
+        source: err,
+    })
+}
+
+/// Formats a line describing how a variable was resolved
+/// (e.g. `version: exec "git describe" (12.3ms) = "v1.2.3"`).
+/// `value` should already be redacted if the variable is sensitive.
+fn format_trace(
+    key: &str,
+    name: &str,
+    config: &VariableConfig,
+    source: Option<&VariableSource>,
+    value: Option<String>,
+    elapsed: Duration,
+) -> String {
+    let elapsed = format!("{:.1}ms", elapsed.as_secs_f64() * 1000.0);
+
+    let (Some(source), Some(value)) = (source, value) else {
+        return format!("{}: unresolved ({})", key, elapsed);
+    };
+
+    let source = match (source, config) {
+        (VariableSource::Cli, _) => "arg".to_string(),
+        (VariableSource::Env, _) => format!("env ${}", name),
+        (VariableSource::Answers, _) => "answers".to_string(),
+        (VariableSource::Defaults, VariableConfig::Execution(execution_conf)) => {
+            format!("exec {:?}", execution_conf.execution.command())
+        }
+        (VariableSource::Defaults, VariableConfig::Env(env_conf)) => {
+            format!("from_env ${}", env_conf.from_env)
+        }
+        (VariableSource::Defaults, VariableConfig::Keyring(keyring_conf)) => format!(
+            "keyring {}/{}",
+            keyring_conf.keyring.service, keyring_conf.keyring.account
+        ),
+        (VariableSource::Defaults, VariableConfig::Secret(secret_conf)) => {
+            format!("secret {:?}", secret_conf.secret.path)
+        }
+        (VariableSource::Defaults, _) => "literal".to_string(),
+        (VariableSource::Prompt, _) => "prompt".to_string(),
+    };
+
+    format!("{}: {} ({}) = {}", key, source, elapsed, value)
 }
 
-/// Uses bash-style variable substitution to replace variable names with their values.
+/// Uses bash-style variable substitution to replace variable names with their values, after
+/// rendering any templates with [`render_tags`].
+/// Templates which fail to render only have their references substituted, since the text isn't
+/// executed. Use [`try_substitute_variables`] for commands.
 pub fn substitute_variables(template: &str, variables: &VariableMap) -> String {
+    let rendered = render_tags(template, variables, Shell::None)
+        .unwrap_or_else(|_| substitute_tag_references(template, variables));
+    substitute_references(&rendered, variables)
+}
+
+/// Like [`substitute_variables`], but also makes the provided arguments available to templates as
+/// the `args` list (e.g. `{{ args | join(sep=",") }}`).
+pub fn substitute_variables_with_args(
+    template: &str,
+    variables: &VariableMap,
+    args: &[String],
+) -> String {
+    let rendered = if template::has_tags(template) {
+        template::render_with_args(template, variables, Some(args), Shell::None).unwrap_or_else(
+            |_| {
+                let mut variables = variables.clone();
+                variables.insert(template::ARGS_NAME.to_string(), args.join(" "));
+                substitute_tag_references(template, &variables)
+            },
+        )
+    } else {
+        template.to_string()
+    };
+    substitute_references(&rendered, variables)
+}
+
+/// Like [`substitute_variables`], but fails if the template can't be rendered.
+pub fn try_substitute_variables(
+    template: &str,
+    variables: &VariableMap,
+) -> Result<String, tera::Error> {
+    let rendered = render_tags(template, variables, Shell::None)?;
+    Ok(substitute_references(&rendered, variables))
+}
+
+/// Replaces `$name` references with the value of the matching variable (or environment variable).
+fn substitute_references(template: &str, variables: &VariableMap) -> String {
     let mut result = String::new();
     let mut chars = template.chars().peekable();
 
@@ -196,6 +759,57 @@ pub fn substitute_variables(template: &str, variables: &VariableMap) -> String {
     result
 }
 
+/// Renders templates containing Tera tags (e.g. `{{ host }}` or
+/// `{% if verbose %}--verbose{% endif %}`), see [`template::render`].
+/// Templates which can't be rendered (e.g. because they contain a Go template passed to
+/// `docker --format`) only have their `{{ <name> }}` and `{{ plz.<name> }}` references replaced
+/// instead, leaving any other tags as they are. Templates using syntax only Tera supports (see
+/// [`template::requires_rendering`]) fail instead, so mistakes aren't executed.
+/// The `quote` filter escapes values for the provided shell.
+pub fn render_tags(
+    template: &str,
+    variables: &VariableMap,
+    shell: Shell,
+) -> Result<String, tera::Error> {
+    if !template::has_tags(template) {
+        return Ok(template.to_string());
+    }
+
+    match template::render(template, variables, shell) {
+        Ok(rendered) => Ok(rendered),
+        Err(err) if template::requires_rendering(template) => Err(err),
+        Err(_) => Ok(substitute_tag_references(template, variables)),
+    }
+}
+
+/// Replaces `{{ <name> }}` references with the value of the matching variable, and
+/// `{{ plz.<name> }}` references with the value of the matching `PLZ_<NAME>` variable.
+/// References to variables that aren't set (and any other tags) are left as is.
+fn substitute_tag_references(template: &str, variables: &VariableMap) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}").map(|end| end + 2) else {
+            break;
+        };
+
+        let tag = &rest[start..start + length];
+        let name = tag[2..tag.len() - 2].trim();
+        let value = match name.strip_prefix("plz.") {
+            Some(name) => variables.get(&format!("PLZ_{}", name.to_uppercase())),
+            None => variables.get(name),
+        };
+
+        result.push_str(&rest[..start]);
+        result.push_str(value.map_or(tag, String::as_str));
+        rest = &rest[start + length..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
 #[derive(Error, Debug)]
 #[error("failed to resolve variable \"{key}\"")]
 pub enum VariableResolutionError {
@@ -215,10 +829,50 @@ pub enum VariableResolutionError {
         source: FromUtf8Error,
     },
 
+    #[error("failed to parse the value of variable \"{key}\"")]
+    ParseOutput {
+        key: String,
+        source: FilterError,
+    },
+
     Prompt {
         key: String,
         source: PromptError,
     },
+
+    #[error("variable \"{key}\" has on_error set to default, but doesn't have a default value")]
+    MissingDefault {
+        key: String,
+    },
+
+    #[error("variable \"{key}\" requires the environment variable {name} to be set")]
+    MissingEnvironmentVariable {
+        key: String,
+        name: String,
+    },
+
+    #[error("variable \"{key}\" couldn't find a secret for the account \"{account}\" of the service \"{service}\" in the keyring")]
+    KeyringSecretNotFound {
+        key: String,
+        service: String,
+        account: String,
+    },
+
+    Secret {
+        key: String,
+        source: SecretError,
+    },
+
+    #[error("variable \"{key}\" has an invalid cache ttl")]
+    InvalidCacheTtl {
+        key: String,
+        source: DurationError,
+    },
+
+    #[error("variables can't reference each other in a cycle: {chain}")]
+    CircularReference {
+        chain: String,
+    },
 }
 
 #[cfg(test)]
@@ -226,13 +880,15 @@ mod tests {
     use super::*;
     use crate::args::MockArgumentResolver;
     use crate::config::VariableConfig::Prompt;
+    use crate::config::{parse_config, Platform};
     use crate::config::{
-        BashCommandConfig, ExecutionConfigVariant, ExecutionVariableConfig, LiteralVariableConfig,
-        PromptConfig, PromptOptionsVariant, PromptVariableConfig, SelectOptionsConfig,
-        SelectPromptOptions, ShellCommandConfigVariant, VariableConfig,
+        BashCommandConfig, EnvVariableConfig, ExecutionConfigVariant, ExecutionSelectOptionsConfig,
+        ExecutionVariableConfig, KeyringVariableConfig, LiteralVariableConfig, PromptConfig,
+        PromptOptionsVariant, PromptVariableConfig, RawCommandConfigVariant, SelectOptionsConfig,
+        SelectPromptOptions, ShellCommandConfigVariant, VariableCacheConfig, VariableConfig,
     };
     use crate::exec::{ExitStatus, MockCommandExecutor, Output};
-    use crate::prompt::MockPromptExecutor;
+    use crate::prompt::{MockPromptExecutor, ScriptedPromptBackend, TerminalPromptExecutor};
     use std::env::set_var;
 
     #[test]
@@ -250,8 +906,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         let name = "name";
@@ -273,6 +931,49 @@ mod tests {
         assert_eq!(resolved_value, value);
     }
 
+    #[test]
+    fn variable_resolver_resolves_references_before_the_variables_using_them() {
+        // Arrange
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            prompt_executor: Box::new(MockPromptExecutor::new()),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        let shorthand = |value: &str| VariableConfig::ShorthandLiteral(value.to_string());
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert("url".to_string(), shorthand("https://{{host}}:$port"));
+        variable_configs.insert("host".to_string(), shorthand("{{ domain }}"));
+        variable_configs.insert("port".to_string(), shorthand("8080"));
+        variable_configs.insert("domain".to_string(), shorthand("example.com"));
+
+        let mut circular_configs = variable_configs.clone();
+        circular_configs.insert("domain".to_string(), shorthand("$url"));
+
+        // Act
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+        let circular_result = variable_resolver.resolve_variables(&circular_configs);
+
+        // Assert
+        assert_eq!(resolved_variables["url"], "https://example.com:8080");
+        assert!(matches!(
+            circular_result,
+            Err(VariableResolutionError::CircularReference { chain })
+                if chain == "url -> host -> domain -> url"
+        ));
+    }
+
     #[test]
     fn variable_resolver_resolves_literal() {
         // Arrange
@@ -287,8 +988,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         let name = "name";
@@ -300,6 +1003,8 @@ mod tests {
                 value: value.to_string(),
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
@@ -337,8 +1042,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         let name = "name";
@@ -348,6 +1055,13 @@ mod tests {
             VariableConfig::Execution(ExecutionVariableConfig {
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: None,
+                default: None,
                 execution: ExecutionConfigVariant::ShellCommand(ShellCommandConfigVariant::Bash(
                     BashCommandConfig {
                         working_directory: None,
@@ -369,117 +1083,497 @@ mod tests {
     }
 
     #[test]
-    fn variable_resolver_resolves_text_prompt_variable() {
+    fn variable_resolver_uses_default_when_execution_variable_times_out() {
         // Arrange
-        let command_executor = MockCommandExecutor::new();
+        let mut timeout_executor = MockCommandExecutor::new();
+        timeout_executor.expect_get_output().returning(|_, _| {
+            Err(ExecutionError::Timeout {
+                command: "git describe".to_string(),
+                timeout: "5s".to_string(),
+            })
+        });
+
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_with_timeout()
+            .withf(|timeout| timeout == "5s")
+            .return_once(move |_| Box::new(timeout_executor));
 
         let mut argument_resolver = MockArgumentResolver::new();
         argument_resolver
             .expect_get()
             .times(0..)
             .returning(|_| None);
-
-        let value = "Alice";
-        let mut prompt_executor = MockPromptExecutor::new();
-        prompt_executor
-            .expect_execute()
-            .once()
-            .returning(|_| Ok(value.to_string()));
+        let prompt_executor = MockPromptExecutor::new();
 
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
-        let name = "name";
         let mut variable_configs = VariableConfigMap::new();
         variable_configs.insert(
-            name.to_string(),
-            Prompt(PromptVariableConfig {
+            "version".to_string(),
+            VariableConfig::Execution(ExecutionVariableConfig {
                 argument: None,
                 environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "Enter your name".to_string(),
-                    options: Default::default(),
-                },
+                no_arg: false,
+                sensitive: false,
+                timeout: Some("5s".to_string()),
+                on_error: ExecutionErrorPolicy::Default,
+                parse: None,
+                cache: None,
+                default: Some("unknown".to_string()),
+                execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                    "git describe".to_string(),
+                )),
             }),
         );
 
         // Act
-        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
 
         // Assert
-        assert!(!resolved_variables.is_err());
-
-        let binding = resolved_variables.unwrap().clone();
-        let resolved_value = binding.get(name).unwrap().as_str();
-        assert_eq!(resolved_value, value);
+        assert_eq!(resolved_variables.get("version").unwrap(), "unknown");
     }
 
     #[test]
-    fn variable_resolver_resolves_select_prompt_variable() {
+    fn variable_resolver_extracts_json_from_execution_variable() {
         // Arrange
-        let command_executor = MockCommandExecutor::new();
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor.expect_get_output().returning(|_, _| {
+            Ok(Output {
+                status: ExitStatus::Success,
+                stdout: br#"{"items": [{"id": "pod-1"}, {"id": "pod-2"}]}"#.to_vec(),
+                stderr: vec![],
+            })
+        });
 
         let mut argument_resolver = MockArgumentResolver::new();
         argument_resolver
             .expect_get()
             .times(0..)
             .returning(|_| None);
-
-        let value = "Alice";
-        let mut prompt_executor = MockPromptExecutor::new();
-        prompt_executor
-            .expect_execute()
-            .once()
-            .returning(|_| Ok(value.to_string()));
+        let prompt_executor = MockPromptExecutor::new();
 
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
-        let name = "name";
         let mut variable_configs = VariableConfigMap::new();
         variable_configs.insert(
-            name.to_string(),
-            Prompt(PromptVariableConfig {
+            "pod".to_string(),
+            VariableConfig::Execution(ExecutionVariableConfig {
                 argument: None,
                 environment_variable_name: None,
-                prompt: PromptConfig {
-                    message: "Select your name".to_string(),
-                    options: PromptOptionsVariant::Select(SelectPromptOptions {
-                        options: SelectOptionsConfig::Literal(vec![
-                            "Alice".to_string(),
-                            "Bob".to_string(),
-                            "Charlie".to_string(),
-                            "Dale".to_string(),
-                        ]),
-                    }),
-                },
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: Some(ParseConfig::Json {
+                    json: "$.items[0].id".to_string(),
+                }),
+                cache: None,
+                default: None,
+                execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                    "kubectl get pods -o json".to_string(),
+                )),
             }),
         );
 
         // Act
-        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
 
         // Assert
-        assert!(!resolved_variables.is_err());
-
-        let binding = resolved_variables.unwrap().clone();
-        let resolved_value = binding.get(name).unwrap().as_str();
-        assert_eq!(resolved_value, value);
+        assert_eq!(resolved_variables.get("pod").unwrap(), "pod-1");
     }
 
     #[test]
-    fn variable_resolver_uses_custom_env_var() {
+    fn referenced_variables_skips_unreferenced_execution_variables() {
         // Arrange
-        let command_executor = MockCommandExecutor::new();
-        let mut argument_resolver = MockArgumentResolver::new();
+        let yaml = r#"
+variables:
+  profile:
+    exec: aws configure get profile
+  cluster:
+    exec: kubectl config current-context --profile $profile
+  owner:
+    exec: whoami
+  label: team-$owner
+  saved:
+    exec: date +%s
+  unused:
+    exec: sleep 10
+commands:
+  deploy:
+    save_env:
+      path: .env
+      vars: [saved]
+    actions:
+      - command: helm upgrade api --kube-context {{ cluster }}
+        env:
+          LABEL: $label
+"#;
+        let config = parse_config(&yaml.to_string(), Platform::Linux, None).unwrap();
+
+        // Act
+        let variable_configs = referenced_variables(
+            &config.variables,
+            &config.commands["deploy"],
+            &config.options,
+        );
+
+        // Assert
+        let keys: Vec<&str> = variable_configs.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["profile", "cluster", "owner", "label", "saved"]);
+    }
+
+    #[test]
+    fn variable_resolver_reuses_cached_execution_variable() {
+        // Arrange
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .times(1)
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: b"123456789012\n".to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let state_dir = tempfile::TempDir::new().unwrap();
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(MockPromptExecutor::new()),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: Some(state_dir.path().to_path_buf()),
+        };
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "account_id".to_string(),
+            VariableConfig::Execution(ExecutionVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                timeout: None,
+                on_error: ExecutionErrorPolicy::Fail,
+                parse: None,
+                cache: Some(VariableCacheConfig {
+                    ttl: "10m".to_string(),
+                }),
+                default: None,
+                execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                    "aws sts get-caller-identity --query Account --output text".to_string(),
+                )),
+            }),
+        );
+
+        // Act
+        let first = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+        let second = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+
+        // Assert
+        assert_eq!(first["account_id"], "123456789012");
+        assert_eq!(second["account_id"], "123456789012");
+    }
+
+    #[test]
+    fn variable_resolver_extracts_regex_capture_from_env_variable() {
+        // Arrange
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            prompt_executor: Box::new(MockPromptExecutor::new()),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        set_var(
+            "PLZ_TEST_PARSE_DATABASE_URL",
+            "postgres://app@db.internal:5432/orders",
+        );
+        let env_variable = |regex: &str| {
+            VariableConfig::Env(EnvVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                from_env: "PLZ_TEST_PARSE_DATABASE_URL".to_string(),
+                parse: Some(ParseConfig::Regex {
+                    regex: regex.to_string(),
+                }),
+                default: None,
+                required: true,
+            })
+        };
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert("port".to_string(), env_variable(r":(\d+)/"));
+
+        let mut unmatched_configs = VariableConfigMap::new();
+        unmatched_configs.insert("user".to_string(), env_variable(r"mysql://(\w+)@"));
+
+        // Act
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+        let unmatched = variable_resolver.resolve_variables(&unmatched_configs);
+
+        // Assert
+        assert_eq!(resolved_variables["port"], "5432");
+        assert!(matches!(
+            unmatched,
+            Err(VariableResolutionError::ParseOutput {
+                source: FilterError::NoRegexMatch { .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn variable_resolver_resolves_text_prompt_variable() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let value = "Alice";
+        let mut prompt_executor = MockPromptExecutor::new();
+        prompt_executor
+            .expect_execute()
+            .once()
+            .returning(|_, _| Ok(value.to_string()));
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        let name = "name";
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            name.to_string(),
+            Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "Enter your name".to_string(),
+                    default: None,
+                    options: Default::default(),
+                },
+            }),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+
+        // Assert
+        assert!(!resolved_variables.is_err());
+
+        let binding = resolved_variables.unwrap().clone();
+        let resolved_value = binding.get(name).unwrap().as_str();
+        assert_eq!(resolved_value, value);
+    }
+
+    #[test]
+    fn variable_resolver_resolves_prompts_after_the_prompts_they_depend_on() {
+        // Arrange
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .withf(|_, variables| variables.get("cluster").map(String::as_str) == Some("prod"))
+            .once()
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: b"default\nkube-system\n".to_vec(),
+                    stderr: vec![],
+                })
+            });
+
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let prompt_backend = ScriptedPromptBackend::new(vec!["prod".to_string(), "".to_string()]);
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            prompt_executor: Box::new(TerminalPromptExecutor::new(
+                Box::new(command_executor),
+                Box::new(prompt_backend),
+            )),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "namespace".to_string(),
+            Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "Which namespace in $cluster?".to_string(),
+                    default: Some("kube-system".to_string()),
+                    options: PromptOptionsVariant::Select(SelectPromptOptions {
+                        options: SelectOptionsConfig::Execution(ExecutionSelectOptionsConfig {
+                            execution: ExecutionConfigVariant::ShellCommand(
+                                ShellCommandConfigVariant::Bash(BashCommandConfig {
+                                    working_directory: None,
+                                    command: "kubectl --context $cluster get namespaces"
+                                        .to_string(),
+                                }),
+                            ),
+                        }),
+                    }),
+                },
+            }),
+        );
+        variable_configs.insert(
+            "cluster".to_string(),
+            Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "Which cluster?".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Select(SelectPromptOptions {
+                        options: SelectOptionsConfig::Literal(vec![
+                            "dev".to_string(),
+                            "prod".to_string(),
+                        ]),
+                    }),
+                },
+            }),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+
+        // Assert
+        assert_eq!(resolved_variables["cluster"], "prod");
+        assert_eq!(resolved_variables["namespace"], "kube-system");
+    }
+
+    #[test]
+    fn variable_resolver_resolves_select_prompt_variable() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let value = "Alice";
+        let mut prompt_executor = MockPromptExecutor::new();
+        prompt_executor
+            .expect_execute()
+            .once()
+            .returning(|_, _| Ok(value.to_string()));
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        let name = "name";
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            name.to_string(),
+            Prompt(PromptVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                prompt: PromptConfig {
+                    message: "Select your name".to_string(),
+                    default: None,
+                    options: PromptOptionsVariant::Select(SelectPromptOptions {
+                        options: SelectOptionsConfig::Literal(vec![
+                            "Alice".to_string(),
+                            "Bob".to_string(),
+                            "Charlie".to_string(),
+                            "Dale".to_string(),
+                        ]),
+                    }),
+                },
+            }),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+
+        // Assert
+        assert!(!resolved_variables.is_err());
+
+        let binding = resolved_variables.unwrap().clone();
+        let resolved_value = binding.get(name).unwrap().as_str();
+        assert_eq!(resolved_value, value);
+    }
+
+    #[test]
+    fn variable_resolver_uses_custom_env_var() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+        let mut argument_resolver = MockArgumentResolver::new();
         argument_resolver
             .expect_get()
             .times(0..)
@@ -489,8 +1583,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         let name = "name";
@@ -503,6 +1599,8 @@ mod tests {
                 value: value.to_string(),
                 argument: None,
                 environment_variable_name: Some(env_var_name.to_string()),
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
@@ -517,6 +1615,306 @@ mod tests {
         assert_eq!(resolved_value, value);
     }
 
+    #[test]
+    fn variable_resolver_resolves_env_variable() {
+        // Arrange
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(MockCommandExecutor::new()),
+            prompt_executor: Box::new(MockPromptExecutor::new()),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        set_var("PLZ_TEST_FROM_ENV_TOKEN", "secret");
+        let env_variable = |from_env: &str, default: Option<&str>, required: bool| {
+            VariableConfig::Env(EnvVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
+                from_env: from_env.to_string(),
+                parse: None,
+                default: default.map(str::to_string),
+                required,
+            })
+        };
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "from_env_token".to_string(),
+            env_variable("PLZ_TEST_FROM_ENV_TOKEN", None, true),
+        );
+        variable_configs.insert(
+            "from_env_region".to_string(),
+            env_variable("PLZ_TEST_FROM_ENV_UNSET", Some("eu-west-1"), true),
+        );
+        variable_configs.insert(
+            "from_env_optional".to_string(),
+            env_variable("PLZ_TEST_FROM_ENV_UNSET", None, false),
+        );
+
+        let mut required_configs = VariableConfigMap::new();
+        required_configs.insert(
+            "from_env_required".to_string(),
+            env_variable("PLZ_TEST_FROM_ENV_UNSET", None, true),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+        let missing = variable_resolver.resolve_variables(&required_configs);
+
+        // Assert
+        assert_eq!(resolved_variables["from_env_token"], "secret");
+        assert_eq!(resolved_variables["from_env_region"], "eu-west-1");
+        assert_eq!(resolved_variables["from_env_optional"], "");
+        assert!(matches!(
+            missing,
+            Err(VariableResolutionError::MissingEnvironmentVariable { name, .. })
+                if name == "PLZ_TEST_FROM_ENV_UNSET"
+        ));
+    }
+
+    #[test]
+    fn variable_resolver_reads_keyring_variable_as_sensitive() {
+        // Arrange
+        let mut command_executor = MockCommandExecutor::new();
+        command_executor
+            .expect_get_output()
+            .withf(|execution, _| execution.command().contains("'github'"))
+            .returning(|_, _| {
+                Ok(Output {
+                    status: ExitStatus::Success,
+                    stdout: b"hunter2\n".to_vec(),
+                    stderr: vec![],
+                })
+            });
+        command_executor.expect_get_output().returning(|_, _| {
+            Ok(Output {
+                status: ExitStatus::Fail(44),
+                stdout: vec![],
+                stderr: vec![],
+            })
+        });
+
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| None);
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(MockPromptExecutor::new()),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Default::default(),
+            state_dir: None,
+        };
+
+        let keyring_variable = |service: &str| {
+            VariableConfig::Keyring(KeyringVariableConfig {
+                argument: None,
+                environment_variable_name: None,
+                no_arg: false,
+                keyring: KeyringConfig {
+                    service: service.to_string(),
+                    account: "octocat".to_string(),
+                },
+            })
+        };
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert("token".to_string(), keyring_variable("github"));
+
+        let mut missing_configs = VariableConfigMap::new();
+        missing_configs.insert("token".to_string(), keyring_variable("gitlab"));
+
+        // Act
+        let resolved_variables = variable_resolver
+            .resolve_variables(&variable_configs)
+            .unwrap();
+        let missing = variable_resolver.resolve_variables(&missing_configs);
+
+        // Assert
+        assert_eq!(resolved_variables["token"], "hunter2");
+        assert_eq!(mask("token=hunter2"), format!("token={}", MASK));
+        assert!(matches!(
+            missing,
+            Err(VariableResolutionError::KeyringSecretNotFound { service, .. })
+                if service == "gitlab"
+        ));
+    }
+
+    #[test]
+    fn variable_resolver_checks_sources_in_order_of_precedence() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| Some("from-cli".to_string()));
+        argument_resolver
+            .expect_was_provided()
+            .times(0..)
+            .returning(|_| true);
+        let prompt_executor = MockPromptExecutor::new();
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Options {
+                variable_precedence: vec![
+                    VariableSource::Env,
+                    VariableSource::Cli,
+                    VariableSource::Defaults,
+                ],
+                ..Default::default()
+            },
+            state_dir: None,
+        };
+
+        set_var("PLZ_TEST_PRECEDENCE_REGION", "from-env");
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "PLZ_TEST_PRECEDENCE_REGION".to_string(),
+            VariableConfig::ShorthandLiteral("from-config".to_string()),
+        );
+        variable_configs.insert(
+            "PLZ_TEST_PRECEDENCE_SIZE".to_string(),
+            VariableConfig::ShorthandLiteral("from-config".to_string()),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+
+        // Assert
+        let resolved_variables = resolved_variables.unwrap();
+        assert_eq!(
+            resolved_variables
+                .get("PLZ_TEST_PRECEDENCE_REGION")
+                .unwrap(),
+            "from-env"
+        );
+        assert_eq!(
+            resolved_variables.get("PLZ_TEST_PRECEDENCE_SIZE").unwrap(),
+            "from-cli"
+        );
+    }
+
+    #[test]
+    fn variable_resolver_ignores_argument_defaults_from_cli() {
+        // Arrange
+        let command_executor = MockCommandExecutor::new();
+        let mut argument_resolver = MockArgumentResolver::new();
+        argument_resolver
+            .expect_get()
+            .times(0..)
+            .returning(|_| Some("from-config".to_string()));
+        argument_resolver
+            .expect_was_provided()
+            .times(0..)
+            .returning(|_| false);
+        let prompt_executor = MockPromptExecutor::new();
+
+        let variable_resolver = RealVariableResolver {
+            command_executor: Box::new(command_executor),
+            prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
+            argument_resolver: Box::new(argument_resolver),
+            options: Options {
+                variable_precedence: vec![
+                    VariableSource::Cli,
+                    VariableSource::Env,
+                    VariableSource::Defaults,
+                ],
+                ..Default::default()
+            },
+            state_dir: None,
+        };
+
+        set_var("PLZ_TEST_ARGUMENT_DEFAULT_REGION", "from-env");
+
+        let mut variable_configs = VariableConfigMap::new();
+        variable_configs.insert(
+            "PLZ_TEST_ARGUMENT_DEFAULT_REGION".to_string(),
+            VariableConfig::ShorthandLiteral("from-config".to_string()),
+        );
+
+        // Act
+        let resolved_variables = variable_resolver.resolve_variables(&variable_configs);
+
+        // Assert
+        assert_eq!(
+            resolved_variables
+                .unwrap()
+                .get("PLZ_TEST_ARGUMENT_DEFAULT_REGION")
+                .unwrap(),
+            "from-env"
+        );
+    }
+
+    #[test]
+    fn format_trace_describes_source() {
+        // Arrange
+        let execution_config = VariableConfig::Execution(ExecutionVariableConfig {
+            execution: ExecutionConfigVariant::RawCommand(RawCommandConfigVariant::Shorthand(
+                "git describe".to_string(),
+            )),
+            argument: None,
+            environment_variable_name: Some("VERSION".to_string()),
+            no_arg: false,
+            sensitive: false,
+            timeout: None,
+            on_error: ExecutionErrorPolicy::Fail,
+            parse: None,
+            cache: None,
+            default: None,
+        });
+        let elapsed = Duration::from_micros(12_345);
+
+        // Act
+        let exec_trace = format_trace(
+            "version",
+            "VERSION",
+            &execution_config,
+            Some(&VariableSource::Defaults),
+            Some("\"v1.2.3\"".to_string()),
+            elapsed,
+        );
+        let env_trace = format_trace(
+            "version",
+            "VERSION",
+            &execution_config,
+            Some(&VariableSource::Env),
+            Some("\"v1.0.0\"".to_string()),
+            elapsed,
+        );
+        let unresolved_trace =
+            format_trace("version", "VERSION", &execution_config, None, None, elapsed);
+
+        // Assert
+        assert_eq!(
+            exec_trace,
+            "version: exec \"git describe\" (12.3ms) = \"v1.2.3\""
+        );
+        assert_eq!(env_trace, "version: env $VERSION (12.3ms) = \"v1.0.0\"");
+        assert_eq!(unresolved_trace, "version: unresolved (12.3ms)");
+    }
+
     #[test]
     fn substitute_variables_substitutes_variables() {
         // Arrange
@@ -610,6 +2008,37 @@ mod tests {
         assert_eq!(result, "Hello, Alice-the-Smith!")
     }
 
+    #[test]
+    fn substitute_variables_substitutes_plz_references() {
+        // Arrange
+        let template = "Step {{ plz.failed_step }} failed with {{plz.exit_code}} {{ .Names }}";
+        let mut variables = VariableMap::new();
+        variables.insert("PLZ_FAILED_STEP".to_string(), "migrate".to_string());
+        variables.insert("PLZ_EXIT_CODE".to_string(), "2".to_string());
+
+        // Act
+        let result = substitute_variables(template, &variables);
+
+        // Assert
+        assert_eq!(result, "Step migrate failed with 2 {{ .Names }}")
+    }
+
+    #[test]
+    fn builtin_variables_can_be_substituted() {
+        // Arrange
+        let user = audit::current_user().unwrap_or_else(|| "{{ plz.user }}".to_string());
+        let host = audit::current_host().unwrap_or_else(|| "{{ plz.hostname }}".to_string());
+
+        // Act
+        let result = substitute_variables(
+            "deployed by {{ plz.user }}@{{ plz.hostname }}",
+            &builtin_variables(),
+        );
+
+        // Assert
+        assert_eq!(result, format!("deployed by {}@{}", user, host));
+    }
+
     #[test]
     fn environment_variables_can_be_substituted_in_shorthand_literal_variables() {
         // Arrange
@@ -625,8 +2054,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         unsafe {
@@ -667,8 +2098,10 @@ mod tests {
         let variable_resolver = RealVariableResolver {
             command_executor: Box::new(command_executor),
             prompt_executor: Box::new(prompt_executor),
+            answer_executor: None,
             argument_resolver: Box::new(argument_resolver),
             options: Default::default(),
+            state_dir: None,
         };
 
         unsafe {
@@ -684,6 +2117,8 @@ mod tests {
                 value: value.to_string(),
                 argument: None,
                 environment_variable_name: None,
+                no_arg: false,
+                sensitive: false,
             }),
         );
 
@@ -0,0 +1,84 @@
+use std::io;
+use std::path::Path;
+use std::process;
+use thiserror::Error;
+
+/// The maximum number of changed files listed when the working tree isn't clean.
+const MAX_LISTED_CHANGES: usize = 10;
+
+#[derive(Error, Debug)]
+pub enum WorktreeError {
+    #[error("the git working tree has uncommitted changes:\n{changes}\ncommit or stash them, or pass --allow-dirty to execute the command anyway")]
+    Dirty { changes: String },
+
+    #[error("failed to run git")]
+    GitUnavailable(#[source] io::Error),
+
+    #[error("failed to check the git working tree: {message}")]
+    StatusFailed { message: String },
+}
+
+/// Checks that the git working tree containing the provided directory doesn't have any
+/// uncommitted changes (including untracked files), returning an error listing them if it does.
+pub fn check_clean(dir: &Path) -> Result<(), WorktreeError> {
+    let output = process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(dir)
+        .output()
+        .map_err(WorktreeError::GitUnavailable)?;
+
+    if !output.status.success() {
+        return Err(WorktreeError::StatusFailed {
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+
+    match format_changes(&String::from_utf8_lossy(&output.stdout)) {
+        Some(changes) => Err(WorktreeError::Dirty { changes }),
+        None => Ok(()),
+    }
+}
+
+/// Formats the changes listed by `git status --porcelain`, one per line, or returns `None` if
+/// there aren't any.
+fn format_changes(status: &str) -> Option<String> {
+    let changes: Vec<&str> = status.lines().filter(|line| !line.is_empty()).collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    let mut lines: Vec<String> = changes
+        .iter()
+        .take(MAX_LISTED_CHANGES)
+        .map(|change| format!("  {}", change))
+        .collect();
+    if changes.len() > MAX_LISTED_CHANGES {
+        lines.push(format!(
+            "  ...and {} more",
+            changes.len() - MAX_LISTED_CHANGES
+        ));
+    }
+
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_changes_lists_changed_files() {
+        // Arrange
+        let many: String = (0..12).map(|i| format!("?? file{}.txt\n", i)).collect();
+
+        // Act
+        let clean = format_changes("");
+        let dirty = format_changes(" M src/main.rs\n?? notes.txt\n");
+        let truncated = format_changes(&many).unwrap();
+
+        // Assert
+        assert_eq!(clean, None);
+        assert_eq!(dirty.as_deref(), Some("   M src/main.rs\n  ?? notes.txt"));
+        assert!(truncated.ends_with("  ?? file9.txt\n  ...and 2 more"));
+    }
+}